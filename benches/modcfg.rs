@@ -0,0 +1,50 @@
+use beammm::game::ModCfg;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use std::hint::black_box;
+
+/// Build a db.json with `count` mods shaped like the ones the game writes.
+fn db_json(count: usize) -> String {
+    let mods: Vec<String> = (0..count)
+        .map(|i| {
+            format!(
+                r#""mod{i}":{{"active":{active},"modname":"mod{i}","filename":"mod{i}.zip","fullpath":"/mods/repo/mod{i}.zip","dirname":"/mods/repo/","modType":"vehicle","dateAdded":1700000000,"stat":{{"filesize":123456789,"modtime":1700000000,"createtime":1700000000}},"modData":{{"tagid":"M{i}","resource_version_id":{i},"username":"someone","title":"Mod number {i}","tag_line":"A mod used for benchmarking BeamMM","message":"{message}"}},"hashes":[["/vehicles/mod{i}/info.json","0123456789abcdef0123456789abcdef01234567"]]}}"#,
+                active = i % 2 == 0,
+                message = "Lorem ipsum dolor sit amet. ".repeat(20),
+            )
+        })
+        .collect();
+    format!(r#"{{"version":1.1,"mods":{{{}}}}}"#, mods.join(","))
+}
+
+fn bench_modcfg(c: &mut Criterion) {
+    let json = db_json(2000);
+    let mod_cfg = ModCfg::load(json.as_bytes()).unwrap();
+    let mods_dir = tempfile::tempdir().unwrap();
+    mod_cfg.save_to_path(mods_dir.path()).unwrap();
+
+    c.bench_function("load 2000 mods", |b| {
+        b.iter(|| ModCfg::load(black_box(json.as_bytes())).unwrap())
+    });
+    c.bench_function("load_from_path 2000 mods", |b| {
+        b.iter(|| ModCfg::load_from_path(black_box(mods_dir.path())).unwrap())
+    });
+    c.bench_function("save 2000 mods", |b| {
+        b.iter(|| mod_cfg.save(black_box(Vec::new())).unwrap())
+    });
+    c.bench_function("toggle and save_to_path 2000 mods", |b| {
+        b.iter_batched(
+            || mod_cfg.clone(),
+            |mut mod_cfg| {
+                mod_cfg.set_mod_active("mod1", true).unwrap();
+                mod_cfg.save_to_path(mods_dir.path()).unwrap();
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    c.bench_function("save_to_path unchanged 2000 mods", |b| {
+        b.iter(|| mod_cfg.save_to_path(black_box(mods_dir.path())).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_modcfg);
+criterion_main!(benches);
@@ -0,0 +1,63 @@
+/*
+ * C bindings for BeamMM, the BeamNG.drive mod manager.
+ *
+ * Functions returning int use 0 for success and -1 for errors. Functions returning pointers
+ * return NULL on errors. Call bmm_last_error to get the message of the last error on the calling
+ * thread. Strings returned by BeamMM must be freed with bmm_string_free. Changes to mods are kept
+ * in memory until bmm_save is called. Functions that write files fail while the BeamMM CLI holds
+ * the lock on its directory.
+ */
+
+#ifndef BEAMMM_H
+#define BEAMMM_H
+
+#include <stdbool.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An open BeamMM session, holding a loaded mod configuration. */
+typedef struct BmmSession BmmSession;
+
+/* Open a session on a mods directory and presets directory. */
+BmmSession *bmm_open(const char *mods_dir, const char *presets_dir);
+
+/* Open a session using the same directories and settings as the BeamMM CLI. */
+BmmSession *bmm_open_default(void);
+
+/* Close a session, discarding unsaved changes. */
+void bmm_close(BmmSession *session);
+
+/* List the installed mods as a JSON array of {"name", "active"} objects, sorted by name. */
+char *bmm_list_mods(const BmmSession *session);
+
+/* List the presets as a JSON array of {"name", "enabled", "mods"} objects, sorted by name. */
+char *bmm_list_presets(const BmmSession *session);
+
+/* Get whether a mod is active: 1 if active, 0 if inactive, -1 if the mod doesn't exist. */
+int bmm_is_mod_active(const BmmSession *session, const char *mod_name);
+
+/* Enable or disable a mod. */
+int bmm_set_mod_active(BmmSession *session, const char *mod_name, bool active);
+
+/* Enable or disable a preset, saving the preset. Call bmm_apply_presets afterwards. */
+int bmm_set_preset_enabled(BmmSession *session, const char *preset_name, bool enabled);
+
+/* Apply the enabled presets, skipping mods that aren't installed. */
+int bmm_apply_presets(BmmSession *session);
+
+/* Save the mod configuration to db.json. */
+int bmm_save(const BmmSession *session);
+
+/* Get the message of the last error on the calling thread, or NULL if there was none. */
+const char *bmm_last_error(void);
+
+/* Free a string returned by BeamMM. */
+void bmm_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* BEAMMM_H */
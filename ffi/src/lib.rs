@@ -0,0 +1,468 @@
+use beammm::{
+    config::Config,
+    game::{MissingModPolicy, ModCfg},
+    lock::Lock,
+    path, Preset, Profile,
+};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_int, CStr, CString},
+    path::{Path, PathBuf},
+    ptr,
+};
+
+#[cfg(feature = "python")]
+mod python;
+
+thread_local! {
+    /// The message of the last error on this thread.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// An open BeamMM session, holding a loaded mod configuration.
+///
+/// C callers only ever see a pointer to it.
+pub struct BmmSession {
+    /// The loaded mod configuration.
+    mod_config: ModCfg,
+    /// The mods directory of the current game version.
+    mods_dir: PathBuf,
+    /// The directory where the presets are stored.
+    presets_dir: PathBuf,
+    /// The BeamMM directory locked while saving, if there is one.
+    beammm_dir: Option<PathBuf>,
+}
+
+/// Record an error so `bmm_last_error` can return it.
+fn set_last_error(message: impl ToString) {
+    // Interior nul bytes can't be represented, so they are dropped.
+    let message = message.to_string().replace('\0', "");
+    LAST_ERROR.with(|e| *e.borrow_mut() = CString::new(message).ok());
+}
+
+/// Turn a result into a status code, recording the error if there is one.
+fn status(result: beammm::Result<()>) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            set_last_error(e);
+            -1
+        }
+    }
+}
+
+/// Read a C string argument.
+///
+/// # Safety
+///
+/// `s` must be null or point to a nul-terminated string.
+unsafe fn read_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        set_last_error("A required string argument was null.");
+        return None;
+    }
+    match CStr::from_ptr(s).to_str() {
+        Ok(s) => Some(s),
+        Err(e) => {
+            set_last_error(e);
+            None
+        }
+    }
+}
+
+/// Hand a Rust string to C. It must be freed with `bmm_string_free`.
+fn into_c_string(s: String) -> *mut c_char {
+    match CString::new(s) {
+        Ok(s) => s.into_raw(),
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Find the BeamMM directory the CLI uses, if it exists, so its lock can be taken.
+fn find_beammm_dir() -> Option<PathBuf> {
+    path::find_beammm_dir(false).ok().filter(|dir| dir.is_dir())
+}
+
+/// Lock the BeamMM directory while db.json or presets are written, like the CLI does, so a CLI run
+/// in progress isn't overwritten. Nothing is locked without a BeamMM directory.
+///
+/// # Errors
+///
+/// Locked: If another process, like the CLI, holds the lock.
+fn lock(beammm_dir: Option<&Path>) -> beammm::Result<Option<Lock>> {
+    beammm_dir.map(Lock::acquire).transpose()
+}
+
+/// Load the mod configuration, applying BeamMM's own settings from its config.
+fn open(
+    mods_dir: &Path,
+    presets_dir: &Path,
+    beammm_dir: Option<&Path>,
+) -> beammm::Result<BmmSession> {
+    let mut mod_config = ModCfg::load_or_default(mods_dir)?;
+    if let Some(beammm_dir) = beammm_dir {
+        let config = Config::load_from_path(beammm_dir)?;
+        mod_config.set_blacklist(config.blacklist().iter().cloned());
+        mod_config.set_whitelist(config.enforced_whitelist());
+        mod_config.set_aliases(config.mod_aliases().clone());
+        mod_config.set_compact(config.is_compact_db_json());
+    }
+    Ok(BmmSession {
+        mod_config,
+        mods_dir: mods_dir.to_owned(),
+        presets_dir: presets_dir.to_owned(),
+        beammm_dir: beammm_dir.map(Path::to_owned),
+    })
+}
+
+/// Open a session on a mods directory and presets directory.
+///
+/// Returns null on error; see `bmm_last_error`.
+///
+/// # Safety
+///
+/// Both arguments must be nul-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn bmm_open(
+    mods_dir: *const c_char,
+    presets_dir: *const c_char,
+) -> *mut BmmSession {
+    let (Some(mods_dir), Some(presets_dir)) = (read_str(mods_dir), read_str(presets_dir)) else {
+        return ptr::null_mut();
+    };
+    match open(Path::new(mods_dir), Path::new(presets_dir), None) {
+        Ok(mut session) => {
+            // The CLI locks its BeamMM directory even when given a mods directory.
+            session.beammm_dir = find_beammm_dir();
+            Box::into_raw(Box::new(session))
+        }
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Open a session using the same directories and settings as the BeamMM CLI.
+///
+/// Returns null on error; see `bmm_last_error`.
+#[no_mangle]
+pub extern "C" fn bmm_open_default() -> *mut BmmSession {
+    let session = (|| {
+        let beammm_dir = path::beammm_dir()?;
+        let beamng_dir = match Config::load_from_path(&beammm_dir)?.game_dir() {
+            Some(dir) if dir.exists() => dir.to_owned(),
+            _ => path::beamng_dir_default()?,
+        };
+        let version = beammm::game_version(&beamng_dir)?;
+        let mods_dir = path::mods_dir(&beamng_dir, &version)?;
+        let profile = Profile::active(&path::profiles_dir(&beammm_dir)?)?;
+        let presets_dir = path::profile_presets_dir(&beammm_dir, &profile)?;
+        open(&mods_dir, &presets_dir, Some(&beammm_dir))
+    })();
+    match session {
+        Ok(session) => Box::into_raw(Box::new(session)),
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Close a session, discarding unsaved changes.
+///
+/// # Safety
+///
+/// `session` must be null or a session returned by `bmm_open` or `bmm_open_default` that hasn't
+/// been closed yet.
+#[no_mangle]
+pub unsafe extern "C" fn bmm_close(session: *mut BmmSession) {
+    if !session.is_null() {
+        drop(Box::from_raw(session));
+    }
+}
+
+/// List the installed mods as a JSON array of `{"name", "active"}` objects, sorted by name.
+///
+/// The string must be freed with `bmm_string_free`. Returns null on error.
+///
+/// # Safety
+///
+/// `session` must be an open session.
+#[no_mangle]
+pub unsafe extern "C" fn bmm_list_mods(session: *const BmmSession) -> *mut c_char {
+    let session = &*session;
+    let mut mods: Vec<&String> = session.mod_config.get_mods().collect();
+    mods.sort();
+    let mods: Vec<serde_json::Value> = mods
+        .into_iter()
+        .map(|m| serde_json::json!({"name": m, "active": session.mod_config.is_mod_active(m)}))
+        .collect();
+    into_c_string(serde_json::Value::from(mods).to_string())
+}
+
+/// List the presets as a JSON array of `{"name", "enabled", "mods"}` objects, sorted by name.
+///
+/// The string must be freed with `bmm_string_free`. Returns null on error.
+///
+/// # Safety
+///
+/// `session` must be an open session.
+#[no_mangle]
+pub unsafe extern "C" fn bmm_list_presets(session: *const BmmSession) -> *mut c_char {
+    let session = &*session;
+    let presets = (|| {
+        let mut names: Vec<String> = Preset::list(&session.presets_dir)?.collect();
+        names.sort();
+        let mut presets = vec![];
+        for name in names {
+            let preset = Preset::load_from_path(&name, &session.presets_dir)?;
+            presets.push(serde_json::json!({
+                "name": name,
+                "enabled": preset.is_enabled(),
+                "mods": preset.get_mods(),
+            }));
+        }
+        beammm::Result::Ok(presets)
+    })();
+    match presets {
+        Ok(presets) => into_c_string(serde_json::Value::from(presets).to_string()),
+        Err(e) => {
+            set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Get whether a mod is active: 1 if active, 0 if inactive, -1 if the mod doesn't exist.
+///
+/// # Safety
+///
+/// `session` must be an open session and `mod_name` a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn bmm_is_mod_active(
+    session: *const BmmSession,
+    mod_name: *const c_char,
+) -> c_int {
+    let Some(mod_name) = read_str(mod_name) else {
+        return -1;
+    };
+    match (*session).mod_config.is_mod_active(mod_name) {
+        Some(active) => active.into(),
+        None => -1,
+    }
+}
+
+/// Enable or disable a mod. Returns 0 on success and -1 on error.
+///
+/// # Safety
+///
+/// `session` must be an open session and `mod_name` a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn bmm_set_mod_active(
+    session: *mut BmmSession,
+    mod_name: *const c_char,
+    active: bool,
+) -> c_int {
+    let Some(mod_name) = read_str(mod_name) else {
+        return -1;
+    };
+    status((*session).mod_config.set_mod_active(mod_name, active))
+}
+
+/// Enable or disable a preset, saving the preset. Call `bmm_apply_presets` afterwards. Returns 0
+/// on success and -1 on error, e.g. while the BeamMM CLI is running.
+///
+/// # Safety
+///
+/// `session` must be an open session and `preset_name` a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn bmm_set_preset_enabled(
+    session: *mut BmmSession,
+    preset_name: *const c_char,
+    enabled: bool,
+) -> c_int {
+    let Some(preset_name) = read_str(preset_name) else {
+        return -1;
+    };
+    let session = &mut *session;
+    status(lock(session.beammm_dir.as_deref()).and_then(|_lock| {
+        Preset::set_many_enabled(
+            [preset_name],
+            enabled,
+            &session.presets_dir,
+            &mut session.mod_config,
+        )
+    }))
+}
+
+/// Apply the enabled presets, skipping mods that aren't installed. Returns 0 on success and -1 on
+/// error.
+///
+/// # Safety
+///
+/// `session` must be an open session.
+#[no_mangle]
+pub unsafe extern "C" fn bmm_apply_presets(session: *mut BmmSession) -> c_int {
+    let session = &mut *session;
+    status(
+        session
+            .mod_config
+            .apply_presets(&session.presets_dir, MissingModPolicy::Skip),
+    )
+}
+
+/// Save the mod configuration to db.json. Returns 0 on success and -1 on error, e.g. while the
+/// BeamMM CLI is running.
+///
+/// # Safety
+///
+/// `session` must be an open session.
+#[no_mangle]
+pub unsafe extern "C" fn bmm_save(session: *const BmmSession) -> c_int {
+    let session = &*session;
+    status(
+        lock(session.beammm_dir.as_deref())
+            .and_then(|_lock| session.mod_config.save_to_path(&session.mods_dir)),
+    )
+}
+
+/// Get the message of the last error on the calling thread, or null if there was none.
+///
+/// The string is owned by BeamMM and stays valid until the next error on the same thread.
+#[no_mangle]
+pub extern "C" fn bmm_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Free a string returned by BeamMM.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by BeamMM that hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn bmm_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    unsafe fn take_string(s: *mut c_char) -> String {
+        let string = CStr::from_ptr(s).to_str().unwrap().to_owned();
+        bmm_string_free(s);
+        string
+    }
+
+    #[test]
+    fn session() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mods_dir = tmp.path().join("mods");
+        let presets_dir = tmp.path().join("presets");
+        fs::create_dir(&mods_dir).unwrap();
+        fs::create_dir(&presets_dir).unwrap();
+        fs::write(
+            mods_dir.join("db.json"),
+            r#"{"mods":{"mod1":{"active":true},"mod2":{"active":false}}}"#,
+        )
+        .unwrap();
+        Preset::new("preset".into(), vec!["mod2".into()])
+            .unwrap()
+            .save_to_path(&presets_dir)
+            .unwrap();
+
+        unsafe {
+            let session = bmm_open(
+                c(mods_dir.to_str().unwrap()).as_ptr(),
+                c(presets_dir.to_str().unwrap()).as_ptr(),
+            );
+            assert!(!session.is_null());
+
+            assert_eq!(
+                take_string(bmm_list_mods(session)),
+                r#"[{"active":true,"name":"mod1"},{"active":false,"name":"mod2"}]"#
+            );
+            assert_eq!(bmm_set_mod_active(session, c("mod1").as_ptr(), false), 0);
+            assert_eq!(bmm_is_mod_active(session, c("mod1").as_ptr()), 0);
+            assert_eq!(bmm_is_mod_active(session, c("missing").as_ptr()), -1);
+
+            assert_eq!(
+                bmm_set_preset_enabled(session, c("preset").as_ptr(), true),
+                0
+            );
+            assert_eq!(bmm_apply_presets(session), 0);
+            assert_eq!(bmm_is_mod_active(session, c("mod2").as_ptr()), 1);
+            assert!(take_string(bmm_list_presets(session)).contains(r#""enabled":true"#));
+
+            assert_eq!(bmm_set_mod_active(session, c("missing").as_ptr(), true), -1);
+            let error = CStr::from_ptr(bmm_last_error()).to_str().unwrap();
+            assert!(error.contains("missing"));
+
+            assert_eq!(bmm_save(session), 0);
+            bmm_close(session);
+        }
+
+        let mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+        assert!(!mod_cfg.is_mod_active("mod1").unwrap());
+        assert!(mod_cfg.is_mod_active("mod2").unwrap());
+    }
+
+    #[test]
+    fn saving_while_locked() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mods_dir = tmp.path().join("mods");
+        let presets_dir = tmp.path().join("presets");
+        fs::create_dir(&mods_dir).unwrap();
+        fs::create_dir(&presets_dir).unwrap();
+        fs::write(
+            mods_dir.join("db.json"),
+            r#"{"mods":{"mod1":{"active":true}}}"#,
+        )
+        .unwrap();
+        Preset::new("preset".into(), vec!["mod1".into()])
+            .unwrap()
+            .save_to_path(&presets_dir)
+            .unwrap();
+        let session = Box::into_raw(Box::new(
+            open(&mods_dir, &presets_dir, Some(tmp.path())).unwrap(),
+        ));
+
+        let lock = Lock::acquire(tmp.path()).unwrap();
+        unsafe {
+            assert_eq!(bmm_set_mod_active(session, c("mod1").as_ptr(), false), 0);
+            assert_eq!(bmm_save(session), -1);
+            let error = CStr::from_ptr(bmm_last_error()).to_str().unwrap();
+            assert!(error.contains("beammm.lock"));
+            assert_eq!(
+                bmm_set_preset_enabled(session, c("preset").as_ptr(), true),
+                -1
+            );
+
+            drop(lock);
+            assert_eq!(bmm_save(session), 0);
+            bmm_close(session);
+        }
+        let mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+        assert!(!mod_cfg.is_mod_active("mod1").unwrap());
+    }
+
+    #[test]
+    fn open_missing_dir() {
+        unsafe {
+            let session = bmm_open(c("/missing/mods").as_ptr(), c("/missing/presets").as_ptr());
+            assert!(session.is_null());
+            assert!(!bmm_last_error().is_null());
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use beammm::{game, path};
+use pyo3::{create_exception, exceptions::PyException, prelude::*};
+use std::path::PathBuf;
+
+create_exception!(beammm, BeamMMError, PyException, "An error from BeamMM.");
+
+/// Turn a BeamMM result into a Python result.
+fn py<T>(result: beammm::Result<T>) -> PyResult<T> {
+    result.map_err(|e| BeamMMError::new_err(e.to_string()))
+}
+
+/// BeamNG.drive's mod configuration, stored in the mods directory's db.json.
+#[pyclass]
+struct ModCfg(game::ModCfg);
+
+#[pymethods]
+impl ModCfg {
+    /// Load the mod configuration from a mods directory.
+    #[staticmethod]
+    fn load(mods_dir: PathBuf) -> PyResult<Self> {
+        py(game::ModCfg::load_from_path(&mods_dir)).map(Self)
+    }
+
+    /// Save the mod configuration to a mods directory. Fails while the BeamMM CLI is running.
+    fn save(&self, mods_dir: PathBuf) -> PyResult<()> {
+        let _lock = py(crate::lock(crate::find_beammm_dir().as_deref()))?;
+        py(self.0.save_to_path(&mods_dir))
+    }
+
+    /// Get the names of the installed mods, sorted.
+    fn mods(&self) -> Vec<String> {
+        let mut mods: Vec<String> = self.0.get_mods().cloned().collect();
+        mods.sort();
+        mods
+    }
+
+    /// Get whether a mod is active, or None if it isn't installed.
+    fn is_mod_active(&self, mod_name: &str) -> Option<bool> {
+        self.0.is_mod_active(mod_name)
+    }
+
+    /// Enable or disable a mod.
+    fn set_mod_active(&mut self, mod_name: &str, active: bool) -> PyResult<()> {
+        py(self.0.set_mod_active(mod_name, active))
+    }
+
+    /// Enable or disable several mods. No mods are changed if any of them can't be.
+    fn set_mods_active(&mut self, mod_names: Vec<String>, active: bool) -> PyResult<()> {
+        py(self.0.set_mods_active(&mod_names, active))
+    }
+
+    /// Enable or disable every mod.
+    fn set_all_mods_active(&mut self, active: bool) -> PyResult<()> {
+        py(self.0.set_all_mods_active(active))
+    }
+
+    /// Apply the enabled presets. `policy` is how presets with missing mods are handled: "fail",
+    /// "skip", or "prune".
+    #[pyo3(signature = (presets_dir, policy = "fail"))]
+    fn apply_presets(&mut self, presets_dir: PathBuf, policy: &str) -> PyResult<()> {
+        let policy = py(policy.parse())?;
+        py(self.0.apply_presets(&presets_dir, policy))
+    }
+}
+
+/// A named group of mods that can be enabled and disabled together.
+#[pyclass]
+struct Preset(beammm::Preset);
+
+#[pymethods]
+impl Preset {
+    /// Create a new preset. It isn't saved until `save` is called.
+    #[new]
+    fn new(name: String, mods: Vec<String>) -> PyResult<Self> {
+        py(beammm::Preset::new(name, mods)).map(Self)
+    }
+
+    /// Load a preset from the presets directory.
+    #[staticmethod]
+    fn load(name: &str, presets_dir: PathBuf) -> PyResult<Self> {
+        py(beammm::Preset::load_from_path(name, &presets_dir)).map(Self)
+    }
+
+    /// Get the names of the presets in the presets directory, sorted.
+    #[staticmethod]
+    fn list(presets_dir: PathBuf) -> PyResult<Vec<String>> {
+        let mut presets: Vec<String> = py(beammm::Preset::list(&presets_dir))?.collect();
+        presets.sort();
+        Ok(presets)
+    }
+
+    /// Delete a preset from the presets directory, sending it to the system trash unless
+    /// `permanent` is set. Fails while the BeamMM CLI is running.
+    #[staticmethod]
+    #[pyo3(signature = (name, presets_dir, permanent = false))]
+    fn delete(name: &str, presets_dir: PathBuf, permanent: bool) -> PyResult<()> {
+        let _lock = py(crate::lock(crate::find_beammm_dir().as_deref()))?;
+        py(beammm::Preset::delete(name, &presets_dir, permanent))
+    }
+
+    /// Save the preset to the presets directory. Fails while the BeamMM CLI is running.
+    fn save(&self, presets_dir: PathBuf) -> PyResult<()> {
+        let _lock = py(crate::lock(crate::find_beammm_dir().as_deref()))?;
+        py(self.0.save_to_path(&presets_dir))
+    }
+
+    /// The name of the preset.
+    #[getter]
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    /// The mods in the preset.
+    #[getter]
+    fn mods(&self) -> Vec<String> {
+        self.0.get_mods().clone()
+    }
+
+    /// Whether the preset is enabled.
+    #[getter]
+    fn enabled(&self) -> bool {
+        self.0.is_enabled()
+    }
+
+    /// Enable the preset. Save it and apply presets to enable its mods.
+    fn enable(&mut self) {
+        self.0.enable()
+    }
+
+    /// Disable the preset and its mods. Save it and apply presets so mods in other enabled
+    /// presets are enabled again.
+    fn disable(&mut self, mod_cfg: &mut ModCfg) -> PyResult<()> {
+        py(self.0.disable(&mut mod_cfg.0))
+    }
+
+    /// Add mods to the preset.
+    fn add_mods(&mut self, mods: Vec<String>) {
+        self.0.add_mods(&mods)
+    }
+
+    /// Remove mods from the preset.
+    fn remove_mods(&mut self, mods: Vec<String>) {
+        self.0.remove_mods(&mods)
+    }
+
+    /// Replace the preset's mods.
+    fn set_mods(&mut self, mods: Vec<String>) {
+        self.0.set_mods(mods)
+    }
+
+    /// Remove every mod from the preset.
+    fn clear_mods(&mut self) {
+        self.0.clear_mods()
+    }
+}
+
+/// Get the BeamNG.drive data directory.
+#[pyfunction]
+fn beamng_dir() -> PyResult<PathBuf> {
+    py(path::beamng_dir_default())
+}
+
+/// Get the current game version, e.g. "0.32", from the game's data directory.
+#[pyfunction]
+fn game_version(data_dir: PathBuf) -> PyResult<String> {
+    py(beammm::game_version(&data_dir)).map(|v| v.to_string())
+}
+
+/// Get the mods directory for a game version in the game's data directory.
+#[pyfunction]
+fn mods_dir(data_dir: PathBuf, version: &str) -> PyResult<PathBuf> {
+    let version = py(version.parse())?;
+    py(path::mods_dir(&data_dir, &version))
+}
+
+/// Get the BeamMM directory, creating it if it doesn't exist.
+#[pyfunction]
+fn beammm_dir() -> PyResult<PathBuf> {
+    py(path::beammm_dir())
+}
+
+/// Get the presets directory in the BeamMM directory, creating it if it doesn't exist.
+#[pyfunction]
+fn presets_dir(beammm_dir: PathBuf) -> PyResult<PathBuf> {
+    py(path::presets_dir(&beammm_dir))
+}
+
+/// Manage BeamNG.drive mods and presets.
+#[pymodule]
+#[pyo3(name = "beammm")]
+fn python_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add("BeamMMError", module.py().get_type::<BeamMMError>())?;
+    module.add_class::<ModCfg>()?;
+    module.add_class::<Preset>()?;
+    module.add_function(wrap_pyfunction!(beamng_dir, module)?)?;
+    module.add_function(wrap_pyfunction!(game_version, module)?)?;
+    module.add_function(wrap_pyfunction!(mods_dir, module)?)?;
+    module.add_function(wrap_pyfunction!(beammm_dir, module)?)?;
+    module.add_function(wrap_pyfunction!(presets_dir, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,813 @@
+use crate::{
+    game::{lenient_json, ModCfg, ModRoot},
+    path,
+    Error::*,
+    Result,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ffi::OsStr,
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+/// Check that a mod archive can be opened.
+///
+/// The archive's central directory is read and the header of every file in it is checked, which
+/// catches truncated downloads and archives that aren't zips at all. File contents aren't
+/// decompressed.
+///
+/// # Arguments
+///
+/// `archive`: The path to the mod archive.
+///
+/// # Errors
+///
+/// * `CorruptArchive`: If the archive can't be read as a zip.
+/// * `std::io::Error`: If the archive can't be opened.
+pub fn check(archive: impl AsRef<Path>) -> Result<()> {
+    let archive = archive.as_ref();
+    let corrupt = |e: zip::result::ZipError| CorruptArchive {
+        archive: archive.to_owned(),
+        reason: e.to_string(),
+    };
+    let file = File::open(archive)?;
+    let mut zip = zip::ZipArchive::new(BufReader::new(file)).map_err(corrupt)?;
+    for i in 0..zip.len() {
+        zip.by_index_raw(i).map_err(corrupt)?;
+    }
+    Ok(())
+}
+
+/// Credits read from the info.json files inside a mod archive.
+#[derive(Debug, Default, PartialEq)]
+pub struct ArchiveInfo {
+    /// The mod's title.
+    pub title: Option<String>,
+    /// Who made the mod.
+    pub author: Option<String>,
+    /// The license the mod is released under.
+    pub license: Option<String>,
+}
+
+/// Read the title, author, and license of a mod from the info.json files inside its archive.
+///
+/// Mods from the repository carry a `mod_info/*/info.json`, which is preferred. Otherwise the
+/// info.json files of the mod's vehicles and levels are used. Each field is taken from the first
+/// info.json that has it. info.json files that can't be parsed are skipped.
+///
+/// # Arguments
+///
+/// `archive`: The path to the mod archive.
+///
+/// # Errors
+///
+/// * `CorruptArchive`: If the archive can't be read as a zip.
+/// * `std::io::Error`: If the archive can't be opened.
+pub fn read_info(archive: impl AsRef<Path>) -> Result<ArchiveInfo> {
+    let archive = archive.as_ref();
+    let corrupt = |e: zip::result::ZipError| CorruptArchive {
+        archive: archive.to_owned(),
+        reason: e.to_string(),
+    };
+    let file = File::open(archive)?;
+    let mut zip = zip::ZipArchive::new(BufReader::new(file)).map_err(corrupt)?;
+
+    let mut info_files: Vec<String> = zip
+        .file_names()
+        .filter(|name| name.to_lowercase().ends_with("info.json"))
+        .map(String::from)
+        .collect();
+    // Sorting by whether the file is outside mod_info puts mod_info first.
+    info_files.sort_by_key(|name| (!name.starts_with("mod_info/"), name.clone()));
+
+    let mut info = ArchiveInfo::default();
+    for name in info_files {
+        let mut json = vec![];
+        zip.by_name(&name)
+            .map_err(corrupt)?
+            .read_to_end(&mut json)?;
+        let json = json.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&json);
+        let Ok(serde_json::Value::Object(fields)) = serde_json::from_slice(&lenient_json(json))
+        else {
+            continue;
+        };
+        let field = |keys: &[&str]| {
+            keys.iter()
+                .filter_map(|key| fields.get(*key)?.as_str())
+                .map(str::trim)
+                .find(|value| !value.is_empty())
+                .map(String::from)
+        };
+        info.title = info.title.or_else(|| field(&["title", "Name", "name"]));
+        info.author = info
+            .author
+            .or_else(|| field(&["Author", "author", "authors", "username"]));
+        info.license = info.license.or_else(|| field(&["License", "license"]));
+    }
+    Ok(info)
+}
+
+/// Folders the game loads from the top level of a mod archive, paired with what a mod with the
+/// folder holds.
+const CONTENT_FOLDERS: [(&str, &str); 8] = [
+    ("vehicles", "vehicle"),
+    ("levels", "map"),
+    ("gameplay", "scenarios or missions"),
+    ("ui", "UI apps"),
+    ("lua", "scripts"),
+    ("scripts", "scripts"),
+    ("art", "art assets"),
+    ("mod_info", "repository info"),
+];
+
+/// A file inside a mod archive, listed by `peek`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveFile {
+    /// The path of the file inside the archive, with `/` separators.
+    pub path: String,
+    /// The uncompressed size of the file in bytes.
+    pub size: u64,
+}
+
+/// A file or folder at the top level of a mod archive, found by `peek`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopLevelEntry {
+    /// The name of the file or folder. Folders end in `/`.
+    pub name: String,
+    /// How many files it holds, or 1 for a file.
+    pub files: usize,
+    /// The uncompressed size of the files it holds in bytes.
+    pub size: u64,
+}
+
+/// What a mod archive holds, found by `peek`.
+#[derive(Debug, Default, PartialEq)]
+pub struct ArchiveContents {
+    /// Every file in the archive, sorted by path. Folders aren't listed on their own.
+    pub files: Vec<ArchiveFile>,
+    /// The files and folders at the top level of the archive, sorted by name.
+    pub top_level: Vec<TopLevelEntry>,
+    /// What the mod seems to hold going by the folders the game loads, e.g. `vehicle` or `map`.
+    pub holds: Vec<&'static str>,
+    /// The folder everything is wrapped in when the mod was zipped one level too deep, e.g.
+    /// `MyCar/` holding `vehicles/`, so the game won't find anything in it.
+    pub wrapped_in: Option<String>,
+    /// Zips inside the archive, which the game doesn't open.
+    pub nested_archives: Vec<String>,
+}
+
+impl ArchiveContents {
+    /// The total uncompressed size of the files in the archive in bytes.
+    pub fn size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+}
+
+/// List the files inside a mod archive and work out what it holds, to tell whether it's a
+/// vehicle, a map, or zipped wrongly before enabling it. File contents aren't decompressed.
+///
+/// # Arguments
+///
+/// `archive`: The path to the mod archive.
+///
+/// # Errors
+///
+/// * `CorruptArchive`: If the archive can't be read as a zip.
+/// * `std::io::Error`: If the archive can't be opened.
+///
+/// # Examples
+/// ```rust
+/// use beammm::archive;
+/// use std::io::Write;
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// let path = temp_dir.path().join("car.zip");
+/// let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+/// zip.start_file("MyCar/vehicles/car/car.jbeam", zip::write::SimpleFileOptions::default())
+///     .unwrap();
+/// zip.write_all(b"{}").unwrap();
+/// zip.finish().unwrap();
+///
+/// let contents = archive::peek(&path).unwrap();
+/// assert_eq!(contents.files[0].size, 2);
+/// assert_eq!(contents.wrapped_in.as_deref(), Some("MyCar/"));
+/// ```
+pub fn peek(archive: impl AsRef<Path>) -> Result<ArchiveContents> {
+    let archive = archive.as_ref();
+    let corrupt = |e: zip::result::ZipError| CorruptArchive {
+        archive: archive.to_owned(),
+        reason: e.to_string(),
+    };
+    let file = File::open(archive)?;
+    let mut zip = zip::ZipArchive::new(BufReader::new(file)).map_err(corrupt)?;
+
+    let mut contents = ArchiveContents::default();
+    let mut top_level: BTreeMap<String, TopLevelEntry> = BTreeMap::new();
+    for i in 0..zip.len() {
+        let entry = zip.by_index_raw(i).map_err(corrupt)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let path = entry.name().replace('\\', "/");
+        let name = match path.split_once('/') {
+            Some((folder, _)) => format!("{}/", folder),
+            None => path.clone(),
+        };
+        let top = top_level.entry(name.clone()).or_insert(TopLevelEntry {
+            name,
+            files: 0,
+            size: 0,
+        });
+        top.files += 1;
+        top.size += entry.size();
+        if path.to_lowercase().ends_with(".zip") {
+            contents.nested_archives.push(path.clone());
+        }
+        contents.files.push(ArchiveFile {
+            path,
+            size: entry.size(),
+        });
+    }
+    contents.files.sort_by(|a, b| a.path.cmp(&b.path));
+    contents.nested_archives.sort();
+
+    let holds = |folders: &mut dyn Iterator<Item = &str>| {
+        let folders: Vec<String> = folders.map(str::to_lowercase).collect();
+        let mut holds: Vec<&'static str> = CONTENT_FOLDERS
+            .iter()
+            .filter(|(folder, _)| folders.iter().any(|f| f == folder))
+            .map(|(_, holds)| *holds)
+            .collect();
+        holds.dedup();
+        holds
+    };
+    contents.holds = holds(&mut top_level.keys().filter_map(|name| name.strip_suffix('/')));
+    // A single folder holding the folders the game loads means the mod was zipped one level too
+    // deep, like zipping the folder `MyCar` instead of its contents.
+    if let [wrapper] = top_level.keys().collect::<Vec<_>>().as_slice() {
+        if contents.holds.is_empty() && wrapper.ends_with('/') {
+            let nested = holds(&mut contents.files.iter().filter_map(|file| {
+                file.path
+                    .strip_prefix(wrapper.as_str())?
+                    .split_once('/')
+                    .map(|(f, _)| f)
+            }));
+            if !nested.is_empty() {
+                contents.wrapped_in = Some(wrapper.to_string());
+                contents.holds = nested;
+            }
+        }
+    }
+    contents.top_level = top_level.into_values().collect();
+    Ok(contents)
+}
+
+/// Find every corrupt mod archive in the mods directory and its subdirectories.
+///
+/// # Arguments
+///
+/// `mods_dir`: The mods directory of the current game version.
+///
+/// # Returns
+///
+/// The corrupt archives along with why they are corrupt, sorted by path.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading the mods directory or opening an archive.
+pub fn find_corrupt(mods_dir: impl AsRef<Path>) -> Result<Vec<(PathBuf, String)>> {
+    let mods_dir = mods_dir.as_ref();
+    let mut corrupt = vec![];
+    for archive in archives(mods_dir)? {
+        match check(&archive) {
+            Ok(()) => (),
+            Err(CorruptArchive { archive, reason }) => corrupt.push((archive, reason)),
+            Err(e) => return Err(e),
+        }
+    }
+    corrupt.sort();
+    Ok(corrupt)
+}
+
+/// Hash the contents of a mod archive.
+///
+/// # Arguments
+///
+/// `archive`: The path to the mod archive.
+///
+/// # Returns
+///
+/// The SHA-256 hash of the archive, in lowercase hex.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading the archive.
+pub fn hash(archive: impl AsRef<Path>) -> Result<String> {
+    let archive = archive.as_ref();
+    let mut hasher = Sha256::new();
+    io::copy(&mut BufReader::new(File::open(archive)?), &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Find installed mods whose archives are byte-identical, which usually happens when a mod is
+/// downloaded again under a different filename.
+///
+/// Only archives with the same size are hashed. Mods whose archive is missing are ignored.
+///
+/// # Arguments
+///
+/// `mod_config`: The ModCfg of the installed mods.
+/// `mods_dir`: The mods directory of the current game version.
+///
+/// # Returns
+///
+/// Each group of duplicate mods, sorted. The first mod of a group is the one worth keeping:
+/// repository mods come first since they can be updated, then the rest by name.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading an archive.
+pub fn find_duplicates(
+    mod_config: &ModCfg,
+    mods_dir: impl AsRef<Path>,
+) -> Result<Vec<Vec<String>>> {
+    let mods_dir = mods_dir.as_ref();
+    let mut by_size: HashMap<u64, Vec<String>> = HashMap::new();
+    for mod_name in mod_config.get_mods() {
+        let Some(archive) = mod_config.archive_path(mod_name, mods_dir) else {
+            continue;
+        };
+        if let Ok(metadata) = fs::metadata(&archive) {
+            by_size
+                .entry(metadata.len())
+                .or_default()
+                .push(mod_name.clone());
+        }
+    }
+
+    let mut by_hash: HashMap<String, Vec<String>> = HashMap::new();
+    for mod_names in by_size.into_values().filter(|m| m.len() > 1) {
+        for mod_name in mod_names {
+            // The archive was just found above.
+            let archive = mod_config.archive_path(&mod_name, mods_dir).unwrap();
+            by_hash.entry(hash(&archive)?).or_default().push(mod_name);
+        }
+    }
+
+    let mut duplicates: Vec<Vec<String>> = by_hash
+        .into_values()
+        .filter(|m| m.len() > 1)
+        .map(|mut mod_names| {
+            mod_names.sort_by_key(|m| (mod_config.repo_version(m).is_none(), m.clone()));
+            mod_names
+        })
+        .collect();
+    duplicates.sort();
+    Ok(duplicates)
+}
+
+/// Find mods installed both from the repository, into `mods/repo/`, and by hand, into `mods/`.
+/// The game loads both copies, which makes them conflict.
+///
+/// Two archives are the same mod if they carry the same repository ID in `mod_info/<ID>/`, as
+/// archives downloaded from the repository website do, or have the same filename, ignoring case.
+/// Archives that can't be read are compared by filename alone. Mods whose archive is missing are
+/// ignored.
+///
+/// # Arguments
+///
+/// `mod_config`: The ModCfg of the installed mods.
+/// `mods_dir`: The mods directory of the current game version.
+///
+/// # Returns
+///
+/// Pairs of the repository mod and the manual mod that is the same mod, sorted.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue opening an archive.
+pub fn find_repo_duplicates(
+    mod_config: &ModCfg,
+    mods_dir: impl AsRef<Path>,
+) -> Result<Vec<(String, String)>> {
+    let mods_dir = mods_dir.as_ref();
+    let repo_dir = mods_dir.join("repo");
+    let mut repo_mods: HashMap<String, Vec<&str>> = HashMap::new();
+    let mut manual_mods = vec![];
+    for mod_name in mod_config.get_mods() {
+        let Some(archive) = mod_config.archive_path(mod_name, mods_dir) else {
+            continue;
+        };
+        if !archive.try_exists()? {
+            continue;
+        }
+        let keys = identity_keys(&archive)?;
+        if archive.starts_with(&repo_dir) {
+            for key in keys {
+                repo_mods.entry(key).or_default().push(mod_name);
+            }
+        } else {
+            manual_mods.push((mod_name, keys));
+        }
+    }
+
+    let mut duplicates = BTreeSet::new();
+    for (manual_mod, keys) in manual_mods {
+        for key in keys {
+            for repo_mod in repo_mods.get(&key).into_iter().flatten() {
+                duplicates.insert((repo_mod.to_string(), manual_mod.clone()));
+            }
+        }
+    }
+    Ok(duplicates.into_iter().collect())
+}
+
+/// Get what identifies the mod in an archive: its repository ID, if it has one, and its filename.
+fn identity_keys(archive: &Path) -> Result<Vec<String>> {
+    let mut keys = vec![];
+    if let Some(stem) = archive.file_stem() {
+        keys.push(format!("file:{}", stem.to_string_lossy().to_lowercase()));
+    }
+    let file = File::open(archive)?;
+    if let Ok(zip) = zip::ZipArchive::new(BufReader::new(file)) {
+        let id = zip
+            .file_names()
+            .filter_map(|name| name.strip_prefix("mod_info/")?.split('/').next())
+            .find(|id| !id.is_empty());
+        if let Some(id) = id {
+            keys.push(format!("id:{}", id));
+        }
+    }
+    Ok(keys)
+}
+
+/// Turn a mod archive's filename, without its extension, into the name it gets with
+/// `--normalize-names`: lowercase words joined by underscores, without version numbers or copy
+/// markers like `(1)`.
+///
+/// A word is a version number if it is digits separated by dots, like `1.2`, optionally
+/// starting with `v`, like `v2`. Names that would end up empty are only lowercased.
+///
+/// # Arguments
+///
+/// `name`: The filename without its extension.
+///
+/// # Examples
+/// ```rust
+/// use beammm::archive::normalized_name;
+///
+/// assert_eq!(normalized_name("Cool Car v1.2"), "cool_car");
+/// assert_eq!(normalized_name("my-mod_1.0.3 (1)"), "my_mod");
+/// assert_eq!(normalized_name("pickup 2"), "pickup_2");
+/// ```
+pub fn normalized_name(name: &str) -> String {
+    let is_version = |word: &str| {
+        let (number, prefixed) = match word.strip_prefix(['v', 'V']) {
+            Some(number) => (number, true),
+            None => (word, false),
+        };
+        !number.is_empty()
+            && number.starts_with(|c: char| c.is_ascii_digit())
+            && number.chars().all(|c| c.is_ascii_digit() || c == '.')
+            && (prefixed || number.contains('.'))
+    };
+    let is_copy_marker = |word: &str| {
+        word.strip_prefix('(')
+            .and_then(|w| w.strip_suffix(')'))
+            .is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()))
+    };
+
+    let words: Vec<String> = name
+        .split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+        .filter(|w| !w.is_empty() && !is_version(w) && !is_copy_marker(w))
+        .map(|w| {
+            w.chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .collect();
+    let normalized = words.join("_");
+    let normalized: Vec<&str> = normalized.split('_').filter(|w| !w.is_empty()).collect();
+    if normalized.is_empty() {
+        name.to_lowercase()
+    } else {
+        normalized.join("_")
+    }
+}
+
+/// Move a mod archive into the quarantine directory so the game no longer loads it.
+///
+/// If the quarantine already holds an archive with the same name, a number is added to the new
+/// archive's name so nothing is overwritten.
+///
+/// # Arguments
+///
+/// `archive`: The path to the mod archive.
+/// `quarantine_dir`: The directory quarantined archives are kept in.
+///
+/// # Returns
+///
+/// The new path of the archive.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue moving the archive.
+pub fn quarantine(archive: impl AsRef<Path>, quarantine_dir: impl AsRef<Path>) -> Result<PathBuf> {
+    let archive = archive.as_ref();
+    let quarantine_dir = quarantine_dir.as_ref();
+    let stem = archive
+        .file_stem()
+        .and_then(OsStr::to_str)
+        .unwrap_or("archive");
+    let extension = archive.extension().and_then(OsStr::to_str).unwrap_or("zip");
+
+    let mut target = quarantine_dir.join(format!("{}.{}", stem, extension));
+    let mut n = 1;
+    while target.try_exists()? {
+        target = quarantine_dir.join(format!("{} ({}).{}", stem, n, extension));
+        n += 1;
+    }
+
+    fs::create_dir_all(quarantine_dir)?;
+    move_archive(archive, &target)?;
+    Ok(target)
+}
+
+/// Move a mod archive, replacing any file already at the target.
+///
+/// # Arguments
+///
+/// `archive`: The path to the mod archive.
+/// `target`: The path to move the archive to.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue moving the archive.
+pub fn move_archive(archive: impl AsRef<Path>, target: impl AsRef<Path>) -> Result<()> {
+    let archive = archive.as_ref();
+    let target = target.as_ref();
+    // Renaming fails across drives, in which case the archive is copied instead.
+    if fs::rename(archive, target).is_err() {
+        fs::copy(archive, target)?;
+        fs::remove_file(archive)?;
+    }
+    Ok(())
+}
+
+/// Get every zip archive in the mods directory and `mods/repo`, including their subdirectories.
+/// Unpacked mods are folders, so any archives inside them are part of a mod and left out.
+fn archives(mods_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut found = vec![];
+    for root in [ModRoot::Mods, ModRoot::Repo] {
+        found.extend(
+            path::root_files(mods_dir, root)?
+                .into_iter()
+                .filter(|f| f.extension().is_some_and(|e| e.eq_ignore_ascii_case("zip"))),
+        );
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Write a small valid zip archive.
+    fn write_zip(path: &Path) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        zip.start_file("info.json", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"{}").unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn reading_archive_info() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = tmp.path().join("pickup_skins.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("vehicles/pickup/info.json", options)
+            .unwrap();
+        zip.write_all(
+            b"{\"Name\": \"Pickup\", \"Author\": \"BeamNG\", \"License\": \"CC BY-NC\",}",
+        )
+        .unwrap();
+        zip.start_file("mod_info/ABC123/info.json", options)
+            .unwrap();
+        zip.write_all(b"{\"title\": \"Pickup Skins\", \"username\": \"skinner\"}")
+            .unwrap();
+        zip.start_file("levels/broken/info.json", options).unwrap();
+        zip.write_all(b"not json").unwrap();
+        zip.finish().unwrap();
+
+        let info = read_info(&archive).unwrap();
+        assert_eq!(
+            info,
+            ArchiveInfo {
+                title: Some("Pickup Skins".into()),
+                author: Some("skinner".into()),
+                license: Some("CC BY-NC".into()),
+            }
+        );
+
+        write_zip(&archive);
+        assert_eq!(read_info(&archive).unwrap(), ArchiveInfo::default());
+    }
+
+    #[test]
+    fn peeking_into_archives() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = tmp.path().join("map.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, contents) in [
+            ("levels/canyon/info.json", "{}"),
+            ("levels/canyon/main.level.json", "[]"),
+            ("vehicles/rally/rally.jbeam", "{}"),
+            ("readme.txt", "Enjoy the canyon!"),
+            ("extras/old_version.zip", ""),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.add_directory("empty/", options).unwrap();
+        zip.finish().unwrap();
+
+        let contents = peek(&archive).unwrap();
+        assert_eq!(contents.files.len(), 5);
+        assert_eq!(contents.files[0].path, "extras/old_version.zip");
+        assert_eq!(contents.size(), 23);
+        let top_level: Vec<_> = contents
+            .top_level
+            .iter()
+            .map(|e| (e.name.as_str(), e.files, e.size))
+            .collect();
+        assert_eq!(
+            top_level,
+            vec![
+                ("extras/", 1, 0),
+                ("levels/", 2, 4),
+                ("readme.txt", 1, 17),
+                ("vehicles/", 1, 2)
+            ]
+        );
+        assert_eq!(contents.holds, vec!["vehicle", "map"]);
+        assert_eq!(contents.wrapped_in, None);
+        assert_eq!(contents.nested_archives, vec!["extras/old_version.zip"]);
+
+        // Mods zipped one level too deep are found through their wrapping folder.
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        zip.start_file("Canyon Map/levels/canyon/info.json", options)
+            .unwrap();
+        zip.finish().unwrap();
+        let contents = peek(&archive).unwrap();
+        assert_eq!(contents.wrapped_in.as_deref(), Some("Canyon Map/"));
+        assert_eq!(contents.holds, vec!["map"]);
+
+        fs::write(&archive, "not a zip").unwrap();
+        assert!(matches!(peek(&archive), Err(CorruptArchive { .. })));
+    }
+
+    #[test]
+    fn finding_corrupt_archives() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mods_dir = tmp.path();
+        fs::create_dir(mods_dir.join("repo")).unwrap();
+
+        write_zip(&mods_dir.join("good.zip"));
+        write_zip(&mods_dir.join("repo").join("truncated.zip"));
+        let truncated = fs::read(mods_dir.join("repo").join("truncated.zip")).unwrap();
+        fs::write(
+            mods_dir.join("repo").join("truncated.zip"),
+            &truncated[..truncated.len() / 2],
+        )
+        .unwrap();
+        fs::write(mods_dir.join("not_a_zip.zip"), "hello").unwrap();
+        fs::write(mods_dir.join("db.json"), "{}").unwrap();
+
+        let corrupt: Vec<PathBuf> = find_corrupt(mods_dir)
+            .unwrap()
+            .into_iter()
+            .map(|(archive, _)| archive)
+            .collect();
+        assert_eq!(
+            corrupt,
+            vec![
+                mods_dir.join("not_a_zip.zip"),
+                mods_dir.join("repo").join("truncated.zip")
+            ]
+        );
+    }
+
+    #[test]
+    fn quarantining_archives() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mods_dir = tmp.path().join("mods");
+        let quarantine_dir = tmp.path().join("quarantine");
+        fs::create_dir(&mods_dir).unwrap();
+
+        fs::write(mods_dir.join("broken.zip"), "first").unwrap();
+        let first = quarantine(mods_dir.join("broken.zip"), &quarantine_dir).unwrap();
+        fs::write(mods_dir.join("broken.zip"), "second").unwrap();
+        let second = quarantine(mods_dir.join("broken.zip"), &quarantine_dir).unwrap();
+
+        assert!(!mods_dir.join("broken.zip").exists());
+        assert_eq!(first, quarantine_dir.join("broken.zip"));
+        assert_eq!(second, quarantine_dir.join("broken (1).zip"));
+        assert_eq!(fs::read_to_string(second).unwrap(), "second");
+    }
+
+    #[test]
+    fn finding_duplicates() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mods_dir = tmp.path();
+        fs::write(
+            mods_dir.join("db.json"),
+            serde_json::json!({"mods": {
+                "car": {"active": true, "filename": "car.zip"},
+                "car_1": {"active": false, "filename": "car_1.zip"},
+                "car_repo": {
+                    "active": false,
+                    "fullpath": "/mods/repo/car_repo.zip",
+                    "modData": {"resource_id": 1, "current_version_id": 2},
+                },
+                "same_size": {"active": true, "filename": "same_size.zip"},
+                "missing": {"active": true, "filename": "missing.zip"},
+            }})
+            .to_string(),
+        )
+        .unwrap();
+        fs::create_dir(mods_dir.join("repo")).unwrap();
+        fs::write(mods_dir.join("car.zip"), "car").unwrap();
+        fs::write(mods_dir.join("car_1.zip"), "car").unwrap();
+        fs::write(mods_dir.join("repo").join("car_repo.zip"), "car").unwrap();
+        fs::write(mods_dir.join("same_size.zip"), "bus").unwrap();
+        let mod_cfg = ModCfg::load_from_path(mods_dir).unwrap();
+
+        let duplicates = find_duplicates(&mod_cfg, mods_dir).unwrap();
+
+        assert_eq!(duplicates, vec![vec!["car_repo", "car", "car_1"]]);
+    }
+
+    #[test]
+    fn finding_repo_duplicates() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mods_dir = tmp.path();
+        fs::write(
+            mods_dir.join("db.json"),
+            serde_json::json!({"mods": {
+                "car": {
+                    "active": true,
+                    "fullpath": "/mods/repo/car.zip",
+                    "modData": {"resource_id": 1, "current_version_id": 2},
+                },
+                "truck": {
+                    "active": true,
+                    "fullpath": "/mods/repo/truck.zip",
+                    "modData": {"resource_id": 3, "current_version_id": 4},
+                },
+                "car_manual": {"active": true, "filename": "CAR.zip"},
+                "truck_v2": {"active": false, "filename": "truck_v2.zip"},
+                "bus": {"active": true, "filename": "bus.zip"},
+            }})
+            .to_string(),
+        )
+        .unwrap();
+        fs::create_dir(mods_dir.join("repo")).unwrap();
+        let write_mod_info = |path: &Path, id: &str| {
+            let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+            zip.start_file(
+                format!("mod_info/{}/info.json", id),
+                zip::write::SimpleFileOptions::default(),
+            )
+            .unwrap();
+            zip.write_all(b"{}").unwrap();
+            zip.finish().unwrap();
+        };
+        write_zip(&mods_dir.join("repo").join("car.zip"));
+        write_mod_info(&mods_dir.join("repo").join("truck.zip"), "TRUCK1");
+        // Same filename as a repository mod, but not the same contents.
+        fs::write(mods_dir.join("CAR.zip"), "not a zip").unwrap();
+        // Same repository ID under a different filename.
+        write_mod_info(&mods_dir.join("truck_v2.zip"), "TRUCK1");
+        write_mod_info(&mods_dir.join("bus.zip"), "BUS1");
+        let mod_cfg = ModCfg::load_from_path(mods_dir).unwrap();
+
+        let duplicates = find_repo_duplicates(&mod_cfg, mods_dir).unwrap();
+
+        assert_eq!(
+            duplicates,
+            vec![
+                ("car".to_string(), "car_manual".to_string()),
+                ("truck".to_string(), "truck_v2".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,448 @@
+use crate::{game::ModCfg, Error::*, GameVersion, Preset, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, Write},
+    path::{Component, Path},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+/// The version of the backup layout written by this version of BeamMM.
+pub const FORMAT: u32 = 1;
+
+/// The name of the manifest inside a backup.
+const MANIFEST: &str = "manifest.json";
+
+/// The manifest describing what a backup contains.
+///
+/// A backup is a zip with the manifest, the game's `db.json`, the presets under `presets/`, and
+/// optionally the mod archives under `mods/`, laid out as they are in the mods directory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Manifest {
+    /// The version of the backup layout.
+    pub format: u32,
+    /// The version of BeamMM that made the backup.
+    pub beammm_version: String,
+    /// The game version the backup was made from, e.g. `0.32`.
+    pub game_version: String,
+    /// When the backup was made, in seconds since the Unix epoch.
+    pub created: u64,
+    /// The names of the backed up presets.
+    pub presets: Vec<String>,
+    /// The backed up mod archives, relative to the mods directory.
+    #[serde(default)]
+    pub archives: Vec<String>,
+}
+
+/// Back up the mod configuration and presets, and optionally the mod archives, to a single zip.
+///
+/// Mod archives are already compressed, so they are stored as they are. Mods whose archive can't
+/// be found are left out.
+///
+/// # Arguments
+///
+/// `out`: The path of the backup to create. An existing file is overwritten.
+/// `mod_config`: The ModCfg to back up.
+/// `mods_dir`: The mods directory of the current game version.
+/// `presets_dir`: Where preset config files are stored.
+/// `version`: The current game version.
+/// `include_archives`: Whether to back up the mod archives too.
+/// `now`: The current time.
+///
+/// # Returns
+///
+/// The manifest of the backup.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading the presets or archives, or writing the backup.
+/// Possible serde_json errors if there is an issue serializing the mod configuration.
+pub fn create(
+    out: impl AsRef<Path>,
+    mod_config: &ModCfg,
+    mods_dir: impl AsRef<Path>,
+    presets_dir: impl AsRef<Path>,
+    version: &GameVersion,
+    include_archives: bool,
+    now: SystemTime,
+) -> Result<Manifest> {
+    let out = out.as_ref();
+    let mods_dir = mods_dir.as_ref();
+    let presets_dir = presets_dir.as_ref();
+    let mut presets: Vec<String> = Preset::list(presets_dir)?.collect();
+    presets.sort();
+
+    let mut archives = vec![];
+    if include_archives {
+        for mod_name in mod_config.get_mods() {
+            let Some(archive) = mod_config.archive_path(mod_name, mods_dir) else {
+                continue;
+            };
+            let Ok(relative) = archive.strip_prefix(mods_dir) else {
+                continue;
+            };
+            if archive.is_file() {
+                let components: Vec<_> = relative.iter().map(|c| c.to_string_lossy()).collect();
+                archives.push(components.join("/"));
+            }
+        }
+        archives.sort();
+        archives.dedup();
+    }
+
+    let manifest = Manifest {
+        format: FORMAT,
+        beammm_version: env!("CARGO_PKG_VERSION").into(),
+        game_version: version.to_string(),
+        created: now
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_secs(),
+        presets,
+        archives,
+    };
+
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(out)?));
+    let options = SimpleFileOptions::default();
+
+    zip.start_file(MANIFEST, options).map_err(io::Error::from)?;
+    serde_json::to_writer_pretty(&mut zip, &manifest)?;
+    zip.start_file("db.json", options)
+        .map_err(io::Error::from)?;
+    mod_config.save(&mut zip)?;
+    for preset in &manifest.presets {
+        zip.start_file(format!("presets/{}.json", preset), options)
+            .map_err(io::Error::from)?;
+        let file = File::open(presets_dir.join(format!("{}.json", preset)))?;
+        io::copy(&mut BufReader::new(file), &mut zip)?;
+    }
+
+    let stored = options
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+    for archive in &manifest.archives {
+        zip.start_file(format!("mods/{}", archive), stored)
+            .map_err(io::Error::from)?;
+        let file = File::open(mods_dir.join(archive))?;
+        io::copy(&mut BufReader::new(file), &mut zip)?;
+    }
+
+    zip.finish().map_err(io::Error::from)?.flush()?;
+    Ok(manifest)
+}
+
+/// What was restored from a backup.
+#[derive(Debug, Default, PartialEq)]
+pub struct Restored {
+    /// Whether db.json was restored.
+    pub db_json: bool,
+    /// The names of the restored presets.
+    pub presets: Vec<String>,
+    /// The restored mod archives, relative to the mods directory.
+    pub archives: Vec<String>,
+}
+
+/// Read and validate the manifest of a backup.
+///
+/// # Arguments
+///
+/// `backup`: The path of the backup.
+///
+/// # Errors
+///
+/// * `InvalidBackup`: If the backup isn't a zip, its manifest is missing or made by a newer
+///   BeamMM, or a file listed in the manifest is missing or has an unsafe path.
+/// * `std::io::Error`: If the backup can't be opened.
+pub fn read_manifest(backup: impl AsRef<Path>) -> Result<Manifest> {
+    let backup = backup.as_ref();
+    let mut zip = open(backup)?;
+    validate(backup, &mut zip)
+}
+
+/// Restore a backup into the current game version's mods directory and the presets directory.
+///
+/// The game version of the backup doesn't need to match, so a backup made before a game update
+/// can be restored after it. Existing files are only overwritten if `overwrite` allows it; files
+/// that don't exist yet are always restored.
+///
+/// # Arguments
+///
+/// `backup`: The path of the backup.
+/// `mods_dir`: The mods directory of the current game version.
+/// `presets_dir`: Where preset config files are stored.
+/// `overwrite`: Called with the path of each existing file that would be overwritten, returning
+///   whether to overwrite it.
+///
+/// # Returns
+///
+/// What was restored.
+///
+/// # Errors
+///
+/// * `InvalidBackup`: If the backup fails validation, see `read_manifest`.
+/// * `std::io::Error`: If there is an issue reading the backup or writing the restored files.
+/// * Errors from `overwrite` are passed on.
+pub fn restore(
+    backup: impl AsRef<Path>,
+    mods_dir: impl AsRef<Path>,
+    presets_dir: impl AsRef<Path>,
+    mut overwrite: impl FnMut(&Path) -> Result<bool>,
+) -> Result<Restored> {
+    let backup = backup.as_ref();
+    let mods_dir = mods_dir.as_ref();
+    let presets_dir = presets_dir.as_ref();
+    let mut zip = open(backup)?;
+    let manifest = validate(backup, &mut zip)?;
+    let mut restored = Restored::default();
+
+    let mut extract = |name: &str, target: &Path| -> Result<bool> {
+        if target.try_exists()? && !overwrite(target)? {
+            return Ok(false);
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut entry = zip.by_name(name).map_err(io::Error::from)?;
+        let mut file = BufWriter::new(File::create(target)?);
+        io::copy(&mut entry, &mut file)?;
+        file.flush()?;
+        Ok(true)
+    };
+
+    restored.db_json = extract("db.json", &mods_dir.join("db.json"))?;
+    for preset in &manifest.presets {
+        let filename = format!("{}.json", preset);
+        if extract(
+            &format!("presets/{}", filename),
+            &presets_dir.join(filename),
+        )? {
+            restored.presets.push(preset.clone());
+        }
+    }
+    for archive in &manifest.archives {
+        let target = crate::path::join_relative(mods_dir, archive);
+        if extract(&format!("mods/{}", archive), &target)? {
+            restored.archives.push(archive.clone());
+        }
+    }
+    Ok(restored)
+}
+
+/// Open a backup as a zip.
+fn open(backup: &Path) -> Result<ZipArchive<BufReader<File>>> {
+    let file = File::open(backup)?;
+    ZipArchive::new(BufReader::new(file)).map_err(|e| InvalidBackup {
+        backup: backup.to_owned(),
+        reason: e.to_string(),
+    })
+}
+
+/// Read a backup's manifest and check that the backup matches it.
+fn validate<R: Read + Seek>(backup: &Path, zip: &mut ZipArchive<R>) -> Result<Manifest> {
+    let invalid = |reason: String| InvalidBackup {
+        backup: backup.to_owned(),
+        reason,
+    };
+
+    let manifest: Manifest = match zip.by_name(MANIFEST) {
+        Ok(entry) => serde_json::from_reader(BufReader::new(entry))
+            .map_err(|e| invalid(format!("unreadable manifest: {}", e)))?,
+        Err(_) => return Err(invalid("no manifest".into())),
+    };
+    if manifest.format > FORMAT {
+        return Err(invalid(format!(
+            "made by BeamMM {}, update BeamMM to restore it",
+            manifest.beammm_version
+        )));
+    }
+
+    let presets = manifest
+        .presets
+        .iter()
+        .map(|p| (p, format!("presets/{}.json", p)));
+    let archives = manifest.archives.iter().map(|a| (a, format!("mods/{}", a)));
+    let db_json = "db.json".to_string();
+    for (path, name) in std::iter::once((&db_json, db_json.clone()))
+        .chain(presets)
+        .chain(archives)
+    {
+        // Paths come from the backup, so they must not escape the directory they're restored to.
+        let safe = Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+        if !safe {
+            return Err(invalid(format!("unsafe path {}", path)));
+        }
+        if zip.index_for_name(&name).is_none() {
+            return Err(invalid(format!("{} is missing", name)));
+        }
+    }
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+    use std::{fs, io::Read};
+
+    #[test]
+    fn creating_backups() {
+        let mock = MockData::new();
+        let tmp = tempfile::tempdir().unwrap();
+        let out = tmp.path().join("backup.zip");
+        let version = "0.32".parse().unwrap();
+
+        let mut mod_cfg = mock.modcfg;
+        fs::write(mock.mods_dir.join("mod4.zip"), "mod4 archive").unwrap();
+        mod_cfg.register_archive(mock.mods_dir.join("mod4.zip"));
+
+        let manifest = create(
+            &out,
+            &mod_cfg,
+            &mock.mods_dir,
+            &mock.presets_dir,
+            &version,
+            true,
+            SystemTime::now(),
+        )
+        .unwrap();
+        assert_eq!(manifest.game_version, "0.32");
+        assert_eq!(manifest.presets, vec!["preset1", "preset2"]);
+        assert_eq!(manifest.archives, vec!["mod4.zip"]);
+
+        let mut zip = zip::ZipArchive::new(File::open(&out).unwrap()).unwrap();
+        let mut names: Vec<&str> = zip.file_names().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "db.json",
+                "manifest.json",
+                "mods/mod4.zip",
+                "presets/preset1.json",
+                "presets/preset2.json",
+            ]
+        );
+        let mut archive = String::new();
+        zip.by_name("mods/mod4.zip")
+            .unwrap()
+            .read_to_string(&mut archive)
+            .unwrap();
+        assert_eq!(archive, "mod4 archive");
+
+        let manifest = create(
+            &out,
+            &mod_cfg,
+            &mock.mods_dir,
+            &mock.presets_dir,
+            &version,
+            false,
+            SystemTime::now(),
+        )
+        .unwrap();
+        assert!(manifest.archives.is_empty());
+    }
+
+    #[test]
+    fn restoring_backups() {
+        let mock = MockData::new();
+        let tmp = tempfile::tempdir().unwrap();
+        let backup = tmp.path().join("backup.zip");
+
+        let mut mod_cfg = mock.modcfg;
+        fs::write(mock.mods_dir.join("mod4.zip"), "mod4 archive").unwrap();
+        mod_cfg.register_archive(mock.mods_dir.join("mod4.zip"));
+        mod_cfg.save_to_path(&mock.mods_dir).unwrap();
+        let manifest = create(
+            &backup,
+            &mod_cfg,
+            &mock.mods_dir,
+            &mock.presets_dir,
+            &"0.32".parse().unwrap(),
+            true,
+            SystemTime::now(),
+        )
+        .unwrap();
+        assert_eq!(read_manifest(&backup).unwrap(), manifest);
+
+        // Restoring into a fresh install writes everything without asking.
+        let mods_dir = tmp.path().join("mods");
+        let presets_dir = tmp.path().join("presets");
+        let restored = restore(&backup, &mods_dir, &presets_dir, |_| panic!()).unwrap();
+        assert_eq!(
+            restored,
+            Restored {
+                db_json: true,
+                presets: vec!["preset1".into(), "preset2".into()],
+                archives: vec!["mod4.zip".into()],
+            }
+        );
+        let restored_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+        assert_eq!(restored_cfg.is_mod_active("mod4"), Some(true));
+        assert_eq!(
+            Preset::load_from_path("preset2", &presets_dir).unwrap(),
+            mock.preset2
+        );
+        assert_eq!(
+            fs::read_to_string(mods_dir.join("mod4.zip")).unwrap(),
+            "mod4 archive"
+        );
+
+        // Existing files are only overwritten when allowed.
+        fs::write(presets_dir.join("preset1.json"), "changed").unwrap();
+        let restored = restore(&backup, &mods_dir, &presets_dir, |path| {
+            Ok(path.ends_with("preset1.json"))
+        })
+        .unwrap();
+        assert_eq!(
+            restored,
+            Restored {
+                db_json: false,
+                presets: vec!["preset1".into()],
+                archives: vec![],
+            }
+        );
+        assert_eq!(
+            Preset::load_from_path("preset1", &presets_dir).unwrap(),
+            mock.preset1
+        );
+    }
+
+    #[test]
+    fn rejecting_invalid_backups() {
+        let tmp = tempfile::tempdir().unwrap();
+        let backup = tmp.path().join("backup.zip");
+        let write_backup = |manifest: &str| {
+            let mut zip = ZipWriter::new(File::create(&backup).unwrap());
+            let options = SimpleFileOptions::default();
+            zip.start_file(MANIFEST, options).unwrap();
+            zip.write_all(manifest.as_bytes()).unwrap();
+            zip.start_file("db.json", options).unwrap();
+            zip.write_all(b"{}").unwrap();
+            zip.finish().unwrap();
+        };
+        let manifest = |format: u32, presets: &str| {
+            format!(
+                r#"{{"format":{},"beammm_version":"9.0.0","game_version":"0.32","created":0,"presets":[{}]}}"#,
+                format, presets
+            )
+        };
+
+        fs::write(&backup, "not a zip").unwrap();
+        assert!(matches!(read_manifest(&backup), Err(InvalidBackup { .. })));
+
+        write_backup(&manifest(FORMAT, ""));
+        assert!(read_manifest(&backup).is_ok());
+
+        write_backup(&manifest(FORMAT + 1, ""));
+        assert!(matches!(read_manifest(&backup), Err(InvalidBackup { .. })));
+
+        write_backup(&manifest(FORMAT, r#""preset1""#));
+        assert!(matches!(read_manifest(&backup), Err(InvalidBackup { .. })));
+
+        write_backup(&manifest(FORMAT, r#""../preset1""#));
+        assert!(matches!(read_manifest(&backup), Err(InvalidBackup { .. })));
+    }
+}
@@ -0,0 +1,818 @@
+use crate::{
+    Error::{self, *},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    ffi::OsString,
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+/// BeamMM's persistent settings.
+///
+/// The config is stored in the BeamMM directory. Settings that haven't been set are left out of
+/// the file, so a missing file is the same as the default config.
+///
+/// # Examples
+/// ```rust
+/// use beammm::config::Config;
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let beammm_dir = temp_dir.path();
+/// let mut config = Config::load_from_path(&beammm_dir).unwrap();
+/// config.set_game_dir(Some("C:/Games/BeamNG.drive".into()));
+/// config.save_to_path(&beammm_dir).unwrap();
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Config {
+    /// The game data directory chosen when several were found.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    game_dir: Option<PathBuf>,
+    /// Mods that must never be enabled.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    blacklist: BTreeSet<String>,
+    /// Mods that may be active while the whitelist is enforced.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    whitelist: BTreeSet<String>,
+    /// Whether only whitelisted mods may be active.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    whitelist_enforced: bool,
+    /// Names of removed mods mapped to the mods that replace them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    mod_aliases: BTreeMap<String, String>,
+    /// Whether db.json is saved as compact JSON instead of pretty-printed.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    compact_db_json: bool,
+    /// Whether db.json and preset files are saved with sorted keys and mod lists.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    sorted_json: bool,
+    /// Whether presets are backed up before they are deleted.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    backup_deleted_presets: bool,
+    /// The URL compatibility rules are downloaded from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rules_url: Option<String>,
+    /// Whether to check for newer BeamMM releases.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    check_for_updates: bool,
+    /// Command aliases mapped to the arguments they stand for.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    aliases: BTreeMap<String, String>,
+    /// Shared, read-only presets directories by namespace.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    preset_namespaces: BTreeMap<String, PathBuf>,
+    /// Levels mapped to the presets to use on them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    map_presets: BTreeMap<String, String>,
+    /// How many automatic db.json backups to keep.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    backup_retention: Option<Retention>,
+    /// How network operations are retried.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retry_policy: Option<RetryPolicy>,
+}
+
+impl Config {
+    /// The filename of the config file.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    fn filename() -> PathBuf {
+        PathBuf::from("config.json")
+    }
+
+    /// Load the config from a reader.
+    ///
+    /// # Arguments
+    ///
+    /// `reader`: The reader to load the config from.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue reading or deserializing the config.
+    pub fn load<R: BufRead>(reader: R) -> Result<Self> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Load the config from a file. If the file doesn't exist yet, the default config is returned.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The BeamMM directory where the config file is stored.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading the file or serde_json errors if there is
+    /// an issue deserializing the config.
+    pub fn load_from_path(beammm_dir: impl AsRef<Path>) -> Result<Self> {
+        let beammm_dir = beammm_dir.as_ref();
+        let path = beammm_dir.join(Self::filename());
+        if path.try_exists()? {
+            let file = File::open(&path).map_err(Error::io(&path))?;
+            let reader = BufReader::new(file);
+            Self::load(reader)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Serialize and save the config to a writer.
+    ///
+    /// # Arguments
+    ///
+    /// `writer`: The writer to save the config to.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue serializing the config or writing.
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Serialize and save the config to a file.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The BeamMM directory where the config file will be saved.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue creating the file or writing to it.
+    /// Possible serde_json errors if there is an issue serializing the config.
+    pub fn save_to_path(&self, beammm_dir: impl AsRef<Path>) -> Result<()> {
+        let beammm_dir = beammm_dir.as_ref();
+        let path = beammm_dir.join(Self::filename());
+        let file = File::create(&path).map_err(Error::io(&path))?;
+        let writer = BufWriter::new(file);
+        self.save(writer)
+    }
+
+    /// Get the game data directory chosen when several were found, if one was chosen.
+    pub fn game_dir(&self) -> Option<&Path> {
+        self.game_dir.as_deref()
+    }
+
+    /// Set or clear the game data directory to use when several are found.
+    ///
+    /// # Arguments
+    ///
+    /// `game_dir`: The chosen game data directory, or `None` to choose again next time.
+    pub fn set_game_dir(&mut self, game_dir: Option<PathBuf>) {
+        self.game_dir = game_dir;
+    }
+
+    /// Get the mods that must never be enabled.
+    pub fn blacklist(&self) -> &BTreeSet<String> {
+        &self.blacklist
+    }
+
+    /// Add or remove a mod from the blacklist.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    /// `blacklisted`: Whether the mod should be blacklisted.
+    pub fn set_blacklisted(&mut self, mod_name: &str, blacklisted: bool) {
+        if blacklisted {
+            self.blacklist.insert(mod_name.into());
+        } else {
+            self.blacklist.remove(mod_name);
+        }
+    }
+
+    /// Get the mods that may be active while the whitelist is enforced.
+    pub fn whitelist(&self) -> &BTreeSet<String> {
+        &self.whitelist
+    }
+
+    /// Add or remove a mod from the whitelist.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    /// `whitelisted`: Whether the mod should be whitelisted.
+    pub fn set_whitelisted(&mut self, mod_name: &str, whitelisted: bool) {
+        if whitelisted {
+            self.whitelist.insert(mod_name.into());
+        } else {
+            self.whitelist.remove(mod_name);
+        }
+    }
+
+    /// Whether only whitelisted mods may be active.
+    pub fn is_whitelist_enforced(&self) -> bool {
+        self.whitelist_enforced
+    }
+
+    /// Start or stop enforcing the whitelist.
+    ///
+    /// # Arguments
+    ///
+    /// `enforced`: Whether only whitelisted mods may be active.
+    pub fn set_whitelist_enforced(&mut self, enforced: bool) {
+        self.whitelist_enforced = enforced;
+    }
+
+    /// Get the whitelist to enforce, if enforcement is turned on. Suitable for
+    /// `ModCfg::set_whitelist`.
+    pub fn enforced_whitelist(&self) -> Option<HashSet<String>> {
+        self.whitelist_enforced
+            .then(|| self.whitelist.iter().cloned().collect())
+    }
+
+    /// Whether db.json is saved as compact JSON, the way the game writes it. Suitable for
+    /// `ModCfg::set_compact`.
+    pub fn is_compact_db_json(&self) -> bool {
+        self.compact_db_json
+    }
+
+    /// Choose whether db.json is saved as compact JSON or pretty-printed.
+    ///
+    /// # Arguments
+    ///
+    /// `compact`: Whether to save compact JSON.
+    pub fn set_compact_db_json(&mut self, compact: bool) {
+        self.compact_db_json = compact;
+    }
+
+    /// Whether db.json and preset files are saved in a stable, sorted order. Suitable for
+    /// `ModCfg::set_sorted` and `Preset::sort_all`.
+    pub fn is_sorted_json(&self) -> bool {
+        self.sorted_json
+    }
+
+    /// Choose whether db.json and preset files are saved in a stable, sorted order.
+    ///
+    /// # Arguments
+    ///
+    /// `sorted`: Whether to sort.
+    pub fn set_sorted_json(&mut self, sorted: bool) {
+        self.sorted_json = sorted;
+    }
+
+    /// Whether presets are backed up with `preset_backups::create` before they are deleted.
+    pub fn is_backup_deleted_presets(&self) -> bool {
+        self.backup_deleted_presets
+    }
+
+    /// Choose whether presets are backed up before they are deleted.
+    ///
+    /// # Arguments
+    ///
+    /// `backup`: Whether to back them up.
+    pub fn set_backup_deleted_presets(&mut self, backup: bool) {
+        self.backup_deleted_presets = backup;
+    }
+
+    /// Get the URL compatibility rules are downloaded from, if one was set.
+    pub fn rules_url(&self) -> Option<&str> {
+        self.rules_url.as_deref()
+    }
+
+    /// Set the URL compatibility rules are downloaded from.
+    ///
+    /// # Arguments
+    ///
+    /// `url`: The URL of a rules file. Pass `None` to stop downloading rules.
+    pub fn set_rules_url(&mut self, url: Option<String>) {
+        self.rules_url = url;
+    }
+
+    /// Whether to check for newer BeamMM releases. Off unless turned on.
+    pub fn check_for_updates(&self) -> bool {
+        self.check_for_updates
+    }
+
+    /// Turn checking for newer BeamMM releases on or off.
+    ///
+    /// # Arguments
+    ///
+    /// `check`: Whether to check for newer releases.
+    pub fn set_check_for_updates(&mut self, check: bool) {
+        self.check_for_updates = check;
+    }
+
+    /// Get the names of removed mods mapped to the mods that replace them. Suitable for
+    /// `ModCfg::set_aliases`.
+    pub fn mod_aliases(&self) -> &BTreeMap<String, String> {
+        &self.mod_aliases
+    }
+
+    /// Make a removed mod's name refer to the mod that replaces it.
+    ///
+    /// Aliases that referred to the removed mod are pointed at the replacement as well, and an
+    /// alias for the replacement itself is dropped since the replacement is installed.
+    ///
+    /// # Arguments
+    ///
+    /// `alias`: The name of the removed mod.
+    /// `mod_name`: The name of the mod that replaces it.
+    pub fn set_mod_alias(&mut self, alias: &str, mod_name: &str) {
+        for target in self.mod_aliases.values_mut() {
+            if target == alias {
+                *target = mod_name.into();
+            }
+        }
+        self.mod_aliases.remove(mod_name);
+        self.mod_aliases.insert(alias.into(), mod_name.into());
+    }
+
+    /// Get how many automatic db.json backups to keep. Suitable for `db_backups::prune`.
+    pub fn backup_retention(&self) -> Retention {
+        self.backup_retention.unwrap_or_default()
+    }
+
+    /// Set how many automatic db.json backups to keep.
+    ///
+    /// # Arguments
+    ///
+    /// `retention`: Which backups to keep. Pass `None` to go back to the default.
+    pub fn set_backup_retention(&mut self, retention: Option<Retention>) {
+        self.backup_retention = retention;
+    }
+
+    /// Get how network operations are retried. Suitable for `net::get`.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.unwrap_or_default()
+    }
+
+    /// Set how network operations are retried.
+    ///
+    /// # Arguments
+    ///
+    /// `policy`: The retry policy. Pass `None` to go back to the default.
+    pub fn set_retry_policy(&mut self, policy: Option<RetryPolicy>) {
+        self.retry_policy = policy;
+    }
+
+    /// Get the command aliases mapped to the arguments they stand for.
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+
+    /// Add, change, or remove a command alias.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The name of the alias, e.g. `race`.
+    /// `args`: The arguments the alias stands for, separated by spaces and quoted like in a shell,
+    ///   e.g. `--enable-preset racing --disable-preset "night offroad"`. Pass `None` to remove the
+    ///   alias.
+    pub fn set_alias(&mut self, name: &str, args: Option<String>) {
+        match args {
+            Some(args) => self.aliases.insert(name.into(), args),
+            None => self.aliases.remove(name),
+        };
+    }
+
+    /// Get the shared presets directories by namespace. Suitable for `Namespaces::new`.
+    pub fn preset_namespaces(&self) -> &BTreeMap<String, PathBuf> {
+        &self.preset_namespaces
+    }
+
+    /// Add, change, or remove a shared presets directory, whose presets are then named like
+    /// `namespace/preset`.
+    ///
+    /// # Arguments
+    ///
+    /// `namespace`: The name of the namespace, e.g. `team`.
+    /// `dir`: The shared presets directory. Pass `None` to remove the namespace.
+    pub fn set_preset_namespace(&mut self, namespace: &str, dir: Option<PathBuf>) {
+        match dir {
+            Some(dir) => self.preset_namespaces.insert(namespace.into(), dir),
+            None => self.preset_namespaces.remove(namespace),
+        };
+    }
+
+    /// Get the levels mapped to the presets to use on them.
+    pub fn map_presets(&self) -> &BTreeMap<String, String> {
+        &self.map_presets
+    }
+
+    /// Map a level, or any activity named like one, to the preset to use on it.
+    ///
+    /// # Arguments
+    ///
+    /// `level`: The name of the level, e.g. `utah`. Level names are case-insensitive.
+    /// `preset`: The name of the preset. Pass `None` to remove the mapping.
+    pub fn set_map_preset(&mut self, level: &str, preset: Option<String>) {
+        let level = level.to_lowercase();
+        match preset {
+            Some(preset) => self.map_presets.insert(level, preset),
+            None => self.map_presets.remove(&level),
+        };
+    }
+
+    /// Get the preset mapped to a level.
+    ///
+    /// # Arguments
+    ///
+    /// `level`: The name of the level, e.g. `Utah`. Level names are case-insensitive.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::config::Config;
+    ///
+    /// let mut config = Config::default();
+    /// config.set_map_preset("utah", Some("offroad".into()));
+    ///
+    /// assert_eq!(config.preset_for_map("Utah"), Some("offroad"));
+    /// assert_eq!(config.preset_for_map("west_coast_usa"), None);
+    /// ```
+    pub fn preset_for_map(&self, level: &str) -> Option<&str> {
+        self.map_presets
+            .get(&level.to_lowercase())
+            .map(String::as_str)
+    }
+
+    /// Expand a command alias used as the first argument into the arguments it stands for. The
+    /// rest of the arguments are kept after them, and aliases aren't expanded again.
+    ///
+    /// # Arguments
+    ///
+    /// `args`: The command line arguments, starting with the program name.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::config::Config;
+    /// use std::ffi::OsString;
+    ///
+    /// let mut config = Config::default();
+    /// config.set_alias("race", Some("--enable-preset racing".into()));
+    ///
+    /// let args = ["beammm", "race", "-y"].map(OsString::from).to_vec();
+    /// assert_eq!(
+    ///     config.expand_aliases(args),
+    ///     vec!["beammm", "--enable-preset", "racing", "-y"]
+    /// );
+    /// ```
+    pub fn expand_aliases(&self, mut args: Vec<OsString>) -> Vec<OsString> {
+        let Some(expanded) = args
+            .get(1)
+            .and_then(|arg| arg.to_str())
+            .and_then(|arg| self.aliases.get(arg))
+        else {
+            return args;
+        };
+        let expanded: Vec<OsString> = split_args(expanded)
+            .into_iter()
+            .map(OsString::from)
+            .collect();
+        args.splice(1..2, expanded);
+        args
+    }
+}
+
+/// Split arguments given as one string, like the arguments an alias stands for or a preset's launch
+/// arguments, the way a shell would, so arguments with spaces can be quoted, e.g.
+/// `--enable-preset "night racing"`. Single quotes keep everything up to the next single quote,
+/// and a backslash escapes the next character, except in single quotes. A quote that is never
+/// closed runs to the end.
+///
+/// # Arguments
+///
+/// `args`: The arguments, separated by whitespace.
+///
+/// # Examples
+/// ```rust
+/// use beammm::config::split_args;
+///
+/// assert_eq!(split_args(r#"-level "west coast""#), vec!["-level", "west coast"]);
+/// ```
+pub fn split_args(args: &str) -> Vec<String> {
+    let mut split = vec![];
+    // `None` between arguments, so quoted empty arguments are kept.
+    let mut current: Option<String> = None;
+    let mut chars = args.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => split.extend(current.take()),
+            '\'' => {
+                let arg = current.get_or_insert_with(String::new);
+                arg.extend(chars.by_ref().take_while(|&c| c != '\''));
+            }
+            '"' => {
+                let arg = current.get_or_insert_with(String::new);
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => arg.extend(chars.next()),
+                        c => arg.push(c),
+                    }
+                }
+            }
+            '\\' => current.get_or_insert_with(String::new).extend(chars.next()),
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    split.extend(current);
+    split
+}
+
+/// How many automatic db.json backups to keep.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Retention {
+    /// Keep the most recent backups, up to this many.
+    KeepLast(usize),
+    /// Keep the backups taken in this many days.
+    KeepDays(u64),
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention::KeepLast(20)
+    }
+}
+
+impl FromStr for Retention {
+    type Err = crate::Error;
+
+    /// Parse a retention policy: a number of backups like `20`, or a number of days like `30d`.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || InvalidRetention { policy: s.into() };
+        let s = s.trim();
+        match s.strip_suffix('d') {
+            Some(days) => Ok(Retention::KeepDays(days.parse().map_err(|_| invalid())?)),
+            None => Ok(Retention::KeepLast(s.parse().map_err(|_| invalid())?)),
+        }
+    }
+}
+
+impl fmt::Display for Retention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Retention::KeepLast(count) => write!(f, "the last {} backup(s)", count),
+            Retention::KeepDays(days) => write!(f, "backups from the last {} day(s)", days),
+        }
+    }
+}
+
+/// How network operations are retried when they fail.
+///
+/// A failed request is retried after a backoff that doubles with every retry, until it succeeds,
+/// runs out of retries, or would take longer than the timeout in total. Settings missing from the
+/// config file keep their default.
+///
+/// # Examples
+/// ```rust
+/// use beammm::config::RetryPolicy;
+/// use std::time::Duration;
+///
+/// let policy = RetryPolicy::default()
+///     .with_retries(5)
+///     .with_backoff(Duration::from_secs(1))
+///     .with_timeout(Duration::from_secs(120));
+/// assert_eq!(policy.delay(2), Duration::from_secs(4));
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// How many times a failed request is retried.
+    retries: u32,
+    /// How long to wait before the first retry, in milliseconds.
+    backoff_ms: u64,
+    /// How long an operation may take in total, retries included, in seconds.
+    timeout_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            backoff_ms: 500,
+            timeout_secs: 30,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Set how many times a failed request is retried.
+    ///
+    /// # Arguments
+    ///
+    /// `retries`: The number of retries. 0 never retries.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Set how long to wait before the first retry. Each retry waits twice as long as the one
+    /// before.
+    ///
+    /// # Arguments
+    ///
+    /// `backoff`: The wait before the first retry, kept to the millisecond.
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff_ms = backoff.as_millis() as u64;
+        self
+    }
+
+    /// Set how long an operation may take in total, retries included.
+    ///
+    /// # Arguments
+    ///
+    /// `timeout`: The time limit, kept to the second.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout_secs = timeout.as_secs();
+        self
+    }
+
+    /// Get how many times a failed request is retried.
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Get how long to wait before the first retry.
+    pub fn backoff(&self) -> Duration {
+        Duration::from_millis(self.backoff_ms)
+    }
+
+    /// Get how long an operation may take in total, retries included.
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    /// Get how long to wait before a retry.
+    ///
+    /// # Arguments
+    ///
+    /// `retry`: The number of retries made so far, starting at 0.
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.backoff().saturating_mul(2u32.saturating_pow(retry))
+    }
+}
+
+impl fmt::Display for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} retries, starting {} ms apart, within {} s",
+            self.retries, self.backoff_ms, self.timeout_secs
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_missing_config() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let config = Config::load_from_path(tmp.path()).unwrap();
+
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn saving_and_loading_game_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let beammm_dir = tmp.path();
+
+        let mut config = Config::default();
+        config.set_game_dir(Some("/games/BeamNG.drive".into()));
+        config.save_to_path(beammm_dir).unwrap();
+
+        let config = Config::load_from_path(beammm_dir).unwrap();
+        assert_eq!(config.game_dir(), Some(Path::new("/games/BeamNG.drive")));
+    }
+
+    #[test]
+    fn blacklisting_mods() {
+        let tmp = tempfile::tempdir().unwrap();
+        let beammm_dir = tmp.path();
+
+        let mut config = Config::default();
+        config.set_blacklisted("mod1", true);
+        config.set_blacklisted("mod2", true);
+        config.set_blacklisted("mod2", false);
+        config.save_to_path(beammm_dir).unwrap();
+
+        let config = Config::load_from_path(beammm_dir).unwrap();
+        assert_eq!(config.blacklist().iter().collect::<Vec<_>>(), vec!["mod1"]);
+    }
+
+    #[test]
+    fn enforcing_whitelist() {
+        let mut config = Config::default();
+        config.set_whitelisted("mod1", true);
+        assert_eq!(config.enforced_whitelist(), None);
+
+        config.set_whitelist_enforced(true);
+        assert_eq!(
+            config.enforced_whitelist(),
+            Some(HashSet::from(["mod1".to_string()]))
+        );
+    }
+
+    #[test]
+    fn chaining_mod_aliases() {
+        let mut config = Config::default();
+        config.set_mod_alias("mod_copy", "mod");
+        config.set_mod_alias("mod", "mod_final");
+
+        assert_eq!(
+            config.mod_aliases(),
+            &BTreeMap::from([
+                ("mod".to_string(), "mod_final".to_string()),
+                ("mod_copy".to_string(), "mod_final".to_string()),
+            ])
+        );
+
+        // A mod that replaces its own alias is installed again, so the alias goes away.
+        config.set_mod_alias("mod_final", "mod");
+        assert_eq!(config.mod_aliases().get("mod"), None);
+        assert_eq!(config.mod_aliases().get("mod_final").unwrap(), "mod");
+    }
+
+    #[test]
+    fn expanding_aliases() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+        let mut config = Config::default();
+        config.set_alias(
+            "race",
+            Some("--enable-preset racing  --disable-preset offroad".into()),
+        );
+        config.set_alias("loop", Some("loop".into()));
+
+        assert_eq!(
+            config.expand_aliases(args(&["beammm", "race", "-y"])),
+            args(&[
+                "beammm",
+                "--enable-preset",
+                "racing",
+                "--disable-preset",
+                "offroad",
+                "-y"
+            ])
+        );
+        // Only the first argument is an alias, and expanded aliases aren't expanded again.
+        assert_eq!(
+            config.expand_aliases(args(&["beammm", "-y", "race"])),
+            args(&["beammm", "-y", "race"])
+        );
+        assert_eq!(
+            config.expand_aliases(args(&["beammm", "loop"])),
+            args(&["beammm", "loop"])
+        );
+        // Arguments that aren't valid Unicode are passed through.
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStringExt;
+            let invalid = vec![OsString::from("beammm"), OsString::from_vec(vec![0xff])];
+            assert_eq!(config.expand_aliases(invalid.clone()), invalid);
+        }
+
+        config.set_alias("race", None);
+        assert!(!config.aliases().contains_key("race"));
+    }
+
+    #[test]
+    fn splitting_alias_args() {
+        assert_eq!(
+            split_args(r#"--enable-preset "night racing" --import 'C:\My Mods'"#),
+            vec!["--enable-preset", "night racing", "--import", r"C:\My Mods"]
+        );
+        assert_eq!(
+            split_args(r#"a\ b "say \"hi\"" '' x"y"z"#),
+            vec!["a b", r#"say "hi""#, "", "xyz"]
+        );
+        assert_eq!(split_args("  "), Vec::<String>::new());
+        // A quote that is never closed runs to the end.
+        assert_eq!(
+            split_args(r#"--enable-preset "night racing"#),
+            vec!["--enable-preset", "night racing"]
+        );
+    }
+
+    #[test]
+    fn retry_policy_defaults_missing_settings() {
+        let config = Config::load(r#"{"retry_policy":{"retries":10}}"#.as_bytes()).unwrap();
+        let policy = config.retry_policy();
+        assert_eq!(policy.retries(), 10);
+        assert_eq!(policy.backoff(), RetryPolicy::default().backoff());
+        assert_eq!(policy.delay(0), Duration::from_millis(500));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+        assert_eq!(Config::default().retry_policy(), RetryPolicy::default());
+    }
+
+    #[test]
+    fn parsing_retention() {
+        assert_eq!("20".parse::<Retention>().unwrap(), Retention::KeepLast(20));
+        assert_eq!("30d".parse::<Retention>().unwrap(), Retention::KeepDays(30));
+        assert!(matches!(
+            "forever".parse::<Retention>(),
+            Err(InvalidRetention { .. })
+        ));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::Result;
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// What BeamMM knew when it crashed, written to the crash reports directory so it can be attached
+/// to a bug report.
+///
+/// # Examples
+/// ```rust
+/// use beammm::crash::CrashReport;
+/// use std::time::SystemTime;
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let reports_dir = temp_dir.path().join("crash-reports");
+/// let report = CrashReport {
+///     message: "index out of bounds".into(),
+///     ..Default::default()
+/// };
+/// let path = report.write(&reports_dir, SystemTime::now()).unwrap();
+/// assert!(std::fs::read_to_string(path).unwrap().contains("index out of bounds"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CrashReport {
+    /// The panic message.
+    pub message: String,
+    /// Where in the source the panic happened, e.g. `src/main.rs:10:5`.
+    pub location: Option<String>,
+    /// The backtrace of the panic.
+    pub backtrace: String,
+    /// The directories BeamMM resolved before it crashed, named, e.g. `mods directory`.
+    pub paths: Vec<(String, PathBuf)>,
+    /// The last lines of the operation log, oldest first.
+    pub log_lines: Vec<String>,
+}
+
+impl CrashReport {
+    /// Format the report as text.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        // Writing to a String never fails.
+        let _ = writeln!(text, "BeamMM {} crashed", env!("CARGO_PKG_VERSION"));
+        let _ = writeln!(
+            text,
+            "OS: {} ({})",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        );
+        let _ = writeln!(text, "\nMessage: {}", self.message);
+        if let Some(location) = &self.location {
+            let _ = writeln!(text, "Location: {}", location);
+        }
+        let _ = writeln!(text, "\nPaths:");
+        for (name, path) in self.paths.iter() {
+            let _ = writeln!(text, "  {}: {}", name, path.display());
+        }
+        let _ = writeln!(text, "\nRecent log lines:");
+        for line in self.log_lines.iter() {
+            let _ = writeln!(text, "  {}", line);
+        }
+        let _ = writeln!(text, "\nBacktrace:\n{}", self.backtrace);
+        text
+    }
+
+    /// Write the report to a file named after the time, e.g. `2024-10-16T120348Z.txt`.
+    ///
+    /// # Arguments
+    ///
+    /// `reports_dir`: The directory crash reports are written to. It is created if needed.
+    /// `time`: When the crash happened.
+    ///
+    /// # Returns
+    ///
+    /// The path to the written report.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue creating the directory or writing the report.
+    pub fn write(&self, reports_dir: impl AsRef<Path>, time: SystemTime) -> Result<PathBuf> {
+        let reports_dir = reports_dir.as_ref();
+        fs::create_dir_all(reports_dir)?;
+        // Colons aren't allowed in filenames on Windows.
+        let stamp = humantime::format_rfc3339_seconds(time)
+            .to_string()
+            .replace(':', "");
+        let mut path = reports_dir.join(format!("{}.txt", stamp));
+        // Crashes within the same second get a number, rather than replacing the first report.
+        let mut n = 1;
+        while path.try_exists()? {
+            n += 1;
+            path = reports_dir.join(format!("{} ({}).txt", stamp, n));
+        }
+        fs::write(&path, self.to_text())?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn writing_crash_reports() {
+        let temp_dir = tempdir().unwrap();
+        let reports_dir = temp_dir.path().join("crash-reports");
+        let report = CrashReport {
+            message: "something broke".into(),
+            location: Some("src/main.rs:10:5".into()),
+            backtrace: "0: beammm::main".into(),
+            paths: vec![("mods directory".into(), PathBuf::from("/mods"))],
+            log_lines: vec!["Running: beammm --list-mods".into()],
+        };
+        let time = SystemTime::now();
+
+        let first = report.write(&reports_dir, time).unwrap();
+        let second = report.write(&reports_dir, time).unwrap();
+        assert_ne!(first, second);
+        assert!(first.to_string_lossy().ends_with("Z.txt"));
+
+        let text = fs::read_to_string(first).unwrap();
+        assert!(text.contains("Message: something broke"));
+        assert!(text.contains("Location: src/main.rs:10:5"));
+        assert!(text.contains("  mods directory: /mods"));
+        assert!(text.contains("  Running: beammm --list-mods"));
+        assert!(text.contains("0: beammm::main"));
+    }
+}
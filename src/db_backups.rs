@@ -0,0 +1,256 @@
+use crate::{config::Retention, game::ModCfg, Result};
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// A copy of db.json taken automatically before BeamMM saved over it.
+///
+/// Unlike the archives made by `backup::create`, these are taken on every run that finds db.json
+/// changed since the last copy, and old ones are pruned according to a `Retention` policy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbBackup {
+    /// The path to the copy.
+    pub path: PathBuf,
+    /// When the copy was taken.
+    pub time: SystemTime,
+    /// The size of the copy in bytes.
+    pub size: u64,
+}
+
+impl DbBackup {
+    /// Load the mod config saved in the backup.
+    ///
+    /// # Errors
+    ///
+    /// * `std::io::Error`: If there is an issue reading the backup.
+    /// * `InvalidModCfg`: If the backup can't be parsed.
+    pub fn load(&self) -> Result<ModCfg> {
+        ModCfg::load(BufReader::new(File::open(&self.path)?))
+    }
+}
+
+/// Format a time for a backup's filename, e.g. `20241016T120348.123Z`. It sorts the same as the
+/// time and has no characters that are invalid in filenames on Windows.
+pub(crate) fn stamp(time: SystemTime) -> String {
+    humantime::format_rfc3339_millis(time)
+        .to_string()
+        .replace(['-', ':'], "")
+}
+
+/// Parse a time formatted by `stamp`.
+pub(crate) fn parse_stamp(stamp: &str) -> Option<SystemTime> {
+    let date = stamp.get(0..8)?;
+    let time = stamp.get(9..)?;
+    let rfc3339 = format!(
+        "{}-{}-{}T{}:{}:{}",
+        date.get(0..4)?,
+        date.get(4..6)?,
+        date.get(6..8)?,
+        time.get(0..2)?,
+        time.get(2..4)?,
+        time.get(4..)?
+    );
+    humantime::parse_rfc3339(&rfc3339).ok()
+}
+
+/// Copy db.json into the backups directory, unless it is the same as the most recent backup.
+///
+/// # Arguments
+///
+/// `backups_dir`: Where the db.json backups of the current game version are kept.
+/// `mods_dir`: The mods directory of the current game version.
+/// `time`: The current time.
+///
+/// # Returns
+///
+/// The new backup, or `None` if there is no db.json or it hasn't changed since the last backup.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading db.json or the last backup, or writing the new
+/// backup.
+pub fn create(
+    backups_dir: impl AsRef<Path>,
+    mods_dir: impl AsRef<Path>,
+    time: SystemTime,
+) -> Result<Option<DbBackup>> {
+    let backups_dir = backups_dir.as_ref();
+    let db_json = mods_dir.as_ref().join("db.json");
+    if !db_json.try_exists()? {
+        return Ok(None);
+    }
+    let contents = fs::read(db_json)?;
+    if let Some(latest) = list(backups_dir)?.first() {
+        if fs::read(&latest.path)? == contents {
+            return Ok(None);
+        }
+    }
+
+    fs::create_dir_all(backups_dir)?;
+    let stamp = stamp(time);
+    // The filename only keeps milliseconds, so the time is rounded to match what `list` reads.
+    let time = parse_stamp(&stamp).unwrap_or(time);
+    let path = backups_dir.join(format!("db-{}.json", stamp));
+    fs::write(&path, &contents)?;
+    Ok(Some(DbBackup {
+        path,
+        time,
+        size: contents.len() as u64,
+    }))
+}
+
+/// List the db.json backups, most recent first.
+///
+/// # Arguments
+///
+/// `backups_dir`: Where the db.json backups of the current game version are kept.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading the backups directory.
+pub fn list(backups_dir: impl AsRef<Path>) -> Result<Vec<DbBackup>> {
+    let backups_dir = backups_dir.as_ref();
+    if !backups_dir.try_exists()? {
+        return Ok(vec![]);
+    }
+    let mut backups = vec![];
+    for entry in fs::read_dir(backups_dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(time) = name
+            .to_str()
+            .and_then(|n| n.strip_prefix("db-"))
+            .and_then(|n| n.strip_suffix(".json"))
+            .and_then(parse_stamp)
+        else {
+            continue;
+        };
+        backups.push(DbBackup {
+            path: entry.path(),
+            time,
+            size: entry.metadata()?.len(),
+        });
+    }
+    backups.sort_by_key(|b| std::cmp::Reverse(b.time));
+    Ok(backups)
+}
+
+/// Find the most recent db.json backup taken at or before a time.
+///
+/// # Arguments
+///
+/// `backups_dir`: Where the db.json backups of the current game version are kept.
+/// `at`: The time to look back from, or `None` for the most recent backup of all.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading the backups directory.
+pub fn latest(backups_dir: impl AsRef<Path>, at: Option<SystemTime>) -> Result<Option<DbBackup>> {
+    Ok(list(backups_dir)?
+        .into_iter()
+        .find(|backup| at.is_none_or(|at| backup.time <= at)))
+}
+
+/// Delete the db.json backups the retention policy doesn't keep. The most recent backup is always
+/// kept.
+///
+/// # Arguments
+///
+/// `backups_dir`: Where the db.json backups of the current game version are kept.
+/// `retention`: Which backups to keep.
+/// `now`: The current time.
+///
+/// # Returns
+///
+/// The deleted backups, most recent first.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading the backups directory or deleting a backup.
+pub fn prune(
+    backups_dir: impl AsRef<Path>,
+    retention: Retention,
+    now: SystemTime,
+) -> Result<Vec<DbBackup>> {
+    let mut pruned = vec![];
+    for (i, backup) in list(backups_dir)?.into_iter().enumerate() {
+        let keep = match retention {
+            Retention::KeepLast(count) => i < count,
+            // The most recent backup is always kept, so there is one to go back to even after a
+            // long break.
+            Retention::KeepDays(days) => {
+                i == 0
+                    || now
+                        .duration_since(backup.time)
+                        .map_or(true, |age| age <= Duration::from_secs(days * 24 * 60 * 60))
+            }
+        };
+        if !keep {
+            fs::remove_file(&backup.path)?;
+            pruned.push(backup);
+        }
+    }
+    Ok(pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+    use tempfile::tempdir;
+
+    #[test]
+    fn backing_up_db_json() {
+        let mock = MockData::new();
+        let temp_dir = tempdir().unwrap();
+        let backups_dir = temp_dir.path().join("db");
+        let now = SystemTime::now();
+
+        let first = create(&backups_dir, &mock.mods_dir, now).unwrap().unwrap();
+        assert_eq!(parse_stamp(&stamp(now)), Some(first.time));
+        // Nothing changed, so there is nothing to back up.
+        assert!(
+            create(&backups_dir, &mock.mods_dir, now + Duration::from_secs(1))
+                .unwrap()
+                .is_none()
+        );
+
+        let mut mod_cfg = mock.modcfg;
+        for i in 1..=3 {
+            mod_cfg.set_mod_active("mod1", i % 2 == 0).unwrap();
+            mod_cfg.save_to_path(&mock.mods_dir).unwrap();
+            let time = now + Duration::from_secs(i * 24 * 60 * 60);
+            create(&backups_dir, &mock.mods_dir, time).unwrap().unwrap();
+        }
+
+        let backups = list(&backups_dir).unwrap();
+        assert_eq!(backups.len(), 4);
+        assert_eq!(backups.last().unwrap(), &first);
+
+        // Going back in time finds the backup taken before then.
+        let second = latest(&backups_dir, Some(now + Duration::from_secs(36 * 60 * 60)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(second, backups[2]);
+        assert_eq!(second.load().unwrap().is_mod_active("mod1"), Some(false));
+        assert_eq!(latest(&backups_dir, None).unwrap().unwrap(), backups[0]);
+        assert!(latest(&backups_dir, Some(now - Duration::from_secs(1)))
+            .unwrap()
+            .is_none());
+
+        let later = now + Duration::from_secs(3 * 24 * 60 * 60);
+        let pruned = prune(&backups_dir, Retention::KeepLast(3), later).unwrap();
+        assert_eq!(pruned, vec![first]);
+        let pruned = prune(&backups_dir, Retention::KeepDays(1), later).unwrap();
+        assert_eq!(pruned.len(), 2);
+        assert_eq!(list(&backups_dir).unwrap().len(), 1);
+        // The most recent backup is kept however old it is.
+        let much_later = later + Duration::from_secs(30 * 24 * 60 * 60);
+        assert!(prune(&backups_dir, Retention::KeepDays(1), much_later)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{game::ModCfg, Error, Error::*, Result};
+use std::{path::Path, str::FromStr};
+
+/// A criterion that selects mods, such as `size>500MB`, `category=map`, or `source=manual`.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{filter::Filter, game::ModCfg};
+/// use std::path::Path;
+///
+/// let mod_cfg = ModCfg::load(
+///     r#"{"mods":{"map1":{"active":true,"modType":"terrain"},"car1":{"active":true,"modType":"vehicle"}}}"#
+///         .as_bytes(),
+/// )
+/// .unwrap();
+/// let filters = vec!["category=map".parse::<Filter>().unwrap()];
+/// let matching = beammm::filter::matching(&filters, &mod_cfg, Path::new("mods"));
+/// assert_eq!(matching, vec!["map1"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// The size of the mod's archive compared to a number of bytes.
+    Size(Comparison, u64),
+    /// The mod's type as recorded by the game, e.g. `vehicle` or `map`.
+    Category(String),
+    /// Where the mod was installed from.
+    Source(Source),
+}
+
+/// How a value is compared in a filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// `<`
+    Less,
+    /// `<=`
+    AtMost,
+    /// `=`
+    Equal,
+    /// `>=`
+    AtLeast,
+    /// `>`
+    Greater,
+}
+
+/// Where a mod was installed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// From the BeamNG.drive repository.
+    Repo,
+    /// Placed in the mods directory by hand.
+    Manual,
+}
+
+impl Comparison {
+    /// Compare two values.
+    fn compare(self, a: u64, b: u64) -> bool {
+        match self {
+            Self::Less => a < b,
+            Self::AtMost => a <= b,
+            Self::Equal => a == b,
+            Self::AtLeast => a >= b,
+            Self::Greater => a > b,
+        }
+    }
+}
+
+/// Parse a size such as `500MB` or `1.5GB` into bytes. Units are powers of 1024, and a number
+/// without a unit is in bytes.
+fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim().to_uppercase();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let multiplier: u64 = match unit.trim() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        _ => return None,
+    };
+    let number: f64 = number.parse().ok()?;
+    Some((number * multiplier as f64) as u64)
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |reason: &str| InvalidFilter {
+            filter: s.into(),
+            reason: reason.into(),
+        };
+
+        let op_start = s
+            .find(['<', '>', '='])
+            .ok_or_else(|| invalid("expected a comparison like `size>500MB`"))?;
+        let (key, rest) = s.split_at(op_start);
+        let (comparison, value) = if let Some(value) = rest.strip_prefix("<=") {
+            (Comparison::AtMost, value)
+        } else if let Some(value) = rest.strip_prefix(">=") {
+            (Comparison::AtLeast, value)
+        } else if let Some(value) = rest.strip_prefix('<') {
+            (Comparison::Less, value)
+        } else if let Some(value) = rest.strip_prefix('>') {
+            (Comparison::Greater, value)
+        } else {
+            (Comparison::Equal, &rest[1..])
+        };
+        let value = value.trim();
+
+        match key.trim().to_lowercase().as_str() {
+            "size" => parse_size(value)
+                .map(|bytes| Self::Size(comparison, bytes))
+                .ok_or_else(|| invalid("expected a size like `500MB`")),
+            "category" if comparison == Comparison::Equal => {
+                Ok(Self::Category(value.to_lowercase()))
+            }
+            "source" if comparison == Comparison::Equal => match value.to_lowercase().as_str() {
+                "repo" => Ok(Self::Source(Source::Repo)),
+                "manual" => Ok(Self::Source(Source::Manual)),
+                _ => Err(invalid("expected `repo` or `manual`")),
+            },
+            "category" | "source" => Err(invalid("only `=` can be used")),
+            _ => Err(invalid("expected one of: size, category, source")),
+        }
+    }
+}
+
+impl Filter {
+    /// Whether a mod matches the filter. Mods whose archive can't be found never match size
+    /// filters.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg listing the installed mods.
+    /// `mod_name`: The name of the mod.
+    /// `mods_dir`: The mods directory of the current game version.
+    pub fn matches(&self, mod_config: &ModCfg, mod_name: &str, mods_dir: impl AsRef<Path>) -> bool {
+        let mods_dir = mods_dir.as_ref();
+        match self {
+            Self::Size(comparison, bytes) => archive_size(mod_config, mod_name, mods_dir)
+                .is_some_and(|size| comparison.compare(size, *bytes)),
+            Self::Category(category) => mod_config.mod_type(mod_name).is_some_and(|mod_type| {
+                let mod_type = mod_type.to_lowercase();
+                // The game calls maps terrains.
+                mod_type == *category || (category == "map" && mod_type == "terrain")
+            }),
+            Self::Source(source) => {
+                let from_repo = mod_config.repo_version(mod_name).is_some();
+                from_repo == (*source == Source::Repo)
+            }
+        }
+    }
+}
+
+/// Get the size of a mod's archive in bytes.
+///
+/// # Arguments
+///
+/// `mod_config`: The ModCfg listing the installed mods.
+/// `mod_name`: The name of the mod.
+/// `mods_dir`: The mods directory of the current game version.
+///
+/// # Returns
+///
+/// `Some(u64)`: The size of the archive.
+/// `None`: If the mod's archive can't be found.
+pub fn archive_size(
+    mod_config: &ModCfg,
+    mod_name: &str,
+    mods_dir: impl AsRef<Path>,
+) -> Option<u64> {
+    let mods_dir = mods_dir.as_ref();
+    let archive = mod_config.archive_path(mod_name, mods_dir)?;
+    archive.metadata().ok().map(|m| m.len())
+}
+
+/// Get the installed mods matching every filter, sorted by name.
+///
+/// # Arguments
+///
+/// `filters`: The filters the mods must all match.
+/// `mod_config`: The ModCfg listing the installed mods.
+/// `mods_dir`: The mods directory of the current game version.
+pub fn matching(
+    filters: &[Filter],
+    mod_config: &ModCfg,
+    mods_dir: impl AsRef<Path>,
+) -> Vec<String> {
+    let mods_dir = mods_dir.as_ref();
+    let mut mods: Vec<String> = mod_config
+        .get_mods()
+        .filter(|m| filters.iter().all(|f| f.matches(mod_config, m, mods_dir)))
+        .cloned()
+        .collect();
+    mods.sort();
+    mods
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+    use std::fs;
+
+    #[test]
+    fn parsing_filters() {
+        assert_eq!(
+            "size>500MB".parse::<Filter>().unwrap(),
+            Filter::Size(Comparison::Greater, 500 << 20)
+        );
+        assert_eq!(
+            "size <= 1.5gb".parse::<Filter>().unwrap(),
+            Filter::Size(Comparison::AtMost, 3 << 29)
+        );
+        assert_eq!(
+            "category=Map".parse::<Filter>().unwrap(),
+            Filter::Category("map".into())
+        );
+        assert_eq!(
+            "source=manual".parse::<Filter>().unwrap(),
+            Filter::Source(Source::Manual)
+        );
+        for invalid in [
+            "size",
+            "size>big",
+            "category>map",
+            "source=usb",
+            "color=red",
+        ] {
+            assert!(matches!(
+                invalid.parse::<Filter>(),
+                Err(InvalidFilter { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn matching_mods() {
+        let mock = MockData::new();
+        let mut mod_cfg = mock.modcfg;
+        fs::write(mock.mods_dir.join("big.zip"), vec![0; 2048]).unwrap();
+        mod_cfg.register_archive(mock.mods_dir.join("big.zip"));
+        fs::write(mock.mods_dir.join("small.zip"), vec![0; 16]).unwrap();
+        mod_cfg.register_archive(mock.mods_dir.join("small.zip"));
+
+        let filters = vec!["size>1KB".parse().unwrap()];
+        assert_eq!(matching(&filters, &mod_cfg, &mock.mods_dir), vec!["big"]);
+
+        let filters = vec!["source=manual".parse().unwrap()];
+        assert_eq!(
+            matching(&filters, &mod_cfg, &mock.mods_dir),
+            vec!["big", "mod1", "mod2", "mod3", "small"]
+        );
+
+        let filters = vec![
+            "source=manual".parse().unwrap(),
+            "size<1KB".parse().unwrap(),
+        ];
+        assert_eq!(matching(&filters, &mod_cfg, &mock.mods_dir), vec!["small"]);
+    }
+}
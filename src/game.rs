@@ -1,27 +1,76 @@
-use crate::{Error::*, Preset, Result};
+#[cfg(feature = "native")]
+use crate::{index::PresetIndex, GameVersion};
+use crate::{Error, Error::*, Preset, Result};
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "native")]
+use std::fs;
 use std::{
-    collections::{HashMap, HashSet},
-    fs::File,
-    io::{BufRead, BufReader, BufWriter, Write},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    io::{BufRead, Write},
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
 /// A struct representing BeamNG.drive's mod configuration.
 ///
 /// This struct is used to load, modify, and save the game's mod configuration.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ModCfg {
     /// Installed mods and their data.
-    mods: HashMap<String, Mod>,
+    mods: BTreeMap<String, Mod>,
 
     /// Additional data that is currently unimportant to us but should be preserved.
     #[serde(flatten)]
-    other: HashMap<String, serde_json::Value>,
+    other: BTreeMap<String, serde_json::Value>,
+
+    /// Mods that must never be enabled. This is BeamMM's own setting and isn't saved to db.json.
+    #[serde(skip)]
+    blacklist: HashSet<String>,
+
+    /// When enforced, the only mods that may be active. This is BeamMM's own setting and isn't
+    /// saved to db.json.
+    #[serde(skip)]
+    whitelist: Option<HashSet<String>>,
+
+    /// Names of removed mods mapped to the mods that replace them. This is BeamMM's own setting
+    /// and isn't saved to db.json.
+    #[serde(skip)]
+    aliases: HashMap<String, String>,
+
+    /// Mods presets must leave alone, mapped to whether they are kept enabled or disabled. This is
+    /// BeamMM's own setting and isn't saved to db.json.
+    #[serde(skip)]
+    overrides: HashMap<String, bool>,
+
+    /// How much say presets have over each mod, for mods that aren't just observed. This is
+    /// BeamMM's own setting and isn't saved to db.json.
+    #[serde(skip)]
+    management: HashMap<String, Management>,
+
+    /// Whether db.json is saved as compact JSON, the way the game writes it, instead of
+    /// pretty-printed. This is BeamMM's own setting and isn't saved to db.json.
+    #[serde(skip)]
+    compact: bool,
+
+    /// Whether db.json is saved with every object's keys sorted, so saving the same mods always
+    /// gives the same bytes. This is BeamMM's own setting and isn't saved to db.json.
+    #[serde(skip)]
+    sorted: bool,
+
+    /// Whether the mod configuration changed since it was loaded from db.json, so saving it would
+    /// change db.json.
+    #[serde(skip)]
+    dirty: bool,
+
+    /// The mods whose active state was changed since the mod configuration was loaded. Presets and
+    /// bulk changes skip ignored mods, so ignored mods in here were changed by name.
+    #[serde(skip)]
+    changed: HashSet<String>,
 }
 
 impl ModCfg {
     /// The filename of the mod configuration file.
+    #[cfg(feature = "native")]
     #[cfg_attr(coverage_nightly, coverage(off))]
     fn filename() -> PathBuf {
         PathBuf::from("db.json")
@@ -29,16 +78,66 @@ impl ModCfg {
 
     /// Load the mod configuration from a reader.
     ///
+    /// Unlike a mod configuration loaded with `ModCfg::load_from_path`, it counts as changed, since
+    /// it may differ from the db.json it will be saved over.
+    ///
+    /// The whole mod configuration is read into memory first, since deserializing from memory is
+    /// much faster than from a reader for large configurations. Quirks the game tolerates in
+    /// db.json are accepted too: a UTF-8 byte order mark, trailing commas, and non-finite numbers
+    /// such as `NaN` or `inf`, which are read as `null`.
+    ///
     /// # Arguments
     ///
     /// `reader`: The reader to load the mod configuration from.
     ///
     /// # Errors
     ///
-    /// Possible serde_json errors if there is an issue reading or deserializing the mod
-    /// configuration.
-    pub fn load<R: BufRead>(reader: R) -> Result<Self> {
-        Ok(serde_json::from_reader(reader)?)
+    /// Possible IO errors if there is an issue reading.
+    /// InvalidModCfg: If the mod configuration can't be parsed even leniently, pointing at where
+    /// parsing failed.
+    pub fn load<R: BufRead>(mut reader: R) -> Result<Self> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        let mut mod_cfg = Self::from_bytes(&bytes)?;
+        mod_cfg.dirty = true;
+        Ok(mod_cfg)
+    }
+
+    /// Parse the mod configuration, falling back to a lenient parse if it isn't valid JSON.
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+        let error = match serde_json::from_slice(bytes) {
+            Ok(mod_cfg) => return Ok(mod_cfg),
+            Err(e) => e,
+        };
+        if error.is_syntax() {
+            if let Ok(mod_cfg) = serde_json::from_slice(&lenient_json(bytes)) {
+                return Ok(mod_cfg);
+            }
+        }
+
+        // Report the original error, since its position refers to the file as it is.
+        let (line, column) = (error.line(), error.column());
+        let message = error.to_string();
+        let reason = match message.rfind(" at line ") {
+            Some(index) => message[..index].to_string(),
+            None => message,
+        };
+        let text = String::from_utf8_lossy(bytes);
+        let snippet = text
+            .lines()
+            .nth(line.saturating_sub(1))
+            .map(|l| {
+                let start = column.saturating_sub(40);
+                l.chars().skip(start).take(80).collect::<String>()
+            })
+            .unwrap_or_default();
+        Err(InvalidModCfg {
+            line,
+            column,
+            reason,
+            snippet: snippet.trim().to_string(),
+        })
     }
 
     /// Load the mod configuration from a file.
@@ -49,13 +148,15 @@ impl ModCfg {
     ///
     /// # Errors
     ///
-    /// Possible IO errors if there is an issue reading the file or serde_json errors if there is
-    /// an issue deserializing the mod configuration.
-    pub fn load_from_path(mods_dir: &Path) -> Result<Self> {
+    /// Possible IO errors if there is an issue reading the file.
+    /// InvalidModCfg: Same as `ModCfg::load`.
+    #[cfg(feature = "native")]
+    pub fn load_from_path(mods_dir: impl AsRef<Path>) -> Result<Self> {
+        let mods_dir = mods_dir.as_ref();
         if mods_dir.try_exists()? {
-            let file = File::open(mods_dir.join(Self::filename()))?;
-            let reader = BufReader::new(file);
-            Self::load(reader)
+            let path = mods_dir.join(Self::filename());
+            let bytes = fs::read(&path).map_err(Error::io(&path))?;
+            Self::from_bytes(&bytes)
         } else {
             Err(DirNotFound {
                 dir: mods_dir.into(),
@@ -63,26 +164,76 @@ impl ModCfg {
         }
     }
 
+    /// Load the mod configuration from a file, or start an empty one if the game hasn't written
+    /// it yet.
+    ///
+    /// Before the game's first launch there is no db.json, but presets can still be created and
+    /// mods installed. Nothing is written until the empty ModCfg is saved with
+    /// `ModCfg::save_to_path`, and the game fills in the rest of the file the next time it starts.
+    ///
+    /// # Arguments
+    ///
+    /// `mods_dir`: The directory where the mod configuration file is stored.
+    ///
+    /// # Errors
+    ///
+    /// Same as `ModCfg::load_from_path`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::game::ModCfg;
+    /// # use tempfile::tempdir;
+    ///
+    /// # let temp_mods_dir = tempdir().unwrap();
+    /// # let mods_dir = temp_mods_dir.path();
+    /// let mod_cfg = ModCfg::load_or_default(&mods_dir).unwrap();
+    /// assert_eq!(mod_cfg.get_mods().count(), 0);
+    /// assert!(!mods_dir.join("db.json").exists());
+    ///
+    /// mod_cfg.save_to_path(&mods_dir).unwrap();
+    /// assert!(mods_dir.join("db.json").exists());
+    /// ```
+    #[cfg(feature = "native")]
+    pub fn load_or_default(mods_dir: impl AsRef<Path>) -> Result<Self> {
+        let mods_dir = mods_dir.as_ref();
+        if mods_dir.try_exists()? && !mods_dir.join(Self::filename()).try_exists()? {
+            // Dirty, so runs that only save changes still write the file.
+            return Ok(Self {
+                dirty: true,
+                ..Self::default()
+            });
+        }
+        Self::load_from_path(mods_dir)
+    }
+
     /// Apply all enabled presets in the presets directory.
     ///
-    /// If a preset errors for any reason when enabling, said preset's mods will NOT be
-    /// enabled. Any successfully enabled presets will have its mods fully enabled regardless of
-    /// other presets erroring.
+    /// How a preset referencing mods that don't exist in the ModCfg is handled is decided by
+    /// `policy`, unless the preset carries its own policy, in which case the preset's policy wins.
+    /// With `MissingModPolicy::Fail`, a failing preset's mods will NOT be enabled. Any
+    /// successfully enabled presets will have their mods fully enabled regardless of other presets
+    /// erroring. Blacklisted mods, and mods missing from an enforced whitelist, are skipped and
+    /// stay disabled. Presets that extend another preset also enable the other preset's mods. Mods
+    /// the preset's mods require are enabled too, and a preset requiring a mod that isn't installed
+    /// fails whatever the policy.
     ///
     /// # Arguments
     ///
     /// `presets_dir`: The directory where the presets are stored.
+    /// `policy`: The default policy for presets that reference missing mods.
     ///
     /// # Errors
     ///
-    /// MissingMods: If one or more mods in a preset doesn't exist in the ModCfg.
-    /// PresetsFailed: If one or more presets failed to enable due to missing mods.
+    /// PresetsFailed: If one or more presets failed to enable due to missing mods or unmet
+    /// requirements.
+    /// MissingPreset or PresetCycle: If an enabled preset extends a missing preset or presets
+    /// extend each other in a cycle.
     /// Other errors: If there is an IO error when reading the presets directory or if there is an
     /// issue serializing the presets.
     ///
     /// # Examples
     /// ```rust
-    /// use beammm::{Preset, game::ModCfg};
+    /// use beammm::{Preset, game::{MissingModPolicy, ModCfg}};
     /// # use tempfile::tempdir;
     ///
     /// # // Set up temp mock directories
@@ -94,46 +245,320 @@ impl ModCfg {
     /// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{\"mod1\":{\"active\":true},\"mod2\":{\"active\":true}}}").unwrap();
     /// #
     /// let mut mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
-    /// let mut preset = Preset::new("preset_name".into(), vec!["mod1".into(), "mod2".into()]);
+    /// let mut preset = Preset::new("preset_name".into(), vec!["mod1".into(), "mod2".into()]).unwrap();
     ///
     /// preset.disable(&mut mod_cfg).unwrap();
     /// preset.save_to_path(&presets_dir).unwrap();
     ///
-    /// mod_cfg.apply_presets(&presets_dir).unwrap();
+    /// mod_cfg.apply_presets(&presets_dir, MissingModPolicy::Fail).unwrap();
     /// mod_cfg.save_to_path(&mods_dir).unwrap();
     /// ```
-    pub fn apply_presets(&mut self, presets_dir: &Path) -> Result<()> {
-        let mut missing_mods = HashSet::new();
-        let mut failed_presets = HashSet::new();
-
-        for preset_name in Preset::list(presets_dir)? {
-            let preset = Preset::load_from_path(&preset_name, presets_dir)?;
-            if preset.is_enabled() {
-                match self.set_mods_active(preset.get_mods(), true) {
-                    Ok(()) => (),
-                    Err(e) => match e {
-                        MissingMods { mods } => {
-                            missing_mods.extend(mods);
-                            failed_presets.insert(preset_name);
-                        }
-                        other => return Err(other), // Should not happen
-                    },
+    #[cfg(feature = "native")]
+    pub fn apply_presets(
+        &mut self,
+        presets_dir: impl AsRef<Path>,
+        policy: MissingModPolicy,
+    ) -> Result<()> {
+        self.apply_presets_with_shared(presets_dir, vec![], policy)
+    }
+
+    /// Apply all enabled presets in the presets directory together with presets loaded from
+    /// elsewhere, like those of shared namespaces from `Namespaces::shared_presets`.
+    ///
+    /// Works like `ModCfg::apply_presets`, with the extra presets merged in as if they were in the
+    /// presets directory. Extra presets pruned by `MissingModPolicy::Prune` are only changed in
+    /// memory, since they may be read-only.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: The directory where the presets are stored.
+    /// `shared`: The extra presets. Their names must not clash with those in the presets
+    ///   directory.
+    /// `policy`: The default policy for presets that reference missing mods.
+    ///
+    /// # Errors
+    ///
+    /// The same as `ModCfg::apply_presets`.
+    #[cfg(feature = "native")]
+    pub fn apply_presets_with_shared(
+        &mut self,
+        presets_dir: impl AsRef<Path>,
+        shared: Vec<Preset>,
+        policy: MissingModPolicy,
+    ) -> Result<()> {
+        let presets_dir = presets_dir.as_ref();
+        self.apply_presets_with(presets_dir, shared, policy, true)?
+            .into_result()
+    }
+
+    /// Compute the changes `ModCfg::apply_presets` would make without changing anything.
+    ///
+    /// Neither this ModCfg nor any preset files are modified, even if a preset uses
+    /// `MissingModPolicy::Prune`.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: The directory where the presets are stored.
+    /// `policy`: The default policy for presets that reference missing mods.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors when reading the presets directory or serde_json errors if there is an
+    /// issue deserializing the presets. Presets failing because of missing mods are reported in
+    /// the returned ChangeSet instead.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::{Preset, game::{MissingModPolicy, ModCfg}};
+    /// # use tempfile::tempdir;
+    ///
+    /// # let temp_presets_dir = tempdir().unwrap();
+    /// # let presets_dir = temp_presets_dir.path();
+    /// # let temp_mods_dir = tempdir().unwrap();
+    /// # let mods_dir = temp_mods_dir.path();
+    /// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{\"mod1\":{\"active\":false},\"mod2\":{\"active\":false}}}").unwrap();
+    /// let mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+    /// let mut preset = Preset::new("preset_name".into(), vec!["mod1".into(), "mod2".into()]).unwrap();
+    /// preset.enable();
+    /// preset.save_to_path(&presets_dir).unwrap();
+    ///
+    /// let changes = mod_cfg.apply_presets_preview(&presets_dir, MissingModPolicy::Fail).unwrap();
+    /// assert_eq!(changes.enabled, vec!["mod1", "mod2"]);
+    /// assert!(!mod_cfg.is_mod_active("mod1").unwrap());
+    /// ```
+    #[cfg(feature = "native")]
+    pub fn apply_presets_preview(
+        &self,
+        presets_dir: impl AsRef<Path>,
+        policy: MissingModPolicy,
+    ) -> Result<ChangeSet> {
+        let presets_dir = presets_dir.as_ref();
+        let mut preview = self.clone();
+        let applied = preview.apply_presets_with(presets_dir, vec![], policy, false)?;
+
+        let mut changes = ChangeSet::between(self, &preview);
+        changes.failed_presets = applied.failed_presets.into_iter().collect();
+        changes.failed_presets.sort();
+        Ok(changes)
+    }
+
+    /// Apply all enabled presets in the presets directory, returning what happened.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: The directory where the presets are stored.
+    /// `shared`: Presets from elsewhere to apply along with them, which are never saved.
+    /// `policy`: The default policy for presets that reference missing mods.
+    /// `save_pruned`: Whether presets pruned by `MissingModPolicy::Prune` are saved.
+    #[cfg(feature = "native")]
+    fn apply_presets_with(
+        &mut self,
+        presets_dir: &Path,
+        shared: Vec<Preset>,
+        policy: MissingModPolicy,
+        save_pruned: bool,
+    ) -> Result<AppliedPresets> {
+        // Only the enabled presets and the presets they extend are needed. Extended shared
+        // presets come with `shared`.
+        let mut presets = PresetIndex::load_from_path(presets_dir)?
+            .enabled_with_parents()
+            .into_iter()
+            .filter(|name| crate::namespace::split(name).is_none())
+            .map(|name| Preset::load_from_path(name, presets_dir))
+            .collect::<Result<Vec<_>>>()?;
+        let own = presets.len();
+        presets.extend(shared);
+
+        let applied = self
+            .apply_presets_in(&mut presets, policy)
+            .map_err(|e| match e {
+                MissingPreset { preset, .. } => MissingPreset {
+                    dir: presets_dir.into(),
+                    preset,
+                },
+                e => e,
+            })?;
+        if save_pruned {
+            for &index in applied.pruned.iter().filter(|&&index| index < own) {
+                presets[index].save_to_path(presets_dir)?;
+            }
+        }
+        Ok(applied)
+    }
+
+    /// Apply the enabled presets among already loaded presets.
+    ///
+    /// Works like `ModCfg::apply_presets` without touching the file system, so presets can be kept
+    /// anywhere. Extended presets are looked up in `presets`. Presets pruned by
+    /// `MissingModPolicy::Prune` aren't written back, since they may not be in a presets directory.
+    ///
+    /// # Arguments
+    ///
+    /// `presets`: Every preset, enabled or not.
+    /// `policy`: The default policy for presets that reference missing mods.
+    ///
+    /// # Errors
+    ///
+    /// PresetsFailed: If one or more presets failed to enable due to missing mods.
+    /// MissingPreset or PresetCycle: If an enabled preset extends a missing preset or presets
+    /// extend each other in a cycle.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::{Preset, game::{MissingModPolicy, ModCfg}};
+    ///
+    /// let mut mod_cfg =
+    ///     ModCfg::load(r#"{"mods":{"mod1":{"active":false}}}"#.as_bytes()).unwrap();
+    /// let mut preset = Preset::new("preset_name".into(), vec!["mod1".into()]).unwrap();
+    /// preset.enable();
+    ///
+    /// mod_cfg.apply_loaded_presets(&mut [preset], MissingModPolicy::Fail).unwrap();
+    /// assert!(mod_cfg.is_mod_active("mod1").unwrap());
+    /// ```
+    pub fn apply_loaded_presets(
+        &mut self,
+        presets: &mut [Preset],
+        policy: MissingModPolicy,
+    ) -> Result<()> {
+        self.apply_presets_in(presets, policy)?.into_result()
+    }
+
+    /// Apply the enabled presets among already loaded presets, returning what happened.
+    ///
+    /// # Arguments
+    ///
+    /// `presets`: Every preset, enabled or not.
+    /// `policy`: The default policy for presets that reference missing mods.
+    fn apply_presets_in(
+        &mut self,
+        presets: &mut [Preset],
+        policy: MissingModPolicy,
+    ) -> Result<AppliedPresets> {
+        let mut applied = AppliedPresets::default();
+        // The mods every enabled preset asks for are collected first and then enabled in a single
+        // pass, so the outcome doesn't depend on the order of the presets.
+        let mut desired = BTreeSet::new();
+        // Mods of presets that failed are left as they are, even if they are managed.
+        let mut kept = BTreeSet::new();
+
+        for index in 0..presets.len() {
+            if !presets[index].is_enabled() {
+                continue;
+            }
+
+            let load = |name: &str| {
+                presets
+                    .iter()
+                    .find(|p| p.name() == name)
+                    .cloned()
+                    .ok_or_else(|| MissingPreset {
+                        dir: PathBuf::new(),
+                        preset: name.into(),
+                    })
+            };
+            let mut mods = presets[index].resolve_mods_with(load)?;
+            let requirements = presets[index].resolve_requirements_with(load)?;
+
+            // Mods needing a mod that isn't installed would be broken in game, so the whole preset
+            // fails regardless of the missing mod policy.
+            let mut unmet = vec![];
+            let mut required_mods = vec![];
+            for mod_name in &mods {
+                for required in requirements.get(mod_name).into_iter().flatten() {
+                    if self.is_mod_active(required).is_none() {
+                        unmet.push((mod_name.clone(), required.clone()));
+                    } else if !mods.contains(required) && !required_mods.contains(required) {
+                        required_mods.push(required.clone());
+                    }
+                }
+            }
+            if !unmet.is_empty() {
+                kept.extend(mods);
+                applied.unmet_requirements.extend(unmet);
+                applied
+                    .failed_presets
+                    .insert(presets[index].name().to_owned());
+                continue;
+            }
+            mods.extend(required_mods);
+            // Overridden mods stay as they were set by hand, and ignored mods are never touched.
+            mods.retain(|m| self.override_of(m).is_none() && !self.is_ignored(m));
+
+            let missing = self.missing_mods(&mods);
+            if !missing.is_empty() {
+                let preset = &mut presets[index];
+                match preset.missing_mod_policy().unwrap_or(policy) {
+                    MissingModPolicy::Fail => {
+                        applied.missing_mods.extend(missing);
+                        applied.failed_presets.insert(preset.name().to_owned());
+                        kept.extend(mods);
+                        continue;
+                    }
+                    MissingModPolicy::Skip => (),
+                    MissingModPolicy::Prune => {
+                        preset.prune_missing(self);
+                        applied.pruned.push(index);
+                    }
                 }
             }
+            // Pruned mods are gone and missing mods inherited from an extended preset are
+            // skipped, since pruning only changes this preset.
+            desired.extend(mods.into_iter().filter(|m| !missing.contains(m)));
         }
 
-        if !failed_presets.is_empty() {
-            Err(PresetsFailed {
-                mods: missing_mods,
-                presets: failed_presets,
+        // Blacklisted mods and mods missing from an enforced whitelist are skipped.
+        self.set_mods_active(self.allowed_mods(&desired), true)?;
+        // Managed mods belong to the presets, so those no enabled preset asks for are disabled.
+        let wanted: HashSet<&str> = desired
+            .iter()
+            .chain(kept.iter())
+            .map(|m| self.resolve_alias(m))
+            .collect();
+        let unwanted: Vec<String> = self
+            .management
+            .iter()
+            .filter(|(m, management)| {
+                **management == Management::Managed
+                    && self.mods.contains_key(*m)
+                    && !wanted.contains(m.as_str())
+                    && !self.overrides.contains_key(*m)
             })
-        } else {
-            Ok(())
-        }
+            .map(|(m, _)| m.clone())
+            .collect();
+        self.set_mods_active(unwanted, false)?;
+        Ok(applied)
+    }
+
+    /// Compare this ModCfg against the mod configuration currently saved on disk.
+    ///
+    /// Useful to detect drift, since the game may rewrite db.json at any time.
+    ///
+    /// # Arguments
+    ///
+    /// `mods_dir`: The directory where the mod configuration file is stored.
+    ///
+    /// # Returns
+    ///
+    /// The changes saving this ModCfg would make to the on-disk mod configuration. A mod
+    /// configuration the game hasn't written yet counts as empty.
+    ///
+    /// # Errors
+    ///
+    /// Same as `ModCfg::load_from_path`.
+    #[cfg(feature = "native")]
+    pub fn diff_against_path(&self, mods_dir: impl AsRef<Path>) -> Result<ChangeSet> {
+        let mods_dir = mods_dir.as_ref();
+        let on_disk = Self::load_or_default(mods_dir)?;
+        Ok(ChangeSet::between(&on_disk, self))
     }
 
     /// Serialize and save the mod configuration to a writer.
     ///
+    /// The mod configuration is pretty-printed unless compact output was chosen with
+    /// `ModCfg::set_compact`.
+    /// Keys are written in sorted order if chosen with `ModCfg::set_sorted`. Arrays are written
+    /// as the game wrote them, since their order may matter to the game.
+    ///
     /// # Arguments
     ///
     /// `writer`: The writer to save the mod configuration to.
@@ -143,7 +568,19 @@ impl ModCfg {
     /// Possible serde_json errors if there is an issue serializing the mod configuration or
     /// writing.
     pub fn save<W: Write>(&self, mut writer: W) -> Result<()> {
-        serde_json::to_writer_pretty(&mut writer, self)?;
+        // serde_json's maps keep their keys sorted, so going through a Value sorts the fields of
+        // each mod too.
+        let value = if self.sorted {
+            serde_json::to_value(self)?
+        } else {
+            serde_json::Value::Null
+        };
+        match (self.compact, self.sorted) {
+            (true, true) => serde_json::to_writer(&mut writer, &value)?,
+            (true, false) => serde_json::to_writer(&mut writer, self)?,
+            (false, true) => serde_json::to_writer_pretty(&mut writer, &value)?,
+            (false, false) => serde_json::to_writer_pretty(&mut writer, self)?,
+        }
         writer.flush()?;
 
         Ok(())
@@ -151,18 +588,49 @@ impl ModCfg {
 
     /// Serialize and save the mod configuration to a file.
     ///
+    /// The file isn't rewritten if it already holds this exact mod configuration, so saving an
+    /// unchanged ModCfg is cheap and doesn't touch the game's files.
+    ///
+    /// Ignored mods that weren't changed by name are saved as they are in the file on disk, so
+    /// changes the game made to them since the ModCfg was loaded are kept.
+    ///
     /// # Arguments
     ///
     /// `mods_dir`: The directory where the mod configuration file will be saved.
     ///
     /// # Errors
     ///
+    /// PermissionDenied: If BeamMM isn't allowed to write db.json, e.g. while the game holds it.
     /// Possible IO errors if there is an issue creating the file or writing to it.
     /// Possible serde_json errors if there is an issue serializing the mod configuration.
-    pub fn save_to_path(&self, mods_dir: &Path) -> Result<()> {
-        let file = File::create(mods_dir.join(Self::filename()))?;
-        let writer = BufWriter::new(file);
-        self.save(writer)
+    #[cfg(feature = "native")]
+    pub fn save_to_path(&self, mods_dir: impl AsRef<Path>) -> Result<()> {
+        let mods_dir = mods_dir.as_ref();
+        let path = mods_dir.join(Self::filename());
+        let on_disk = fs::read(&path).ok();
+        let mut bytes = vec![];
+        // Ignored mods keep whatever state the game gave them since this ModCfg was loaded, unless
+        // they were changed by name.
+        let on_disk_cfg = on_disk.as_deref().and_then(|b| Self::from_bytes(b).ok());
+        match on_disk_cfg {
+            Some(on_disk_cfg) if self.management.values().any(|m| *m == Management::Ignored) => {
+                let mut merged = self.clone();
+                for (mod_name, mod_) in on_disk_cfg.mods {
+                    if self.is_ignored(&mod_name)
+                        && !self.changed.contains(&mod_name)
+                        && merged.mods.contains_key(&mod_name)
+                    {
+                        merged.mods.insert(mod_name, mod_);
+                    }
+                }
+                merged.save(&mut bytes)?;
+            }
+            _ => self.save(&mut bytes)?,
+        }
+        if on_disk.is_some_and(|on_disk| on_disk == bytes) {
+            return Ok(());
+        }
+        fs::write(&path, bytes).map_err(Error::io(&path))
     }
 
     /// Set a mod to be active or inactive.
@@ -175,20 +643,39 @@ impl ModCfg {
     /// # Errors
     ///
     /// MissingMods: If the mod doesn't exist in the ModCfg.
+    /// BlacklistedMods: If the mod is blacklisted and `active` is true.
+    /// NotWhitelistedMods: If a whitelist is enforced, the mod isn't on it, and `active` is true.
     pub fn set_mod_active(&mut self, mod_name: &str, active: bool) -> Result<()> {
+        let mod_name = self.resolve_alias(mod_name).to_owned();
+        let mod_name = mod_name.as_str();
+        if active && self.mods.contains_key(mod_name) {
+            if self.is_blacklisted(mod_name) {
+                return Err(BlacklistedMods {
+                    mods: vec![mod_name.into()],
+                });
+            }
+            if !self.is_whitelisted(mod_name) {
+                return Err(NotWhitelistedMods {
+                    mods: vec![mod_name.into()],
+                });
+            }
+        }
         if let Some(mod_) = self.mods.get_mut(mod_name) {
-            mod_.active = active;
+            if mod_.active != active {
+                mod_.active = active;
+                self.dirty = true;
+                self.changed.insert(mod_name.into());
+            }
             Ok(())
         } else {
-            Err(MissingMods {
-                mods: vec![mod_name.into()],
-            })
+            Err(self.missing_mods_error(vec![mod_name.into()]))
         }
     }
 
     /// Set multiple mods to be active or inactive.
     ///
-    /// If any mods don't exist in the ModCfg, no mods will be set active or inactive.
+    /// If any mods don't exist in the ModCfg, or any mods to enable are blacklisted or missing from
+    /// an enforced whitelist, no mods will be set active or inactive.
     ///
     /// # Arguments
     ///
@@ -198,34 +685,211 @@ impl ModCfg {
     /// # Errors
     ///
     /// MissingMods: If one or more mods don't exist in the ModCfg.
-    pub fn set_mods_active(&mut self, mod_names: &[String], active: bool) -> Result<()> {
-        // First validate mods. If all exist, then we will set them active.
-        let mut missing_mods = vec![];
-        for mod_name in mod_names {
-            if !self.mods.contains_key(mod_name) {
-                missing_mods.push(mod_name.clone());
-            }
-        }
+    /// BlacklistedMods: If one or more mods are blacklisted and `active` is true.
+    /// NotWhitelistedMods: If a whitelist is enforced, one or more mods aren't on it, and `active`
+    /// is true.
+    pub fn set_mods_active(
+        &mut self,
+        mod_names: impl IntoIterator<Item = impl AsRef<str>>,
+        active: bool,
+    ) -> Result<()> {
+        let mod_names: Vec<String> = mod_names
+            .into_iter()
+            .map(|m| m.as_ref().to_owned())
+            .collect();
+        // First validate mods. If all exist, then we will set them active. Aliases are resolved
+        // like `set_mod_active` does, so the mods it enables are the ones checked here.
+        let missing_mods = self.missing_mods(&mod_names);
+        let blacklisted_mods: Vec<String> = mod_names
+            .iter()
+            .map(|m| self.resolve_alias(m))
+            .filter(|m| active && self.is_blacklisted(m))
+            .map(str::to_owned)
+            .collect();
+        let not_whitelisted_mods: Vec<String> = mod_names
+            .iter()
+            .map(|m| self.resolve_alias(m))
+            .filter(|m| active && !self.is_whitelisted(m))
+            .map(str::to_owned)
+            .collect();
 
         if !missing_mods.is_empty() {
-            Err(MissingMods { mods: missing_mods })
+            Err(self.missing_mods_error(missing_mods))
+        } else if !blacklisted_mods.is_empty() {
+            Err(BlacklistedMods {
+                mods: blacklisted_mods,
+            })
+        } else if !not_whitelisted_mods.is_empty() {
+            Err(NotWhitelistedMods {
+                mods: not_whitelisted_mods,
+            })
         } else {
-            for mod_name in mod_names {
-                self.set_mod_active(mod_name, active).unwrap(); // We've checked that every mod exists.
-                                                                // enable_mod can only error if a mod
-                                                                // doesn't exist so this is safe.
+            for mod_name in &mod_names {
+                // We've checked everything set_mod_active checks, so this doesn't fail.
+                self.set_mod_active(mod_name, active)?;
             }
             Ok(())
         }
     }
 
+    /// Get the mods from `mod_names` that don't exist in the ModCfg.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_names`: The names of the mods to check.
+    pub fn missing_mods(
+        &self,
+        mod_names: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Vec<String> {
+        mod_names
+            .into_iter()
+            .filter(|m| !self.mods.contains_key(self.resolve_alias(m.as_ref())))
+            .map(|m| m.as_ref().to_owned())
+            .collect()
+    }
+
+    /// Build a MissingMods error for mods that don't exist in the ModCfg, suggesting installed mods
+    /// with similar names.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_names`: The names of the missing mods.
+    pub fn missing_mods_error(&self, mod_names: Vec<String>) -> Error {
+        let mut suggestions: Vec<String> = vec![];
+        for suggestion in mod_names.iter().filter_map(|m| self.suggest_mod(m)) {
+            if !suggestions.contains(&suggestion) {
+                suggestions.push(suggestion);
+            }
+        }
+        MissingMods {
+            mods: mod_names,
+            suggestions,
+        }
+    }
+
+    /// Find the installed mod whose name is closest to a name that doesn't match any mod.
+    ///
+    /// Names are compared ignoring case by edit distance. Only close matches are suggested, so
+    /// unrelated names don't get a suggestion.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name that doesn't match any mod.
+    ///
+    /// # Returns
+    ///
+    /// `Some(String)`: The closest installed mod.
+    /// `None`: If no installed mod is close enough.
+    pub fn suggest_mod(&self, mod_name: &str) -> Option<String> {
+        let mod_name = mod_name.to_lowercase();
+        let max_distance = (mod_name.chars().count() / 3).max(1);
+        self.mods
+            .keys()
+            .map(|m| (strsim::levenshtein(&mod_name, &m.to_lowercase()), m))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, m)| m.clone())
+    }
+
     /// Get a list of mods in the mod configuration.
     #[cfg_attr(coverage_nightly, coverage(off))]
     pub fn get_mods(&self) -> impl Iterator<Item = &String> {
         self.mods.keys()
     }
 
-    /// Set all mods to be active or inactive.
+    /// Iterate over the mods in the mod configuration along with their status, sorted by name.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::game::ModCfg;
+    ///
+    /// let mod_cfg = ModCfg::load(
+    ///     r#"{"mods":{"map1":{"active":false,"modType":"terrain"},"car1":{"active":true}}}"#
+    ///         .as_bytes(),
+    /// )
+    /// .unwrap();
+    /// let active: Vec<&str> = mod_cfg
+    ///     .iter_mods()
+    ///     .filter(|m| m.is_active())
+    ///     .map(|m| m.name())
+    ///     .collect();
+    /// assert_eq!(active, vec!["car1"]);
+    /// ```
+    pub fn iter_mods(&self) -> impl Iterator<Item = ModEntry<'_>> {
+        self.mods.iter().map(|(name, mod_)| ModEntry { name, mod_ })
+    }
+
+    /// Iterate over the names of the active mods, sorted.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::game::ModCfg;
+    ///
+    /// let mod_cfg = ModCfg::load(
+    ///     r#"{"mods":{"map1":{"active":false},"car2":{"active":true},"car1":{"active":true}}}"#
+    ///         .as_bytes(),
+    /// )
+    /// .unwrap();
+    /// assert_eq!(mod_cfg.active_mods().collect::<Vec<_>>(), vec!["car1", "car2"]);
+    /// assert_eq!(mod_cfg.inactive_mods().collect::<Vec<_>>(), vec!["map1"]);
+    /// assert_eq!((mod_cfg.active_count(), mod_cfg.inactive_count()), (2, 1));
+    /// ```
+    pub fn active_mods(&self) -> impl Iterator<Item = &str> {
+        self.mods
+            .iter()
+            .filter(|(_, mod_)| mod_.active)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Iterate over the names of the inactive mods, sorted.
+    pub fn inactive_mods(&self) -> impl Iterator<Item = &str> {
+        self.mods
+            .iter()
+            .filter(|(_, mod_)| !mod_.active)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Get the number of active mods.
+    pub fn active_count(&self) -> usize {
+        self.active_mods().count()
+    }
+
+    /// Get the number of inactive mods.
+    pub fn inactive_count(&self) -> usize {
+        self.inactive_mods().count()
+    }
+
+    /// Get the names of the mods no preset contains, sorted, to find strays that still need to be
+    /// organized into presets or removed. A preset naming a removed mod's alias counts as
+    /// containing the mod that replaced it.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: The directory where the presets are stored.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading the presets directory, or serde_json errors
+    /// if there is an issue deserializing a preset.
+    #[cfg(feature = "native")]
+    pub fn unmanaged_mods(&self, presets_dir: impl AsRef<Path>) -> Result<Vec<&str>> {
+        let index = PresetIndex::load_from_path(presets_dir)?;
+        let managed: HashSet<&str> = index
+            .iter()
+            .flat_map(|(_, entry)| entry.mods())
+            .map(|mod_name| self.resolve_alias(mod_name))
+            .collect();
+        Ok(self
+            .mods
+            .keys()
+            .map(String::as_str)
+            .filter(|mod_name| !managed.contains(mod_name))
+            .collect())
+    }
+
+    /// Set all mods to be active or inactive. Mods that aren't allowed to be enabled, because they
+    /// are blacklisted or missing from an enforced whitelist, are skipped when enabling. Ignored
+    /// mods are always skipped.
     ///
     /// # Arguments
     ///
@@ -235,234 +899,2234 @@ impl ModCfg {
     ///
     /// MissingMods: If one or more mods don't exist in the ModCfg.
     pub fn set_all_mods_active(&mut self, active: bool) -> Result<()> {
-        let mods: Vec<String> = self.get_mods().cloned().collect();
+        let mods: Vec<String> = self
+            .get_mods()
+            .filter(|m| !self.is_ignored(m))
+            .cloned()
+            .collect();
+        let mods = if active {
+            self.allowed_mods(&mods)
+        } else {
+            mods
+        };
         self.set_mods_active(&mods, active)
     }
 
-    /// Get the active status of a mod.
+    /// Set the mods that must never be enabled.
+    ///
+    /// Blacklisted mods that are already active stay active until they are disabled.
     ///
     /// # Arguments
     ///
-    /// `mod_name`: The name of the mod to check the active status of.
+    /// `blacklist`: The names of the blacklisted mods.
+    pub fn set_blacklist(&mut self, blacklist: impl IntoIterator<Item = String>) {
+        self.blacklist = blacklist.into_iter().collect();
+    }
+
+    /// Check whether a mod is blacklisted. Aliases are followed on both sides, so blacklisting
+    /// the name of a removed copy also blacklists the mod that replaces it.
     ///
-    /// # Returns
+    /// # Arguments
     ///
-    /// `Some(bool)`: The active status of the mod if it exists.
-    /// `None`: If the mod doesn't exist in the ModCfg.
-    pub fn is_mod_active(&self, mod_name: &str) -> Option<bool> {
-        self.mods.get(mod_name).map(|m| m.active)
+    /// `mod_name`: The name of the mod.
+    pub fn is_blacklisted(&self, mod_name: &str) -> bool {
+        let mod_name = self.resolve_alias(mod_name);
+        self.blacklist
+            .iter()
+            .any(|blacklisted| self.resolve_alias(blacklisted) == mod_name)
     }
-}
-
-/// A struct representing a BeamNG.drive mod.
-#[derive(Serialize, Deserialize, Debug)]
-struct Mod {
-    /// Whether the mod is active.
-    active: bool,
-
-    /// Other currently unimportant data.
-    #[serde(flatten)]
-    other: HashMap<String, serde_json::Value>,
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::test_utils::MockData;
+    /// Set the mods presets must leave alone, replacing the previous overrides.
+    ///
+    /// Applying presets neither enables nor disables an overridden mod, so a mod disabled by hand
+    /// stays disabled even though an enabled preset contains it. The mods themselves aren't
+    /// changed here.
+    ///
+    /// # Arguments
+    ///
+    /// `overrides`: The names of the mods mapped to whether they are kept enabled or disabled.
+    pub fn set_overrides(&mut self, overrides: impl IntoIterator<Item = (String, bool)>) {
+        self.overrides = overrides.into_iter().collect();
+    }
 
-    #[test]
-    fn loading_modcfg() {
-        let mock_dirs = MockData::new();
+    /// Get whether a mod is kept enabled or disabled when presets are applied, if it is. Aliases
+    /// are followed, so an override on a mod also applies to the names that refer to it.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    pub fn override_of(&self, mod_name: &str) -> Option<bool> {
+        self.overrides.get(self.resolve_alias(mod_name)).copied()
+    }
 
-        // Load the modcfg here instead of just relying on the MockDirs struct so we can test the loading.
-        let mod_cfg = ModCfg::load_from_path(&mock_dirs.mods_dir).unwrap();
+    /// Set how much say presets have over each mod, replacing the previous settings. Mods that
+    /// aren't given are observed. Aliases are followed, so set them with `ModCfg::set_aliases`
+    /// first.
+    ///
+    /// # Arguments
+    ///
+    /// `management`: The names of the mods mapped to how they are managed.
+    pub fn set_management(&mut self, management: impl IntoIterator<Item = (String, Management)>) {
+        self.management = management
+            .into_iter()
+            .map(|(mod_name, management)| (self.resolve_alias(&mod_name).to_string(), management))
+            .collect();
+    }
 
-        assert_eq!(mod_cfg.mods.len(), 3);
-        assert!(mod_cfg.mods.get("mod1").unwrap().active);
-        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
-        assert!(mod_cfg.mods.get("mod3").unwrap().active);
+    /// Get how much say presets have over a mod.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    pub fn management_of(&self, mod_name: &str) -> Management {
+        self.management
+            .get(self.resolve_alias(mod_name))
+            .copied()
+            .unwrap_or_default()
+    }
 
-        // Check that the other data is preserved.
-        assert_eq!(mod_cfg.other.len(), 1);
-        let other_data = mod_cfg.other.get("other").unwrap();
-        assert_eq!(
-            other_data.get("key").unwrap(),
-            &serde_json::Value::String("value".into())
-        );
+    /// Check whether BeamMM must never touch a mod, e.g. because it is managed in game.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    pub fn is_ignored(&self, mod_name: &str) -> bool {
+        self.management_of(mod_name) == Management::Ignored
     }
 
-    #[test]
-    fn load_bad_path() {
-        let tmp = tempfile::tempdir().unwrap();
-        let temp_dir = tmp.path();
+    /// Enforce a whitelist, or stop enforcing it.
+    ///
+    /// While a whitelist is enforced, only mods on it may be enabled. Mods that are already active
+    /// stay active until `ModCfg::enforce_whitelist` is called.
+    ///
+    /// # Arguments
+    ///
+    /// `whitelist`: The names of the only mods that may be active, or `None` to allow every mod.
+    pub fn set_whitelist(&mut self, whitelist: Option<HashSet<String>>) {
+        self.whitelist = whitelist;
+    }
 
-        let result = ModCfg::load_from_path(&temp_dir.join("bad_path"));
-        assert!(matches!(result, Err(DirNotFound { .. })));
+    /// Check whether a mod may be active under the enforced whitelist. Every mod may be active when
+    /// no whitelist is enforced.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    pub fn is_whitelisted(&self, mod_name: &str) -> bool {
+        self.whitelist
+            .as_ref()
+            .is_none_or(|whitelist| whitelist.contains(mod_name))
     }
 
-    #[test]
+    /// Disable every active mod that isn't on the enforced whitelist.
+    ///
+    /// # Returns
+    ///
+    /// The names of the disabled mods, sorted. Empty if no whitelist is enforced.
+    pub fn enforce_whitelist(&mut self) -> Vec<String> {
+        let mut disabled: Vec<String> = self
+            .mods
+            .iter()
+            .filter(|(name, mod_)| mod_.active && !self.is_whitelisted(name))
+            .map(|(name, _)| name.clone())
+            .collect();
+        disabled.sort();
+        for mod_name in disabled.iter() {
+            self.set_mod_active(mod_name, false).unwrap(); // The mods were just read from the config.
+        }
+        disabled
+    }
+
+    /// Get the mods from `mod_names` that are allowed to be enabled, with aliases resolved.
+    fn allowed_mods(&self, mod_names: impl IntoIterator<Item = impl AsRef<str>>) -> Vec<String> {
+        mod_names
+            .into_iter()
+            .map(|m| self.resolve_alias(m.as_ref()).to_owned())
+            .filter(|m| !self.is_blacklisted(m) && self.is_whitelisted(m))
+            .collect()
+    }
+
+    /// Get the path to a mod's archive, based on where the game says the mod is installed.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    /// `mods_dir`: The mods directory of the current game version.
+    ///
+    /// # Returns
+    ///
+    /// `Some(PathBuf)`: The path to the mod's archive. The archive isn't guaranteed to exist.
+    /// `None`: If the mod doesn't exist or the game doesn't record where it is installed.
+    #[cfg(feature = "native")]
+    pub fn archive_path(&self, mod_name: &str, mods_dir: impl AsRef<Path>) -> Option<PathBuf> {
+        let mods_dir = mods_dir.as_ref();
+        let mod_ = self.mods.get(mod_name)?;
+        // The game stores paths relative to the version directory, e.g. `/mods/repo/mod.zip`.
+        if let Some(full_path) = mod_.full_path() {
+            let relative = full_path.trim_start_matches('/');
+            let relative = relative.strip_prefix("mods/").unwrap_or(relative);
+            return Some(crate::path::join_relative(mods_dir, relative));
+        }
+        mod_.filename().map(|f| mods_dir.join(f))
+    }
+
+    /// Register a mod archive placed directly in the mods directory, or in its `repo` folder like
+    /// the game's downloads, so it can be managed before the game has registered it itself.
+    ///
+    /// The game names mods after their archive's filename in lowercase. Only the entries the game
+    /// needs to find the archive are written; the game fills in the rest the next time it starts.
+    /// The mod is active unless it is blacklisted or missing from an enforced whitelist.
+    ///
+    /// # Arguments
+    ///
+    /// `archive`: The path to the mod archive.
+    ///
+    /// # Returns
+    ///
+    /// `Some(String)`: The name of the newly registered mod.
+    /// `None`: If a mod with the same name is already registered, or the archive has no filename.
+    pub fn register_archive(&mut self, archive: impl AsRef<Path>) -> Option<String> {
+        let archive = archive.as_ref();
+        let filename = archive.file_name()?.to_str()?;
+        let mod_name = archive.file_stem()?.to_str()?.to_lowercase();
+        if self.mods.contains_key(&mod_name) {
+            return None;
+        }
+
+        let active = !self.is_blacklisted(&mod_name) && self.is_whitelisted(&mod_name);
+        let mut mod_ = Mod::new(active);
+        mod_.mod_name = Some(mod_name.clone());
+        let dir = match archive.parent().and_then(Path::file_name) {
+            Some(dir) if dir == "repo" => "/mods/repo/",
+            _ => "/mods/",
+        };
+        mod_.set_archive(&format!("{}{}", dir, filename));
+        self.add_mod(&mod_name, mod_);
+        Some(mod_name)
+    }
+
+    /// Get the repository version of a mod installed from the BeamNG.drive repository.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    ///
+    /// # Returns
+    ///
+    /// `Some(RepoVersion)`: If the mod exists and was installed from the repository.
+    /// `None`: If the mod doesn't exist or isn't a repository mod.
+    pub fn repo_version(&self, mod_name: &str) -> Option<RepoVersion> {
+        self.mods.get(mod_name)?.repo_version()
+    }
+
+    /// Get what the game records about each mod installed from the repository, sorted by name, so
+    /// subscriptions in the game's repository browser can be reconciled with BeamMM's installs.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::game::ModCfg;
+    ///
+    /// let mod_cfg = ModCfg::load(
+    ///     r#"{"mods":{"car":{"active":true,"dirname":"/mods/repo/","modData":{"tagid":"M7","resource_id":7}}}}"#
+    ///         .as_bytes(),
+    /// )
+    /// .unwrap();
+    /// let (name, info) = mod_cfg.repo_mods().next().unwrap();
+    /// assert_eq!(name, "car");
+    /// assert_eq!(info.tag_id.as_deref(), Some("M7"));
+    /// assert!(info.subscribed);
+    /// ```
+    pub fn repo_mods(&self) -> impl Iterator<Item = (&str, RepoInfo)> {
+        self.mods
+            .iter()
+            .filter_map(|(name, mod_)| Some((name.as_str(), mod_.repo_info()?)))
+    }
+
+    /// Record the repository version of a mod in db.json, e.g. after BeamMM updated its archive,
+    /// leaving the rest of the game's repository data as it is.
+    ///
+    /// The new version reaches db.json with the next `ModCfg::save_to_path`.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    /// `version`: The repository version of the installed archive.
+    ///
+    /// # Errors
+    ///
+    /// MissingMods: If the mod doesn't exist in the ModCfg.
+    pub fn set_repo_version(&mut self, mod_name: &str, version: &RepoVersion) -> Result<()> {
+        match self.mods.get_mut(mod_name) {
+            Some(mod_) => {
+                mod_.set_repo_version(version);
+                self.dirty = true;
+                Ok(())
+            }
+            None => Err(self.missing_mods_error(vec![mod_name.into()])),
+        }
+    }
+
+    /// Get the type of a mod as recorded by the game, e.g. `vehicle` or `terrain`.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    ///
+    /// # Returns
+    ///
+    /// `Some(&str)`: The mod's type.
+    /// `None`: If the mod doesn't exist or the game hasn't recorded its type.
+    pub fn mod_type(&self, mod_name: &str) -> Option<&str> {
+        self.mods.get(mod_name)?.mod_type()
+    }
+
+    /// Get the active status of a mod.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod to check the active status of.
+    ///
+    /// # Returns
+    ///
+    /// `Some(bool)`: The active status of the mod if it exists.
+    /// `None`: If the mod doesn't exist in the ModCfg.
+    pub fn is_mod_active(&self, mod_name: &str) -> Option<bool> {
+        self.mods
+            .get(self.resolve_alias(mod_name))
+            .map(|m| m.active)
+    }
+
+    /// Set the names of removed mods that refer to the mods that replace them.
+    ///
+    /// Presets and other references to a removed mod keep working through its alias. Aliases are
+    /// ignored for mods that are installed again.
+    ///
+    /// # Arguments
+    ///
+    /// `aliases`: The names of removed mods paired with the names of the mods that replace them.
+    pub fn set_aliases(&mut self, aliases: impl IntoIterator<Item = (String, String)>) {
+        self.aliases = aliases.into_iter().collect();
+    }
+
+    /// Choose whether the mod configuration is saved as compact JSON, matching how the game
+    /// writes db.json, or pretty-printed.
+    ///
+    /// # Arguments
+    ///
+    /// `compact`: Whether to save compact JSON.
+    pub fn set_compact(&mut self, compact: bool) {
+        self.dirty |= self.compact != compact;
+        self.compact = compact;
+    }
+
+    /// Choose whether the mod configuration is saved with sorted keys, so repeated saves are
+    /// byte-identical and diff cleanly when db.json is tracked with git or a sync tool.
+    ///
+    /// # Arguments
+    ///
+    /// `sorted`: Whether to sort keys.
+    pub fn set_sorted(&mut self, sorted: bool) {
+        self.dirty |= self.sorted != sorted;
+        self.sorted = sorted;
+    }
+
+    /// Whether the mod configuration changed since it was loaded with `ModCfg::load_from_path`.
+    ///
+    /// Saving a mod configuration that hasn't changed would only rewrite db.json with what it
+    /// already holds, so callers can skip saving it.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::game::ModCfg;
+    /// # use tempfile::tempdir;
+    ///
+    /// # let temp_mods_dir = tempdir().unwrap();
+    /// # let mods_dir = temp_mods_dir.path();
+    /// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{\"mod1\":{\"active\":true}}}").unwrap();
+    /// let mut mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+    /// mod_cfg.set_mod_active("mod1", true).unwrap();
+    /// assert!(!mod_cfg.is_dirty());
+    ///
+    /// mod_cfg.set_mod_active("mod1", false).unwrap();
+    /// assert!(mod_cfg.is_dirty());
+    /// ```
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Copy BeamMM's own settings, which aren't saved to db.json, from another ModCfg. Useful when
+    /// reloading db.json.
+    ///
+    /// # Arguments
+    ///
+    /// `other`: The ModCfg to copy the blacklist, whitelist, aliases, overrides, management, and
+    /// output format from.
+    pub fn copy_settings_from(&mut self, other: &ModCfg) {
+        self.blacklist = other.blacklist.clone();
+        self.whitelist = other.whitelist.clone();
+        self.aliases = other.aliases.clone();
+        self.overrides = other.overrides.clone();
+        self.management = other.management.clone();
+        self.compact = other.compact;
+        self.sorted = other.sorted;
+    }
+
+    /// Get the name of the mod a name refers to, following its alias if the mod was removed.
+    pub(crate) fn resolve_alias<'a>(&'a self, mod_name: &'a str) -> &'a str {
+        match self.aliases.get(mod_name) {
+            Some(target) if !self.mods.contains_key(mod_name) => target,
+            _ => mod_name,
+        }
+    }
+
+    /// Add a mod entry, replacing the entry with the same name if there is one. Use
+    /// `register_archive` to add a mod for an archive in the mods directory.
+    ///
+    /// The game only sees the entry once the ModCfg is saved with `ModCfg::save_to_path`.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    /// `mod_`: The mod's entry.
+    ///
+    /// # Returns
+    ///
+    /// `Some(Mod)`: The entry that was replaced.
+    /// `None`: If there was no mod with the same name.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::game::{Mod, ModCfg};
+    ///
+    /// let mut mod_cfg = ModCfg::default();
+    /// mod_cfg.add_mod("mod1", Mod::new(true));
+    /// assert_eq!(mod_cfg.is_mod_active("mod1"), Some(true));
+    ///
+    /// let removed = mod_cfg.remove_mod("mod1").unwrap();
+    /// assert!(removed.is_active());
+    /// assert_eq!(mod_cfg.is_mod_active("mod1"), None);
+    /// ```
+    pub fn add_mod(&mut self, mod_name: &str, mod_: Mod) -> Option<Mod> {
+        self.dirty = true;
+        self.mods.insert(mod_name.into(), mod_)
+    }
+
+    /// Remove a mod entry, leaving its archive in place. Use `uninstall_mod` to delete the archive
+    /// as well.
+    ///
+    /// db.json keeps listing the mod until `ModCfg::save_to_path`, so delete the archive only after
+    /// saving, e.g. with `Transaction::remove_on_commit`.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod to remove.
+    ///
+    /// # Errors
+    ///
+    /// MissingMods: If the mod doesn't exist in the ModCfg.
+    pub fn remove_mod(&mut self, mod_name: &str) -> Result<Mod> {
+        let mod_ = self
+            .mods
+            .remove(mod_name)
+            .ok_or_else(|| self.missing_mods_error(vec![mod_name.into()]))?;
+        self.dirty = true;
+        Ok(mod_)
+    }
+
+    /// Remove a mod and delete its archive, sending the archive to the system trash unless it
+    /// should be removed permanently.
+    ///
+    /// The archive is deleted right away, while db.json keeps listing the mod until
+    /// `ModCfg::save_to_path`.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod to remove.
+    /// `mods_dir`: The mods directory of the current game version.
+    /// `permanent`: Whether to remove the archive permanently instead of sending it to the trash.
+    ///
+    /// # Errors
+    ///
+    /// MissingMods: If the mod doesn't exist in the ModCfg.
+    /// Possible IO errors if there is an issue deleting the archive.
+    #[cfg(feature = "native")]
+    pub fn uninstall_mod(
+        &mut self,
+        mod_name: &str,
+        mods_dir: impl AsRef<Path>,
+        permanent: bool,
+    ) -> Result<()> {
+        let mods_dir = mods_dir.as_ref();
+        let archive = self.archive_path(mod_name, mods_dir);
+        self.remove_mod(mod_name)?;
+        if let Some(archive) = archive {
+            if archive.try_exists()? {
+                crate::path::remove_file(archive, permanent)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rename a mod, renaming its archive to match. The archive keeps its directory and extension.
+    ///
+    /// The archive is renamed right away, while db.json keeps the old name until
+    /// `ModCfg::save_to_path`. Record the returned move in the run's `Transaction`, so the archive
+    /// is renamed back if saving fails.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod to rename.
+    /// `new_name`: The new name of the mod.
+    /// `mods_dir`: The mods directory of the current game version.
+    ///
+    /// # Returns
+    ///
+    /// The archive's old and new paths, or `None` if no archive was renamed.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingMods`: If the mod doesn't exist in the ModCfg.
+    /// * `ModExists`: If another mod or archive already has the new name.
+    /// * `std::io::Error`: If there is an issue renaming the archive.
+    #[cfg(feature = "native")]
+    pub fn rename_mod(
+        &mut self,
+        mod_name: &str,
+        new_name: &str,
+        mods_dir: impl AsRef<Path>,
+    ) -> Result<Option<(PathBuf, PathBuf)>> {
+        let mods_dir = mods_dir.as_ref();
+        if !self.mods.contains_key(mod_name) {
+            return Err(self.missing_mods_error(vec![mod_name.into()]));
+        }
+        let taken = || ModExists {
+            mod_name: new_name.into(),
+        };
+        if new_name != mod_name && self.mods.contains_key(new_name) {
+            return Err(taken());
+        }
+
+        let archive = self.archive_path(mod_name, mods_dir);
+        let extension = archive
+            .as_ref()
+            .and_then(|a| a.extension())
+            .and_then(|e| e.to_str())
+            .unwrap_or("zip")
+            .to_owned();
+        let new_filename = format!("{}.{}", new_name, extension);
+        let mut moved = None;
+        if let Some(archive) = archive.filter(|a| a.exists()) {
+            let target = archive.with_file_name(&new_filename);
+            // On case-insensitive file systems, the target exists if only the case changes.
+            let same_file =
+                target.to_string_lossy().to_lowercase() == archive.to_string_lossy().to_lowercase();
+            if target.try_exists()? && !same_file {
+                return Err(taken());
+            }
+            if target != archive {
+                fs::rename(&archive, &target)?;
+                moved = Some((archive, target));
+            }
+        }
+
+        // The entry was found above.
+        let mut mod_ = self.mods.remove(mod_name).unwrap();
+        let dir_name = mod_
+            .full_path()
+            .and_then(|p| p.rfind('/').map(|i| p[..=i].to_owned()))
+            .unwrap_or("/mods/".into());
+        mod_.set_archive(&format!("{}{}", dir_name, new_filename));
+        if mod_.mod_name.is_some() {
+            mod_.mod_name = Some(new_name.into());
+        }
+        self.mods.insert(new_name.into(), mod_);
+        self.dirty = true;
+        Ok(moved)
+    }
+}
+
+/// What applying presets did, before it is turned into a result.
+#[derive(Debug, Default)]
+struct AppliedPresets {
+    /// The missing mods of presets that failed because of them.
+    missing_mods: HashSet<String>,
+    /// The presets that failed.
+    failed_presets: HashSet<String>,
+    /// Mods paired with the mods they require that aren't installed.
+    unmet_requirements: Vec<(String, String)>,
+    /// The indices of the presets that were pruned.
+    pruned: Vec<usize>,
+}
+
+impl AppliedPresets {
+    /// Turn what happened into an error if any presets failed.
+    fn into_result(self) -> Result<()> {
+        if self.failed_presets.is_empty() {
+            Ok(())
+        } else {
+            Err(PresetsFailed {
+                mods: self.missing_mods,
+                presets: self.failed_presets,
+                requirements: self.unmet_requirements,
+            })
+        }
+    }
+}
+
+/// Rewrite JSON the game tolerates into valid JSON by dropping trailing commas and replacing
+/// non-finite numbers, e.g. `NaN`, `-inf`, or `1.#INF`, with `null`.
+///
+/// # Arguments
+///
+/// `json`: The JSON to rewrite, without a byte order mark.
+pub(crate) fn lenient_json(json: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(json.len());
+    let mut i = 0;
+    while i < json.len() {
+        match json[i] {
+            b'"' => {
+                // Copy strings verbatim, including escaped quotes.
+                let start = i;
+                i += 1;
+                while i < json.len() && json[i] != b'"' {
+                    i += if json[i] == b'\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(json.len());
+                out.extend_from_slice(&json[start..i]);
+            }
+            b',' => {
+                let next = json[i + 1..].iter().find(|b| !b.is_ascii_whitespace());
+                if !matches!(next, Some(b'}' | b']')) {
+                    out.push(b',');
+                }
+                i += 1;
+            }
+            b if b.is_ascii_alphanumeric() || b == b'-' || b == b'+' || b == b'.' => {
+                let start = i;
+                while i < json.len()
+                    && (json[i].is_ascii_alphanumeric() || b"-+.#()".contains(&json[i]))
+                {
+                    i += 1;
+                }
+                let token = String::from_utf8_lossy(&json[start..i]);
+                let valid = matches!(token.as_ref(), "true" | "false" | "null")
+                    || token.parse::<f64>().is_ok_and(f64::is_finite);
+                if valid {
+                    out.extend_from_slice(&json[start..i]);
+                } else {
+                    out.extend_from_slice(b"null");
+                }
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Get every game version that has a version directory in the game's data directory.
+///
+/// # Arguments
+///
+/// `data_dir`: The game's data directory. Usually `%LocalAppData%/BeamNG.drive`.
+///
+/// # Returns
+///
+/// The versions found, sorted from oldest to newest.
+///
+/// # Errors
+///
+/// * `DirNotFound`: If the specified `data_dir` doesn't exist.
+/// * `std::io::Error`: If there is trouble reading the directory.
+#[cfg(feature = "native")]
+pub fn installed_versions(data_dir: impl AsRef<Path>) -> Result<Vec<GameVersion>> {
+    let data_dir = data_dir.as_ref();
+    if !data_dir.try_exists()? {
+        return Err(DirNotFound {
+            dir: data_dir.to_owned(),
+        });
+    }
+    let mut versions: Vec<GameVersion> = fs::read_dir(data_dir)?
+        .filter_map(|f| f.ok().map(|f| f.path())) // Get rid of errors and map to path type
+        .filter(|f| f.is_dir())
+        .filter_map(|d| d.file_name()?.to_str()?.parse().ok()) // Toss out non-version dirs.
+        .collect();
+    versions.sort();
+    Ok(versions)
+}
+
+/// How to handle enabled presets that reference mods missing from the ModCfg.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "native", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum MissingModPolicy {
+    /// Fail the whole preset. None of its mods are enabled.
+    #[default]
+    Fail,
+    /// Enable the mods that exist and skip the missing ones.
+    Skip,
+    /// Remove the missing mods from the preset file, then enable the rest.
+    Prune,
+}
+
+impl FromStr for MissingModPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "fail" => Ok(Self::Fail),
+            "skip" => Ok(Self::Skip),
+            "prune" => Ok(Self::Prune),
+            _ => Err(InvalidMissingModPolicy { policy: s.into() }),
+        }
+    }
+}
+
+/// How much say presets have over a mod, set per mod with `ModCfg::set_management`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Management {
+    /// Enabled presets enable the mod, but it is otherwise left as it is set.
+    #[default]
+    Observed,
+    /// The mod belongs to the presets: it is enabled while an enabled preset contains it and
+    /// disabled otherwise.
+    Managed,
+    /// BeamMM never changes the mod, e.g. because it is managed in game. Presets and bulk changes
+    /// like `ModCfg::set_all_mods_active` leave its state alone, and saving db.json keeps the
+    /// state the game gave it. Only changing the mod by name still does, and is saved.
+    Ignored,
+}
+
+/// A set of changes to the active state of mods.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChangeSet {
+    /// Mods that are turned on, sorted by name.
+    pub enabled: Vec<String>,
+    /// Mods that are turned off, sorted by name.
+    pub disabled: Vec<String>,
+    /// Enabled presets that fail to apply because of missing mods, sorted by name.
+    pub failed_presets: Vec<String>,
+}
+
+impl ChangeSet {
+    /// Compute the changes that turn `before` into `after`.
+    ///
+    /// Mods that only exist in one of the two ModCfgs are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// `before`: The ModCfg before the changes.
+    /// `after`: The ModCfg after the changes.
+    pub fn between(before: &ModCfg, after: &ModCfg) -> Self {
+        let mut changes = Self::default();
+        for (mod_name, mod_) in &after.mods {
+            match before.is_mod_active(mod_name) {
+                Some(false) if mod_.active => changes.enabled.push(mod_name.clone()),
+                Some(true) if !mod_.active => changes.disabled.push(mod_name.clone()),
+                _ => (),
+            }
+        }
+        changes.enabled.sort();
+        changes.disabled.sort();
+        changes
+    }
+
+    /// Whether the ChangeSet changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.enabled.is_empty() && self.disabled.is_empty() && self.failed_presets.is_empty()
+    }
+}
+
+/// The directories inside the mods directory where the game keeps mods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ModRoot {
+    /// `mods/` itself, where mods installed by hand go.
+    Mods,
+    /// `mods/repo/`, where the game keeps mods subscribed to in the repository browser.
+    Repo,
+    /// `mods/unpacked/`, where the game keeps unpacked mods, which are folders instead of archives.
+    Unpacked,
+}
+
+impl ModRoot {
+    /// Every mods root.
+    pub const ALL: [Self; 3] = [Self::Mods, Self::Repo, Self::Unpacked];
+
+    /// Get the name of the root's directory inside the mods directory, or `None` for the mods
+    /// directory itself.
+    pub fn dir_name(self) -> Option<&'static str> {
+        match self {
+            Self::Mods => None,
+            Self::Repo => Some("repo"),
+            Self::Unpacked => Some("unpacked"),
+        }
+    }
+
+    /// Find the root a path recorded by the game is in.
+    ///
+    /// # Arguments
+    ///
+    /// `path`: A path relative to the version directory, e.g. `/mods/repo/mod.zip`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::game::ModRoot;
+    ///
+    /// assert_eq!(ModRoot::of_game_path("/mods/repo/mod.zip"), ModRoot::Repo);
+    /// assert_eq!(ModRoot::of_game_path("/mods/unpacked/mod/"), ModRoot::Unpacked);
+    /// assert_eq!(ModRoot::of_game_path("/mods/mod.zip"), ModRoot::Mods);
+    /// ```
+    pub fn of_game_path(path: &str) -> Self {
+        let path = path.to_lowercase().replace('\\', "/");
+        let relative = path.trim_start_matches('/');
+        let relative = relative.strip_prefix("mods/").unwrap_or(relative);
+        Self::ALL
+            .into_iter()
+            .find(|root| {
+                root.dir_name()
+                    .is_some_and(|dir| relative.starts_with(&format!("{}/", dir)))
+            })
+            .unwrap_or(Self::Mods)
+    }
+}
+
+impl std::fmt::Display for ModRoot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Mods in the mods directory itself were installed by hand.
+        f.write_str(self.dir_name().unwrap_or("local"))
+    }
+}
+
+/// The version of a mod installed from the BeamNG.drive repository.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RepoVersion {
+    /// The repository's ID of the mod.
+    pub resource_id: u64,
+    /// The repository's ID of the installed version of the mod.
+    pub version_id: u64,
+    /// The human readable version, if the repository provides one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// Read an ID from the `modData` the game stores for repository mods.
+fn mod_data_id(mod_data: &serde_json::Value, key: &str) -> Option<u64> {
+    // The game isn't consistent about storing IDs as numbers or strings.
+    match mod_data.get(key)? {
+        serde_json::Value::Number(n) => n.as_u64(),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Read a string from the `modData` the game stores for repository mods.
+fn mod_data_str(mod_data: &serde_json::Value, key: &str) -> Option<String> {
+    mod_data
+        .get(key)
+        .and_then(serde_json::Value::as_str)
+        .map(String::from)
+}
+
+impl RepoVersion {
+    /// Read the repository version from the `modData` the game stores for repository mods.
+    fn from_mod_data(mod_data: &serde_json::Value) -> Option<Self> {
+        Some(Self {
+            resource_id: mod_data_id(mod_data, "resource_id")?,
+            version_id: mod_data_id(mod_data, "current_version_id")?,
+            version: mod_data_str(mod_data, "version_string"),
+        })
+    }
+
+    /// Get the URL of the mod's page on the repository.
+    pub fn url(&self) -> String {
+        format!("https://www.beamng.com/resources/{}/", self.resource_id)
+    }
+}
+
+impl std::fmt::Display for RepoVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.version {
+            Some(version) => write!(
+                f,
+                "{} (resource {}, version {})",
+                version, self.resource_id, self.version_id
+            ),
+            None => write!(
+                f,
+                "resource {}, version {}",
+                self.resource_id, self.version_id
+            ),
+        }
+    }
+}
+
+/// What the game records about a mod installed from the BeamNG.drive repository, read from the
+/// mod's `modData` in db.json. Anything the game didn't record is `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoInfo {
+    /// The repository's tag for the mod, e.g. `M1234`.
+    pub tag_id: Option<String>,
+    /// The repository's ID of the mod.
+    pub resource_id: Option<u64>,
+    /// The repository's ID of the installed version of the mod.
+    pub version_id: Option<u64>,
+    /// The human readable version.
+    pub version: Option<String>,
+    /// The mod's title on the repository.
+    pub title: Option<String>,
+    /// The name of the mod's author on the repository.
+    pub username: Option<String>,
+    /// Whether the mod was subscribed to in the game's repository browser, meaning the game keeps
+    /// it in the `mods/repo` directory and updates it itself.
+    pub subscribed: bool,
+}
+
+impl RepoInfo {
+    /// Get the repository version, if both IDs are known.
+    pub fn repo_version(&self) -> Option<RepoVersion> {
+        Some(RepoVersion {
+            resource_id: self.resource_id?,
+            version_id: self.version_id?,
+            version: self.version.clone(),
+        })
+    }
+}
+
+/// A struct representing a BeamNG.drive mod.
+///
+/// The fields the game commonly records are typed. Everything else is kept as it is, so it's
+/// written back unchanged.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Mod {
+    /// Whether the mod is active.
+    active: bool,
+
+    /// The name the game gave the mod.
+    #[serde(default, rename = "modname", skip_serializing_if = "Option::is_none")]
+    mod_name: Option<String>,
+
+    /// The filename of the mod's archive, e.g. `mod.zip`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    filename: Option<String>,
+
+    /// The path to the mod's archive relative to the version directory, e.g. `/mods/repo/mod.zip`.
+    #[serde(default, rename = "fullpath", skip_serializing_if = "Option::is_none")]
+    full_path: Option<String>,
+
+    /// The directory of the mod's archive relative to the version directory, e.g. `/mods/repo/`.
+    #[serde(default, rename = "dirname", skip_serializing_if = "Option::is_none")]
+    dir_name: Option<String>,
+
+    /// The mod's type, e.g. `vehicle` or `terrain`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mod_type: Option<String>,
+
+    /// When the game registered the mod, in seconds since the Unix epoch. The game writes Lua
+    /// numbers, so this may not be a whole number, and it's kept as written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    date_added: Option<serde_json::Number>,
+
+    /// Information about the mod's archive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stat: Option<ModStat>,
+
+    /// Other currently unimportant data.
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_json::Value>,
+}
+
+/// Information the game records about a mod's archive.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct ModStat {
+    /// The size of the archive in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    filesize: Option<serde_json::Number>,
+
+    /// When the archive was last modified, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modtime: Option<serde_json::Number>,
+
+    /// Other currently unimportant data.
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_json::Value>,
+}
+
+/// Turn a number the game wrote into a whole number, ignoring negative numbers.
+fn whole_number(number: Option<&serde_json::Number>) -> Option<u64> {
+    let number = number?;
+    number
+        .as_u64()
+        .or_else(|| number.as_f64().filter(|n| *n >= 0.0).map(|n| n as u64))
+}
+
+impl Mod {
+    /// Create a mod entry with nothing but its active status. The game fills in the rest the next
+    /// time it starts, as long as it can find the archive, see `set_archive`.
+    ///
+    /// # Arguments
+    ///
+    /// `active`: Whether the mod is active.
+    pub fn new(active: bool) -> Self {
+        Self {
+            active,
+            ..Self::default()
+        }
+    }
+
+    /// Set where the mod's archive is, updating `fullpath`, `filename`, and `dirname`.
+    ///
+    /// # Arguments
+    ///
+    /// `full_path`: The path to the archive relative to the version directory, e.g.
+    ///   `/mods/repo/mod.zip`.
+    pub fn set_archive(&mut self, full_path: &str) {
+        let (dir_name, filename) = match full_path.rfind('/') {
+            Some(i) => full_path.split_at(i + 1),
+            None => ("", full_path),
+        };
+        self.dir_name = Some(dir_name.into());
+        self.filename = Some(filename.into());
+        self.full_path = Some(full_path.into());
+    }
+
+    /// Whether the mod is active.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Get the name the game gave the mod, if it recorded one.
+    pub fn mod_name(&self) -> Option<&str> {
+        self.mod_name.as_deref()
+    }
+
+    /// Get the filename of the mod's archive, e.g. `mod.zip`.
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Get the path to the mod's archive relative to the version directory, e.g.
+    /// `/mods/repo/mod.zip`.
+    pub fn full_path(&self) -> Option<&str> {
+        self.full_path.as_deref()
+    }
+
+    /// Get the directory of the mod's archive relative to the version directory, e.g.
+    /// `/mods/repo/`.
+    pub fn dir_name(&self) -> Option<&str> {
+        self.dir_name.as_deref()
+    }
+
+    /// Get the mod's type as recorded by the game, e.g. `vehicle` or `terrain`.
+    pub fn mod_type(&self) -> Option<&str> {
+        self.mod_type.as_deref()
+    }
+
+    /// Get when the game registered the mod, in seconds since the Unix epoch.
+    pub fn date_added(&self) -> Option<u64> {
+        whole_number(self.date_added.as_ref())
+    }
+
+    /// Get the size of the mod's archive in bytes, as recorded by the game.
+    pub fn file_size(&self) -> Option<u64> {
+        whole_number(self.stat.as_ref()?.filesize.as_ref())
+    }
+
+    /// Get when the mod's archive was last modified, in seconds since the Unix epoch, as recorded
+    /// by the game.
+    pub fn modified(&self) -> Option<u64> {
+        whole_number(self.stat.as_ref()?.modtime.as_ref())
+    }
+
+    /// Get the repository version, if the mod was installed from the repository.
+    pub fn repo_version(&self) -> Option<RepoVersion> {
+        RepoVersion::from_mod_data(self.other.get("modData")?)
+    }
+
+    /// Get what the game records about the mod on the repository, if it was installed from the
+    /// repository.
+    pub fn repo_info(&self) -> Option<RepoInfo> {
+        let mod_data = self.other.get("modData")?;
+        Some(RepoInfo {
+            tag_id: mod_data_str(mod_data, "tagid"),
+            resource_id: mod_data_id(mod_data, "resource_id"),
+            version_id: mod_data_id(mod_data, "current_version_id")
+                .or_else(|| mod_data_id(mod_data, "resource_version_id")),
+            version: mod_data_str(mod_data, "version_string"),
+            title: mod_data_str(mod_data, "title"),
+            username: mod_data_str(mod_data, "username"),
+            subscribed: self.is_subscribed(),
+        })
+    }
+
+    /// Whether the mod was subscribed to in the game's repository browser. The game keeps
+    /// subscribed mods in the `mods/repo` directory.
+    pub fn is_subscribed(&self) -> bool {
+        self.root() == ModRoot::Repo
+    }
+
+    /// Get the mods root the mod is installed in, based on where the game says it is.
+    pub fn root(&self) -> ModRoot {
+        ModRoot::of_game_path(self.dir_name().or(self.full_path()).unwrap_or_default())
+    }
+
+    /// Record the repository version in the mod's `modData`, leaving the rest of it as it is.
+    ///
+    /// # Arguments
+    ///
+    /// `version`: The repository version of the installed archive.
+    pub fn set_repo_version(&mut self, version: &RepoVersion) {
+        let mod_data = self
+            .other
+            .entry("modData".into())
+            .or_insert_with(|| serde_json::json!({}));
+        if !mod_data.is_object() {
+            *mod_data = serde_json::json!({});
+        }
+        mod_data["resource_id"] = version.resource_id.into();
+        mod_data["current_version_id"] = version.version_id.into();
+        match &version.version {
+            Some(v) => mod_data["version_string"] = v.as_str().into(),
+            None => {
+                if let Some(mod_data) = mod_data.as_object_mut() {
+                    mod_data.remove("version_string");
+                }
+            }
+        }
+    }
+
+    /// Get the rest of the mod's entry in db.json.
+    pub fn other(&self) -> &BTreeMap<String, serde_json::Value> {
+        &self.other
+    }
+}
+
+/// A mod in a ModCfg along with its name, as yielded by `ModCfg::iter_mods`.
+#[derive(Debug, Clone, Copy)]
+pub struct ModEntry<'a> {
+    /// The name of the mod.
+    name: &'a str,
+    /// The mod's entry in db.json.
+    mod_: &'a Mod,
+}
+
+impl<'a> ModEntry<'a> {
+    /// Get the name of the mod.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Whether the mod is active.
+    pub fn is_active(&self) -> bool {
+        self.mod_.active
+    }
+
+    /// Get the mod's type as recorded by the game, e.g. `vehicle` or `terrain`.
+    pub fn mod_type(&self) -> Option<&'a str> {
+        self.mod_.mod_type()
+    }
+
+    /// Get the repository version, if the mod was installed from the repository.
+    pub fn repo_version(&self) -> Option<RepoVersion> {
+        self.mod_.repo_version()
+    }
+
+    /// Get the mod's entry in db.json.
+    pub fn entry(&self) -> &'a Mod {
+        self.mod_
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+
+    #[test]
+    fn listing_installed_versions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let data_dir = tmp.path();
+        for dir in ["0.10", "0.9", "0.32", "mods", "0.33.1"] {
+            fs::create_dir(data_dir.join(dir)).unwrap();
+        }
+        fs::write(data_dir.join("0.34"), "not a directory").unwrap();
+
+        let versions: Vec<String> = installed_versions(data_dir)
+            .unwrap()
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(versions, vec!["0.9", "0.10", "0.32", "0.33.1"]);
+    }
+
+    #[test]
+    fn loading_modcfg() {
+        let mock_dirs = MockData::new();
+
+        // Load the modcfg here instead of just relying on the MockDirs struct so we can test the loading.
+        let mod_cfg = ModCfg::load_from_path(&mock_dirs.mods_dir).unwrap();
+
+        assert_eq!(mod_cfg.mods.len(), 3);
+        assert!(mod_cfg.mods.get("mod1").unwrap().active);
+        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
+        assert!(mod_cfg.mods.get("mod3").unwrap().active);
+
+        // Check that the other data is preserved.
+        assert_eq!(mod_cfg.other.len(), 1);
+        let other_data = mod_cfg.other.get("other").unwrap();
+        assert_eq!(
+            other_data.get("key").unwrap(),
+            &serde_json::Value::String("value".into())
+        );
+    }
+
+    #[test]
+    fn load_bad_path() {
+        let tmp = tempfile::tempdir().unwrap();
+        let temp_dir = tmp.path();
+
+        let result = ModCfg::load_from_path(temp_dir.join("bad_path"));
+        assert!(matches!(result, Err(DirNotFound { .. })));
+    }
+
+    #[test]
+    fn loading_lenient_modcfg() {
+        let json = "\u{feff}{\"mods\":{\"mod1\":{\"active\":true,\"stat\":{\"size\":NaN,\"time\":-1.#INF},\"title\":\"NaN, ]\",},},}";
+        let mod_cfg = ModCfg::load(json.as_bytes()).unwrap();
+
+        assert!(mod_cfg.is_mod_active("mod1").unwrap());
+        let mod1 = mod_cfg.mods.get("mod1").unwrap();
+        let stat = mod1.stat.as_ref().unwrap();
+        assert_eq!(stat.other["size"], serde_json::Value::Null);
+        assert_eq!(stat.other["time"], serde_json::Value::Null);
+        assert_eq!(mod1.other["title"], "NaN, ]");
+    }
+
+    #[test]
+    fn loading_invalid_modcfg() {
+        let json = "{\"mods\":{\n  \"mod1\":{\"active\" true}\n}}";
+        let result = ModCfg::load(json.as_bytes());
+
+        match result {
+            Err(InvalidModCfg {
+                line,
+                column,
+                snippet,
+                ..
+            }) => {
+                assert_eq!((line, column), (2, 20));
+                assert_eq!(snippet, "\"mod1\":{\"active\" true}");
+            }
+            other => panic!("expected InvalidModCfg, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn load_or_default_modcfg() {
+        let mock_dirs = MockData::new();
+        let mod_cfg = ModCfg::load_or_default(&mock_dirs.mods_dir).unwrap();
+        assert!(mod_cfg.is_mod_active("mod1").unwrap());
+
+        fs::remove_file(mock_dirs.mods_dir.join("db.json")).unwrap();
+        let mod_cfg = ModCfg::load_or_default(&mock_dirs.mods_dir).unwrap();
+        assert_eq!(mod_cfg.get_mods().count(), 0);
+        // Loading never writes db.json, saving does.
+        assert!(!mock_dirs.mods_dir.join("db.json").exists());
+        assert!(mod_cfg.is_dirty());
+        mod_cfg.save_to_path(&mock_dirs.mods_dir).unwrap();
+        let saved = fs::read_to_string(mock_dirs.mods_dir.join("db.json")).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&saved).unwrap(),
+            serde_json::json!({ "mods": {} })
+        );
+
+        let result = ModCfg::load_or_default(mock_dirs.mods_dir.join("bad_path"));
+        assert!(matches!(result, Err(DirNotFound { .. })));
+    }
+
+    #[test]
     fn save_modcfg() {
         let mock_dirs = MockData::new();
 
-        let mut mod_cfg = mock_dirs.modcfg;
-        mod_cfg.mods.get_mut("mod1").unwrap().active = false;
+        let mut mod_cfg = mock_dirs.modcfg;
+        mod_cfg.mods.get_mut("mod1").unwrap().active = false;
+
+        mod_cfg.save_to_path(&mock_dirs.mods_dir).unwrap();
+
+        let mod_cfg = ModCfg::load_from_path(&mock_dirs.mods_dir).unwrap();
+
+        assert!(!mod_cfg.mods.get("mod1").unwrap().active);
+    }
+
+    #[test]
+    fn save_compact_modcfg() {
+        let mock_dirs = MockData::new();
+
+        let mut mod_cfg = mock_dirs.modcfg;
+        mod_cfg.set_compact(true);
+        mod_cfg.save_to_path(&mock_dirs.mods_dir).unwrap();
+
+        let saved = fs::read_to_string(mock_dirs.mods_dir.join("db.json")).unwrap();
+        assert!(!saved.contains('\n'));
+        let loaded = ModCfg::load_from_path(&mock_dirs.mods_dir).unwrap();
+        assert!(loaded.is_mod_active("mod1").unwrap());
+    }
+
+    #[test]
+    fn save_sorted_modcfg() {
+        let mock_dirs = MockData::new();
+        let db = r#"{"mods":{"mod1":{"modname":"mod1","active":true,"zeta":1,"alpha":[2,1]}},"installed":1}"#;
+        fs::write(mock_dirs.mods_dir.join("db.json"), db).unwrap();
+
+        let mut mod_cfg = ModCfg::load_from_path(&mock_dirs.mods_dir).unwrap();
+        mod_cfg.set_sorted(true);
+        mod_cfg.set_compact(true);
+        mod_cfg.save_to_path(&mock_dirs.mods_dir).unwrap();
+
+        let saved = fs::read_to_string(mock_dirs.mods_dir.join("db.json")).unwrap();
+        assert_eq!(
+            saved,
+            r#"{"installed":1,"mods":{"mod1":{"active":true,"alpha":[2,1],"modname":"mod1","zeta":1}}}"#
+        );
+        // Saving again gives the same bytes.
+        let mut reloaded = ModCfg::load_from_path(&mock_dirs.mods_dir).unwrap();
+        reloaded.copy_settings_from(&mod_cfg);
+        let mut bytes = vec![];
+        reloaded.save(&mut bytes).unwrap();
+        assert_eq!(bytes, saved.as_bytes());
+    }
+
+    #[test]
+    fn set_mod_active() {
+        let mock_dirs = MockData::new();
+
+        let mut mod_cfg = mock_dirs.modcfg;
+        mod_cfg.set_mod_active("mod1", false).unwrap();
+
+        assert!(!mod_cfg.mods.get("mod1").unwrap().active);
+
+        mod_cfg.set_mod_active("mod1", true).unwrap();
+
+        assert!(mod_cfg.mods.get("mod1").unwrap().active);
+    }
+
+    #[test]
+    fn set_mod_active_missing() {
+        let mock_dirs = MockData::new();
+
+        let mut mod_cfg = mock_dirs.modcfg;
+
+        let result = mod_cfg.set_mod_active("fake_mod", true);
+        assert!(matches!(result, Err(MissingMods { .. })));
+    }
+
+    #[test]
+    fn suggesting_mods() {
+        let mock_dirs = MockData::new();
+
+        let mut mod_cfg = mock_dirs.modcfg;
+
+        assert_eq!(mod_cfg.suggest_mod("MOD1"), Some("mod1".into()));
+        assert_eq!(mod_cfg.suggest_mod("mdo"), None);
+        assert_eq!(mod_cfg.suggest_mod("unrelated"), None);
+
+        let result = mod_cfg.set_mods_active(["mod_2", "fake_mod"], true);
+        match result {
+            Err(e @ MissingMods { .. }) => {
+                assert_eq!(
+                    e.to_string(),
+                    "Mods not found: [\"mod_2\", \"fake_mod\"]. Did you mean `mod2`?"
+                );
+            }
+            other => panic!("expected MissingMods, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tracking_changes() {
+        let mock_data = MockData::new();
+        let mut mod_cfg = ModCfg::load_from_path(&mock_data.mods_dir).unwrap();
+        assert!(!mod_cfg.is_dirty());
+
+        // Setting a mod to the state it is already in changes nothing.
+        mod_cfg.set_mod_active("mod1", true).unwrap();
+        mod_cfg
+            .apply_presets(&mock_data.presets_dir, MissingModPolicy::Fail)
+            .unwrap();
+        assert!(!mod_cfg.is_dirty());
+
+        mod_cfg.set_mod_active("mod1", false).unwrap();
+        assert!(mod_cfg.is_dirty());
+
+        let mut mod_cfg = ModCfg::load_from_path(&mock_data.mods_dir).unwrap();
+        mod_cfg.remove_mod("mod3").unwrap();
+        assert!(mod_cfg.is_dirty());
+    }
+
+    #[test]
+    fn set_mods_active() {
+        let mock_dirs = MockData::new();
+
+        let mut mod_cfg = mock_dirs.modcfg;
+        mod_cfg.set_mods_active(["mod1", "mod2"], false).unwrap();
+
+        assert!(!mod_cfg.mods.get("mod1").unwrap().active);
+        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
+
+        mod_cfg.set_mods_active(["mod1", "mod2"], true).unwrap();
+
+        assert!(mod_cfg.mods.get("mod1").unwrap().active);
+        assert!(mod_cfg.mods.get("mod2").unwrap().active);
+    }
+
+    #[test]
+    fn set_mods_active_missing() {
+        let mock_dirs = MockData::new();
+
+        let mut mod_cfg = mock_dirs.modcfg;
+
+        let result = mod_cfg.set_mods_active(["mod1", "fake_mod"], true);
+        assert!(matches!(result, Err(MissingMods { .. })));
+
+        // Check that no mods were set active.
+        assert!(mod_cfg.mods.get("mod1").unwrap().active);
+        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
+    }
+
+    #[test]
+    fn set_all_mods_active() {
+        let mock_dirs = MockData::new();
+
+        let mut mod_cfg = mock_dirs.modcfg;
+        mod_cfg.set_all_mods_active(false).unwrap();
+
+        assert!(!mod_cfg.mods.get("mod1").unwrap().active);
+        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
+
+        mod_cfg.set_all_mods_active(true).unwrap();
+
+        assert!(mod_cfg.mods.get("mod1").unwrap().active);
+        assert!(mod_cfg.mods.get("mod2").unwrap().active);
+    }
+
+    #[test]
+    fn blacklisted_mods_are_never_enabled() {
+        let mock_dirs = MockData::new();
+
+        let mut mod_cfg = mock_dirs.modcfg;
+        mod_cfg.set_blacklist(["mod2".to_string()]);
+
+        let result = mod_cfg.set_mod_active("mod2", true);
+        assert!(matches!(result, Err(BlacklistedMods { .. })));
+        let result = mod_cfg.set_mods_active(["mod1", "mod2"], true);
+        assert!(matches!(result, Err(BlacklistedMods { .. })));
+        assert!(!mod_cfg.is_mod_active("mod2").unwrap());
+
+        // Enabling all mods skips blacklisted ones, while disabling still works.
+        mod_cfg.set_all_mods_active(false).unwrap();
+        mod_cfg.set_all_mods_active(true).unwrap();
+        assert!(mod_cfg.is_mod_active("mod1").unwrap());
+        assert!(!mod_cfg.is_mod_active("mod2").unwrap());
+    }
+
+    #[test]
+    fn blacklisting_aliases() {
+        let mock_dirs = MockData::new();
+
+        let mut mod_cfg = mock_dirs.modcfg;
+        mod_cfg.set_mod_active("mod2", false).unwrap();
+        mod_cfg.set_aliases([("mod2_copy".to_string(), "mod2".to_string())]);
+        // The removed copy's name is blacklisted, so the mod that replaced it is too.
+        mod_cfg.set_blacklist(["mod2_copy".to_string()]);
+
+        assert!(mod_cfg.is_blacklisted("mod2"));
+        assert!(mod_cfg.is_blacklisted("mod2_copy"));
+        assert!(!mod_cfg.is_blacklisted("mod1"));
+        for name in ["mod2", "mod2_copy"] {
+            let result = mod_cfg.set_mod_active(name, true);
+            assert!(matches!(result, Err(BlacklistedMods { .. })));
+        }
+        assert!(!mod_cfg.is_mod_active("mod2").unwrap());
+    }
+
+    #[test]
+    fn apply_presets_skips_blacklisted_mods() {
+        let mock_data = MockData::new();
+
+        let mut preset2 = mock_data.preset2;
+        let mut mod_cfg = mock_data.modcfg;
+        mod_cfg.set_blacklist(["mod2".to_string()]);
+
+        preset2.enable();
+        preset2.save_to_path(&mock_data.presets_dir).unwrap();
+
+        mod_cfg
+            .apply_presets(&mock_data.presets_dir, MissingModPolicy::Fail)
+            .unwrap();
+
+        assert!(mod_cfg.is_mod_active("mod1").unwrap());
+        assert!(!mod_cfg.is_mod_active("mod2").unwrap());
+    }
+
+    #[test]
+    fn enforcing_whitelist() {
+        let mock_data = MockData::new();
+
+        let mut preset2 = mock_data.preset2;
+        let mut mod_cfg = mock_data.modcfg;
+        mod_cfg.set_whitelist(Some(HashSet::from(["mod1".to_string()])));
+
+        let result = mod_cfg.set_mod_active("mod2", true);
+        assert!(matches!(result, Err(NotWhitelistedMods { .. })));
+
+        // Mods already active are disabled when the whitelist is enforced.
+        assert_eq!(mod_cfg.enforce_whitelist(), vec!["mod3"]);
+        assert!(mod_cfg.is_mod_active("mod1").unwrap());
+        assert!(!mod_cfg.is_mod_active("mod3").unwrap());
+
+        // Presets only enable whitelisted mods.
+        preset2.enable();
+        preset2.save_to_path(&mock_data.presets_dir).unwrap();
+        mod_cfg
+            .apply_presets(&mock_data.presets_dir, MissingModPolicy::Fail)
+            .unwrap();
+        assert!(!mod_cfg.is_mod_active("mod2").unwrap());
+
+        // Without a whitelist, every mod is allowed again.
+        mod_cfg.set_whitelist(None);
+        mod_cfg.set_all_mods_active(true).unwrap();
+        assert!(mod_cfg.enforce_whitelist().is_empty());
+        assert!(mod_cfg.is_mod_active("mod2").unwrap());
+    }
+
+    #[test]
+    fn apply_presets_with_extends() {
+        let mock_data = MockData::new();
+
+        let mut mod_cfg = mock_data.modcfg;
+        mod_cfg.set_all_mods_active(false).unwrap();
+
+        // preset2 is disabled but its mods are still inherited.
+        let mut preset = Preset::new("preset3".into(), vec!["mod3".into()]).unwrap();
+        preset.set_extends(Some("preset2".into()));
+        preset.enable();
+        preset.save_to_path(&mock_data.presets_dir).unwrap();
+        let mut preset1 = mock_data.preset1;
+        preset1.force_disable(&mut mod_cfg);
+        preset1.save_to_path(&mock_data.presets_dir).unwrap();
+
+        mod_cfg
+            .apply_presets(&mock_data.presets_dir, MissingModPolicy::Fail)
+            .unwrap();
+
+        assert!(mod_cfg.is_mod_active("mod1").unwrap());
+        assert!(mod_cfg.is_mod_active("mod2").unwrap());
+        assert!(mod_cfg.is_mod_active("mod3").unwrap());
+    }
+
+    #[test]
+    fn apply_loaded_presets() {
+        let mock_data = MockData::new();
+
+        let mut mod_cfg = mock_data.modcfg;
+        mod_cfg.set_all_mods_active(false).unwrap();
+
+        let mut preset =
+            Preset::new("preset3".into(), vec!["mod3".into(), "missing".into()]).unwrap();
+        preset.set_extends(Some("preset2".into()));
+        preset.enable();
+        preset.set_missing_mod_policy(Some(MissingModPolicy::Prune));
+        let mut presets = vec![mock_data.preset2, preset];
+
+        mod_cfg
+            .apply_loaded_presets(&mut presets, MissingModPolicy::Fail)
+            .unwrap();
+
+        assert!(mod_cfg.is_mod_active("mod1").unwrap());
+        assert!(mod_cfg.is_mod_active("mod2").unwrap());
+        assert!(mod_cfg.is_mod_active("mod3").unwrap());
+        assert_eq!(presets[1].get_mods(), &vec!["mod3".to_string()]);
+
+        // Extended presets must be among the loaded presets.
+        let result = mod_cfg.apply_loaded_presets(&mut presets[1..], MissingModPolicy::Fail);
+        assert!(matches!(result, Err(MissingPreset { .. })));
+    }
+
+    #[test]
+    fn is_mod_active() {
+        let mock_dirs = MockData::new();
+
+        let mod_cfg = mock_dirs.modcfg;
+
+        assert!(mod_cfg.is_mod_active("mod1").unwrap());
+        assert!(!mod_cfg.is_mod_active("mod2").unwrap());
+        assert!(mod_cfg.is_mod_active("mod3").unwrap());
+        assert!(mod_cfg.is_mod_active("fake_mod").is_none());
+    }
+
+    #[test]
+    fn apply_presets() {
+        let mock_data = MockData::new();
+
+        let mut preset1 = mock_data.preset1;
+        let mut preset2 = mock_data.preset2;
+
+        let mut mod_cfg = mock_data.modcfg;
+
+        // Enable both presets and make sure both mods are enabled.
+
+        preset1.enable();
+        preset2.enable();
+
+        preset1.save_to_path(&mock_data.presets_dir).unwrap();
+        preset2.save_to_path(&mock_data.presets_dir).unwrap();
+
+        mod_cfg
+            .apply_presets(&mock_data.presets_dir, MissingModPolicy::Fail)
+            .unwrap();
+
+        assert!(mod_cfg.mods.get("mod1").unwrap().active);
+        assert!(mod_cfg.mods.get("mod2").unwrap().active);
+
+        // Disable just preset 2, which has both mod1 and mod2. Before applying preset, both mods
+        // should be disabled. But, since preset 1 is still enabled, after applying preset, mod1
+        // should be enabled.
+
+        preset2.disable(&mut mod_cfg).unwrap();
+
+        preset2.save_to_path(&mock_data.presets_dir).unwrap();
+
+        assert!(!mod_cfg.mods.get("mod1").unwrap().active);
+        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
+
+        mod_cfg
+            .apply_presets(&mock_data.presets_dir, MissingModPolicy::Fail)
+            .unwrap();
+
+        assert!(mod_cfg.mods.get("mod1").unwrap().active);
+        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
+    }
+
+    #[test]
+    fn apply_presets_missing_mods() {
+        let mock_data = MockData::new();
+
+        let mut preset1 = mock_data.preset1;
+        let mut preset2 = mock_data.preset2;
+
+        let mut mod_cfg = mock_data.modcfg;
+
+        // Enable both presets and make sure both mods are enabled.
+
+        preset1.enable();
+        preset2.enable();
+
+        preset1.save_to_path(&mock_data.presets_dir).unwrap();
+        preset2.save_to_path(&mock_data.presets_dir).unwrap();
+
+        // Remove mod2 from the modcfg so that preset2 will fail to enable.
+        mod_cfg.mods.remove("mod2");
+
+        let result = mod_cfg.apply_presets(&mock_data.presets_dir, MissingModPolicy::Fail);
+        assert!(matches!(result, Err(PresetsFailed { .. })));
+
+        // Check that mod1 is still enabled.
+        assert!(mod_cfg.mods.get("mod1").unwrap().active);
+    }
+
+    #[test]
+    fn apply_presets_requirements() {
+        let mock_data = MockData::new();
 
-        mod_cfg.save_to_path(&mock_dirs.mods_dir).unwrap();
+        let mut mod_cfg = mock_data.modcfg;
+        mod_cfg.set_all_mods_active(false).unwrap();
 
-        let mod_cfg = ModCfg::load_from_path(&mock_dirs.mods_dir).unwrap();
+        // mod1 pulls in mod3 through its requirement.
+        let mut preset1 = mock_data.preset1;
+        preset1.set_requirements("mod1", vec!["mod3".into()]);
+        preset1.save_to_path(&mock_data.presets_dir).unwrap();
 
-        assert!(!mod_cfg.mods.get("mod1").unwrap().active);
+        mod_cfg
+            .apply_presets(&mock_data.presets_dir, MissingModPolicy::Fail)
+            .unwrap();
+        assert!(mod_cfg.is_mod_active("mod1").unwrap());
+        assert!(mod_cfg.is_mod_active("mod3").unwrap());
+
+        // A requirement that isn't installed fails the preset, even when skipping missing mods.
+        mod_cfg.set_all_mods_active(false).unwrap();
+        preset1.set_requirements("mod1", vec!["mod3".into(), "vehicle".into()]);
+        preset1.save_to_path(&mock_data.presets_dir).unwrap();
+
+        let result = mod_cfg.apply_presets(&mock_data.presets_dir, MissingModPolicy::Skip);
+        match result {
+            Err(PresetsFailed {
+                presets,
+                requirements,
+                ..
+            }) => {
+                assert_eq!(presets, HashSet::from(["preset1".to_string()]));
+                assert_eq!(requirements, vec![("mod1".into(), "vehicle".into())]);
+            }
+            other => panic!("expected PresetsFailed, got {:?}", other),
+        }
+        assert!(!mod_cfg.is_mod_active("mod1").unwrap());
     }
 
     #[test]
-    fn set_mod_active() {
-        let mock_dirs = MockData::new();
+    fn apply_presets_skip_missing_mods() {
+        let mock_data = MockData::new();
 
-        let mut mod_cfg = mock_dirs.modcfg;
-        mod_cfg.set_mod_active("mod1", false).unwrap();
+        let mut preset2 = mock_data.preset2;
+        let mut mod_cfg = mock_data.modcfg;
 
-        assert!(!mod_cfg.mods.get("mod1").unwrap().active);
+        preset2.enable();
+        preset2.add_mod("fake_mod");
+        preset2.save_to_path(&mock_data.presets_dir).unwrap();
 
-        mod_cfg.set_mod_active("mod1", true).unwrap();
+        mod_cfg
+            .apply_presets(&mock_data.presets_dir, MissingModPolicy::Skip)
+            .unwrap();
 
+        // The existing mods are enabled and the preset file is left untouched.
         assert!(mod_cfg.mods.get("mod1").unwrap().active);
+        assert!(mod_cfg.mods.get("mod2").unwrap().active);
+        let preset2 = Preset::load_from_path("preset2", &mock_data.presets_dir).unwrap();
+        assert!(preset2.get_mods().contains(&"fake_mod".into()));
     }
 
     #[test]
-    fn set_mod_active_missing() {
-        let mock_dirs = MockData::new();
+    fn apply_presets_respects_overrides() {
+        let mock_data = MockData::new();
 
-        let mut mod_cfg = mock_dirs.modcfg;
+        let mut preset2 = mock_data.preset2;
+        let mut mod_cfg = mock_data.modcfg;
 
-        let result = mod_cfg.set_mod_active("fake_mod", true);
-        assert!(matches!(result, Err(MissingMods { .. })));
+        preset2.enable();
+        preset2.save_to_path(&mock_data.presets_dir).unwrap();
+        mod_cfg.set_mod_active("mod2", false).unwrap();
+        mod_cfg.set_overrides([("mod2".to_string(), false)]);
+
+        mod_cfg
+            .apply_presets(&mock_data.presets_dir, MissingModPolicy::Fail)
+            .unwrap();
+
+        // mod2 was disabled by hand, so the enabled preset leaves it disabled.
+        assert!(mod_cfg.mods.get("mod1").unwrap().active);
+        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
+        assert_eq!(mod_cfg.override_of("mod2"), Some(false));
+        assert_eq!(mod_cfg.override_of("mod1"), None);
+
+        // Presets that refer to mod2 by an alias leave it alone too.
+        mod_cfg.set_aliases([("mod2_copy".to_string(), "mod2".to_string())]);
+        let mut preset3 = Preset::new("preset3".into(), vec!["mod2_copy".into()]).unwrap();
+        preset3.enable();
+        preset3.save_to_path(&mock_data.presets_dir).unwrap();
+        mod_cfg
+            .apply_presets(&mock_data.presets_dir, MissingModPolicy::Fail)
+            .unwrap();
+        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
+        assert_eq!(mod_cfg.override_of("mod2_copy"), Some(false));
     }
 
     #[test]
-    fn set_mods_active() {
-        let mock_dirs = MockData::new();
+    fn apply_presets_respects_management() {
+        let mock_data = MockData::new();
+
+        let mut preset2 = mock_data.preset2;
+        let mut mod_cfg = mock_data.modcfg;
+
+        preset2.enable();
+        preset2.save_to_path(&mock_data.presets_dir).unwrap();
+        mod_cfg.set_management([
+            ("mod2".to_string(), Management::Ignored),
+            ("mod3".to_string(), Management::Managed),
+        ]);
 
-        let mut mod_cfg = mock_dirs.modcfg;
         mod_cfg
-            .set_mods_active(&["mod1".into(), "mod2".into()], false)
+            .apply_presets(&mock_data.presets_dir, MissingModPolicy::Fail)
             .unwrap();
 
-        assert!(!mod_cfg.mods.get("mod1").unwrap().active);
-        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
+        // mod2 is ignored, so the enabled preset leaves it disabled, and mod3 is managed but in
+        // no enabled preset, so it is disabled.
+        assert!(mod_cfg.is_mod_active("mod1").unwrap());
+        assert!(!mod_cfg.is_mod_active("mod2").unwrap());
+        assert!(!mod_cfg.is_mod_active("mod3").unwrap());
+
+        // Bulk changes skip ignored mods too.
+        mod_cfg.set_all_mods_active(true).unwrap();
+        assert!(!mod_cfg.is_mod_active("mod2").unwrap());
 
+        // Saving keeps the state the game gave ignored mods in the meantime.
+        let mut on_disk = ModCfg::load_from_path(&mock_data.mods_dir).unwrap();
+        on_disk.set_mod_active("mod2", true).unwrap();
+        on_disk.save_to_path(&mock_data.mods_dir).unwrap();
+        mod_cfg.save_to_path(&mock_data.mods_dir).unwrap();
+        let saved = ModCfg::load_from_path(&mock_data.mods_dir).unwrap();
+        assert!(saved.is_mod_active("mod2").unwrap());
+        assert!(saved.is_mod_active("mod3").unwrap());
+
+        // Changing an ignored mod by name is saved.
+        let mut reloaded = ModCfg::load_from_path(&mock_data.mods_dir).unwrap();
+        reloaded.set_management([("mod2".to_string(), Management::Ignored)]);
+        reloaded.set_mod_active("mod2", false).unwrap();
+        reloaded.save_to_path(&mock_data.mods_dir).unwrap();
+        let saved = ModCfg::load_from_path(&mock_data.mods_dir).unwrap();
+        assert!(!saved.is_mod_active("mod2").unwrap());
+
+        // Mods managed by an alias are managed as the mod it refers to.
+        mod_cfg.set_aliases([("mod3_old".to_string(), "mod3".to_string())]);
+        mod_cfg.set_management([("mod3_old".to_string(), Management::Managed)]);
+        assert_eq!(mod_cfg.management_of("mod3"), Management::Managed);
+        mod_cfg.set_mod_active("mod3", true).unwrap();
         mod_cfg
-            .set_mods_active(&["mod1".into(), "mod2".into()], true)
+            .apply_presets(&mock_data.presets_dir, MissingModPolicy::Fail)
             .unwrap();
+        assert!(!mod_cfg.is_mod_active("mod3").unwrap());
+    }
 
-        assert!(mod_cfg.mods.get("mod1").unwrap().active);
-        assert!(mod_cfg.mods.get("mod2").unwrap().active);
+    #[test]
+    fn apply_presets_in_any_order() {
+        let mock_data = MockData::new();
+        let mut skipping =
+            Preset::new("skipping".into(), vec!["mod2".into(), "fake1".into()]).unwrap();
+        skipping.set_missing_mod_policy(Some(MissingModPolicy::Skip));
+        let failing = Preset::new("failing".into(), vec!["mod3".into(), "fake2".into()]).unwrap();
+        let mut presets = vec![skipping, failing];
+        for preset in presets.iter_mut() {
+            preset.enable();
+        }
+
+        let mut outcomes = vec![];
+        for _ in 0..2 {
+            let mut mod_cfg = mock_data.modcfg.clone();
+            mod_cfg.set_all_mods_active(false).unwrap();
+            let result = mod_cfg.apply_loaded_presets(&mut presets, MissingModPolicy::Fail);
+            let active: Vec<String> = mod_cfg.active_mods().map(String::from).collect();
+            outcomes.push((active, result.map_err(|e| e.to_string())));
+            presets.reverse();
+        }
+
+        assert_eq!(outcomes[0], outcomes[1]);
+        assert_eq!(outcomes[0].0, vec!["mod2"]);
+        assert!(outcomes[0].1.is_err());
     }
 
     #[test]
-    fn set_mods_active_missing() {
-        let mock_dirs = MockData::new();
+    fn apply_presets_prune_missing_mods() {
+        let mock_data = MockData::new();
 
-        let mut mod_cfg = mock_dirs.modcfg;
+        let mut preset2 = mock_data.preset2;
+        let mut mod_cfg = mock_data.modcfg;
 
-        let result = mod_cfg.set_mods_active(&["mod1".into(), "fake_mod".into()], true);
-        assert!(matches!(result, Err(MissingMods { .. })));
+        preset2.enable();
+        preset2.add_mod("fake_mod");
+        preset2.save_to_path(&mock_data.presets_dir).unwrap();
 
-        // Check that no mods were set active.
-        assert!(mod_cfg.mods.get("mod1").unwrap().active);
-        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
+        mod_cfg
+            .apply_presets(&mock_data.presets_dir, MissingModPolicy::Prune)
+            .unwrap();
+
+        assert!(mod_cfg.mods.get("mod2").unwrap().active);
+        let preset2 = Preset::load_from_path("preset2", &mock_data.presets_dir).unwrap();
+        assert_eq!(preset2.get_mods(), &["mod1", "mod2"]);
     }
 
     #[test]
-    fn set_all_mods_active() {
-        let mock_dirs = MockData::new();
+    fn apply_presets_preset_policy_overrides() {
+        let mock_data = MockData::new();
 
-        let mut mod_cfg = mock_dirs.modcfg;
-        mod_cfg.set_all_mods_active(false).unwrap();
+        let mut preset2 = mock_data.preset2;
+        let mut mod_cfg = mock_data.modcfg;
 
-        assert!(!mod_cfg.mods.get("mod1").unwrap().active);
-        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
+        preset2.enable();
+        preset2.add_mod("fake_mod");
+        preset2.set_missing_mod_policy(Some(MissingModPolicy::Skip));
+        preset2.save_to_path(&mock_data.presets_dir).unwrap();
 
-        mod_cfg.set_all_mods_active(true).unwrap();
+        // The call asks to fail, but the preset's own policy says skip.
+        mod_cfg
+            .apply_presets(&mock_data.presets_dir, MissingModPolicy::Fail)
+            .unwrap();
 
-        assert!(mod_cfg.mods.get("mod1").unwrap().active);
         assert!(mod_cfg.mods.get("mod2").unwrap().active);
     }
 
     #[test]
-    fn is_mod_active() {
-        let mock_dirs = MockData::new();
+    fn parse_missing_mod_policy() {
+        assert_eq!(
+            "Skip".parse::<MissingModPolicy>().unwrap(),
+            MissingModPolicy::Skip
+        );
+        assert!(matches!(
+            "nope".parse::<MissingModPolicy>(),
+            Err(InvalidMissingModPolicy { .. })
+        ));
+    }
 
-        let mod_cfg = mock_dirs.modcfg;
+    #[test]
+    fn apply_presets_preview() {
+        let mock_data = MockData::new();
 
-        assert!(mod_cfg.is_mod_active("mod1").unwrap());
-        assert!(!mod_cfg.is_mod_active("mod2").unwrap());
-        assert!(mod_cfg.is_mod_active("mod3").unwrap());
-        assert!(mod_cfg.is_mod_active("fake_mod").is_none());
+        let mut preset2 = mock_data.preset2;
+        let mod_cfg = mock_data.modcfg;
+
+        preset2.enable();
+        preset2.add_mod("fake_mod");
+        preset2.set_missing_mod_policy(Some(MissingModPolicy::Prune));
+        preset2.save_to_path(&mock_data.presets_dir).unwrap();
+
+        let changes = mod_cfg
+            .apply_presets_preview(&mock_data.presets_dir, MissingModPolicy::Fail)
+            .unwrap();
+
+        assert_eq!(changes.enabled, vec!["mod2"]);
+        assert!(changes.disabled.is_empty());
+        assert!(changes.failed_presets.is_empty());
+
+        // Nothing was actually changed.
+        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
+        let preset2 = Preset::load_from_path("preset2", &mock_data.presets_dir).unwrap();
+        assert!(preset2.get_mods().contains(&"fake_mod".into()));
     }
 
     #[test]
-    fn apply_presets() {
+    fn apply_presets_preview_failed() {
         let mock_data = MockData::new();
 
-        let mut preset1 = mock_data.preset1;
         let mut preset2 = mock_data.preset2;
+        let mod_cfg = mock_data.modcfg;
+
+        preset2.enable();
+        preset2.add_mod("fake_mod");
+        preset2.save_to_path(&mock_data.presets_dir).unwrap();
+
+        let changes = mod_cfg
+            .apply_presets_preview(&mock_data.presets_dir, MissingModPolicy::Fail)
+            .unwrap();
+
+        assert!(changes.enabled.is_empty());
+        assert_eq!(changes.failed_presets, vec!["preset2"]);
+    }
+
+    #[test]
+    fn changeset_between() {
+        let mock_data = MockData::new();
+
+        let before = mock_data.modcfg;
+        let mut after = before.clone();
+        after.set_mod_active("mod1", false).unwrap();
+        after.set_mod_active("mod2", true).unwrap();
+
+        let changes = ChangeSet::between(&before, &after);
+
+        assert_eq!(changes.enabled, vec!["mod2"]);
+        assert_eq!(changes.disabled, vec!["mod1"]);
+        assert!(ChangeSet::between(&before, &before).is_empty());
+    }
 
+    #[test]
+    fn finding_unmanaged_mods() {
+        let mock_data = MockData::new();
         let mut mod_cfg = mock_data.modcfg;
+        assert_eq!(
+            mod_cfg.unmanaged_mods(&mock_data.presets_dir).unwrap(),
+            vec!["mod3"]
+        );
 
-        // Enable both presets and make sure both mods are enabled.
+        // Presets still name mod2 after it is renamed, through its alias.
+        mod_cfg.set_aliases(BTreeMap::from([("mod2".into(), "mod4".into())]));
+        mod_cfg
+            .rename_mod("mod2", "mod4", &mock_data.mods_dir)
+            .unwrap();
+        assert_eq!(
+            mod_cfg.unmanaged_mods(&mock_data.presets_dir).unwrap(),
+            vec!["mod3"]
+        );
+    }
 
-        preset1.enable();
-        preset2.enable();
+    #[test]
+    fn diff_against_path() {
+        let mock_data = MockData::new();
 
-        preset1.save_to_path(&mock_data.presets_dir).unwrap();
-        preset2.save_to_path(&mock_data.presets_dir).unwrap();
+        let mut mod_cfg = mock_data.modcfg;
+        assert!(mod_cfg
+            .diff_against_path(&mock_data.mods_dir)
+            .unwrap()
+            .is_empty());
 
-        mod_cfg.apply_presets(&mock_data.presets_dir).unwrap();
+        mod_cfg.set_mod_active("mod2", true).unwrap();
 
-        assert!(mod_cfg.mods.get("mod1").unwrap().active);
-        assert!(mod_cfg.mods.get("mod2").unwrap().active);
+        let changes = mod_cfg.diff_against_path(&mock_data.mods_dir).unwrap();
+        assert_eq!(changes.enabled, vec!["mod2"]);
+        assert!(changes.disabled.is_empty());
+    }
 
-        // Disable just preset 2, which has both mod1 and mod2. Before applying preset, both mods
-        // should be disabled. But, since preset 1 is still enabled, after applying preset, mod1
-        // should be enabled.
+    #[test]
+    fn repo_version() {
+        let mock_data = MockData::new();
 
-        preset2.disable(&mut mod_cfg).unwrap();
+        let mut mod_cfg = mock_data.modcfg;
+        assert!(mod_cfg.repo_version("mod1").is_none());
+
+        mod_cfg.mods.get_mut("mod1").unwrap().other.insert(
+            "modData".into(),
+            serde_json::json!({
+                "resource_id": 1234,
+                "current_version_id": "5678",
+                "version_string": "1.2"
+            }),
+        );
 
-        preset2.save_to_path(&mock_data.presets_dir).unwrap();
+        assert_eq!(
+            mod_cfg.repo_version("mod1").unwrap(),
+            RepoVersion {
+                resource_id: 1234,
+                version_id: 5678,
+                version: Some("1.2".into()),
+            }
+        );
+        assert_eq!(
+            mod_cfg.repo_version("mod1").unwrap().url(),
+            "https://www.beamng.com/resources/1234/"
+        );
+    }
 
-        assert!(!mod_cfg.mods.get("mod1").unwrap().active);
-        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
+    #[test]
+    fn repo_mods() {
+        let json = r#"{"mods":{
+            "manual":{"active":true,"filename":"manual.zip"},
+            "subscribed":{"active":true,"dirname":"/mods/repo/","modData":{"tagid":"M1","resource_id":"1","resource_version_id":2,"title":"Car","username":"someone","extra":1}},
+            "downloaded":{"active":false,"fullpath":"/mods/downloaded.zip","modData":{"resource_id":3,"current_version_id":4}}
+        }}"#;
+        let mut mod_cfg = ModCfg::load(json.as_bytes()).unwrap();
+
+        let repo_mods: Vec<(&str, RepoInfo)> = mod_cfg.repo_mods().collect();
+        assert_eq!(repo_mods.len(), 2);
+        assert_eq!(repo_mods[0].0, "downloaded");
+        assert!(!repo_mods[0].1.subscribed);
+        assert_eq!(
+            repo_mods[1].1,
+            RepoInfo {
+                tag_id: Some("M1".into()),
+                resource_id: Some(1),
+                version_id: Some(2),
+                version: None,
+                title: Some("Car".into()),
+                username: Some("someone".into()),
+                subscribed: true,
+            }
+        );
 
-        mod_cfg.apply_presets(&mock_data.presets_dir).unwrap();
+        let version = RepoVersion {
+            resource_id: 1,
+            version_id: 5,
+            version: Some("2.0".into()),
+        };
+        mod_cfg.set_repo_version("subscribed", &version).unwrap();
+        assert_eq!(mod_cfg.repo_version("subscribed"), Some(version.clone()));
+        let mod_data = &mod_cfg.mods["subscribed"].other["modData"];
+        assert_eq!(mod_data["tagid"], "M1");
+        assert_eq!(mod_data["extra"], 1);
+
+        mod_cfg.set_repo_version("manual", &version).unwrap();
+        assert_eq!(mod_cfg.repo_version("manual"), Some(version.clone()));
+        assert!(matches!(
+            mod_cfg.set_repo_version("mod10", &version),
+            Err(MissingMods { .. })
+        ));
+    }
 
-        assert!(mod_cfg.mods.get("mod1").unwrap().active);
-        assert!(!mod_cfg.mods.get("mod2").unwrap().active);
+    #[test]
+    fn archive_path() {
+        let mock_data = MockData::new();
+        let mods_dir = Path::new("mods");
+
+        let mut mod_cfg = mock_data.modcfg;
+        assert!(mod_cfg.archive_path("mod1", mods_dir).is_none());
+
+        let mod1 = mod_cfg.mods.get_mut("mod1").unwrap();
+        mod1.filename = Some("mod1.zip".into());
+        assert_eq!(
+            mod_cfg.archive_path("mod1", mods_dir).unwrap(),
+            mods_dir.join("mod1.zip")
+        );
+
+        let mod1 = mod_cfg.mods.get_mut("mod1").unwrap();
+        mod1.set_archive("/mods/repo/mod1.zip");
+        assert_eq!(
+            mod_cfg.archive_path("mod1", mods_dir).unwrap(),
+            mods_dir.join("repo").join("mod1.zip")
+        );
     }
 
     #[test]
-    fn apply_presets_missing_mods() {
+    fn registering_archives() {
         let mock_data = MockData::new();
+        let mods_dir = Path::new("mods");
 
-        let mut preset1 = mock_data.preset1;
-        let mut preset2 = mock_data.preset2;
+        let mut mod_cfg = mock_data.modcfg;
+        mod_cfg.set_blacklist(["blocked".to_string()]);
+
+        let name = mod_cfg.register_archive(mods_dir.join("New_Car.zip"));
+        assert_eq!(name.as_deref(), Some("new_car"));
+        assert!(mod_cfg.is_mod_active("new_car").unwrap());
+        assert_eq!(
+            mod_cfg.archive_path("new_car", mods_dir).unwrap(),
+            mods_dir.join("New_Car.zip")
+        );
+
+        mod_cfg.register_archive(mods_dir.join("blocked.zip"));
+        assert!(!mod_cfg.is_mod_active("blocked").unwrap());
+
+        mod_cfg.register_archive(mods_dir.join("repo").join("downloaded.zip"));
+        assert_eq!(
+            mod_cfg.archive_path("downloaded", mods_dir).unwrap(),
+            mods_dir.join("repo").join("downloaded.zip")
+        );
+
+        // Already registered mods are left alone.
+        assert_eq!(mod_cfg.register_archive(mods_dir.join("mod2.zip")), None);
+        assert!(!mod_cfg.is_mod_active("mod2").unwrap());
+    }
 
+    #[test]
+    fn aliases() {
+        let mock_data = MockData::new();
         let mut mod_cfg = mock_data.modcfg;
+        mod_cfg.set_aliases([
+            ("mod2_copy".to_string(), "mod2".to_string()),
+            ("mod1".to_string(), "mod2".to_string()),
+        ]);
 
-        // Enable both presets and make sure both mods are enabled.
+        assert!(mod_cfg.missing_mods(["mod2_copy"]).is_empty());
+        mod_cfg.set_mods_active(["mod2_copy"], true).unwrap();
+        assert!(mod_cfg.is_mod_active("mod2").unwrap());
 
-        preset1.enable();
-        preset2.enable();
+        // Installed mods aren't replaced by their alias.
+        mod_cfg.set_mod_active("mod1", false).unwrap();
+        assert!(!mod_cfg.is_mod_active("mod1").unwrap());
+        assert!(mod_cfg.is_mod_active("mod2").unwrap());
+
+        // The mods the aliases refer to are the ones checked against the blacklist.
+        mod_cfg.set_mod_active("mod2", false).unwrap();
+        mod_cfg.set_blacklist(["mod2".to_string()]);
+        let result = mod_cfg.set_mods_active(["mod2_copy"], true);
+        assert!(matches!(result, Err(BlacklistedMods { mods }) if mods == ["mod2"]));
+        assert!(!mod_cfg.is_mod_active("mod2").unwrap());
+    }
 
-        preset1.save_to_path(&mock_data.presets_dir).unwrap();
-        preset2.save_to_path(&mock_data.presets_dir).unwrap();
+    #[test]
+    fn typed_mod_fields() {
+        let json = r#"{"mods":{"car":{"active":true,"modname":"car","filename":"car.zip","fullpath":"/mods/repo/car.zip","dirname":"/mods/repo/","modType":"vehicle","dateAdded":1700000000.5,"stat":{"filesize":2048,"modtime":1700000001,"readonly":false},"valid":true}}}"#;
+        let mod_cfg = ModCfg::load(json.as_bytes()).unwrap();
+
+        let car = mod_cfg.iter_mods().next().unwrap().entry();
+        assert_eq!(car.mod_name(), Some("car"));
+        assert_eq!(car.filename(), Some("car.zip"));
+        assert_eq!(car.full_path(), Some("/mods/repo/car.zip"));
+        assert_eq!(car.dir_name(), Some("/mods/repo/"));
+        assert_eq!(car.mod_type(), Some("vehicle"));
+        assert_eq!(car.date_added(), Some(1700000000));
+        assert_eq!(car.file_size(), Some(2048));
+        assert_eq!(car.modified(), Some(1700000001));
+
+        // Unknown keys are written back unchanged.
+        let mut saved = vec![];
+        mod_cfg.save(&mut saved).unwrap();
+        let saved: serde_json::Value = serde_json::from_slice(&saved).unwrap();
+        let original: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(saved, original);
+    }
 
-        // Remove mod2 from the modcfg so that preset2 will fail to enable.
-        mod_cfg.mods.remove("mod2");
+    #[test]
+    fn iterating_mods() {
+        let mock_data = MockData::new();
+        let entries: Vec<(&str, bool)> = mock_data
+            .modcfg
+            .iter_mods()
+            .map(|m| (m.name(), m.is_active()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![("mod1", true), ("mod2", false), ("mod3", true)]
+        );
+        assert!(mock_data.modcfg.iter_mods().all(|m| m.mod_type().is_none()));
+    }
 
-        let result = mod_cfg.apply_presets(&mock_data.presets_dir);
-        assert!(matches!(result, Err(PresetsFailed { .. })));
+    #[test]
+    fn renaming_mods() {
+        let mock_data = MockData::new();
+        let mut mod_cfg = mock_data.modcfg;
+        let repo_dir = mock_data.mods_dir.join("repo");
+        fs::create_dir(&repo_dir).unwrap();
+        fs::write(repo_dir.join("Cool Car v1.2.zip"), "car").unwrap();
+        let mut car = Mod::new(true);
+        car.mod_name = Some("cool car v1.2".into());
+        car.set_archive("/mods/repo/Cool Car v1.2.zip");
+        mod_cfg.add_mod("cool car v1.2", car);
+
+        let moved = mod_cfg
+            .rename_mod("cool car v1.2", "cool_car", &mock_data.mods_dir)
+            .unwrap();
 
-        // Check that mod1 is still enabled.
-        assert!(mod_cfg.mods.get("mod1").unwrap().active);
+        assert_eq!(
+            moved,
+            Some((
+                repo_dir.join("Cool Car v1.2.zip"),
+                repo_dir.join("cool_car.zip")
+            ))
+        );
+        assert!(repo_dir.join("cool_car.zip").exists());
+        assert!(!repo_dir.join("Cool Car v1.2.zip").exists());
+        assert_eq!(mod_cfg.is_mod_active("cool car v1.2"), None);
+        assert_eq!(
+            mod_cfg.archive_path("cool_car", &mock_data.mods_dir),
+            Some(repo_dir.join("cool_car.zip"))
+        );
+        assert_eq!(mod_cfg.mods["cool_car"].mod_name(), Some("cool_car"));
+        assert!(matches!(
+            mod_cfg.rename_mod("cool_car", "mod1", &mock_data.mods_dir),
+            Err(ModExists { .. })
+        ));
+    }
+
+    #[test]
+    fn removing_mods() {
+        let mock_data = MockData::new();
+        let mut mod_cfg = mock_data.modcfg;
+        let archive = mock_data.mods_dir.join("mod2.zip");
+        fs::write(&archive, "mod2").unwrap();
+        let mut mod2 = Mod::new(false);
+        mod2.set_archive("mod2.zip");
+        assert!(mod_cfg.add_mod("mod2", mod2).is_some());
+
+        mod_cfg
+            .uninstall_mod("mod2", &mock_data.mods_dir, true)
+            .unwrap();
+
+        assert!(!archive.exists());
+        assert_eq!(mod_cfg.is_mod_active("mod2"), None);
+        assert!(matches!(
+            mod_cfg.uninstall_mod("mod2", &mock_data.mods_dir, true),
+            Err(MissingMods { .. })
+        ));
+
+        // Removing only the entry leaves the archive in place.
+        let archive = mock_data.mods_dir.join("mod4.zip");
+        fs::write(&archive, "mod4").unwrap();
+        mod_cfg.register_archive(&archive).unwrap();
+        let removed = mod_cfg.remove_mod("mod4").unwrap();
+        assert!(removed.is_active());
+        assert_eq!(removed.filename(), Some("mod4.zip"));
+        assert_eq!(removed.full_path(), Some("/mods/mod4.zip"));
+        assert_eq!(removed.dir_name(), Some("/mods/"));
+        assert!(archive.exists());
+        assert!(matches!(
+            mod_cfg.remove_mod("mod4"),
+            Err(MissingMods { .. })
+        ));
     }
 }
@@ -0,0 +1,232 @@
+use crate::{
+    archive,
+    game::{ModCfg, ModRoot},
+    path,
+    Error::*,
+    Result,
+};
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The problems found in the mods directory by `check`.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct HealthReport {
+    /// Archives and unpacked mod folders the game hasn't registered in db.json, sorted.
+    pub orphaned: Vec<PathBuf>,
+    /// Mods in db.json whose archive is missing, sorted.
+    pub stale: Vec<String>,
+    /// Archives that can't be read as zips, along with why, sorted. Empty files are left out since
+    /// they are listed in `empty`.
+    pub corrupt: Vec<(PathBuf, String)>,
+    /// Files with nothing in them, sorted.
+    pub empty: Vec<PathBuf>,
+    /// Groups of mods with identical archives, as found by `archive::find_duplicates`.
+    pub duplicates: Vec<Vec<String>>,
+    /// Mods installed both from the repository and by hand, as found by
+    /// `archive::find_repo_duplicates`.
+    pub repo_duplicates: Vec<(String, String)>,
+    /// Archives and unpacked mod folders whose names aren't valid Unicode, sorted. They can't be
+    /// named in db.json or presets, so they are left out of `orphaned`.
+    pub non_unicode: Vec<PathBuf>,
+    /// The size of everything in the mods directory, in bytes.
+    pub total_size: u64,
+}
+
+impl HealthReport {
+    /// Whether no problems were found.
+    pub fn is_healthy(&self) -> bool {
+        self.orphaned.is_empty()
+            && self.stale.is_empty()
+            && self.corrupt.is_empty()
+            && self.empty.is_empty()
+            && self.duplicates.is_empty()
+            && self.repo_duplicates.is_empty()
+            && self.non_unicode.is_empty()
+    }
+}
+
+/// Check the contents of the mods directory for problems.
+///
+/// # Arguments
+///
+/// `mod_config`: The ModCfg of the installed mods.
+/// `mods_dir`: The mods directory of the current game version.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading the mods directory or an archive.
+pub fn check(mod_config: &ModCfg, mods_dir: impl AsRef<Path>) -> Result<HealthReport> {
+    let mods_dir = mods_dir.as_ref();
+    let mut report = HealthReport::default();
+
+    // Paths are compared case-insensitively, like Windows does.
+    let key = |path: &Path| {
+        let path = path.to_string_lossy().replace('\\', "/").to_lowercase();
+        path.trim_end_matches('/').to_string()
+    };
+    let mut registered = HashSet::new();
+    for mod_name in mod_config.get_mods() {
+        let Some(archive) = mod_config.archive_path(mod_name, mods_dir) else {
+            continue;
+        };
+        if archive.try_exists()? {
+            registered.insert(key(&archive));
+        } else {
+            report.stale.push(mod_name.clone());
+        }
+    }
+
+    for (root, dir) in path::mods_roots(mods_dir) {
+        if root != ModRoot::Unpacked {
+            continue;
+        }
+        // Unpacked mods are folders, and the game registers the folders rather than their files.
+        for entry in fs::read_dir(dir)? {
+            let folder = entry?.path();
+            if !folder.is_dir() {
+                continue;
+            }
+            if !is_unicode(&folder) {
+                report.non_unicode.push(folder);
+            } else if !registered.contains(&key(&folder)) {
+                report.orphaned.push(folder);
+            }
+        }
+        for file in path::root_files(mods_dir, root)? {
+            report.total_size += fs::metadata(&file)?.len();
+        }
+    }
+
+    let mut files = path::root_files(mods_dir, ModRoot::Mods)?;
+    files.extend(path::root_files(mods_dir, ModRoot::Repo)?);
+    for file in files {
+        let size = fs::metadata(&file)?.len();
+        report.total_size += size;
+        let is_archive = file
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("zip"));
+        if size == 0 {
+            report.empty.push(file);
+            continue;
+        }
+        if !is_archive {
+            continue;
+        }
+        if !is_unicode(&file) {
+            report.non_unicode.push(file);
+            continue;
+        }
+        match archive::check(&file) {
+            Ok(()) => (),
+            Err(CorruptArchive { archive, reason }) => report.corrupt.push((archive, reason)),
+            Err(e) => return Err(e),
+        }
+        if !registered.contains(&key(&file)) {
+            report.orphaned.push(file);
+        }
+    }
+
+    report.duplicates = archive::find_duplicates(mod_config, mods_dir)?;
+    report.repo_duplicates = archive::find_repo_duplicates(mod_config, mods_dir)?;
+    report.orphaned.sort();
+    report.stale.sort();
+    report.corrupt.sort();
+    report.empty.sort();
+    report.non_unicode.sort();
+    Ok(report)
+}
+
+/// Whether the name of a file or folder is valid Unicode.
+fn is_unicode(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name.to_str().is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+    use std::fs::File;
+
+    #[test]
+    fn checking_health() {
+        let mock = MockData::new();
+        let mut mod_cfg = mock.modcfg;
+        let archive = |name: &str| {
+            let path = mock.mods_dir.join(name);
+            let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+            zip.start_file("info.json", zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.finish().unwrap();
+            path
+        };
+        mod_cfg.register_archive(archive("good.zip"));
+        mod_cfg.register_archive(archive("copy.zip"));
+        mod_cfg.register_archive(archive("gone.zip"));
+        fs::remove_file(mock.mods_dir.join("gone.zip")).unwrap();
+        archive("orphan.zip");
+        fs::write(mock.mods_dir.join("broken.zip"), "not a zip").unwrap();
+        fs::write(mock.mods_dir.join("empty.zip"), "").unwrap();
+        // Files inside unpacked mods are part of the mod, so only unregistered folders are orphans.
+        for name in ["registered", "orphan"] {
+            let folder = mock.mods_dir.join("unpacked").join(name);
+            fs::create_dir_all(&folder).unwrap();
+            fs::write(folder.join("empty.txt"), "").unwrap();
+        }
+        mod_cfg.add_mod(
+            "registered",
+            serde_json::from_str(r#"{"active":true,"fullpath":"/mods/unpacked/registered/"}"#)
+                .unwrap(),
+        );
+
+        let report = check(&mod_cfg, &mock.mods_dir).unwrap();
+        assert!(!report.is_healthy());
+        assert_eq!(
+            report.orphaned,
+            vec![
+                mock.mods_dir.join("broken.zip"),
+                mock.mods_dir.join("orphan.zip"),
+                mock.mods_dir.join("unpacked").join("orphan"),
+            ]
+        );
+        assert_eq!(report.stale, vec!["gone"]);
+        assert_eq!(report.corrupt.len(), 1);
+        assert_eq!(report.corrupt[0].0, mock.mods_dir.join("broken.zip"));
+        assert_eq!(report.empty, vec![mock.mods_dir.join("empty.zip")]);
+        assert_eq!(report.duplicates, vec![vec!["copy", "good"]]);
+        assert!(report.non_unicode.is_empty());
+        assert!(report.total_size > 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn finding_non_unicode_names() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let mock = MockData::new();
+        // "пак" in the Windows-1251 encoding, which isn't valid UTF-8.
+        let name = OsStr::from_bytes(b"\xef\xe0\xea.zip");
+        let archive = mock.mods_dir.join(name);
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        zip.start_file("info.json", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.finish().unwrap();
+        fs::write(mock.mods_dir.join("пак.zip"), "not a zip").unwrap();
+        fs::write(mock.presets_dir.join(name).with_extension("json"), "{}").unwrap();
+
+        let report = check(&mock.modcfg, &mock.mods_dir).unwrap();
+        assert_eq!(report.non_unicode, vec![archive]);
+        // Names in other scripts are fine.
+        assert_eq!(report.orphaned, vec![mock.mods_dir.join("пак.zip")]);
+        assert_eq!(
+            crate::Preset::list_unreadable(&mock.presets_dir)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(crate::Preset::list(&mock.presets_dir).unwrap().count(), 2);
+    }
+}
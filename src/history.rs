@@ -0,0 +1,212 @@
+use crate::{Error::*, Preset, Result};
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// The folder in a presets directory holding the previous versions of its presets. It starts
+/// with a dot so it is hidden, and isn't listed as a preset since presets are files.
+const HISTORY_DIR: &str = ".history";
+
+/// How many previous versions of each preset are kept. The oldest are removed as new ones are
+/// recorded.
+pub const LIMIT: usize = 20;
+
+/// A previous version of a preset, recorded when `Preset::save_to_path` saved over it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Revision {
+    /// The number of the revision. Numbers only go up, so a revision keeps its number as newer
+    /// ones are recorded and older ones are removed.
+    pub number: u32,
+    /// The path to the saved version.
+    pub path: PathBuf,
+    /// When the version was replaced.
+    pub time: SystemTime,
+}
+
+impl Revision {
+    /// Load the preset as it was in this revision.
+    ///
+    /// # Errors
+    ///
+    /// * `std::io::Error`: If there is an issue reading the revision.
+    /// * `serde_json::Error`: If the revision isn't a valid preset.
+    pub fn load(&self) -> Result<Preset> {
+        Preset::load(BufReader::new(File::open(&self.path)?))
+    }
+}
+
+/// Get the directory holding the revisions of a preset, e.g. `presets/.history/racing`.
+fn revisions_dir(name: &str, presets_dir: &Path) -> PathBuf {
+    presets_dir.join(HISTORY_DIR).join(name)
+}
+
+/// Record the version of a preset that is about to be saved over, and remove the oldest
+/// revisions beyond `LIMIT`.
+///
+/// # Arguments
+///
+/// `name`: The name of the preset.
+/// `presets_dir`: Where preset config files are stored.
+/// `contents`: The contents of the preset's file before it is saved over.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading the history or writing the revision.
+pub(crate) fn record(name: &str, presets_dir: &Path, contents: &[u8]) -> Result<()> {
+    let revisions = revisions(name, presets_dir)?;
+    let number = revisions.first().map_or(1, |r| r.number + 1);
+    let dir = revisions_dir(name, presets_dir);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(format!("{}.json", number)), contents)?;
+    // The new revision takes one of the kept places.
+    for old in revisions.iter().skip(LIMIT - 1) {
+        fs::remove_file(&old.path)?;
+    }
+    Ok(())
+}
+
+/// List the previous versions of a preset, most recent first.
+///
+/// # Arguments
+///
+/// `name`: The name of the preset.
+/// `presets_dir`: Where preset config files are stored.
+///
+/// # Errors
+///
+/// * `InvalidPresetName`: If the name can't be used as a preset file name.
+/// * `std::io::Error`: If there is an issue reading the history.
+pub fn revisions(name: &str, presets_dir: impl AsRef<Path>) -> Result<Vec<Revision>> {
+    Preset::validate_name(name)?;
+    let dir = revisions_dir(name, presets_dir.as_ref());
+    if !dir.try_exists()? {
+        return Ok(vec![]);
+    }
+    let mut revisions = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let Some(number) = entry
+            .file_name()
+            .to_str()
+            .and_then(|n| n.strip_suffix(".json"))
+            .and_then(|n| n.parse().ok())
+        else {
+            continue;
+        };
+        revisions.push(Revision {
+            number,
+            path: entry.path(),
+            time: entry.metadata()?.modified()?,
+        });
+    }
+    revisions.sort_by_key(|r| std::cmp::Reverse(r.number));
+    Ok(revisions)
+}
+
+/// Replace a preset with one of its previous versions. The version being replaced is recorded
+/// as a new revision, so the revert can be undone. Whether the preset is enabled stays the same,
+/// since enabling and disabling presets also changes the mods.
+///
+/// # Arguments
+///
+/// `name`: The name of the preset.
+/// `number`: The number of the revision to revert to.
+/// `presets_dir`: Where preset config files are stored.
+///
+/// # Returns
+///
+/// The reverted preset.
+///
+/// # Errors
+///
+/// * `InvalidPresetName`: If the name can't be used as a preset file name.
+/// * `MissingPreset`: If the preset doesn't exist.
+/// * `MissingRevision`: If the preset has no revision with the number.
+/// * `std::io::Error`: If there is an issue reading the revision or saving the preset.
+/// * `serde_json::Error`: If the revision isn't a valid preset.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{history, Preset};
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let presets_dir = temp_dir.path();
+/// let mut preset = Preset::new("racing".into(), vec!["mod1".into()]).unwrap();
+/// preset.save_to_path(&presets_dir).unwrap();
+/// preset.add_mod("mod2");
+/// preset.save_to_path(&presets_dir).unwrap();
+///
+/// let revision = &history::revisions("racing", &presets_dir).unwrap()[0];
+/// let reverted = history::revert("racing", revision.number, &presets_dir).unwrap();
+/// assert_eq!(reverted.get_mods(), &vec!["mod1"]);
+/// ```
+pub fn revert(name: &str, number: u32, presets_dir: impl AsRef<Path>) -> Result<Preset> {
+    let presets_dir = presets_dir.as_ref();
+    let current = Preset::load_from_path(name, presets_dir)?;
+    let Some(revision) = revisions(name, presets_dir)?
+        .into_iter()
+        .find(|r| r.number == number)
+    else {
+        return Err(MissingRevision {
+            preset: name.into(),
+            revision: number,
+        });
+    };
+    let mut preset = revision.load()?;
+    preset.set_enabled(current.is_enabled());
+    preset.save_to_path(presets_dir)?;
+    Ok(preset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+
+    #[test]
+    fn reverting_presets() {
+        let mock = MockData::new();
+        let presets_dir = &mock.presets_dir;
+        assert!(revisions("preset1", presets_dir).unwrap().is_empty());
+
+        let mut preset = Preset::load_from_path("preset1", presets_dir).unwrap();
+        preset.add_mod("mod2");
+        preset.save_to_path(presets_dir).unwrap();
+        // Saving the same preset again, or only disabling it, records nothing.
+        preset.save_to_path(presets_dir).unwrap();
+        preset.set_enabled(false);
+        preset.save_to_path(presets_dir).unwrap();
+        preset.set_enabled(true);
+        preset.save_to_path(presets_dir).unwrap();
+        let history = revisions("preset1", presets_dir).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].number, 1);
+        assert_eq!(history[0].load().unwrap().get_mods(), &vec!["mod1"]);
+        // The history isn't listed as a preset.
+        assert!(!Preset::list(presets_dir).unwrap().any(|p| p == HISTORY_DIR));
+
+        let reverted = revert("preset1", 1, presets_dir).unwrap();
+        assert_eq!(reverted.get_mods(), &vec!["mod1"]);
+        assert!(reverted.is_enabled());
+        // The reverted version can be brought back in turn.
+        let history = revisions("preset1", presets_dir).unwrap();
+        assert_eq!(history[0].number, 2);
+        assert_eq!(history[0].load().unwrap().get_mods().len(), 2);
+        assert!(matches!(
+            revert("preset1", 5, presets_dir),
+            Err(MissingRevision { revision: 5, .. })
+        ));
+
+        for i in 0..LIMIT {
+            preset.set_launch_args(vec![i.to_string()]);
+            preset.save_to_path(presets_dir).unwrap();
+        }
+        let history = revisions("preset1", presets_dir).unwrap();
+        assert_eq!(history.len(), LIMIT);
+        assert_eq!(history[0].number, LIMIT as u32 + 2);
+    }
+}
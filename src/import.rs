@@ -0,0 +1,508 @@
+use crate::{archive, game::ModCfg, Error::*, Preset, Result};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+/// The largest preset file `import_presets` reads. Presets are small, so anything bigger isn't a
+/// preset and isn't worth unpacking.
+const MAX_PRESET_SIZE: u64 = 1024 * 1024;
+
+/// The kinds of mod lists `import` understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    /// A text file with a mod per line, like those exported by other mod managers or written by
+    /// hand. Lines may be mod names or archive filenames, and anything after a comma or tab, like
+    /// a version, is ignored. Empty lines and lines starting with `#` are skipped.
+    List,
+    /// A folder of mod archives, e.g. a backup or another install's mods directory.
+    Folder,
+    /// A db.json from another install, whose repository subscriptions are imported.
+    Subscriptions,
+}
+
+impl ImportSource {
+    /// Tell which kind of mod list a path is: folders are folders of archives, `.json` files are
+    /// subscription dumps, and anything else is a text list.
+    ///
+    /// # Arguments
+    ///
+    /// `path`: The path to the mod list.
+    pub fn detect(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        if path.is_dir() {
+            Self::Folder
+        } else if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+        {
+            Self::Subscriptions
+        } else {
+            Self::List
+        }
+    }
+}
+
+/// The result of importing a mod list.
+#[derive(Debug, Default, PartialEq)]
+pub struct Imported {
+    /// The names of the listed mods that are installed, including those installed by the import,
+    /// sorted.
+    pub mods: Vec<String>,
+    /// The archives copied into the mods directory, at their new paths.
+    pub installed: Vec<PathBuf>,
+    /// The names of the listed mods that aren't installed, sorted.
+    pub missing: Vec<String>,
+    /// The archives that weren't installed because they are corrupt, can't be named, or another
+    /// file in the mods directory has their name, along with why.
+    pub rejected: Vec<(PathBuf, String)>,
+}
+
+/// Import a mod list from another tool, locating the listed mods among the installed ones.
+///
+/// Archives from a folder that aren't installed yet are checked with `archive::check`, copied
+/// into the mods directory, and registered in the ModCfg. Files already in the mods directory are
+/// never overwritten, and the copies are removed again if the import fails. The copies are only
+/// listed in db.json once the ModCfg is saved. Mods listed in text lists and subscription dumps
+/// can't be installed, so those that aren't installed are reported as missing.
+///
+/// # Arguments
+///
+/// `path`: The path to the mod list.
+/// `source`: The kind of mod list, usually from `ImportSource::detect`.
+/// `mods_dir`: The mods directory of the current game version.
+/// `mod_config`: The ModCfg of the installed mods.
+///
+/// # Errors
+///
+/// * `std::io::Error`: If there is an issue reading the list or copying an archive.
+/// * `InvalidModCfg`: If a subscription dump can't be parsed.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{game::ModCfg, import::{self, ImportSource}};
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let mods_dir = temp_dir.path();
+/// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{\"mod1\":{\"active\":true}}}").unwrap();
+/// let mut mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+/// let list = mods_dir.join("mods.txt");
+/// std::fs::write(&list, "# Exported mods\nMod1.zip, 1.2\nmod2\n").unwrap();
+///
+/// let imported = import::import(&list, ImportSource::List, &mods_dir, &mut mod_cfg).unwrap();
+/// assert_eq!(imported.mods, vec!["mod1"]);
+/// assert_eq!(imported.missing, vec!["mod2"]);
+/// ```
+pub fn import(
+    path: impl AsRef<Path>,
+    source: ImportSource,
+    mods_dir: impl AsRef<Path>,
+    mod_config: &mut ModCfg,
+) -> Result<Imported> {
+    let path = path.as_ref();
+    let mods_dir = mods_dir.as_ref();
+    let mut imported = Imported::default();
+
+    let names: Vec<String> = match source {
+        ImportSource::List => fs::read_to_string(path)?
+            .lines()
+            .map(|line| line.split([',', '\t']).next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(mod_name)
+            .collect(),
+        ImportSource::Subscriptions => ModCfg::load(BufReader::new(File::open(path)?))?
+            .iter_mods()
+            .filter(|entry| entry.entry().is_subscribed())
+            .map(|entry| entry.name().to_string())
+            .collect(),
+        ImportSource::Folder => match install_archives(path, mods_dir, mod_config, &mut imported) {
+            Ok(names) => names,
+            Err(e) => {
+                for target in imported.installed.iter() {
+                    let _ = fs::remove_file(target);
+                }
+                return Err(e);
+            }
+        },
+    };
+
+    for name in names {
+        let list = if mod_config.is_mod_active(&name).is_some() {
+            &mut imported.mods
+        } else {
+            &mut imported.missing
+        };
+        if !list.contains(&name) {
+            list.push(name);
+        }
+    }
+    imported.mods.sort();
+    imported.missing.sort();
+    Ok(imported)
+}
+
+/// Copy the archives in a folder that aren't installed yet into the mods directory, for `import`.
+///
+/// # Returns
+///
+/// The names of the mods in the folder, installed or not.
+fn install_archives(
+    folder: &Path,
+    mods_dir: &Path,
+    mod_config: &mut ModCfg,
+    imported: &mut Imported,
+) -> Result<Vec<String>> {
+    let mut archives = vec![];
+    for entry in fs::read_dir(folder)? {
+        let archive = entry?.path();
+        if archive.is_file()
+            && archive
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("zip"))
+        {
+            archives.push(archive);
+        }
+    }
+    archives.sort();
+
+    let mut names = vec![];
+    for archive in archives {
+        // The file name is known to exist since the path came from reading the folder.
+        let filename = archive.file_name().unwrap();
+        let Some(filename_str) = filename.to_str() else {
+            // The game couldn't name the mod in db.json.
+            let reason = "its name isn't valid Unicode".to_string();
+            imported.rejected.push((archive, reason));
+            continue;
+        };
+        let name = mod_name(filename_str);
+        if mod_config.is_mod_active(&name).is_none() {
+            match archive::check(&archive) {
+                Ok(()) => (),
+                Err(CorruptArchive { archive, reason }) => {
+                    imported.rejected.push((archive, reason));
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+            let target = mods_dir.join(filename);
+            // The file may be a mod the game hasn't registered yet, or not a mod at all.
+            if target.try_exists()? {
+                let reason = "a file with its name is already in the mods directory";
+                imported.rejected.push((archive, reason.into()));
+                continue;
+            }
+            fs::copy(&archive, &target)?;
+            mod_config.register_archive(&target);
+            imported.installed.push(target);
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+/// The result of importing presets with `import_presets`.
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportedPresets {
+    /// The preset files installed under their own names, paired with those names.
+    pub imported: Vec<(PathBuf, String)>,
+    /// The preset files installed under a new name because their name was taken or invalid,
+    /// paired with their own name and the new one.
+    pub renamed: Vec<(PathBuf, String, String)>,
+    /// The files that weren't installed, along with why.
+    pub rejected: Vec<(PathBuf, String)>,
+}
+
+/// Install many preset files at once from a zip or a folder, like the presets a modpack ships
+/// with.
+///
+/// Every `.json` file is loaded as a preset first, and files that aren't valid presets are
+/// rejected without stopping the others. Invalid names are made valid with
+/// `Preset::sanitize_name`. Presets whose name is taken, by an existing preset, an earlier file,
+/// or BeamMM itself, are installed under the first free name like `name-2`, and
+/// imported presets extending a renamed one are pointed at its new name. Imported presets are
+/// installed disabled, so their mods are only enabled once the user chooses to.
+///
+/// # Arguments
+///
+/// `path`: The zip or folder of preset files.
+/// `presets_dir`: Where preset config files are stored.
+///
+/// # Errors
+///
+/// * `std::io::Error`: If there is an issue reading the zip or folder or saving a preset.
+/// * `serde_json::Error`: If there is an issue saving a preset.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{import, Preset};
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let presets_dir = temp_dir.path().join("presets");
+/// # let pack = temp_dir.path().join("pack");
+/// # std::fs::create_dir_all(&presets_dir).unwrap();
+/// # std::fs::create_dir_all(&pack).unwrap();
+/// Preset::new("racing".into(), vec![]).unwrap().save_to_path(&presets_dir).unwrap();
+/// Preset::new("racing".into(), vec!["mod1".into()]).unwrap().save_to_path(&pack).unwrap();
+/// std::fs::write(pack.join("notes.json"), "not a preset").unwrap();
+///
+/// let imported = import::import_presets(&pack, &presets_dir).unwrap();
+/// assert_eq!(imported.renamed[0].2, "racing-2");
+/// assert_eq!(imported.rejected.len(), 1);
+/// assert!(Preset::exists("racing-2", &presets_dir));
+/// ```
+pub fn import_presets(
+    path: impl AsRef<Path>,
+    presets_dir: impl AsRef<Path>,
+) -> Result<ImportedPresets> {
+    let path = path.as_ref();
+    let presets_dir = presets_dir.as_ref();
+    let mut imported = ImportedPresets::default();
+
+    let mut files = vec![];
+    if path.is_dir() {
+        let mut paths = vec![];
+        for entry in fs::read_dir(path)? {
+            let file = entry?.path();
+            if file.is_file() && is_json(&file) {
+                paths.push(file);
+            }
+        }
+        paths.sort();
+        for file in paths {
+            let contents = read_limited(File::open(&file)?);
+            files.push((file, contents));
+        }
+    } else {
+        let mut zip =
+            zip::ZipArchive::new(BufReader::new(File::open(path)?)).map_err(io::Error::from)?;
+        for i in 0..zip.len() {
+            let entry = zip.by_index(i).map_err(io::Error::from)?;
+            let file = PathBuf::from(entry.name());
+            if entry.is_file() && is_json(&file) {
+                files.push((file, read_limited(entry)));
+            }
+        }
+    }
+
+    let mut taken: HashSet<String> = HashSet::new();
+    let mut new_names: HashMap<String, String> = HashMap::new();
+    let mut presets = vec![];
+    for (file, contents) in files {
+        let preset = match contents.map(|bytes| Preset::load(bytes.as_slice())) {
+            Ok(Ok(preset)) => preset,
+            Ok(Err(e)) | Err(e) => {
+                imported.rejected.push((file, e.to_string()));
+                continue;
+            }
+        };
+        let name = preset.name().to_string();
+        let base = Preset::sanitize_name(&name);
+        let is_free =
+            |candidate: &str| !taken.contains(candidate) && !Preset::exists(candidate, presets_dir);
+        let mut new_name = base.clone();
+        let mut n = 1;
+        while !is_free(&new_name) {
+            n += 1;
+            new_name = format!("{}-{}", base, n);
+        }
+        taken.insert(new_name.clone());
+        if new_name == name {
+            imported.imported.push((file, name));
+        } else {
+            imported
+                .renamed
+                .push((file, name.clone(), new_name.clone()));
+            new_names.insert(name, new_name.clone());
+        }
+        presets.push((preset, new_name));
+    }
+
+    for (mut preset, name) in presets {
+        let parent = preset.extends().and_then(|p| new_names.get(p)).cloned();
+        if parent.is_some() {
+            preset.set_extends(parent);
+        }
+        preset.set_name(name)?;
+        // The preset was never applied here, so it has no mods to disable.
+        preset.force_disable(&mut ModCfg::default());
+        preset.save_to_path(presets_dir)?;
+    }
+    Ok(imported)
+}
+
+/// Whether a path has a `.json` extension.
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+}
+
+/// Read a file, failing if it's larger than any preset.
+fn read_limited(reader: impl Read) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    reader.take(MAX_PRESET_SIZE + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > MAX_PRESET_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "the file is too large").into());
+    }
+    Ok(bytes)
+}
+
+/// Get the name the game gives a mod from a name or an archive filename: the filename without
+/// its extension, in lowercase.
+fn mod_name(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.strip_suffix(".zip") {
+        Some(stem) => stem.to_string(),
+        None => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[test]
+    fn importing_folders() {
+        let mock = MockData::new();
+        let mut mod_cfg = mock.modcfg;
+        let temp_dir = tempdir().unwrap();
+        let folder = temp_dir.path();
+
+        let mut zip = zip::ZipWriter::new(File::create(folder.join("New_Mod.zip")).unwrap());
+        zip.start_file("info.json", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"{}").unwrap();
+        zip.finish().unwrap();
+        // Installed mods aren't checked or copied again.
+        fs::write(folder.join("mod1.zip"), "").unwrap();
+        fs::write(folder.join("partial.zip"), "still downloading").unwrap();
+
+        assert_eq!(ImportSource::detect(folder), ImportSource::Folder);
+        let imported = import(folder, ImportSource::Folder, &mock.mods_dir, &mut mod_cfg).unwrap();
+
+        assert_eq!(imported.mods, vec!["mod1", "new_mod"]);
+        assert_eq!(imported.installed, vec![mock.mods_dir.join("New_Mod.zip")]);
+        assert!(imported.missing.is_empty());
+        assert_eq!(imported.rejected.len(), 1);
+        assert!(folder.join("New_Mod.zip").exists());
+        assert!(mod_cfg.is_mod_active("new_mod").is_some());
+        assert!(mod_cfg.is_mod_active("partial").is_none());
+
+        // Files the game hasn't registered yet aren't overwritten.
+        let mut zip = zip::ZipWriter::new(File::create(folder.join("clash.zip")).unwrap());
+        zip.start_file("info.json", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.finish().unwrap();
+        fs::write(mock.mods_dir.join("clash.zip"), "mine").unwrap();
+        let imported = import(folder, ImportSource::Folder, &mock.mods_dir, &mut mod_cfg).unwrap();
+        assert!(imported.installed.is_empty());
+        assert!(imported
+            .rejected
+            .iter()
+            .any(|(archive, _)| *archive == folder.join("clash.zip")));
+        assert_eq!(
+            fs::read_to_string(mock.mods_dir.join("clash.zip")).unwrap(),
+            "mine"
+        );
+    }
+
+    #[test]
+    fn importing_subscriptions() {
+        let mock = MockData::new();
+        let mut mod_cfg = mock.modcfg;
+        let dump = mock.mods_dir.join("subscriptions.json");
+        fs::write(
+            &dump,
+            r#"{"mods":{
+                "mod2":{"active":true,"dirname":"/mods/repo/"},
+                "mod4":{"active":true,"dirname":"/mods/repo/"},
+                "mod3":{"active":true,"dirname":"/mods/"}
+            }}"#,
+        )
+        .unwrap();
+
+        assert_eq!(ImportSource::detect(&dump), ImportSource::Subscriptions);
+        let imported = import(
+            &dump,
+            ImportSource::Subscriptions,
+            &mock.mods_dir,
+            &mut mod_cfg,
+        )
+        .unwrap();
+
+        assert_eq!(imported.mods, vec!["mod2"]);
+        assert_eq!(imported.missing, vec!["mod4"]);
+    }
+
+    #[test]
+    fn importing_preset_zips() {
+        let mock = MockData::new();
+        let pack = mock.mods_dir.join("pack.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&pack).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        let files = [
+            (
+                "pack/preset1.json",
+                r#"{"name":"preset1","mods":["mod1","mod3"],"enabled":true}"#,
+            ),
+            (
+                "pack/preset1-extra.json",
+                r#"{"name":"extra","mods":["mod2"],"enabled":false,"extends":"preset1"}"#,
+            ),
+            (
+                "pack/escape.json",
+                r#"{"name":"../escape","mods":[],"enabled":false}"#,
+            ),
+            (
+                "pack/future.json",
+                r#"{"format_version":999,"name":"future","mods":[],"enabled":false}"#,
+            ),
+            ("pack/readme.txt", "Enjoy!"),
+        ];
+        for (name, contents) in files {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let imported = import_presets(&pack, &mock.presets_dir).unwrap();
+
+        assert_eq!(
+            imported.imported,
+            vec![(PathBuf::from("pack/preset1-extra.json"), "extra".into())]
+        );
+        assert_eq!(
+            imported.renamed,
+            vec![
+                (
+                    PathBuf::from("pack/preset1.json"),
+                    "preset1".into(),
+                    "preset1-2".into()
+                ),
+                (
+                    PathBuf::from("pack/escape.json"),
+                    "../escape".into(),
+                    ".._escape".into()
+                )
+            ]
+        );
+        let rejected: Vec<_> = imported.rejected.iter().map(|(f, _)| f).collect();
+        assert_eq!(rejected, vec!["pack/future.json"]);
+        assert!(Preset::exists(".._escape", &mock.presets_dir));
+        // The existing preset is untouched and the imported ones are disabled.
+        let preset1 = Preset::load_from_path("preset1", &mock.presets_dir).unwrap();
+        assert_eq!(preset1.get_mods(), &vec!["mod1"]);
+        let renamed = Preset::load_from_path("preset1-2", &mock.presets_dir).unwrap();
+        assert_eq!(renamed.get_mods(), &vec!["mod1", "mod3"]);
+        assert!(!renamed.is_enabled());
+        let extra = Preset::load_from_path("extra", &mock.presets_dir).unwrap();
+        assert_eq!(extra.extends(), Some("preset1-2"));
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{archive, game::ModCfg, Error::*, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The result of installing the mod archives in the inbox.
+#[derive(Debug, Default, PartialEq)]
+pub struct InboxReport {
+    /// The archives moved into the mods directory, at their new paths.
+    pub installed: Vec<PathBuf>,
+    /// The names of the mods registered in the ModCfg. Archives of mods that are already
+    /// registered, e.g. because their archive went missing, aren't registered again.
+    pub registered: Vec<String>,
+    /// The archives left in the inbox because they are corrupt, or an archive with their name is
+    /// already in the mods directory, along with why. Corrupt archives may still be downloading.
+    pub rejected: Vec<(PathBuf, String)>,
+}
+
+/// Install the mod archives dropped into the inbox.
+///
+/// Every zip archive directly in the inbox is checked with `archive::check`. Valid archives are
+/// moved into the mods directory and registered in the ModCfg so they can be managed right away.
+/// Corrupt archives, and archives whose name is taken in the mods directory, are left in the
+/// inbox, so an installed mod is never overwritten. The archives are moved right away, but the game
+/// only sees them as registered once the ModCfg is saved.
+///
+/// # Arguments
+///
+/// `inbox_dir`: The inbox directory. Nothing is installed if it doesn't exist.
+/// `mods_dir`: The mods directory of the current game version.
+/// `mod_config`: The ModCfg to register the installed mods in.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading the inbox or moving an archive.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{game::ModCfg, inbox};
+/// # use tempfile::tempdir;
+///
+/// # let temp_inbox_dir = tempdir().unwrap();
+/// # let inbox_dir = temp_inbox_dir.path();
+/// # let temp_mods_dir = tempdir().unwrap();
+/// # let mods_dir = temp_mods_dir.path();
+/// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{}}").unwrap();
+/// let mut mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+///
+/// let report = inbox::install(&inbox_dir, &mods_dir, &mut mod_cfg).unwrap();
+/// for (archive, reason) in report.rejected {
+///     println!("{} was left in the inbox: {}", archive.display(), reason);
+/// }
+/// mod_cfg.save_to_path(&mods_dir).unwrap();
+/// ```
+pub fn install(
+    inbox_dir: impl AsRef<Path>,
+    mods_dir: impl AsRef<Path>,
+    mod_config: &mut ModCfg,
+) -> Result<InboxReport> {
+    let inbox_dir = inbox_dir.as_ref();
+    let mods_dir = mods_dir.as_ref();
+    let mut report = InboxReport::default();
+    if !inbox_dir.try_exists()? {
+        return Ok(report);
+    }
+
+    let mut archives = vec![];
+    for entry in fs::read_dir(inbox_dir)? {
+        let path = entry?.path();
+        if path.is_file()
+            && path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("zip"))
+        {
+            archives.push(path);
+        }
+    }
+    archives.sort();
+
+    for archive in archives {
+        match archive::check(&archive) {
+            Ok(()) => (),
+            Err(CorruptArchive { archive, reason }) => {
+                report.rejected.push((archive, reason));
+                continue;
+            }
+            Err(e) => return Err(e),
+        }
+
+        // The file name is known to exist since the path came from reading the inbox.
+        let target = mods_dir.join(archive.file_name().unwrap());
+        if target.try_exists()? {
+            let reason = "an archive with its name is already in the mods directory";
+            report.rejected.push((archive, reason.into()));
+            continue;
+        }
+        archive::move_archive(&archive, &target)?;
+        if let Some(mod_name) = mod_config.register_archive(&target) {
+            report.registered.push(mod_name);
+        }
+        report.installed.push(target);
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+    use std::{fs::File, io::Write};
+
+    #[test]
+    fn installing_from_inbox() {
+        let mock = MockData::new();
+        let mut mod_cfg = mock.modcfg;
+        let tmp = tempfile::tempdir().unwrap();
+        let inbox_dir = tmp.path();
+
+        let mut zip = zip::ZipWriter::new(File::create(inbox_dir.join("new_mod.zip")).unwrap());
+        zip.start_file("info.json", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"{}").unwrap();
+        zip.finish().unwrap();
+        fs::write(inbox_dir.join("partial.zip"), "still downloading").unwrap();
+        fs::write(inbox_dir.join("readme.txt"), "not a mod").unwrap();
+
+        let report = install(inbox_dir, &mock.mods_dir, &mut mod_cfg).unwrap();
+
+        assert_eq!(report.installed, vec![mock.mods_dir.join("new_mod.zip")]);
+        assert_eq!(report.registered, vec!["new_mod"]);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].0, inbox_dir.join("partial.zip"));
+        assert!(mock.mods_dir.join("new_mod.zip").exists());
+        assert!(!inbox_dir.join("new_mod.zip").exists());
+        assert!(inbox_dir.join("partial.zip").exists());
+        assert!(mod_cfg.is_mod_active("new_mod").unwrap());
+    }
+
+    #[test]
+    fn leaving_taken_names() {
+        let mock = MockData::new();
+        let mut mod_cfg = mock.modcfg;
+        let tmp = tempfile::tempdir().unwrap();
+        let inbox_dir = tmp.path();
+
+        let mut zip = zip::ZipWriter::new(File::create(inbox_dir.join("mod1.zip")).unwrap());
+        zip.start_file("info.json", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.finish().unwrap();
+        fs::write(mock.mods_dir.join("mod1.zip"), "installed").unwrap();
+
+        let report = install(inbox_dir, &mock.mods_dir, &mut mod_cfg).unwrap();
+
+        assert!(report.installed.is_empty());
+        assert_eq!(report.rejected[0].0, inbox_dir.join("mod1.zip"));
+        assert!(inbox_dir.join("mod1.zip").exists());
+        assert_eq!(
+            fs::read_to_string(mock.mods_dir.join("mod1.zip")).unwrap(),
+            "installed"
+        );
+    }
+
+    #[test]
+    fn missing_inbox() {
+        let mock = MockData::new();
+        let mut mod_cfg = mock.modcfg;
+
+        let report = install(
+            mock.mods_dir.join("missing_inbox"),
+            &mock.mods_dir,
+            &mut mod_cfg,
+        )
+        .unwrap();
+        assert_eq!(report, InboxReport::default());
+    }
+}
@@ -0,0 +1,387 @@
+use crate::{Preset, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// A summary of every preset in a presets directory, so presets can be listed and looked up
+/// without reading every preset file.
+///
+/// The index is stored in the presets directory. Loading it re-reads only the presets whose files
+/// changed since they were indexed, going by their modification time and size, and drops presets
+/// whose files are gone.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{index::PresetIndex, Preset};
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let presets_dir = temp_dir.path();
+/// let mut preset = Preset::new("racing".into(), vec!["mod1".into(), "mod2".into()]).unwrap();
+/// preset.enable();
+/// preset.save_to_path(&presets_dir).unwrap();
+///
+/// let index = PresetIndex::load_from_path(&presets_dir).unwrap();
+/// assert_eq!(index.enabled(), vec!["racing"]);
+/// assert_eq!(index.containing("mod2"), vec!["racing"]);
+/// assert_eq!(index.get("racing").unwrap().mod_count(), 2);
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct PresetIndex {
+    /// The indexed presets by name.
+    #[serde(default)]
+    presets: BTreeMap<String, IndexEntry>,
+}
+
+/// What the index records about a preset.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    /// Whether the preset is enabled.
+    enabled: bool,
+    /// The preset's own mods, without those of presets it extends.
+    mods: Vec<String>,
+    /// The preset it extends, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    extends: Option<String>,
+    /// When the preset file was last modified.
+    modified: SystemTime,
+    /// The size of the preset file in bytes.
+    size: u64,
+}
+
+/// A mod added to or removed from a preset, found by `PresetIndex::membership_changes`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MembershipChange {
+    /// The name of the preset.
+    pub preset: String,
+    /// The name of the mod.
+    pub mod_name: String,
+    /// Whether the mod was added to the preset, rather than removed.
+    pub added: bool,
+}
+
+impl IndexEntry {
+    /// Whether the preset is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Get the preset's own mods, without those of presets it extends.
+    pub fn mods(&self) -> &[String] {
+        &self.mods
+    }
+
+    /// Get the number of the preset's own mods.
+    pub fn mod_count(&self) -> usize {
+        self.mods.len()
+    }
+
+    /// Get the name of the preset it extends, if any.
+    pub fn extends(&self) -> Option<&str> {
+        self.extends.as_deref()
+    }
+}
+
+impl PresetIndex {
+    /// The filename of the index. It has no `.json` extension so it isn't listed as a preset.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    fn filename() -> PathBuf {
+        PathBuf::from(".index")
+    }
+
+    /// Load the index from a reader as it was saved, without checking it against the presets.
+    ///
+    /// # Arguments
+    ///
+    /// `reader`: The reader to load the index from.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue reading or deserializing the index.
+    pub fn load<R: BufRead>(reader: R) -> Result<Self> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Load the index of a presets directory, updating it for presets changed since it was saved.
+    /// The index is saved again if anything changed. An index that is missing or can't be read is
+    /// built from scratch, and one that can't be saved is rebuilt next time.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: Where preset config files are stored.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading the presets directory, or serde_json errors
+    /// if there is an issue deserializing a changed preset.
+    pub fn load_from_path(presets_dir: impl AsRef<Path>) -> Result<Self> {
+        let presets_dir = presets_dir.as_ref();
+        let path = presets_dir.join(Self::filename());
+        let mut index = match File::open(path) {
+            Ok(file) => Self::load(BufReader::new(file)).unwrap_or_default(),
+            Err(_) => Self::default(),
+        };
+        // The index is only a cache, so presets can still be used from a read-only directory.
+        if index.refresh(presets_dir)? {
+            let _ = index.save_to_path(presets_dir);
+        }
+        Ok(index)
+    }
+
+    /// Serialize and save the index to a writer.
+    ///
+    /// # Arguments
+    ///
+    /// `writer`: The writer to save the index to.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue serializing the index or writing.
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Serialize and save the index to the presets directory.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: Where preset config files are stored.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue creating the file or writing to it.
+    pub fn save_to_path(&self, presets_dir: impl AsRef<Path>) -> Result<()> {
+        let presets_dir = presets_dir.as_ref();
+        let file = File::create(presets_dir.join(Self::filename()))?;
+        self.save(BufWriter::new(file))
+    }
+
+    /// Re-read the presets whose files changed since they were indexed, and drop presets whose
+    /// files are gone.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: Where preset config files are stored.
+    ///
+    /// # Returns
+    ///
+    /// Whether anything in the index changed.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading the presets directory, or serde_json errors
+    /// if there is an issue deserializing a changed preset.
+    pub fn refresh(&mut self, presets_dir: impl AsRef<Path>) -> Result<bool> {
+        let presets_dir = presets_dir.as_ref();
+        let names: Vec<String> = Preset::list(presets_dir)?.collect();
+        let count = self.presets.len();
+        self.presets.retain(|name, _| names.contains(name));
+        let mut changed = self.presets.len() != count;
+
+        for name in names {
+            let metadata = fs::metadata(Preset::path(&name, presets_dir))?;
+            let (modified, size) = (metadata.modified()?, metadata.len());
+            if self
+                .presets
+                .get(&name)
+                .is_some_and(|e| e.modified == modified && e.size == size)
+            {
+                continue;
+            }
+            let preset = Preset::load_from_path(&name, presets_dir)?;
+            let entry = IndexEntry {
+                enabled: preset.is_enabled(),
+                mods: preset.get_mods().clone(),
+                extends: preset.extends().map(String::from),
+                modified,
+                size,
+            };
+            self.presets.insert(name, entry);
+            changed = true;
+        }
+        Ok(changed)
+    }
+
+    /// Get what the index records about a preset.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The name of the preset.
+    pub fn get(&self, name: &str) -> Option<&IndexEntry> {
+        self.presets.get(name)
+    }
+
+    /// Iterate over the indexed presets, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &IndexEntry)> {
+        self.presets
+            .iter()
+            .map(|(name, entry)| (name.as_str(), entry))
+    }
+
+    /// Get the names of the enabled presets, sorted.
+    pub fn enabled(&self) -> Vec<&str> {
+        self.iter()
+            .filter(|(_, entry)| entry.enabled)
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Get the names of the presets that contain a mod themselves, sorted. Presets that only get
+    /// the mod from a preset they extend aren't included.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    pub fn containing(&self, mod_name: &str) -> Vec<&str> {
+        self.iter()
+            .filter(|(_, entry)| entry.mods.iter().any(|m| m == mod_name))
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Get the names of the enabled presets along with every preset they extend, directly or not,
+    /// sorted. These are the presets needed to apply the enabled presets.
+    pub fn enabled_with_parents(&self) -> Vec<&str> {
+        let mut needed: Vec<&str> = vec![];
+        for name in self.enabled() {
+            let mut next = Some(name);
+            // Stopping at presets already added also stops at cycles.
+            while let Some(name) = next.filter(|n| !needed.contains(n)) {
+                needed.push(name);
+                next = self.get(name).and_then(IndexEntry::extends);
+            }
+        }
+        needed.sort();
+        needed
+    }
+
+    /// Find the mods added to and removed from presets between this index and a later one.
+    /// Created presets have all their mods added, and deleted presets all their mods removed.
+    ///
+    /// # Arguments
+    ///
+    /// `after`: The index after the changes.
+    ///
+    /// # Returns
+    ///
+    /// The changes sorted by preset, then by mod.
+    pub fn membership_changes(&self, after: &PresetIndex) -> Vec<MembershipChange> {
+        let mut names: Vec<&String> = self.presets.keys().chain(after.presets.keys()).collect();
+        names.sort();
+        names.dedup();
+
+        let mut changes = vec![];
+        for name in names {
+            let before = self.get(name).map_or(&[][..], IndexEntry::mods);
+            let after = after.get(name).map_or(&[][..], IndexEntry::mods);
+            let mut preset_changes: Vec<MembershipChange> = after
+                .iter()
+                .filter(|m| !before.contains(m))
+                .map(|m| (m, true))
+                .chain(
+                    before
+                        .iter()
+                        .filter(|m| !after.contains(m))
+                        .map(|m| (m, false)),
+                )
+                .map(|(mod_name, added)| MembershipChange {
+                    preset: name.clone(),
+                    mod_name: mod_name.clone(),
+                    added,
+                })
+                .collect();
+            preset_changes.sort_by(|a, b| a.mod_name.cmp(&b.mod_name));
+            changes.extend(preset_changes);
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+
+    #[test]
+    fn indexing_presets() {
+        let mock = MockData::new();
+
+        let index = PresetIndex::load_from_path(&mock.presets_dir).unwrap();
+        assert_eq!(index.enabled(), vec!["preset1"]);
+        assert_eq!(index.containing("mod1"), vec!["preset1", "preset2"]);
+        assert_eq!(index.containing("mod2"), vec!["preset2"]);
+        assert_eq!(index.get("preset2").unwrap().mod_count(), 2);
+        assert!(mock.presets_dir.join(PresetIndex::filename()).exists());
+
+        // Changed and deleted presets are picked up.
+        let mut preset2 = mock.preset2;
+        preset2.enable();
+        preset2.add_mod("mod3");
+        preset2.save_to_path(&mock.presets_dir).unwrap();
+        Preset::delete("preset1", &mock.presets_dir, true).unwrap();
+
+        let index = PresetIndex::load_from_path(&mock.presets_dir).unwrap();
+        assert_eq!(index.enabled(), vec!["preset2"]);
+        assert_eq!(index.containing("mod3"), vec!["preset2"]);
+        assert!(index.get("preset1").is_none());
+
+        // Loading again without changes doesn't re-read anything.
+        let mut reloaded = PresetIndex::load_from_path(&mock.presets_dir).unwrap();
+        assert!(!reloaded.refresh(&mock.presets_dir).unwrap());
+        assert_eq!(reloaded, index);
+    }
+
+    #[test]
+    fn finding_membership_changes() {
+        let mock = MockData::new();
+        let before = PresetIndex::load_from_path(&mock.presets_dir).unwrap();
+
+        let mut preset2 = mock.preset2;
+        preset2.remove_mod("mod1");
+        preset2.add_mod("mod3");
+        preset2.save_to_path(&mock.presets_dir).unwrap();
+        Preset::delete("preset1", &mock.presets_dir, true).unwrap();
+        let after = PresetIndex::load_from_path(&mock.presets_dir).unwrap();
+
+        let change = |preset: &str, mod_name: &str, added| MembershipChange {
+            preset: preset.into(),
+            mod_name: mod_name.into(),
+            added,
+        };
+        assert_eq!(
+            before.membership_changes(&after),
+            vec![
+                change("preset1", "mod1", false),
+                change("preset2", "mod1", false),
+                change("preset2", "mod3", true),
+            ]
+        );
+        assert!(after.membership_changes(&after).is_empty());
+    }
+
+    #[test]
+    fn finding_parents() {
+        let mock = MockData::new();
+        let mut child = Preset::new("child".into(), vec![]).unwrap();
+        child.set_extends(Some("preset2".into()));
+        child.enable();
+        child.save_to_path(&mock.presets_dir).unwrap();
+        let mut preset2 = mock.preset2;
+        preset2.set_extends(Some("child".into()));
+        preset2.save_to_path(&mock.presets_dir).unwrap();
+
+        let index = PresetIndex::load_from_path(&mock.presets_dir).unwrap();
+        assert_eq!(
+            index.enabled_with_parents(),
+            vec!["child", "preset1", "preset2"]
+        );
+    }
+}
@@ -1,20 +1,94 @@
 #![cfg_attr(coverage_nightly, feature(coverage_attribute))]
+// The docs indent the continuation lines of list items further than newer clippy expects.
+#![allow(clippy::doc_overindented_list_items)]
 
 use std::{
     collections::HashSet,
-    fs::{self},
+    fmt,
     io::{self, BufRead, Write},
     path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
 };
 
+#[cfg(feature = "native")]
+use std::sync::{mpsc, Mutex, OnceLock};
+
+#[cfg(feature = "native")]
+pub mod archive;
+#[cfg(feature = "native")]
+pub mod backup;
+pub mod config;
+#[cfg(feature = "native")]
+pub mod crash;
+#[cfg(feature = "native")]
+pub mod db_backups;
+#[cfg(feature = "native")]
+pub mod filter;
 pub mod game;
+#[cfg(feature = "native")]
+pub mod health;
+#[cfg(feature = "native")]
+pub mod history;
+#[cfg(feature = "native")]
+pub mod import;
+#[cfg(feature = "native")]
+pub mod inbox;
+#[cfg(feature = "native")]
+pub mod index;
+#[cfg(feature = "native")]
+pub mod lock;
+#[cfg(feature = "native")]
+pub mod logscan;
+pub mod metadata;
+#[cfg(feature = "native")]
+pub mod migrations;
+#[cfg(feature = "native")]
+pub mod mirror;
+#[cfg(feature = "native")]
+pub mod namespace;
+#[cfg(feature = "native")]
+pub mod net;
+#[cfg(feature = "native")]
+pub mod oplog;
+#[cfg(feature = "native")]
+pub mod pager;
+#[cfg(feature = "native")]
 pub mod path;
+#[cfg(feature = "native")]
+pub mod plan;
 mod preset;
+#[cfg(feature = "native")]
+pub mod preset_backups;
+#[cfg(feature = "native")]
+mod profile;
+#[cfg(feature = "native")]
+pub mod rpc;
+#[cfg(feature = "native")]
+pub mod rules;
+#[cfg(feature = "native")]
+pub mod shell;
+#[cfg(feature = "native")]
+pub mod stash;
+#[cfg(feature = "native")]
+pub mod state;
+#[cfg(feature = "native")]
+pub mod stats;
+#[cfg(feature = "native")]
+pub mod support;
+#[cfg(feature = "native")]
+pub mod table;
+#[cfg(feature = "native")]
+pub mod transaction;
+#[cfg(feature = "native")]
+pub mod update;
 
 #[cfg(test)]
 mod test_utils;
 
 pub use preset::Preset;
+#[cfg(feature = "native")]
+pub use profile::Profile;
 
 /// Result type alias for this crate.
 pub type Result<T> = core::result::Result<T, Error>;
@@ -29,9 +103,19 @@ pub enum Error {
     /// * `dir`: The directory that was specified but doesn't exist.
     #[error("Directory {dir} not found.")]
     DirNotFound { dir: PathBuf },
+    /// When the game version of a mods directory can't be told from where it is.
+    ///
+    /// # Fields
+    ///
+    /// * `dir`: The mods directory.
+    #[error("Can't tell which game version {dir} is for. Put it in a folder named after the version, e.g. 0.32/mods, or pass --game-version.")]
+    UnknownGameVersion { dir: PathBuf },
     /// When the game directory cannot be automatically found. Try launching the game first.
     #[error("Game directory could not automatically be found. Try launching the game first.")]
     GameDirNotFound,
+    /// When the game's installation directory cannot be found automatically.
+    #[error("Game installation directory could not automatically be found.")]
+    InstallDirNotFound,
     /// When `%LocalAppData%` Windows variable isn't found. What's wrong with your Windows install?
     #[error("%LocalAppData% variable could not be found.")]
     MissingLocalAppdata,
@@ -51,18 +135,41 @@ pub enum Error {
     /// # Fields
     ///
     /// * `mods`: The mods that were specified but not found.
-    #[error("Mods not found: {mods:?}")]
-    MissingMods { mods: Vec<String> },
+    /// * `suggestions`: Installed mods with names close to the missing ones, likely meant instead.
+    #[error("Mods not found: {mods:?}{}", did_you_mean(suggestions))]
+    MissingMods {
+        mods: Vec<String>,
+        suggestions: Vec<String>,
+    },
+    /// When blacklisted mods are specified to be enabled.
+    ///
+    /// # Fields
+    ///
+    /// * `mods`: The blacklisted mods that were specified.
+    #[error("Mods are blacklisted and can't be enabled: {mods:?}")]
+    BlacklistedMods { mods: Vec<String> },
+    /// When mods missing from the enforced whitelist are specified to be enabled.
+    ///
+    /// # Fields
+    ///
+    /// * `mods`: The mods that were specified but aren't whitelisted.
+    #[error("Mods are not on the enforced whitelist and can't be enabled: {mods:?}")]
+    NotWhitelistedMods { mods: Vec<String> },
     // When a preset errors when enabling
     //
     // # Fields
     //
     // * `mods`: The mods that were missing.
     // * `presets`: The presets that failed to enable.
-    #[error("Presets failed to enable: {presets:?}, missing these mods: {mods:?}")]
+    // * `requirements`: Mods paired with the mods they require that aren't installed.
+    #[error(
+        "Presets failed to enable: {presets:?}, missing these mods: {mods:?}{}",
+        unmet_requirements(requirements)
+    )]
     PresetsFailed {
         mods: HashSet<String>,
         presets: HashSet<String>,
+        requirements: Vec<(String, String)>,
     },
     /// When a preset already exists.
     ///
@@ -71,6 +178,238 @@ pub enum Error {
     /// * `preset`: The name of the preset that already exists.
     #[error("Preset `{preset}` already exists.")]
     PresetExists { preset: String },
+    /// When a name can't be used for a preset, since preset names are used as file names.
+    ///
+    /// # Fields
+    ///
+    /// * `preset`: The invalid name.
+    /// * `reason`: Why the name can't be used.
+    #[error("Preset name `{preset}` is invalid: {reason}.")]
+    InvalidPresetName { preset: String, reason: String },
+    /// When a preset name refers to a namespace that isn't configured.
+    ///
+    /// # Fields
+    ///
+    /// * `namespace`: The unknown namespace.
+    #[error("Preset namespace `{namespace}` isn't configured. Add it with --preset-namespace.")]
+    UnknownNamespace { namespace: String },
+    /// When trying to change a preset of a shared namespace, which are read-only.
+    ///
+    /// # Fields
+    ///
+    /// * `preset`: The namespace-qualified name of the preset, e.g. `shared/racing`.
+    #[error("Preset `{preset}` is in a shared namespace, which is read-only.")]
+    ReadOnlyPreset { preset: String },
+    /// When trying to revert a preset to a revision that isn't in its history.
+    ///
+    /// # Fields
+    ///
+    /// * `preset`: The name of the preset.
+    /// * `revision`: The number of the missing revision.
+    #[error(
+        "Preset `{preset}` has no revision {revision}. List its revisions with --preset-history."
+    )]
+    MissingRevision { preset: String, revision: u32 },
+    /// When a preset revision isn't a number.
+    ///
+    /// # Fields
+    ///
+    /// * `preset`: The name of the preset.
+    /// * `revision`: The revision that was specified.
+    #[error("Invalid revision `{revision}` of preset `{preset}`. Expected a number from --preset-history.")]
+    InvalidRevision { preset: String, revision: String },
+    /// When a preset file was written by a newer version of BeamMM, in a format this version
+    /// can't read.
+    ///
+    /// # Fields
+    ///
+    /// * `preset`: The name of the preset.
+    /// * `format_version`: The format version of the file.
+    #[error("Preset `{preset}` has format version {format_version}, which is newer than this version of BeamMM supports. Update BeamMM to use it.")]
+    UnsupportedPresetFormat { preset: String, format_version: u64 },
+    /// When a mod can't be renamed because another mod already has the name.
+    ///
+    /// # Fields
+    ///
+    /// * `mod_name`: The name that is already taken.
+    #[error("Mod `{mod_name}` already exists.")]
+    ModExists { mod_name: String },
+    /// When presets extend each other in a cycle.
+    ///
+    /// # Fields
+    ///
+    /// * `presets`: The presets in the cycle, starting and ending with the same preset.
+    #[error("Presets extend each other in a cycle: {}", presets.join(" -> "))]
+    PresetCycle { presets: Vec<String> },
+    /// When a mod archive can't be read as a zip, e.g. because its download was cut short.
+    ///
+    /// # Fields
+    ///
+    /// * `archive`: The path to the archive.
+    /// * `reason`: Why the archive couldn't be read.
+    #[error("Mod archive {archive} is corrupt: {reason}")]
+    CorruptArchive { archive: PathBuf, reason: String },
+    /// When a preset share code can't be decoded.
+    #[error("Invalid preset share code.")]
+    InvalidShareCode,
+    /// When the profile wasn't found.
+    ///
+    /// # Fields
+    ///
+    /// * `dir`: The directory where the profile was supposed to be.
+    /// * `profile`: The name of the profile that was missing.
+    #[error("Could not find profile {profile} in {dir}")]
+    MissingProfile { dir: PathBuf, profile: String },
+    /// When a profile already exists.
+    ///
+    /// # Fields
+    ///
+    /// * `profile`: The name of the profile that already exists.
+    #[error("Profile `{profile}` already exists.")]
+    ProfileExists { profile: String },
+    /// When a name can't be used for a profile, since profile names are used as directory names.
+    ///
+    /// # Fields
+    ///
+    /// * `profile`: The invalid name.
+    /// * `reason`: Why the name can't be used.
+    #[error("Profile name `{profile}` is invalid: {reason}.")]
+    InvalidProfileName { profile: String, reason: String },
+    /// When a missing mod policy string can't be parsed.
+    ///
+    /// # Fields
+    ///
+    /// * `policy`: The policy that was specified.
+    #[error("Invalid missing mod policy `{policy}`. Expected one of: fail, skip, prune.")]
+    InvalidMissingModPolicy { policy: String },
+    /// When a backup retention policy string can't be parsed.
+    ///
+    /// # Fields
+    ///
+    /// * `policy`: The policy that was specified.
+    #[error("Invalid backup retention `{policy}`. Expected a number of backups like `20` or of days like `30d`.")]
+    InvalidRetention { policy: String },
+    /// When a chosen game data directory index is out of range.
+    ///
+    /// # Fields
+    ///
+    /// * `index`: The index that was chosen.
+    /// * `count`: The number of game data directories found.
+    #[error("Invalid game directory index {index}. Expected a number from 1 to {count}.")]
+    InvalidGameDirIndex { index: usize, count: usize },
+    /// When stashing while a stash already exists.
+    #[error("There already is a stash. Pop it with --stash-pop first.")]
+    StashExists,
+    /// When popping the stash while there is none.
+    #[error("There is no stash to pop.")]
+    MissingStash,
+    /// When db.json can't be parsed, even after working around the quirks the game tolerates.
+    ///
+    /// # Fields
+    ///
+    /// * `line`: The line where parsing failed.
+    /// * `column`: The column where parsing failed.
+    /// * `reason`: Why parsing failed.
+    /// * `snippet`: The text around where parsing failed.
+    #[error("Could not parse db.json at line {line}, column {column}: {reason}\n  {snippet}")]
+    InvalidModCfg {
+        line: usize,
+        column: usize,
+        reason: String,
+        snippet: String,
+    },
+
+    /// When a file can't be downloaded.
+    ///
+    /// # Fields
+    ///
+    /// * `url`: The URL of the file.
+    /// * `reason`: Why the download failed.
+    #[error("Could not download {url}: {reason}")]
+    Download { url: String, reason: String },
+    /// When a mod that wasn't installed from the repository is used as a repository mod.
+    ///
+    /// # Fields
+    ///
+    /// * `mod_name`: The name of the mod.
+    #[error("Mod `{mod_name}` wasn't installed from the repository.")]
+    NotRepoMod { mod_name: String },
+    /// When a backup can't be restored.
+    ///
+    /// # Fields
+    ///
+    /// * `backup`: The path to the backup.
+    /// * `reason`: Why the backup can't be restored.
+    #[error("Backup {backup} can't be restored: {reason}")]
+    InvalidBackup { backup: PathBuf, reason: String },
+    /// When there is no db.json backup to restore.
+    ///
+    /// # Fields
+    ///
+    /// * `dir`: The directory the backups are kept in.
+    #[error("No db.json backup to restore in {dir}")]
+    MissingDbBackup { dir: PathBuf },
+    /// When the BeamMM directory was written by a newer version of BeamMM, or its version can't
+    /// be read.
+    ///
+    /// # Fields
+    ///
+    /// * `dir`: The BeamMM directory.
+    /// * `version`: The version recorded in the directory.
+    #[error("The BeamMM directory {dir} has version {version}, which this version of BeamMM doesn't support. Update BeamMM to use it.")]
+    UnsupportedDataVersion { dir: PathBuf, version: String },
+    /// When a plan can't be applied because a file it writes changed since it was made.
+    ///
+    /// # Fields
+    ///
+    /// * `path`: The file that changed.
+    #[error("{path} changed since the plan was made. Make a new plan with --plan.")]
+    StalePlan { path: PathBuf },
+    /// When a mod filter can't be parsed.
+    ///
+    /// # Fields
+    ///
+    /// * `filter`: The filter that was specified.
+    /// * `reason`: Why the filter is invalid.
+    #[error("Invalid filter `{filter}`: {reason}")]
+    InvalidFilter { filter: String, reason: String },
+    /// When a shell command isn't known.
+    ///
+    /// # Fields
+    ///
+    /// * `command`: The command that was given.
+    #[error("Unknown command `{command}`. Type `help` for a list of commands.")]
+    UnknownCommand { command: String },
+    /// When a command in a script fails and the script is run all-or-nothing.
+    ///
+    /// # Fields
+    ///
+    /// * `line`: The line of the script the command is on, starting from 1.
+    /// * `reason`: Why the command failed.
+    #[error("Line {line} of the script failed, so nothing was changed: {reason}")]
+    ScriptFailed { line: usize, reason: String },
+    /// When another BeamMM process holds the lock on the BeamMM directory.
+    ///
+    /// # Fields
+    ///
+    /// * `path`: The path to the lock file.
+    /// * `pid`: The ID of the process holding the lock, if known.
+    #[error(
+        "Another BeamMM process{} is running. Wait for it to finish and try again. (Lock file: {})",
+        pid.map(|pid| format!(" ({pid})")).unwrap_or_default(),
+        path.display()
+    )]
+    Locked { path: PathBuf, pid: Option<u32> },
+    /// When the operating system doesn't let BeamMM read or write a file.
+    ///
+    /// # Fields
+    ///
+    /// * `path`: The file or directory BeamMM wasn't allowed to access.
+    #[error(
+        "Permission denied for {}. The game may be running and holding the file, a folder protection feature like OneDrive's or Windows' Controlled Folder Access may be blocking BeamMM, or the file may be read-only.",
+        path.display()
+    )]
+    PermissionDenied { path: PathBuf },
 
     /// std::io errors.
     #[error("There was an IO error. {0}")]
@@ -83,6 +422,242 @@ pub enum Error {
 
 use Error::*;
 
+impl Error {
+    /// Make a function that turns IO errors on a file into BeamMM errors, for use with
+    /// `map_err`. Permission errors become `PermissionDenied` naming the file, since the bare OS
+    /// message doesn't say which file it was or why.
+    ///
+    /// # Arguments
+    ///
+    /// `path`: The file or directory being accessed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::Error;
+    /// use std::io;
+    ///
+    /// let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+    /// let error = Error::io("mods/db.json")(denied);
+    /// assert_eq!(error.kind(), "PermissionDenied");
+    /// ```
+    pub fn io(path: impl AsRef<Path>) -> impl FnOnce(io::Error) -> Self {
+        let path = path.as_ref().to_path_buf();
+        move |e| match e.kind() {
+            io::ErrorKind::PermissionDenied => PermissionDenied { path },
+            _ => IO(e),
+        }
+    }
+
+    /// Get the name of the kind of error, e.g. `MissingMods`, for frontends to tell errors apart
+    /// without parsing messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DirNotFound { .. } => "DirNotFound",
+            UnknownGameVersion { .. } => "UnknownGameVersion",
+            GameDirNotFound => "GameDirNotFound",
+            InstallDirNotFound => "InstallDirNotFound",
+            MissingLocalAppdata => "MissingLocalAppdata",
+            VersionError => "VersionError",
+            MissingPreset { .. } => "MissingPreset",
+            MissingMods { .. } => "MissingMods",
+            BlacklistedMods { .. } => "BlacklistedMods",
+            NotWhitelistedMods { .. } => "NotWhitelistedMods",
+            PresetsFailed { .. } => "PresetsFailed",
+            PresetExists { .. } => "PresetExists",
+            InvalidPresetName { .. } => "InvalidPresetName",
+            UnknownNamespace { .. } => "UnknownNamespace",
+            ReadOnlyPreset { .. } => "ReadOnlyPreset",
+            MissingRevision { .. } => "MissingRevision",
+            InvalidRevision { .. } => "InvalidRevision",
+            UnsupportedPresetFormat { .. } => "UnsupportedPresetFormat",
+            ModExists { .. } => "ModExists",
+            PresetCycle { .. } => "PresetCycle",
+            CorruptArchive { .. } => "CorruptArchive",
+            InvalidShareCode => "InvalidShareCode",
+            MissingProfile { .. } => "MissingProfile",
+            ProfileExists { .. } => "ProfileExists",
+            InvalidProfileName { .. } => "InvalidProfileName",
+            InvalidMissingModPolicy { .. } => "InvalidMissingModPolicy",
+            InvalidRetention { .. } => "InvalidRetention",
+            InvalidGameDirIndex { .. } => "InvalidGameDirIndex",
+            StashExists => "StashExists",
+            MissingStash => "MissingStash",
+            InvalidModCfg { .. } => "InvalidModCfg",
+            Download { .. } => "Download",
+            NotRepoMod { .. } => "NotRepoMod",
+            InvalidBackup { .. } => "InvalidBackup",
+            MissingDbBackup { .. } => "MissingDbBackup",
+            UnsupportedDataVersion { .. } => "UnsupportedDataVersion",
+            StalePlan { .. } => "StalePlan",
+            InvalidFilter { .. } => "InvalidFilter",
+            UnknownCommand { .. } => "UnknownCommand",
+            ScriptFailed { .. } => "ScriptFailed",
+            Locked { .. } => "Locked",
+            PermissionDenied { .. } => "PermissionDenied",
+            IO(_) => "IO",
+            JSON(_) => "JSON",
+        }
+    }
+
+    /// Get the paths the error is about, if any.
+    pub fn paths(&self) -> Vec<&std::path::Path> {
+        match self {
+            DirNotFound { dir }
+            | UnknownGameVersion { dir }
+            | MissingPreset { dir, .. }
+            | MissingProfile { dir, .. }
+            | MissingDbBackup { dir }
+            | UnsupportedDataVersion { dir, .. } => vec![dir],
+            CorruptArchive { archive, .. } => vec![archive],
+            InvalidBackup { backup, .. } => vec![backup],
+            Locked { path, .. } | StalePlan { path } | PermissionDenied { path } => vec![path],
+            _ => vec![],
+        }
+    }
+
+    /// Get the mods the error is about, if any, sorted.
+    pub fn mods(&self) -> Vec<&str> {
+        let mut mods: Vec<&str> = match self {
+            MissingMods { mods, .. } | BlacklistedMods { mods } | NotWhitelistedMods { mods } => {
+                mods.iter().map(String::as_str).collect()
+            }
+            PresetsFailed { mods, .. } => mods.iter().map(String::as_str).collect(),
+            NotRepoMod { mod_name } | ModExists { mod_name } => vec![mod_name],
+            _ => vec![],
+        };
+        mods.sort();
+        mods
+    }
+
+    /// Get the presets the error is about, if any, sorted.
+    pub fn presets(&self) -> Vec<&str> {
+        let mut presets: Vec<&str> = match self {
+            MissingPreset { preset, .. }
+            | PresetExists { preset }
+            | InvalidPresetName { preset, .. }
+            | ReadOnlyPreset { preset }
+            | MissingRevision { preset, .. }
+            | InvalidRevision { preset, .. }
+            | UnsupportedPresetFormat { preset, .. } => vec![preset],
+            PresetsFailed { presets, .. } => presets.iter().map(String::as_str).collect(),
+            // The cycle starts and ends with the same preset.
+            PresetCycle { presets } => presets.iter().skip(1).map(String::as_str).collect(),
+            _ => vec![],
+        };
+        presets.sort();
+        presets
+    }
+
+    /// Describe the error as a JSON object with its kind, message, and the paths, mods, and
+    /// presets it is about, for frontends to show precise dialogs.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::Error;
+    ///
+    /// let error = Error::BlacklistedMods { mods: vec!["mod1".into()] };
+    /// let json = error.to_json();
+    /// assert_eq!(json["kind"], "BlacklistedMods");
+    /// assert_eq!(json["mods"][0], "mod1");
+    /// ```
+    pub fn to_json(&self) -> serde_json::Value {
+        let paths: Vec<_> = self.paths().iter().map(|p| p.to_string_lossy()).collect();
+        serde_json::json!({
+            "kind": self.kind(),
+            "message": self.to_string(),
+            "paths": paths,
+            "mods": self.mods(),
+            "presets": self.presets(),
+        })
+    }
+}
+
+/// Format unmet mod requirements as a clause to append to an error message.
+fn unmet_requirements(requirements: &[(String, String)]) -> String {
+    if requirements.is_empty() {
+        return String::new();
+    }
+    let requirements: Vec<String> = requirements
+        .iter()
+        .map(|(mod_name, required)| format!("`{}` requires `{}`", mod_name, required))
+        .collect();
+    format!(", unmet requirements: {}", requirements.join(", "))
+}
+
+/// Format suggested mod names as a sentence to append to an error message.
+fn did_you_mean(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        return String::new();
+    }
+    let names: Vec<String> = suggestions.iter().map(|s| format!("`{}`", s)).collect();
+    format!(". Did you mean {}?", names.join(" or "))
+}
+
+/// A game version as used for the game's version directories, e.g. `0.32` or `0.33.1`.
+///
+/// Versions are ordered numerically by component, so `0.10` is newer than `0.9`, and `0.33.1` is
+/// newer than `0.33`.
+///
+/// # Examples
+///
+/// ```rust
+/// use beammm::GameVersion;
+///
+/// let version: GameVersion = "0.32".parse().unwrap();
+/// assert_eq!(version, GameVersion { major: 0, minor: 32, patch: None });
+/// assert!(version > "0.9".parse().unwrap());
+/// assert!(version < "0.32.1".parse().unwrap());
+/// assert_eq!(version.to_string(), "0.32");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GameVersion {
+    /// The major version.
+    pub major: u32,
+    /// The minor version.
+    pub minor: u32,
+    /// The patch version, for version directories of patch releases like `0.33.1`.
+    pub patch: Option<u32>,
+}
+
+impl fmt::Display for GameVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if let Some(patch) = self.patch {
+            write!(f, ".{}", patch)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GameVersion {
+    type Err = Error;
+
+    /// Parse a `major.minor` or `major.minor.patch` version string.
+    ///
+    /// # Errors
+    ///
+    /// * `VersionError`: If the string isn't two or three numbers separated by `.`.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut components = s.trim().split('.');
+        let mut next = || -> Option<Result<u32>> {
+            components
+                .next()
+                .map(|c| c.parse().map_err(|_| VersionError))
+        };
+        let major = next().ok_or(VersionError)??;
+        let minor = next().ok_or(VersionError)??;
+        let patch = next().transpose()?;
+        if next().is_some() {
+            return Err(VersionError);
+        }
+        Ok(GameVersion {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
 /// Get the game's major.minor version e.g. `0.32`.
 ///
 /// # Arguments
@@ -115,7 +690,9 @@ use Error::*;
 /// // Most likely `%LocalAppData%/BeamNG.drive`
 /// let version = game_version(&game_dir).unwrap();
 /// ```
-pub fn game_version(data_dir: &Path) -> Result<String> {
+#[cfg(feature = "native")]
+pub fn game_version(data_dir: impl AsRef<Path>) -> Result<GameVersion> {
+    let data_dir = data_dir.as_ref();
     if !data_dir.try_exists()? {
         return Err(DirNotFound {
             dir: data_dir.to_owned(),
@@ -125,34 +702,41 @@ pub fn game_version(data_dir: &Path) -> Result<String> {
     if version_path.try_exists()? {
         // If the version.txt file exists in the data_dir, we can just read it to find the game
         // version.
-        let full_version = fs::read_to_string(version_path)?;
+        let full_version = std::fs::read_to_string(version_path)?;
         let mut split_version = full_version.trim().split(".");
         let major_version = split_version.next().ok_or(VersionError)?;
         let minor_version = split_version.next().ok_or(VersionError)?;
-        Ok(format!("{}.{}", major_version, minor_version))
+        format!("{}.{}", major_version, minor_version).parse()
     } else {
         // If there is no version.txt, a fallback is to list all the version directories and find
         // the latest one, assuming it is correct.
-        fs::read_dir(data_dir)?
-            .filter_map(|f| f.ok().map(|f| f.path())) // Unwrap all, tossing out any files/dirs that errored.
-            .filter(|f| f.is_dir()) // Toss out non-dirs.
-            .filter_map(
-                |d| {
-                    d.file_name()
-                        .and_then(|d| d.to_str()) // Convert dir name to str.
-                        .map(|d| d.trim().parse::<f32>()) // Parse dir name to float (for version number).
-                        .filter(|n| n.is_ok()) // Toss out dirs that failed to convert to float.
-                        .map(|n| n.unwrap())
-                }, // Safe to unwrap now that we know each value is Ok.
-            )
-            .reduce(f32::max) // Grab max version number
-            .map(|n| n.to_string()) // Map version back to string
+        game::installed_versions(data_dir)?
+            .pop() // Versions are sorted, so the last is the latest.
             .ok_or(VersionError) // If something went wrong and thus we can't find the version then error
     }
 }
 
+/// How confirmation prompts are answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AutoAnswer {
+    /// Ask the user.
+    #[default]
+    Prompt,
+    /// Answer yes without asking, e.g. for `-y`.
+    Yes,
+    /// Answer no without asking, e.g. for `--assume-no`, so unattended runs never do anything that
+    /// needs confirming.
+    No,
+    /// Ask the user, but take the default answer if they don't answer in time, so a hung pipe
+    /// doesn't block forever. `confirm_cli` stops waiting after the timeout, and readers passed to
+    /// `confirm` signal it with a `TimedOut` error.
+    PromptWithTimeout(Duration),
+}
+
 /// Confirm a choice with the user.
 ///
+/// If the input ends, or the reader times out, before the user answers, the default is taken.
+///
 /// For testability, this function requires a BufRead and Write to do reading and writing. For a
 /// simple convenience wrapper around this that uses stdio, use `confirm_cli`.
 ///
@@ -162,7 +746,7 @@ pub fn game_version(data_dir: &Path) -> Result<String> {
 /// `writer`: Thing to write to e.g. stdout.
 /// `msg`: The confirmation message to display to the user.
 /// `default`: The default choice.
-/// `confirm_all`: Whether or not to confirm all.
+/// `auto_answer`: Whether to ask the user or answer without asking.
 ///
 /// # Errors
 ///
@@ -172,24 +756,40 @@ pub fn confirm<R: BufRead, W: Write>(
     mut writer: W,
     msg: &str,
     default: bool,
-    confirm_all: bool,
+    auto_answer: AutoAnswer,
 ) -> Result<bool> {
-    if confirm_all {
-        Ok(true)
-    } else {
-        let y_n = String::from(if default { "(Y/n)" } else { "(y/N)" });
+    match auto_answer {
+        AutoAnswer::Yes => Ok(true),
+        AutoAnswer::No => {
+            writeln!(&mut writer, "{} (assuming no)", msg.trim())?;
+            Ok(false)
+        }
+        AutoAnswer::Prompt | AutoAnswer::PromptWithTimeout(_) => {
+            let y_n = String::from(if default { "(Y/n)" } else { "(y/N)" });
 
-        writeln!(&mut writer, "{} {}", msg.trim(), y_n)?;
+            writeln!(&mut writer, "{} {}", msg.trim(), y_n)?;
 
-        let mut input = String::new();
-        reader.read_line(&mut input)?;
+            // Running out of input or time takes the default instead of leaving the operation
+            // half done.
+            let mut input = String::new();
+            let answered = match reader.read_line(&mut input) {
+                Ok(read) => read > 0,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => false,
+                Err(e) => return Err(e.into()),
+            };
+            if !answered {
+                let answer = if default { "yes" } else { "no" };
+                writeln!(&mut writer, "No answer, so assuming {}.", answer)?;
+                return Ok(default);
+            }
 
-        input = input.trim().to_lowercase();
+            input = input.trim().to_lowercase();
 
-        if default {
-            Ok(input != "n")
-        } else {
-            Ok(input == "y")
+            if default {
+                Ok(input != "n")
+            } else {
+                Ok(input == "y")
+            }
         }
     }
 }
@@ -200,14 +800,246 @@ pub fn confirm<R: BufRead, W: Write>(
 ///
 /// `msg`: The confirmation message to display to the user.
 /// `default`: The default choice.
-/// `confirm_all`: Whether or not to confirm all.
+/// `auto_answer`: Whether to ask the user or answer without asking.
 ///
 /// # Errors
 ///
 /// IO errors are possible from read and write operations.
+#[cfg(feature = "native")]
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn confirm_cli(msg: &str, default: bool, auto_answer: AutoAnswer) -> Result<bool> {
+    match auto_answer {
+        AutoAnswer::PromptWithTimeout(timeout) => {
+            let reader = io::BufReader::new(TimeoutStdin {
+                timeout,
+                line: vec![],
+            });
+            confirm(reader, io::stdout(), msg, default, auto_answer)
+        }
+        _ => confirm(io::stdin().lock(), io::stdout(), msg, default, auto_answer),
+    }
+}
+
+/// Lines of stdin read by a background thread, so waiting for them can time out. The thread is
+/// started on first use and shared, so a line typed after a prompt timed out goes to the next
+/// prompt instead of being lost.
+#[cfg(feature = "native")]
+fn stdin_lines() -> &'static Mutex<mpsc::Receiver<Vec<u8>>> {
+    static LINES: OnceLock<Mutex<mpsc::Receiver<Vec<u8>>>> = OnceLock::new();
+    LINES.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || loop {
+            let mut line = String::new();
+            match io::stdin().read_line(&mut line) {
+                Ok(read) if read > 0 && sender.send(line.into_bytes()).is_ok() => (),
+                // Dropping the sender tells the reader the input ended.
+                _ => break,
+            }
+        });
+        Mutex::new(receiver)
+    })
+}
+
+/// Stdin that gives up with a `TimedOut` error if no line arrives in time.
+#[cfg(feature = "native")]
+struct TimeoutStdin {
+    /// How long to wait for a line.
+    timeout: Duration,
+    /// The rest of the line being read.
+    line: Vec<u8>,
+}
+
+#[cfg(feature = "native")]
+impl io::Read for TimeoutStdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.line.is_empty() {
+            let lines = stdin_lines().lock().map_err(|_| io::ErrorKind::Other)?;
+            match lines.recv_timeout(self.timeout) {
+                Ok(line) => self.line = line,
+                Err(mpsc::RecvTimeoutError::Timeout) => return Err(io::ErrorKind::TimedOut.into()),
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(0),
+            }
+        }
+        let read = buf.len().min(self.line.len());
+        buf[..read].copy_from_slice(&self.line[..read]);
+        self.line.drain(..read);
+        Ok(read)
+    }
+}
+
+/// Have the user choose one of several options.
+///
+/// The options are listed with numbers starting from 1, and the user is asked again until they
+/// enter a valid number.
+///
+/// # Arguments
+///
+/// `reader`: Thing to read from e.g. stdin.
+/// `writer`: Thing to write to e.g. stdout.
+/// `msg`: The message to display to the user above the options.
+/// `options`: The options to choose from.
+///
+/// # Returns
+///
+/// The index of the chosen option in `options`.
+///
+/// # Errors
+///
+/// IO errors are possible from read and write operations, including an `UnexpectedEof` error if
+/// the input ends before a valid choice is made, and a `TimedOut` error if the reader times out.
+pub fn select<R: BufRead, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    msg: &str,
+    options: &[String],
+) -> Result<usize> {
+    writeln!(&mut writer, "{}", msg.trim())?;
+    for (i, option) in options.iter().enumerate() {
+        writeln!(&mut writer, "  {}. {}", i + 1, option)?;
+    }
+
+    loop {
+        write!(&mut writer, "Choose 1-{}: ", options.len())?;
+        writer.flush()?;
+
+        let mut input = String::new();
+        if reader.read_line(&mut input)? == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        match input.trim().parse::<usize>() {
+            Ok(choice) if (1..=options.len()).contains(&choice) => return Ok(choice - 1),
+            _ => writeln!(&mut writer, "Invalid choice.")?,
+        }
+    }
+}
+
+/// Convenience function that wraps the `select` function with stdio. Have the user choose one of
+/// several options.
+///
+/// # Arguments
+///
+/// `msg`: The message to display to the user above the options.
+/// `options`: The options to choose from.
+/// `auto_answer`: With `AutoAnswer::PromptWithTimeout`, how long to wait for the user. There is no
+/// default option, so the user is asked otherwise.
+///
+/// # Errors
+///
+/// IO errors are possible from read and write operations, including a `TimedOut` error if the user
+/// doesn't answer in time.
+#[cfg(feature = "native")]
 #[cfg_attr(coverage_nightly, coverage(off))]
-pub fn confirm_cli(msg: &str, default: bool, confirm_all: bool) -> Result<bool> {
-    confirm(io::stdin().lock(), io::stdout(), msg, default, confirm_all)
+pub fn select_cli(msg: &str, options: &[String], auto_answer: AutoAnswer) -> Result<usize> {
+    match auto_answer {
+        AutoAnswer::PromptWithTimeout(timeout) => {
+            let reader = io::BufReader::new(TimeoutStdin {
+                timeout,
+                line: vec![],
+            });
+            select(reader, io::stdout(), msg, options)
+        }
+        _ => select(io::stdin().lock(), io::stdout(), msg, options),
+    }
+}
+
+/// Have the user check and uncheck any of several options.
+///
+/// The options are listed with numbers starting from 1 and whether they are checked. The user
+/// toggles options by entering their numbers, or ranges such as `3-5`, separated by spaces or
+/// commas, and finishes by entering nothing.
+///
+/// # Arguments
+///
+/// `reader`: Thing to read from e.g. stdin.
+/// `writer`: Thing to write to e.g. stdout.
+/// `msg`: The message to display to the user above the options.
+/// `options`: The options to choose from.
+/// `checked`: Whether each option starts checked. Options past its end start unchecked.
+///
+/// # Returns
+///
+/// Whether each option in `options` ended up checked.
+///
+/// # Errors
+///
+/// IO errors are possible from read and write operations, including an `UnexpectedEof` error if
+/// the input ends before the user finishes.
+pub fn multi_select<R: BufRead, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    msg: &str,
+    options: &[String],
+    checked: &[bool],
+) -> Result<Vec<bool>> {
+    let mut checked: Vec<bool> = (0..options.len())
+        .map(|i| checked.get(i).copied().unwrap_or(false))
+        .collect();
+
+    writeln!(&mut writer, "{}", msg.trim())?;
+    loop {
+        for (i, option) in options.iter().enumerate() {
+            let mark = if checked[i] { 'x' } else { ' ' };
+            writeln!(&mut writer, "  [{}] {}. {}", mark, i + 1, option)?;
+        }
+        write!(
+            &mut writer,
+            "Toggle 1-{}, or press enter to finish: ",
+            options.len()
+        )?;
+        writer.flush()?;
+
+        let mut input = String::new();
+        if reader.read_line(&mut input)? == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        if input.trim().is_empty() {
+            return Ok(checked);
+        }
+
+        match parse_choices(&input, options.len()) {
+            Some(choices) => {
+                for choice in choices {
+                    checked[choice] = !checked[choice];
+                }
+            }
+            None => writeln!(&mut writer, "Invalid choice.")?,
+        }
+    }
+}
+
+/// Parse numbers and ranges such as `1 3-5`, separated by spaces or commas, into indices of
+/// options. Returns `None` if anything isn't a valid choice.
+fn parse_choices(input: &str, count: usize) -> Option<Vec<usize>> {
+    let number = |s: &str| s.parse::<usize>().ok().filter(|n| (1..=count).contains(n));
+    let mut choices = vec![];
+    for part in input.split([' ', ',']).filter(|p| !p.trim().is_empty()) {
+        let part = part.trim();
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (number(start)?, number(end)?),
+            None => (number(part)?, number(part)?),
+        };
+        choices.extend((start.min(end)..=start.max(end)).map(|n| n - 1));
+    }
+    Some(choices)
+}
+
+/// Convenience function that wraps the `multi_select` function with stdio. Have the user check
+/// and uncheck any of several options.
+///
+/// # Arguments
+///
+/// `msg`: The message to display to the user above the options.
+/// `options`: The options to choose from.
+/// `checked`: Whether each option starts checked.
+///
+/// # Errors
+///
+/// IO errors are possible from read and write operations.
+#[cfg(feature = "native")]
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn multi_select_cli(msg: &str, options: &[String], checked: &[bool]) -> Result<Vec<bool>> {
+    multi_select(io::stdin().lock(), io::stdout(), msg, options, checked)
 }
 
 #[cfg(test)]
@@ -232,47 +1064,155 @@ mod tests {
         let mut writer = Vec::new();
 
         let msg = "Are you sure?";
-        let confirm_all = false;
+        let auto_answer = AutoAnswer::Prompt;
 
         {
             let mut reader_n = io::BufReader::new(&input_n[..]);
-            let result = confirm(&mut reader_n, &mut writer, msg, true, confirm_all).unwrap();
+            let result = confirm(&mut reader_n, &mut writer, msg, true, auto_answer).unwrap();
             assert!(!result);
         }
         {
             let mut reader_y = io::BufReader::new(&input_y[..]);
-            let result = confirm(&mut reader_y, &mut writer, msg, true, confirm_all).unwrap();
+            let result = confirm(&mut reader_y, &mut writer, msg, true, auto_answer).unwrap();
             assert!(result);
         }
         {
             let mut reader_nothing = io::BufReader::new(&input_nothing[..]);
-            let result = confirm(&mut reader_nothing, &mut writer, msg, true, confirm_all).unwrap();
+            let result = confirm(&mut reader_nothing, &mut writer, msg, true, auto_answer).unwrap();
             assert!(result);
         }
         {
             let mut reader_n = io::BufReader::new(&input_n[..]);
-            let result = confirm(&mut reader_n, &mut writer, msg, false, confirm_all).unwrap();
+            let result = confirm(&mut reader_n, &mut writer, msg, false, auto_answer).unwrap();
             assert!(!result);
         }
         {
             let mut reader_y = io::BufReader::new(&input_y[..]);
-            let result = confirm(&mut reader_y, &mut writer, msg, false, confirm_all).unwrap();
+            let result = confirm(&mut reader_y, &mut writer, msg, false, auto_answer).unwrap();
             assert!(result);
         }
         {
             let mut reader_nothing = io::BufReader::new(&input_nothing[..]);
             let result =
-                confirm(&mut reader_nothing, &mut writer, msg, false, confirm_all).unwrap();
+                confirm(&mut reader_nothing, &mut writer, msg, false, auto_answer).unwrap();
             assert!(!result);
         }
-        // If confirm_all is true, it should always return true.
+        // Answering automatically ignores the input.
         {
             let mut reader_n = io::BufReader::new(&input_n[..]);
-            let result = confirm(&mut reader_n, &mut writer, msg, true, true).unwrap();
+            let result = confirm(&mut reader_n, &mut writer, msg, true, AutoAnswer::Yes).unwrap();
+            assert!(result);
+        }
+        {
+            let mut reader_y = io::BufReader::new(&input_y[..]);
+            let result = confirm(&mut reader_y, &mut writer, msg, true, AutoAnswer::No).unwrap();
+            assert!(!result);
+        }
+
+        // Running out of input or time takes the default.
+        {
+            let result = confirm(&b""[..], &mut writer, msg, true, auto_answer).unwrap();
+            assert!(result);
+            let result = confirm(&b""[..], &mut writer, msg, false, auto_answer).unwrap();
+            assert!(!result);
+        }
+        {
+            struct TimesOut;
+            impl io::Read for TimesOut {
+                fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                    Err(io::ErrorKind::TimedOut.into())
+                }
+            }
+            let timeout = AutoAnswer::PromptWithTimeout(Duration::from_secs(1));
+            let reader = io::BufReader::new(TimesOut);
+            let result = confirm(reader, &mut writer, msg, true, timeout).unwrap();
             assert!(result);
         }
     }
 
+    #[test]
+    fn test_error_json() {
+        let error = MissingPreset {
+            dir: PathBuf::from("presets"),
+            preset: "racing".into(),
+        };
+        let json = error.to_json();
+        assert_eq!(json["kind"], "MissingPreset");
+        assert_eq!(json["message"], error.to_string());
+        assert_eq!(json["paths"], serde_json::json!(["presets"]));
+        assert_eq!(json["presets"], serde_json::json!(["racing"]));
+
+        let error = PresetsFailed {
+            mods: HashSet::from(["mod2".into(), "mod1".into()]),
+            presets: HashSet::from(["racing".into()]),
+            requirements: vec![],
+        };
+        assert_eq!(error.mods(), vec!["mod1", "mod2"]);
+        assert!(error.paths().is_empty());
+    }
+
+    #[test]
+    fn test_select() {
+        let options = vec!["first".to_string(), "second".to_string()];
+        let mut writer = Vec::new();
+
+        // Invalid choices are asked again.
+        let input = b"0\nthree\n2\n";
+        let result = select(&input[..], &mut writer, "Pick one", &options).unwrap();
+        assert_eq!(result, 1);
+
+        // Running out of input without a valid choice is an error.
+        let input = b"3\n";
+        let result = select(&input[..], &mut writer, "Pick one", &options);
+        assert!(matches!(result, Err(IO(_))));
+
+        // So is running out of time.
+        struct TimesOut;
+        impl io::Read for TimesOut {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::TimedOut.into())
+            }
+        }
+        let result = select(
+            io::BufReader::new(TimesOut),
+            &mut writer,
+            "Pick one",
+            &options,
+        );
+        assert!(matches!(result, Err(IO(e)) if e.kind() == io::ErrorKind::TimedOut));
+    }
+
+    #[test]
+    fn test_multi_select() {
+        let options = vec![
+            "first".into(),
+            "second".into(),
+            "third".into(),
+            "fourth".into(),
+        ];
+        let mut writer = Vec::new();
+
+        // Invalid choices are ignored and the rest are toggled until an empty line.
+        let input = b"0\n1,3-4\n2 3\n\n";
+        let result = multi_select(
+            &input[..],
+            &mut writer,
+            "Pick some",
+            &options,
+            &[true, false],
+        )
+        .unwrap();
+        assert_eq!(result, vec![false, true, false, true]);
+        let output = String::from_utf8(writer).unwrap();
+        assert!(output.contains("[x] 1. first"));
+        assert!(output.contains("Invalid choice."));
+
+        // Running out of input before finishing is an error.
+        let input = b"1\n";
+        let result = multi_select(&input[..], Vec::new(), "Pick some", &options, &[]);
+        assert!(matches!(result, Err(IO(_))));
+    }
+
     #[test]
     fn test_game_version() {
         let temp_dir = tempdir().unwrap();
@@ -283,7 +1223,14 @@ mod tests {
 
         let version = game_version(game_dir).unwrap();
 
-        assert_eq!(version, "0.32");
+        assert_eq!(
+            version,
+            GameVersion {
+                major: 0,
+                minor: 32,
+                patch: None
+            }
+        );
     }
 
     /// Discover the game version based on the folders in the game data directory.
@@ -298,7 +1245,67 @@ mod tests {
 
         let version = game_version(game_dir).unwrap();
 
-        assert_eq!(version, "0.33");
+        assert_eq!(
+            version,
+            GameVersion {
+                major: 0,
+                minor: 33,
+                patch: None
+            }
+        );
+    }
+
+    /// Version directories must be compared numerically, not as decimal numbers.
+    #[test]
+    fn test_discover_two_digit_minor_version() {
+        let temp_dir = tempdir().unwrap();
+        let game_dir = temp_dir.path();
+        std::fs::create_dir(game_dir.join("0.9")).unwrap();
+        std::fs::create_dir(game_dir.join("0.10")).unwrap();
+        std::fs::create_dir(game_dir.join("mods")).unwrap();
+
+        assert_eq!(
+            game_version(game_dir).unwrap(),
+            GameVersion {
+                major: 0,
+                minor: 10,
+                patch: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_discover_patch_version() {
+        let temp_dir = tempdir().unwrap();
+        let game_dir = temp_dir.path();
+        std::fs::create_dir(game_dir.join("0.33")).unwrap();
+        std::fs::create_dir(game_dir.join("0.33.1")).unwrap();
+        std::fs::create_dir(game_dir.join("0.32.10")).unwrap();
+
+        assert_eq!(game_version(game_dir).unwrap().to_string(), "0.33.1");
+    }
+
+    #[test]
+    fn test_parse_game_version() {
+        assert_eq!(
+            "0.33".parse::<GameVersion>().unwrap(),
+            GameVersion {
+                major: 0,
+                minor: 33,
+                patch: None
+            }
+        );
+        assert_eq!(
+            "0.33.1".parse::<GameVersion>().unwrap().to_string(),
+            "0.33.1"
+        );
+        assert!(matches!(
+            "0.33.1.0".parse::<GameVersion>(),
+            Err(VersionError)
+        ));
+        assert!(matches!("mods".parse::<GameVersion>(), Err(VersionError)));
+        assert!(matches!("0..1".parse::<GameVersion>(), Err(VersionError)));
+        assert!("0.9".parse::<GameVersion>().unwrap() < "0.10".parse::<GameVersion>().unwrap());
     }
 
     #[test]
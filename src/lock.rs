@@ -0,0 +1,99 @@
+use crate::{Error::*, Result};
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// An advisory lock on the BeamMM directory, held while changing the mod state so two BeamMM
+/// processes don't interleave reads and writes of db.json.
+///
+/// The lock is released when it is dropped, or by the operating system if the process dies, so a
+/// crashed process never leaves the directory locked. The lock file holds the ID of the process
+/// holding the lock, for error messages.
+///
+/// # Examples
+/// ```rust
+/// use beammm::lock::Lock;
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let beammm_dir = temp_dir.path();
+/// let lock = Lock::acquire(&beammm_dir).unwrap();
+/// assert!(Lock::acquire(&beammm_dir).is_err());
+///
+/// drop(lock);
+/// assert!(Lock::acquire(&beammm_dir).is_ok());
+/// ```
+#[derive(Debug)]
+pub struct Lock {
+    /// The locked file. Closing it releases the lock.
+    _file: File,
+}
+
+impl Lock {
+    /// The filename of the lock file.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    fn filename() -> PathBuf {
+        PathBuf::from("beammm.lock")
+    }
+
+    /// Lock the BeamMM directory without waiting.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The path to the beammm directory.
+    ///
+    /// # Errors
+    ///
+    /// * `Locked`: If another process holds the lock.
+    /// * `std::io::Error`: If there is an issue creating, locking, or writing the lock file.
+    pub fn acquire(beammm_dir: impl AsRef<Path>) -> Result<Self> {
+        let path = beammm_dir.as_ref().join(Self::filename());
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        match file.try_lock() {
+            Ok(()) => (),
+            Err(TryLockError::WouldBlock) => {
+                let pid = fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|pid| pid.trim().parse().ok());
+                return Err(Locked { path, pid });
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())?;
+        Ok(Self { _file: file })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn locking() {
+        let temp_dir = tempdir().unwrap();
+        let beammm_dir = temp_dir.path();
+
+        let lock = Lock::acquire(beammm_dir).unwrap();
+        match Lock::acquire(beammm_dir) {
+            Err(Locked { path, pid }) => {
+                assert_eq!(path, beammm_dir.join(Lock::filename()));
+                assert_eq!(pid, Some(std::process::id()));
+            }
+            other => panic!("expected Locked, got {:?}", other),
+        }
+
+        drop(lock);
+        Lock::acquire(beammm_dir).unwrap();
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{game::ModCfg, Result};
+use std::{
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+/// A problem with a mod found in the game's log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogIssue {
+    /// The name of the mod the problem belongs to.
+    pub mod_name: String,
+    /// What kind of problem it is.
+    pub kind: IssueKind,
+    /// The line of the log the problem was found on, starting from 1.
+    pub line: usize,
+    /// The message the game logged.
+    pub message: String,
+}
+
+/// The kind of a problem found in the game's log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    /// The mod failed to load or raised an error.
+    LoadError,
+    /// The mod needs something that isn't installed, like another mod.
+    MissingDependency,
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::LoadError => "load error",
+            Self::MissingDependency => "missing dependency",
+        })
+    }
+}
+
+/// Get the path of the game's log in a game version's directory.
+///
+/// # Arguments
+///
+/// `version_dir`: The game version's directory, e.g. `BeamNG.drive/0.32`.
+pub fn log_path(version_dir: impl AsRef<Path>) -> PathBuf {
+    let version_dir = version_dir.as_ref();
+    version_dir.join("beamng.log")
+}
+
+/// Scan the game's log for mod errors and missing dependencies.
+///
+/// Log lines look like `12.345|E|GELua.core_modmanager|message`. Errors, and warnings about
+/// something missing, are mapped to mods by the archive paths they mention, e.g.
+/// `/mods/repo/mod.zip`, or otherwise by the installed mod names they mention. Lines that can't be
+/// mapped to a mod are skipped.
+///
+/// # Arguments
+///
+/// `reader`: The reader to read the log from.
+/// `mod_config`: The ModCfg with the installed mods.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading the log.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{game::ModCfg, logscan};
+///
+/// let mod_cfg = ModCfg::load(r#"{"mods":{"mod1":{"active":true}}}"#.as_bytes()).unwrap();
+/// let log = "1.0|E|GELua.core_modmanager|Failed to mount /mods/Mod1.zip\n";
+/// let issues = logscan::scan(log.as_bytes(), &mod_cfg).unwrap();
+/// assert_eq!(issues[0].mod_name, "mod1");
+/// ```
+pub fn scan<R: BufRead>(reader: R, mod_config: &ModCfg) -> Result<Vec<LogIssue>> {
+    let mod_names: Vec<&String> = mod_config.get_mods().collect();
+    let mut issues = vec![];
+
+    // The log isn't guaranteed to be valid UTF-8, since mods can log anything.
+    for (i, line) in reader.split(b'\n').enumerate() {
+        let line = String::from_utf8_lossy(&line?).trim_end().to_string();
+        let mut parts = line.splitn(4, '|');
+        let (Some(_), Some(level), Some(_), Some(message)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+
+        let lower = message.to_lowercase();
+        let missing = ["missing", "not found", "unable to find", "dependency"]
+            .iter()
+            .any(|m| lower.contains(m));
+        let kind = match level.trim() {
+            "E" if missing => IssueKind::MissingDependency,
+            "E" => IssueKind::LoadError,
+            "W" if missing => IssueKind::MissingDependency,
+            _ => continue,
+        };
+
+        let mut mods = archive_mods(&lower);
+        if mods.is_empty() {
+            mods = mod_names
+                .iter()
+                .filter(|m| mentions(&lower, &m.to_lowercase()))
+                .map(|m| m.to_string())
+                .collect();
+        }
+        for mod_name in mods {
+            if mod_config.is_mod_active(&mod_name).is_some() {
+                issues.push(LogIssue {
+                    mod_name,
+                    kind,
+                    line: i + 1,
+                    message: message.trim().to_string(),
+                });
+            }
+        }
+    }
+    Ok(issues)
+}
+
+/// Scan the game's log in a game version's directory. See `scan`.
+///
+/// # Arguments
+///
+/// `version_dir`: The game version's directory, e.g. `BeamNG.drive/0.32`.
+/// `mod_config`: The ModCfg with the installed mods.
+///
+/// # Errors
+///
+/// Possible IO errors if the log doesn't exist or there is an issue reading it.
+pub fn scan_path(version_dir: impl AsRef<Path>, mod_config: &ModCfg) -> Result<Vec<LogIssue>> {
+    let version_dir = version_dir.as_ref();
+    let file = File::open(log_path(version_dir))?;
+    scan(BufReader::new(file), mod_config)
+}
+
+/// Get the names of the mods whose archives a lowercase message mentions. The game names mods
+/// after their archive's filename in lowercase.
+fn archive_mods(message: &str) -> Vec<String> {
+    let mut mods = vec![];
+    for (end, _) in message.match_indices(".zip") {
+        let start = message[..end]
+            .rfind(|c: char| c == '/' || c == '\\' || c.is_whitespace() || c == '"' || c == '\'')
+            .map_or(0, |i| i + 1);
+        let name = &message[start..end];
+        if !name.is_empty() && !mods.iter().any(|m| m == name) {
+            mods.push(name.to_string());
+        }
+    }
+    mods
+}
+
+/// Whether a lowercase message mentions a mod name as a whole word.
+fn mentions(message: &str, mod_name: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+    message.match_indices(mod_name).any(|(start, _)| {
+        let before = message[..start].chars().next_back();
+        let after = message[start + mod_name.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+
+    const LOG: &str = "\
+0.001|I|GELua.core_modmanager|Mounting mods
+1.234|E|GELua.core_modmanager|Failed to mount mod: /mods/repo/Mod2.zip (corrupt)
+1.500|W|GELua.core_vehicles|vehicle mod3 needs missing part common_wheels
+1.600|W|GELua.core_vehicles|mod1 is slow to load
+1.700|E|libbeamng.TorqueScript|mod10 exploded
+2.000|E|GELua.core_modmanager|Something went wrong
+";
+
+    #[test]
+    fn scanning_logs() {
+        let mock = MockData::new();
+        let issues = scan(LOG.as_bytes(), &mock.modcfg).unwrap();
+        assert_eq!(
+            issues,
+            vec![
+                LogIssue {
+                    mod_name: "mod2".into(),
+                    kind: IssueKind::LoadError,
+                    line: 2,
+                    message: "Failed to mount mod: /mods/repo/Mod2.zip (corrupt)".into(),
+                },
+                LogIssue {
+                    mod_name: "mod3".into(),
+                    kind: IssueKind::MissingDependency,
+                    line: 3,
+                    message: "vehicle mod3 needs missing part common_wheels".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn scanning_log_file() {
+        let mock = MockData::new();
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(scan_path(tmp.path(), &mock.modcfg).is_err());
+
+        std::fs::write(log_path(tmp.path()), LOG).unwrap();
+        assert_eq!(scan_path(tmp.path(), &mock.modcfg).unwrap().len(), 2);
+    }
+}
@@ -1,28 +1,142 @@
-use beammm::path::*;
-use clap::Parser;
+use beammm::{
+    config::{Config, Retention},
+    crash::CrashReport,
+    filter::Filter,
+    game::{Management, MissingModPolicy},
+    inbox::InboxReport,
+    index::{MembershipChange, PresetIndex},
+    lock::Lock,
+    metadata::MetadataDb,
+    namespace::{self, Namespaces},
+    oplog::OperationLog,
+    path::*,
+    rules::Rules,
+    stash::Stash,
+    table::Table,
+    transaction::Transaction,
+    update::UpdateCheck,
+    AutoAnswer, GameVersion,
+};
+use clap::{ArgGroup, Parser, ValueEnum};
 use colored::Colorize;
-use std::path::PathBuf;
+use std::{
+    backtrace::Backtrace,
+    collections::{BTreeMap, HashSet},
+    ffi::OsString,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+/// Flags that change files the run's transaction doesn't snapshot, like the config, mod archives
+/// or other profiles, so --plan and --status can't put them back.
+const WRITES_OUTSIDE: [&str; 44] = [
+    "create_profile",
+    "switch_profile",
+    "preset_namespace",
+    "serve",
+    "shell",
+    "run",
+    "apply_plan",
+    "mirror",
+    "restore_from_mirror",
+    "restore",
+    "import_state",
+    "export_state",
+    "backup",
+    "support_bundle",
+    "import",
+    "resolve",
+    "dedup",
+    "dedup_repo",
+    "normalize_names",
+    "watch_inbox",
+    "stash",
+    "stash_pop",
+    "map_preset",
+    "alias",
+    "backup_deleted_presets",
+    "no_backup_deleted_presets",
+    "enforce_whitelist",
+    "no_enforce_whitelist",
+    "whitelist",
+    "unwhitelist",
+    "blacklist",
+    "unblacklist",
+    "compact_db_json",
+    "no_compact_db_json",
+    "sorted_json",
+    "no_sorted_json",
+    "keep_backups",
+    "retries",
+    "retry_backoff",
+    "network_timeout",
+    "rules_url",
+    "update_rules",
+    "check_for_updates",
+    "no_check_for_updates",
+];
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
+#[command(group(ArgGroup::new("toggle").args(["enable", "disable"]).multiple(true)))]
 /// BeamMM CLI - A mod manager backend and command line application for the game BeamNG.drive
 struct Args {
     /// Create a mod preset
     #[arg(long, value_name = "NAME")]
     create_preset: Option<String>,
 
-    /// Permanently delete a preset
+    /// Delete a preset, sending it to the system trash
     #[arg(long, value_name = "NAME")]
     delete_preset: Option<String>,
 
+    /// Remove deleted presets and mod archives permanently instead of sending them to the trash
+    #[arg(long)]
+    permanent: bool,
+
+    /// Copy the preset deleted with --delete-preset into the preset backups first, so it can be
+    /// brought back with --restore-preset
+    #[arg(long, requires = "delete_preset")]
+    backup_preset: bool,
+
+    /// Always back up presets before deleting them
+    #[arg(long)]
+    backup_deleted_presets: bool,
+
+    /// Stop backing up presets before deleting them, unless --backup-preset is given
+    #[arg(long, conflicts_with = "backup_deleted_presets")]
+    no_backup_deleted_presets: bool,
+
+    /// Bring back a deleted preset from its most recent backup
+    #[arg(long, value_name = "NAME")]
+    restore_preset: Option<String>,
+
+    /// List the previous versions of a preset, which are kept each time it is changed
+    #[arg(long, value_name = "NAME")]
+    preset_history: Option<String>,
+
+    /// Replace a preset with one of its previous versions from --preset-history
+    #[arg(long, num_args = 2, value_names = ["NAME", "REV"])]
+    preset_revert: Option<Vec<String>>,
+
     /// Add mods to a preset
     #[arg(long, value_name = "PRESET")]
     preset_add: Option<String>,
 
+    /// Refuse to add mods to a preset unless they are all installed
+    #[arg(long, requires = "preset_add")]
+    strict: bool,
+
     /// Remove mods from a preset
     #[arg(long, value_name = "PRESET")]
     preset_remove: Option<String>,
 
+    /// Remove a mod from every preset
+    #[arg(long, value_name = "MOD")]
+    preset_remove_all: Option<String>,
+
     /// List presets
     #[arg(long, short)]
     list_presets: bool,
@@ -38,183 +152,2571 @@ struct Args {
     #[arg(long)]
     enable: bool,
 
-    /// Enable a preset
-    #[arg(long, value_name = "PRESET")]
-    enable_preset: Option<String>,
+    /// Choose the mods to enable or disable from a checklist of every mod, or of the given mods
+    #[arg(long, requires = "toggle")]
+    interactive: bool,
 
-    /// Disable a preset
-    #[arg(long, value_name = "PRESET")]
-    disable_preset: Option<String>,
+    /// Keep the enabled or disabled mods that way when presets are applied
+    #[arg(long = "override", requires = "toggle")]
+    override_presets: bool,
+
+    /// List the mods kept enabled or disabled when presets are applied
+    #[arg(long)]
+    list_overrides: bool,
+
+    /// Let presets enable the given mods again, or every overridden mod if none are given
+    #[arg(long, value_name = "MODS", num_args = 0..)]
+    clear_overrides: Option<Vec<String>>,
+
+    /// Hand mods over to presets: they are enabled while an enabled preset contains them and disabled otherwise
+    #[arg(long, value_name = "MODS", num_args = 1.., value_delimiter = ',')]
+    manage: Option<Vec<String>>,
+
+    /// Make BeamMM never change mods, e.g. ones managed in game
+    #[arg(long, value_name = "MODS", num_args = 1.., value_delimiter = ',')]
+    ignore: Option<Vec<String>>,
+
+    /// Go back to only letting presets enable mods, without disabling them
+    #[arg(long, value_name = "MODS", num_args = 1.., value_delimiter = ',')]
+    observe: Option<Vec<String>>,
+
+    /// List the mods that are managed or ignored
+    #[arg(long)]
+    list_management: bool,
+
+    /// Enable presets, separated by commas or spaces - pass "all" to enable all presets
+    #[arg(long, value_name = "PRESETS", num_args = 1.., value_delimiter = ',')]
+    enable_preset: Option<Vec<String>>,
+
+    /// Disable presets, separated by commas or spaces - pass "all" to disable all presets
+    #[arg(long, value_name = "PRESETS", num_args = 1.., value_delimiter = ',')]
+    disable_preset: Option<Vec<String>>,
+
+    /// How to print errors and --paths: text, or json for a JSON object, on stderr for errors
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
 
     /// Answer yes to all confirmation prompts
     #[arg(long, short = 'y')]
     confirm_all: bool,
 
+    /// Answer no to all confirmation prompts, so nothing that needs confirming is done
+    #[arg(long, short = 'n', conflicts_with = "confirm_all")]
+    assume_no: bool,
+
+    /// Take the default answer to confirmation prompts that aren't answered within SECS seconds
+    #[arg(long, value_name = "SECS")]
+    prompt_timeout: Option<u64>,
+
     /// Choose a custom BeamNG data directory
     #[arg(long, value_name = "DIR")]
     custom_data_dir: Option<PathBuf>,
 
+    /// Use a mods directory directly instead of finding the game, e.g. a test copy. The game
+    /// version is the name of the folder it is in, unless --game-version is passed
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["custom_data_dir", "game_dir_index"])]
+    mods_dir: Option<PathBuf>,
+
+    /// The game version of the directory passed to --mods-dir, e.g. 0.32
+    #[arg(long, value_name = "VERSION", requires = "mods_dir")]
+    game_version: Option<GameVersion>,
+
+    /// Store BeamMM's data next to the executable instead of in the user's app data, like placing
+    /// a portable.flag file there does
+    #[arg(long)]
+    portable: bool,
+
+    /// Choose which BeamNG data directory to use when several are found, starting from 1
+    #[arg(long, value_name = "INDEX")]
+    game_dir_index: Option<usize>,
+
     /// List installed mods
     #[arg(long)]
     list_mods: bool,
 
+    /// List the game versions found in the BeamNG data directory
+    #[arg(long)]
+    list_versions: bool,
+
+    /// Print every directory BeamMM uses for the current game version
+    #[arg(long)]
+    paths: bool,
+
+    /// List the mods that aren't in any preset
+    #[arg(long)]
+    list_unassigned: bool,
+
+    /// Import a mod list from another tool into a new preset: a text list, a folder of mod archives, or a db.json of repository subscriptions
+    #[arg(long, value_name = "PATH")]
+    import: Option<PathBuf>,
+
+    /// The name of the preset created by --import, instead of the name of the imported file
+    #[arg(long, value_name = "NAME", requires = "import")]
+    import_as: Option<String>,
+
+    /// Install every preset file in a zip or folder, like the presets shipped with a modpack
+    #[arg(long, value_name = "ZIP|DIR")]
+    import_presets: Option<PathBuf>,
+
     /// List preset mods
     #[arg(long)]
     list_preset_mods: Option<String>,
+
+    /// How to handle enabled presets with missing mods: fail, skip, or prune
+    #[arg(long, value_name = "POLICY", default_value = "fail")]
+    missing_mods: MissingModPolicy,
+
+    /// Remove mods that are no longer installed from every preset, asking for each preset
+    #[arg(long)]
+    prune_presets: bool,
+
+    /// Remove mods that are no longer installed from every preset without asking
+    #[arg(long, conflicts_with = "prune_presets")]
+    prune_all_presets: bool,
+
+    /// Set a preset's own missing mod policy - pass "default" to use --missing-mods
+    #[arg(long, num_args = 2, value_names = ["PRESET", "POLICY"])]
+    preset_missing_mods: Option<Vec<String>>,
+
+    /// Attach a note to a mod - pass "" to remove the note
+    #[arg(long, num_args = 2, value_names = ["MOD", "NOTE"])]
+    note: Option<Vec<String>>,
+
+    /// Attach a label to a mod, or pass only a label to select the mods with it
+    #[arg(long, num_args = 1..=2, value_names = ["MOD", "LABEL"])]
+    label: Option<Vec<String>>,
+
+    /// Remove a label from a mod
+    #[arg(long, num_args = 2, value_names = ["MOD", "LABEL"])]
+    unlabel: Option<Vec<String>>,
+
+    /// Show information about a mod
+    #[arg(long, value_name = "MOD")]
+    mod_info: Option<String>,
+
+    /// List the files inside a mod's archive, or any zip file, and what the mod seems to hold
+    #[arg(long, value_name = "MOD")]
+    peek: Option<String>,
+
+    /// Find the mods that failed to load the last time the game ran, from the game's log
+    #[arg(long)]
+    why_broken: bool,
+
+    /// Open a mod's repository page in the browser
+    #[arg(long, value_name = "MOD")]
+    open: Option<String>,
+
+    /// Open the current game version's mods directory in the file manager
+    #[arg(long)]
+    open_mods_dir: bool,
+
+    /// Open the active profile's presets directory in the file manager
+    #[arg(long)]
+    open_presets_dir: bool,
+
+    /// Show mod notes when listing mods
+    #[arg(long)]
+    show_notes: bool,
+
+    /// Pin mods to their installed version so they are never updated
+    #[arg(long)]
+    pin: bool,
+
+    /// Unpin mods so they can be updated again
+    #[arg(long)]
+    unpin: bool,
+
+    /// Mark mods as working on the current game version
+    #[arg(long)]
+    mark_working: bool,
+
+    /// Order of the mod listing
+    #[arg(long, value_enum, default_value = "name")]
+    sort: ModSort,
+
+    /// Group the mod listing into sections, e.g. one per preset plus the mods in no preset
+    #[arg(long, value_enum, value_name = "GROUP", requires = "list_mods")]
+    group_by: Option<ModGroup>,
+
+    /// Blacklist mods so they can never be enabled - blacklisted mods are disabled
+    #[arg(long)]
+    blacklist: bool,
+
+    /// Remove mods from the blacklist
+    #[arg(long)]
+    unblacklist: bool,
+
+    /// Add mods to the whitelist
+    #[arg(long)]
+    whitelist: bool,
+
+    /// Remove mods from the whitelist
+    #[arg(long)]
+    unwhitelist: bool,
+
+    /// Only allow whitelisted mods to be active - other mods are disabled
+    #[arg(long)]
+    enforce_whitelist: bool,
+
+    /// Stop enforcing the whitelist
+    #[arg(long, conflicts_with = "enforce_whitelist")]
+    no_enforce_whitelist: bool,
+
+    /// Save db.json as compact JSON, the way the game writes it
+    #[arg(long)]
+    compact_db_json: bool,
+
+    /// Save db.json pretty-printed again
+    #[arg(long, conflicts_with = "compact_db_json")]
+    no_compact_db_json: bool,
+
+    /// Save db.json with sorted keys and presets with sorted mod lists, so repeated saves are
+    /// byte-identical
+    #[arg(long)]
+    sorted_json: bool,
+
+    /// Stop sorting db.json and presets when saving them
+    #[arg(long, conflicts_with = "sorted_json")]
+    no_sorted_json: bool,
+
+    /// Set how many automatic db.json backups to keep: a number of backups like "20" or of days
+    /// like "30d"
+    #[arg(long, value_name = "POLICY")]
+    keep_backups: Option<Retention>,
+
+    /// List the automatic db.json backups of the current game version
+    #[arg(long)]
+    list_backups: bool,
+
+    /// Restore db.json from one of its automatic backups, chosen from a list unless --at or
+    /// --latest is passed. The current db.json is backed up first, and enabled presets are disabled
+    /// so they don't change the mods again
+    #[arg(long)]
+    restore_db: bool,
+
+    /// Work out what the other flags would change and save it as a plan to review and apply with
+    /// --apply-plan, without changing anything. Only flags that change db.json, the presets or the
+    /// metadata can be planned
+    #[arg(long, value_name = "FILE", conflicts_with_all = WRITES_OUTSIDE)]
+    plan: Option<PathBuf>,
+
+    /// Apply a plan saved with --plan, exactly as it was planned
+    #[arg(long, value_name = "FILE")]
+    apply_plan: Option<PathBuf>,
+
+    /// Restore the most recent db.json backup taken at or before a time, e.g.
+    /// "2024-10-16 18:00:00" (UTC)
+    #[arg(
+        long,
+        value_name = "TIMESTAMP",
+        requires = "restore_db",
+        conflicts_with = "latest"
+    )]
+    at: Option<humantime::Timestamp>,
+
+    /// Restore the most recent db.json backup
+    #[arg(long, requires = "restore_db")]
+    latest: bool,
+
+    /// Go back to the mods that were enabled before presets last changed db.json. Enabled presets
+    /// are disabled so they don't change the mods again
+    #[arg(long, conflicts_with = "restore_db")]
+    rollback_to_good: bool,
+
+    /// Set the URL to download compatibility rules from - pass "none" to stop downloading rules
+    #[arg(long, value_name = "URL")]
+    rules_url: Option<String>,
+
+    /// Download the compatibility rules from the rules URL into the BeamMM directory
+    #[arg(long)]
+    update_rules: bool,
+
+    /// Set how many times failed network requests are retried
+    #[arg(long, value_name = "COUNT")]
+    retries: Option<u32>,
+
+    /// Set how long to wait before retrying a failed network request, in milliseconds. Each retry
+    /// waits twice as long as the one before
+    #[arg(long, value_name = "MILLISECONDS")]
+    retry_backoff: Option<u64>,
+
+    /// Set how long a network operation may take in total, retries included, in seconds
+    #[arg(long, value_name = "SECONDS")]
+    network_timeout: Option<u64>,
+
+    /// Check for newer BeamMM releases, at most once a day
+    #[arg(long)]
+    check_for_updates: bool,
+
+    /// Stop checking for newer BeamMM releases
+    #[arg(long, conflicts_with = "check_for_updates")]
+    no_check_for_updates: bool,
+
+    /// Make NAME, used as the first argument, stand for ARGS, e.g. "--enable-preset racing" - quote arguments with spaces inside ARGS like in a shell, and pass "" to remove the alias
+    #[arg(long, num_args = 2, value_names = ["NAME", "ARGS"], allow_hyphen_values = true)]
+    alias: Option<Vec<String>>,
+
+    /// List the command aliases
+    #[arg(long)]
+    list_aliases: bool,
+
+    /// Map a level to the preset to use on it, e.g. "utah offroad" - pass "" to remove the mapping
+    #[arg(long, num_args = 2, value_names = ["LEVEL", "PRESET"])]
+    map_preset: Option<Vec<String>>,
+
+    /// List the levels mapped to presets
+    #[arg(long)]
+    list_map_presets: bool,
+
+    /// Add a read-only shared presets directory, whose presets are named like NAME/preset - pass
+    /// "" to remove it
+    #[arg(long, num_args = 2, value_names = ["NAME", "DIR"])]
+    preset_namespace: Option<Vec<String>>,
+
+    /// List the shared presets directories
+    #[arg(long)]
+    list_preset_namespaces: bool,
+
+    /// Switch to the preset mapped to a level, disabling the other enabled presets
+    #[arg(long, value_name = "LEVEL")]
+    for_map: Option<String>,
+
+    /// Mark mods as favorites
+    #[arg(long)]
+    favorite: bool,
+
+    /// Unmark mods as favorites
+    #[arg(long)]
+    unfavorite: bool,
+
+    /// Only list favorite mods
+    #[arg(long)]
+    favorites: bool,
+
+    /// Leave favorite mods enabled when disabling all mods
+    #[arg(long)]
+    spare_favorites: bool,
+
+    /// Disable the active mods matching every filter, e.g. size>500MB, category=map, or
+    /// source=manual
+    #[arg(long, value_name = "FILTER", num_args = 1..)]
+    disable_where: Vec<Filter>,
+
+    /// Record the current mod state and disable all mods
+    #[arg(long)]
+    stash: bool,
+
+    /// Restore the mod state recorded by --stash
+    #[arg(long, conflicts_with = "stash")]
+    stash_pop: bool,
+
+    /// Show the changes that would be saved to db.json, without saving them. Only flags that
+    /// change db.json, the presets or the metadata can be combined with it
+    #[arg(long, conflicts_with_all = WRITES_OUTSIDE)]
+    status: bool,
+
+    /// Copy installed repository mods into the offline mirror
+    #[arg(long)]
+    mirror: bool,
+
+    /// Reinstall repository mods from the offline mirror
+    #[arg(long)]
+    restore_from_mirror: bool,
+
+    /// Check every mod archive for corruption
+    #[arg(long)]
+    check_archives: bool,
+
+    /// Move corrupt mod archives found by --check-archives to the quarantine directory and remove their mods from db.json
+    #[arg(long, requires = "check_archives")]
+    quarantine: bool,
+
+    /// Check the mods directory for orphaned, stale, corrupt, empty, and duplicate mods
+    #[arg(long)]
+    health: bool,
+
+    /// Print totals about the installed mods and the presets
+    #[arg(long)]
+    stats: bool,
+
+    /// Show how much disk space the mods of a preset take up, or of every preset with "all"
+    #[arg(long, value_name = "NAME|all")]
+    preset_size: Option<String>,
+
+    /// Serve JSON-RPC requests on stdin and stdout for GUI frontends, one per line
+    #[arg(long)]
+    serve: bool,
+
+    /// Start an interactive shell that keeps the mods loaded between commands, with line editing,
+    /// command history and tab completion
+    #[arg(long)]
+    shell: bool,
+
+    /// Run a script of shell commands, one per line, for reproducible setups
+    #[arg(long, value_name = "FILE")]
+    run: Option<PathBuf>,
+
+    /// Stop the script at the first failing command and undo its changes
+    #[arg(long, requires = "run")]
+    all_or_nothing: bool,
+
+    /// Find mods with byte-identical archives and offer to remove the extra copies
+    #[arg(long)]
+    dedup: bool,
+
+    /// Find mods installed both from the repository and by hand and choose which copy to keep
+    #[arg(long)]
+    dedup_repo: bool,
+
+    /// Rename mod archives to lowercase names without spaces or version numbers, updating db.json
+    /// and the presets
+    #[arg(long)]
+    normalize_names: bool,
+
+    /// Keep installing mod archives dropped into the inbox until stopped
+    #[arg(long)]
+    watch_inbox: bool,
+
+    /// Install a preset's missing mods from the offline mirror, then enable it
+    #[arg(long, value_name = "PRESET")]
+    resolve: Option<String>,
+
+    /// Choose a custom offline mirror directory
+    #[arg(long, value_name = "DIR")]
+    mirror_dir: Option<PathBuf>,
+
+    /// Back up db.json and the presets to a single archive
+    #[arg(long, value_name = "OUT")]
+    backup: Option<PathBuf>,
+
+    /// Include the mod archives in the backup
+    #[arg(long, requires = "backup")]
+    backup_archives: bool,
+
+    /// Print the JSON Schema of preset files
+    #[arg(long)]
+    preset_schema: bool,
+
+    /// Collect diagnostics for a bug report into a single zip, with full paths replaced
+    #[arg(long, value_name = "OUT")]
+    support_bundle: Option<PathBuf>,
+
+    /// Keep full paths in the support bundle
+    #[arg(long, requires = "support_bundle")]
+    include_paths: bool,
+
+    /// Restore db.json, the presets and any mod archives from a backup
+    #[arg(long, value_name = "FILE")]
+    restore: Option<PathBuf>,
+
+    /// Export presets, metadata and mod states to a file, without the mod archives
+    #[arg(long, value_name = "FILE")]
+    export_state: Option<PathBuf>,
+
+    /// Import presets, metadata and mod states from a file made with --export-state
+    #[arg(long, value_name = "FILE")]
+    import_state: Option<PathBuf>,
+
+    /// Create a profile with its own presets and mod state
+    #[arg(long, value_name = "NAME")]
+    create_profile: Option<String>,
+
+    /// Switch to another profile
+    #[arg(long, value_name = "NAME")]
+    switch_profile: Option<String>,
+
+    /// List profiles
+    #[arg(long)]
+    list_profiles: bool,
+
+    /// Print a share code for a preset that others can import with --import-code
+    #[arg(long, value_name = "PRESET")]
+    share: Option<String>,
+
+    /// Import a preset from a share code
+    #[arg(long, value_name = "CODE")]
+    import_code: Option<String>,
+
+    /// Make a preset extend another preset, including its mods - pass "none" to stop extending
+    #[arg(long, num_args = 2, value_names = ["PRESET", "PARENT"])]
+    preset_extends: Option<Vec<String>>,
+
+    /// Set the game launch arguments for a preset, e.g. "-console" - quote arguments with spaces inside ARGS like in a shell, and pass "" to clear them
+    #[arg(long, num_args = 2, value_names = ["PRESET", "ARGS"], allow_hyphen_values = true)]
+    preset_launch_args: Option<Vec<String>>,
+
+    /// Set the mods a mod in a preset requires, enabled along with it - pass no required mods to clear them
+    #[arg(long, num_args = 2.., value_names = ["PRESET", "MOD", "REQUIRED"])]
+    preset_requires: Option<Vec<String>>,
 }
 
-fn main() {
-    // Run the main function and call display on errors to get their pretty messages rather than
-    // the debug output.
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
-    }
+/// Orders for the mod listing.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ModSort {
+    /// Alphabetically by name
+    Name,
+    /// Most recently installed first
+    Installed,
+    /// Most recently updated first
+    Updated,
+    /// Most recently enabled first
+    Enabled,
 }
 
-fn run() -> beammm::Result<()> {
-    let args = Args::parse();
+/// Ways to group the mod listing.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ModGroup {
+    /// One section per preset, plus the mods that aren't in any preset
+    Preset,
+}
 
-    let beamng_dir = if let Some(dir) = args.custom_data_dir {
-        if dir.try_exists()? {
-            dir
-        } else {
-            return Err(beammm::Error::DirNotFound { dir });
+/// Formats for printing errors and resolved paths.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum OutputFormat {
+    /// Lines of text
+    Text,
+    /// A JSON object, e.g. with the kind of error, its message, and what it is about
+    Json,
+}
+
+/// Print what was installed from the inbox.
+///
+/// Rejected archives in `already_reported` aren't printed again.
+fn print_inbox_report(report: &InboxReport, already_reported: &HashSet<PathBuf>) {
+    for archive in report.installed.iter() {
+        println!("{} {}", "installed".green(), archive.display());
+    }
+    for (archive, reason) in report.rejected.iter() {
+        if !already_reported.contains(archive) {
+            println!("{} {} - {}", "rejected".red(), archive.display(), reason);
         }
-    } else {
-        beamng_dir_default()?
-    };
+    }
+}
 
-    let beamng_version = beammm::game_version(&beamng_dir)?;
-    let mods_dir = mods_dir(&beamng_dir, &beamng_version)?;
-    let beammm_dir = beammm_dir()?;
+/// Install mod archives dropped into the inbox as they arrive, until the process is stopped.
+fn watch_inbox(
+    beammm_dir: &Path,
+    mods_dir: &Path,
+    mod_cfg: &mut beammm::game::ModCfg,
+    metadata: &mut MetadataDb,
+) -> beammm::Result<()> {
+    let inbox_dir = inbox_dir(beammm_dir);
+    std::fs::create_dir_all(&inbox_dir)?;
+    println!(
+        "Watching {} for mod archives. Press Ctrl+C to stop.",
+        inbox_dir.display()
+    );
 
-    let presets_dir = presets_dir(&beammm_dir)?;
+    // Archives that are still downloading are rejected until they are complete, so they are only
+    // reported once.
+    let mut reported = HashSet::new();
+    loop {
+        std::thread::sleep(Duration::from_secs(2));
+        let _lock = match Lock::acquire(beammm_dir) {
+            Ok(lock) => lock,
+            // Try again once the other process is done.
+            Err(beammm::Error::Locked { .. }) => continue,
+            Err(e) => return Err(e),
+        };
+        // Another process may have changed the mods since the last check.
+        let mut reloaded = beammm::game::ModCfg::load_or_default(mods_dir)?;
+        reloaded.copy_settings_from(mod_cfg);
+        *mod_cfg = reloaded;
+        *metadata = MetadataDb::load_from_path(beammm_dir)?;
 
-    let mut beamng_mod_cfg = beammm::game::ModCfg::load_from_path(&mods_dir)?;
+        let report = beammm::inbox::install(&inbox_dir, mods_dir, mod_cfg)?;
+        print_inbox_report(&report, &reported);
+        reported = report.rejected.into_iter().map(|(a, _)| a).collect();
 
-    if let Some(preset_name) = args.list_preset_mods {
-        let preset = beammm::Preset::load_from_path(&preset_name, &presets_dir)?;
-        let status = if preset.is_enabled() {
-            "enabled ".green()
-        } else {
-            "disabled".red()
-        };
-        println!("Mods in preset '{}' ({}):", preset_name, status);
-        for mod_name in preset.get_mods() {
-            println!("{}", mod_name);
+        if !report.installed.is_empty() {
+            metadata.record_installed(report.registered.iter(), SystemTime::now());
+            mod_cfg.save_to_path(mods_dir)?;
+            metadata.save_to_path(beammm_dir)?;
         }
     }
+}
 
-    if args.list_presets {
-        for preset_name in beammm::Preset::list(&presets_dir)? {
-            let preset = beammm::Preset::load_from_path(&preset_name, &presets_dir)?;
-            let status = if preset.is_enabled() {
-                "enabled ".green()
-            } else {
-                "disabled".red()
-            };
-            println!("{} {}", status, preset_name);
+/// Print pending changes as a unified diff: `+` for enabled mods and mods added to presets, `-`
+/// for disabled mods and mods removed from presets.
+fn print_diff(enabled: &[String], disabled: &[String], preset_changes: &[MembershipChange]) {
+    if !enabled.is_empty() || !disabled.is_empty() {
+        println!("{}", "--- db.json".bold());
+        println!("{}", "+++ db.json".bold());
+        println!("{}", "@@ active mods @@".cyan());
+        for mod_name in disabled.iter() {
+            println!("{}", format!("-{}", mod_name).red());
+        }
+        for mod_name in enabled.iter() {
+            println!("{}", format!("+{}", mod_name).green());
         }
     }
-    if let Some(preset_name) = args.create_preset {
-        // Check if the preset already exists
-        if beammm::Preset::exists(&preset_name, &presets_dir) {
-            return Err(beammm::Error::PresetExists {
-                preset: preset_name,
-            });
+    let mut preset = None;
+    for change in preset_changes.iter() {
+        if preset != Some(&change.preset) {
+            preset = Some(&change.preset);
+            println!("{}", format!("--- presets/{}.json", change.preset).bold());
+            println!("{}", format!("+++ presets/{}.json", change.preset).bold());
+            println!("{}", "@@ mods @@".cyan());
+        }
+        if change.added {
+            println!("{}", format!("+{}", change.mod_name).green());
+        } else {
+            println!("{}", format!("-{}", change.mod_name).red());
         }
+    }
+}
 
-        let preset = beammm::Preset::new(preset_name.clone(), args.mods.clone().unwrap_or(vec![]));
-        preset.save_to_path(&presets_dir)?;
-        println!("Preset '{}' created successfully.", preset_name);
-        if let Some(_mods) = args.mods.clone() {
-            println!("With mods:");
-            for mod_name in preset.get_mods() {
-                println!("  - {}", mod_name);
+/// Print what a plan changes.
+fn print_plan(plan: &beammm::plan::Plan) {
+    print_diff(&plan.enabled, &plan.disabled, &plan.preset_changes);
+    println!("Files to write:");
+    for file in plan.files.iter() {
+        let change = match (&file.before, &file.contents) {
+            (None, _) => "created",
+            (_, None) => "removed",
+            _ => "changed",
+        };
+        println!("  - {} ({})", file.path.display(), change);
+    }
+}
+
+/// Format an optional time for display.
+fn format_time(time: Option<SystemTime>, missing: &str) -> String {
+    time.map(|t| humantime::format_rfc3339_seconds(t).to_string())
+        .unwrap_or(missing.into())
+}
+
+/// Format a number of bytes for display.
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.1} GB", b as f64 / (1u64 << 30) as f64),
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1u64 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KB", b as f64 / (1u64 << 10) as f64),
+        b => format!("{} B", b),
+    }
+}
+
+/// Choose the BeamNG data directory to use.
+///
+/// When several data directories are found, the one chosen with `--game-dir-index` is used. If no
+/// index was given, the directory chosen previously is used, or the user is asked to choose one.
+/// The choice is remembered in the config.
+fn choose_beamng_dir(
+    beammm_dir: &Path,
+    index: Option<usize>,
+    auto_answer: AutoAnswer,
+) -> beammm::Result<PathBuf> {
+    let mut found = beamng_dirs_default();
+    if found.len() <= 1 && index.is_none() {
+        return found.pop().ok_or(beammm::Error::GameDirNotFound);
+    }
+
+    let mut config = Config::load_from_path(beammm_dir)?;
+    let chosen = match index {
+        Some(index) if (1..=found.len()).contains(&index) => index - 1,
+        Some(index) => {
+            return Err(beammm::Error::InvalidGameDirIndex {
+                index,
+                count: found.len(),
+            })
+        }
+        None => match found
+            .iter()
+            .position(|d| Some(d.as_path()) == config.game_dir())
+        {
+            Some(remembered) => return Ok(found.swap_remove(remembered)),
+            None => {
+                let options: Vec<String> = found.iter().map(|d| d.display().to_string()).collect();
+                beammm::select_cli(
+                    "Multiple BeamNG.drive data directories were found.",
+                    &options,
+                    auto_answer,
+                )?
             }
+        },
+    };
+
+    let beamng_dir = found.swap_remove(chosen);
+    config.set_game_dir(Some(beamng_dir.clone()));
+    config.save_to_path(beammm_dir)?;
+    Ok(beamng_dir)
+}
+
+fn main() {
+    let args = Args::parse_from(expand_aliases(std::env::args_os().collect()));
+    let output = args.output;
+    let beammm_dir = choose_beammm_dir(args.portable);
+    let log = beammm_dir
+        .as_ref()
+        .ok()
+        .map(|dir| OperationLog::new(logs_dir(dir)));
+    if let Ok(dir) = &beammm_dir {
+        install_panic_hook(dir.clone(), log.clone());
+    }
+    let command: Vec<String> = std::env::args_os()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    write_log(
+        log.as_ref(),
+        &format!("Running: beammm {}", command.join(" ")),
+    );
+
+    // Run the main function and call display on errors to get their pretty messages rather than
+    // the debug output.
+    if let Err(e) = run(args, beammm_dir, log.as_ref()) {
+        write_log(log.as_ref(), &format!("Failed: {}", e));
+        let exit_code = 1;
+        match output {
+            OutputFormat::Text => eprintln!("Error: {}", e),
+            OutputFormat::Json => {
+                let mut json = e.to_json();
+                json["exit_code"] = exit_code.into();
+                eprintln!("{}", json);
+            }
+        }
+        std::process::exit(exit_code);
+    }
+}
+
+/// Get the BeamMM directory, next to the executable if `portable` is set.
+fn choose_beammm_dir(portable: bool) -> beammm::Result<PathBuf> {
+    if portable {
+        portable_beammm_dir()
+    } else {
+        beammm_dir()
+    }
+}
+
+/// The directories resolved so far in this run, named, for crash reports.
+static RESOLVED_PATHS: Mutex<Vec<(String, PathBuf)>> = Mutex::new(vec![]);
+
+/// Remember a resolved directory so it is included if BeamMM crashes.
+fn record_path(name: &str, path: &Path) {
+    if let Ok(mut paths) = RESOLVED_PATHS.lock() {
+        paths.push((name.into(), path.to_path_buf()));
+    }
+}
+
+/// Write a crash report to the crash reports directory when BeamMM panics, and tell the user
+/// where it is. The panic is still printed as usual.
+fn install_panic_hook(beammm_dir: PathBuf, log: Option<OperationLog>) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let message = info.payload_as_str().unwrap_or("Box<dyn Any>").to_string();
+        let report = CrashReport {
+            message,
+            location: info.location().map(|location| location.to_string()),
+            backtrace: Backtrace::force_capture().to_string(),
+            // The lock is poisoned if the panic happened while recording a path.
+            paths: RESOLVED_PATHS
+                .lock()
+                .map(|paths| paths.clone())
+                .unwrap_or_default(),
+            log_lines: log
+                .as_ref()
+                .and_then(|log| log.recent(20).ok())
+                .unwrap_or_default(),
+        };
+        match report.write(crash_reports_dir(&beammm_dir), SystemTime::now()) {
+            Ok(path) => eprintln!(
+                "BeamMM crashed. A crash report was written to {}. Please attach it when reporting the bug.",
+                path.display()
+            ),
+            Err(e) => eprintln!("BeamMM crashed, and the crash report couldn't be written: {}", e),
+        }
+    }));
+}
+
+/// Write a line to the operation log. Logging is best effort, so problems with the log never stop
+/// an operation.
+fn write_log(log: Option<&OperationLog>, message: &str) {
+    if let Some(log) = log {
+        let _ = log.write(message, SystemTime::now());
+    }
+}
+
+/// Expand a command alias from the config used as the first argument, before the arguments are
+/// parsed.
+fn expand_aliases(args: Vec<OsString>) -> Vec<OsString> {
+    // The directory isn't created yet, since the arguments may only ask for --help.
+    let dir = beammm::path::find_beammm_dir(args.iter().any(|arg| arg == "--portable"));
+    match dir.and_then(|dir| Config::load_from_path(&dir)) {
+        Ok(config) => config.expand_aliases(args),
+        // Problems with the directory or config are reported after parsing.
+        Err(_) => args,
+    }
+}
+
+/// Run BeamMM with the parsed arguments in the BeamMM directory chosen for them. Problems choosing
+/// the directory are only reported once it is needed, so e.g. --preset-schema works without one.
+fn run(
+    mut args: Args,
+    beammm_dir: beammm::Result<PathBuf>,
+    log: Option<&OperationLog>,
+) -> beammm::Result<()> {
+    let auto_answer = if args.assume_no {
+        AutoAnswer::No
+    } else if args.confirm_all {
+        AutoAnswer::Yes
+    } else if let Some(secs) = args.prompt_timeout {
+        AutoAnswer::PromptWithTimeout(Duration::from_secs(secs))
+    } else {
+        AutoAnswer::Prompt
+    };
+
+    // The schema doesn't depend on any installation, so it is printed even without one.
+    if args.preset_schema {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&beammm::Preset::json_schema())?
+        );
+        return Ok(());
+    }
+
+    let beammm_dir = long_path(beammm_dir?)?;
+    record_path("BeamMM directory", &beammm_dir);
+    // Held until the end so another BeamMM process can't change db.json, or the BeamMM directory
+    // while it is migrated, in the meantime.
+    let lock = Lock::acquire(&beammm_dir)?;
+    if let Some(migration) = beammm::migrations::migrate(&beammm_dir, SystemTime::now())? {
+        let message = format!(
+            "Upgraded the BeamMM directory from version {} to {}. The old state was backed up to {}",
+            migration.from,
+            migration.to,
+            migration.backup.display()
+        );
+        write_log(log, &message);
+        eprintln!("{}", message);
+    }
+
+    let (beamng_dir, beamng_version, mods_dir) = if let Some(dir) = args.mods_dir.take() {
+        if !dir.try_exists()? {
+            return Err(beammm::Error::DirNotFound { dir });
+        }
+        let Some(version) = args.game_version.or_else(|| mods_dir_version(&dir)) else {
+            return Err(beammm::Error::UnknownGameVersion { dir });
+        };
+        if !dir.join("db.json").try_exists()? {
+            let prompt = format!("{} has no db.json. Create an empty one?", dir.display());
+            if !beammm::confirm_cli(&prompt, false, auto_answer)? {
+                return Ok(());
+            }
+        }
+        // The mods directory is in `<data dir>/<version>/mods`.
+        let data_dir = dir.parent().and_then(Path::parent).unwrap_or(&dir);
+        (data_dir.to_path_buf(), version, dir)
+    } else {
+        let beamng_dir = if let Some(dir) = args.custom_data_dir.take() {
+            if dir.try_exists()? {
+                dir
+            } else {
+                return Err(beammm::Error::DirNotFound { dir });
+            }
+        } else {
+            choose_beamng_dir(&beammm_dir, args.game_dir_index, auto_answer)?
+        };
+        let beamng_version = beammm::game_version(&beamng_dir)?;
+        let mods_dir = mods_dir(&beamng_dir, &beamng_version)?;
+        (beamng_dir, beamng_version, mods_dir)
+    };
+    // Every other path is joined onto these, so making them extended-length on Windows lets
+    // deeply nested folders and long mod filenames go past MAX_PATH.
+    let (beamng_dir, mods_dir) = (long_path(&beamng_dir)?, long_path(&mods_dir)?);
+    record_path("game data directory", &beamng_dir);
+    record_path("mods directory", &mods_dir);
+
+    if args.list_versions {
+        for version in beammm::game::installed_versions(&beamng_dir)? {
+            let status = if version == beamng_version {
+                "current".green()
+            } else {
+                "       ".normal()
+            };
+            println!("{} {}", status, version);
+        }
+    }
+
+    let profiles_dir = profiles_dir(&beammm_dir)?;
+
+    let mut beamng_mod_cfg = beammm::game::ModCfg::load_or_default(&mods_dir)?;
+    let mut config = Config::load_from_path(&beammm_dir)?;
+    beamng_mod_cfg.set_blacklist(config.blacklist().iter().cloned());
+    beamng_mod_cfg.set_whitelist(config.enforced_whitelist());
+    beamng_mod_cfg.set_aliases(config.mod_aliases().clone());
+    beamng_mod_cfg.set_compact(config.is_compact_db_json());
+    beamng_mod_cfg.set_sorted(config.is_sorted_json());
+
+    if let Some(path) = &args.apply_plan {
+        let plan = beammm::plan::Plan::load_from_path(path)?;
+        if plan.is_empty() {
+            println!("The plan changes nothing.");
+            return Ok(());
+        }
+        print_plan(&plan);
+        if !beammm::confirm_cli("Apply the plan?", true, auto_answer)? {
+            return Ok(());
+        }
+        let db_backups_dir = db_backups_dir(&beammm_dir, &beamng_version);
+        let now = SystemTime::now();
+        beammm::db_backups::create(&db_backups_dir, &mods_dir, now)?;
+        beammm::db_backups::prune(&db_backups_dir, config.backup_retention(), now)?;
+        let presets_dir =
+            profile_presets_dir(&beammm_dir, &beammm::Profile::active(&profiles_dir)?)?;
+        plan.apply(&presets_dir)?;
+        write_log(log, &format!("Applied plan {}", path.display()));
+        println!("Applied the plan.");
+        return Ok(());
+    }
+
+    // Started before the inbox is installed, so if any later step fails, the archives are moved
+    // back into the inbox to be installed again on the next run. The rest is snapshotted once the
+    // active profile's presets are known.
+    let mut transaction = Transaction::new();
+    // --status and --plan must not change anything, so the inbox is left for the next run.
+    if !args.status && args.plan.is_none() {
+        let inbox_dir = inbox_dir(&beammm_dir);
+        let inbox_report = beammm::inbox::install(&inbox_dir, &mods_dir, &mut beamng_mod_cfg)?;
+        for archive in inbox_report.installed.iter() {
+            // Installed archives keep the name they had in the inbox.
+            if let Some(name) = archive.file_name() {
+                transaction.record_move(inbox_dir.join(name), archive);
+            }
+        }
+        print_inbox_report(&inbox_report, &HashSet::new());
+    }
+
+    if let Some(profile_name) = args.create_profile {
+        if beammm::Profile::exists(&profile_name, &profiles_dir) {
+            return Err(beammm::Error::ProfileExists {
+                profile: profile_name,
+            });
+        }
+        // New profiles start out with the current mod state.
+        let mut profile = beammm::Profile::new(profile_name.clone())?;
+        profile.capture(&beamng_mod_cfg);
+        profile.save_to_path(&profiles_dir)?;
+        println!("Profile '{}' created successfully.", profile_name);
+        println!("Use the --switch-profile flag to switch to it.");
+    }
+    if let Some(profile_name) = args.switch_profile {
+        beammm::Profile::switch(&profile_name, &profiles_dir, &mut beamng_mod_cfg)?;
+        println!("Switched to profile '{}'.", profile_name);
+    }
+    if args.list_profiles {
+        let active = beammm::Profile::active(&profiles_dir)?;
+        let mut profiles: Vec<String> = beammm::Profile::list(&profiles_dir)?.collect();
+        if !profiles.iter().any(|p| p == beammm::Profile::DEFAULT_NAME) {
+            profiles.insert(0, beammm::Profile::DEFAULT_NAME.into());
+        }
+        let mut table = Table::new(["STATUS", "NAME"]);
+        for profile_name in profiles {
+            let status = if profile_name == active {
+                "active".green()
+            } else {
+                "inactive".normal()
+            };
+            table.add_row([status.to_string(), profile_name]);
+        }
+        table.print()?;
+    }
+
+    let presets_dir = profile_presets_dir(&beammm_dir, &beammm::Profile::active(&profiles_dir)?)?;
+    // Presets are compared against this to show how their mods changed.
+    let index_before = PresetIndex::load_from_path(&presets_dir)?;
+    for file in beammm::Preset::list_unreadable(&presets_dir)? {
+        eprintln!(
+            "{}",
+            format!(
+                "Warning: {} is ignored since its name isn't valid Unicode. Rename it to use it.",
+                file.display()
+            )
+            .yellow()
+        );
+    }
+    for file in beammm::Preset::list_invalid(&presets_dir)? {
+        eprintln!(
+            "{}",
+            format!(
+                "Warning: {} is ignored since its name isn't a valid preset name. Rename it to use it, or remove it with --delete-preset.",
+                file.display()
+            )
+            .yellow()
+        );
+    }
+    if let Some(values) = args.preset_namespace {
+        // Clap guarantees exactly two values.
+        let (namespace, dir) = (&values[0], values[1].trim());
+        if dir.is_empty() {
+            config.set_preset_namespace(namespace, None);
+            println!("Removed preset namespace {}.", namespace);
+        } else {
+            // Namespaces are named like presets, and can't contain the separator themselves.
+            beammm::Preset::validate_name(namespace)?;
+            let dir = std::path::absolute(dir)?;
+            if !dir.is_dir() {
+                println!(
+                    "{}",
+                    format!("{} isn't a directory yet.", dir.display()).yellow()
+                );
+            }
+            println!(
+                "Presets in {} are now named {}/<preset>.",
+                dir.display(),
+                namespace
+            );
+            config.set_preset_namespace(namespace, Some(dir));
+        }
+        config.save_to_path(&beammm_dir)?;
+    }
+    if args.list_preset_namespaces {
+        if config.preset_namespaces().is_empty() {
+            println!("No shared presets directories. Add one with --preset-namespace.");
+        }
+        for (namespace, dir) in config.preset_namespaces() {
+            println!("{} = {}", namespace, dir.display());
+        }
+    }
+    let namespaces = Namespaces::new(&presets_dir, config.preset_namespaces().clone());
+    for preset in namespaces.unknown_enabled()? {
+        eprintln!(
+            "{}",
+            format!(
+                "Warning: shared preset '{}' is enabled, but its namespace isn't configured, so it is skipped. Disable it with --disable-preset.",
+                preset
+            )
+            .yellow()
+        );
+    }
+
+    if args.paths {
+        let paths = ResolvedPaths::new(
+            &beammm_dir,
+            &beamng_dir,
+            &beamng_version,
+            &mods_dir,
+            &presets_dir,
+        );
+        match args.output {
+            OutputFormat::Text => {
+                println!("Game data directory: {}", paths.game_data_dir.display());
+                println!("Game version: {}", paths.game_version);
+                println!("Mods directory: {}", paths.mods_dir.display());
+                for (root, dir) in paths.mods_roots.iter() {
+                    println!("  {} mods: {}", root, dir.display());
+                }
+                println!("BeamMM directory: {}", paths.beammm_dir.display());
+                println!("Presets: {}", paths.presets_dir.display());
+                println!("Profiles: {}", paths.profiles_dir.display());
+                println!("Mirror: {}", paths.mirror_dir.display());
+                println!("db.json backups: {}", paths.db_backups_dir.display());
+                println!("Preset backups: {}", paths.preset_backups_dir.display());
+                println!("Inbox: {}", paths.inbox_dir.display());
+                println!("Quarantine: {}", paths.quarantine_dir.display());
+                println!("Logs: {}", paths.logs_dir.display());
+                println!("Crash reports: {}", paths.crash_reports_dir.display());
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&paths)?),
+        }
+    }
+
+    if args.open_mods_dir {
+        open::that(&mods_dir)?;
+        println!("Opened {}", mods_dir.display());
+    }
+    if args.open_presets_dir {
+        open::that(&presets_dir)?;
+        println!("Opened {}", presets_dir.display());
+    }
+
+    // Loaded before the interactive modes take the ModCfg, so they respect overrides and how
+    // mods are managed. They save db.json themselves, so the archives installed from the inbox
+    // are kept once they start.
+    let mut metadata = MetadataDb::load_from_path(&beammm_dir)?;
+    beamng_mod_cfg.set_overrides(metadata.overrides());
+    beamng_mod_cfg.set_management(metadata.management());
+
+    if args.serve {
+        let mut server = beammm::rpc::Server::new(beamng_mod_cfg, mods_dir, presets_dir);
+        server.set_preset_namespaces(config.preset_namespaces().clone());
+        transaction.commit()?;
+        return server.serve(std::io::stdin().lock(), std::io::stdout());
+    }
+    if args.shell {
+        let mut shell = beammm::shell::Shell::new(beamng_mod_cfg, mods_dir, presets_dir);
+        shell.set_preset_namespaces(config.preset_namespaces().clone());
+        transaction.commit()?;
+        return shell.run_interactive();
+    }
+    if let Some(script) = args.run {
+        let reader = BufReader::new(File::open(script)?);
+        let mut shell = beammm::shell::Shell::new(beamng_mod_cfg, mods_dir, presets_dir);
+        shell.set_preset_namespaces(config.preset_namespaces().clone());
+        transaction.commit()?;
+        return shell.run_script(reader, std::io::stdout(), args.all_or_nothing);
+    }
+
+    // Presets are saved as their flags are handled but db.json only at the end, so if any step
+    // fails, returning the error drops the transaction and puts every file back.
+    transaction.snapshot(mods_dir.join("db.json"))?;
+    // Flags like --dedup save the mod aliases they add right away.
+    transaction.snapshot(beammm_dir.join("config.json"))?;
+    transaction.snapshot(beammm_dir.join("metadata.json"))?;
+    transaction.snapshot_dir(&presets_dir)?;
+    let preset_backups_dir = beammm::path::preset_backups_dir(&beammm_dir);
+    transaction.snapshot_dir(&preset_backups_dir)?;
+    let last_known_good_file = beammm::path::last_known_good_file(&beammm_dir, &beamng_version);
+    transaction.snapshot(&last_known_good_file)?;
+
+    let now = SystemTime::now();
+    metadata.record_installed(beamng_mod_cfg.get_mods(), now);
+    // Mods just downloaded from the repository are made for the current game version.
+    for mod_name in metadata.sync_repo_versions(&beamng_mod_cfg, now) {
+        metadata.set_confirmed_version(&mod_name, Some(beamng_version));
+    }
+
+    if args.mirror || args.restore_from_mirror {
+        let mirror_dir = match args.mirror_dir.clone() {
+            Some(dir) => dir,
+            None => mirror_dir(&beammm_dir)?,
+        };
+        if args.mirror {
+            let mirrored = beammm::mirror::sync(&beamng_mod_cfg, &mods_dir, &mirror_dir)?;
+            println!("Mirrored {} new mod archive(s):", mirrored.len());
+            for archive in mirrored.iter() {
+                println!("  - {}", archive.display());
+            }
+        }
+        if args.restore_from_mirror {
+            let restored = beammm::mirror::restore(&beamng_mod_cfg, &mods_dir, &mirror_dir)?;
+            println!("Restored {} mod archive(s):", restored.len());
+            for archive in restored.iter() {
+                println!("  - {}", archive.display());
+            }
+            if !restored.is_empty() {
+                println!("Start the game to register the restored mods.");
+            }
+        }
+    }
+
+    if let Some(backup) = &args.restore {
+        let manifest = beammm::backup::read_manifest(backup)?;
+        if manifest.game_version != beamng_version.to_string() {
+            println!(
+                "The backup was made on game version {}, restoring it into {}.",
+                manifest.game_version, beamng_version
+            );
+        }
+        let restored = beammm::backup::restore(backup, &mods_dir, &presets_dir, |path| {
+            beammm::confirm_cli(
+                &format!("Overwrite {}?", path.display()),
+                false,
+                auto_answer,
+            )
+        })?;
+        if restored.db_json {
+            // Reload so the restored db.json isn't overwritten when saving.
+            let mut restored_cfg = beammm::game::ModCfg::load_or_default(&mods_dir)?;
+            restored_cfg.copy_settings_from(&beamng_mod_cfg);
+            beamng_mod_cfg = restored_cfg;
+        }
+        println!(
+            "Restored {}{} preset(s) and {} mod archive(s) from {}",
+            if restored.db_json { "db.json, " } else { "" },
+            restored.presets.len(),
+            restored.archives.len(),
+            backup.display()
+        );
+    }
+
+    if let Some(path) = &args.import_state {
+        let reader = BufReader::new(File::open(path)?);
+        let imported = beammm::state::State::load(reader)?.apply(
+            &mut beamng_mod_cfg,
+            &mut metadata,
+            &presets_dir,
+        )?;
+        println!(
+            "Imported {} preset(s) and the state of {} mod(s).",
+            imported.presets, imported.mods
+        );
+        if !imported.missing.is_empty() {
+            println!("These mods aren't installed:");
+            for (mod_name, repo_version) in imported.missing {
+                match repo_version {
+                    Some(repo_version) => println!("  - {} ({})", mod_name, repo_version.url()),
+                    None => println!("  - {}", mod_name),
+                }
+            }
+        }
+    }
+    if let Some(path) = &args.export_state {
+        let state = beammm::state::State::capture(&beamng_mod_cfg, &metadata, &presets_dir)?;
+        state.save(BufWriter::new(File::create(path)?))?;
+        println!("Exported the state to {}", path.display());
+    }
+
+    if let Some(out) = &args.backup {
+        let manifest = beammm::backup::create(
+            out,
+            &beamng_mod_cfg,
+            &mods_dir,
+            &presets_dir,
+            &beamng_version,
+            args.backup_archives,
+            now,
+        )?;
+        println!(
+            "Backed up db.json, {} preset(s) and {} mod archive(s) to {}",
+            manifest.presets.len(),
+            manifest.archives.len(),
+            out.display()
+        );
+    }
+
+    if let Some(out) = &args.support_bundle {
+        let mut redactions = vec![];
+        if !args.include_paths {
+            redactions.push((mods_dir.clone(), "<mods>".into()));
+            redactions.push((beamng_dir.clone(), "<game data>".into()));
+            redactions.push((beammm_dir.clone(), "<BeamMM>".into()));
+            if let Some(home_dir) = dirs::home_dir() {
+                redactions.push((home_dir, "<home>".into()));
+            }
+        }
+        let bundle = beammm::support::SupportBundle {
+            config: &config,
+            mod_config: &beamng_mod_cfg,
+            game_version: &beamng_version,
+            mods_dir: &mods_dir,
+            presets_dir: &presets_dir,
+            log_lines: log.and_then(|log| log.recent(200).ok()).unwrap_or_default(),
+            redactions,
+        };
+        let files = bundle.write(out)?;
+        println!(
+            "Wrote {} to {}. Check it before attaching it to an issue.",
+            files.join(", "),
+            out.display()
+        );
+        if !args.include_paths {
+            println!(
+                "Full paths were replaced with placeholders. Use --include-paths to keep them."
+            );
+        }
+    }
+
+    if args.stats {
+        let mut stats = beammm::stats::Stats::collect(&beamng_mod_cfg, &mods_dir, &presets_dir)?;
+        let shared = namespaces.list_shared()?;
+        stats.presets += shared.len();
+        stats.enabled_presets += namespaces
+            .enabled_shared()?
+            .iter()
+            .filter(|name| shared.contains(name))
+            .count();
+        let counts = |counts: &BTreeMap<String, usize>| {
+            counts
+                .iter()
+                .map(|(name, count)| format!("{} {}", name, count))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        println!(
+            "Mods: {} ({} enabled, {} disabled)",
+            stats.mods, stats.enabled, stats.disabled
+        );
+        println!(
+            "Size: {} ({} enabled)",
+            format_size(stats.total_size),
+            format_size(stats.enabled_size)
+        );
+        println!("Sources: {}", counts(&stats.by_source));
+        println!("Types: {}", counts(&stats.by_type));
+        println!(
+            "Presets: {} ({} enabled)",
+            stats.presets, stats.enabled_presets
+        );
+        if !stats.largest.is_empty() {
+            println!("Largest mods:");
+            for (mod_name, size) in stats.largest.iter() {
+                println!("  - {} ({})", mod_name, format_size(*size));
+            }
+        }
+    }
+    if let Some(preset) = args.preset_size {
+        let all = preset == "all";
+        let names: Vec<String> = if all {
+            beammm::Preset::list(&presets_dir)?
+                .chain(namespaces.list_shared()?)
+                .collect()
+        } else {
+            vec![preset]
+        };
+        let footprint =
+            beammm::stats::Footprint::collect_with(&names, &beamng_mod_cfg, &mods_dir, |name| {
+                namespaces.load(name)
+            })?;
+        for preset in footprint.presets.iter() {
+            println!(
+                "{}: {} ({} mod(s))",
+                preset.name,
+                format_size(preset.size),
+                preset.mods.len()
+            );
+            if !all {
+                for (mod_name, size) in preset.mods.iter() {
+                    println!("  - {} ({})", mod_name, format_size(*size));
+                }
+            }
+            if !preset.missing.is_empty() {
+                println!(
+                    "{}",
+                    format!("  Not installed: {}", preset.missing.join(", ")).yellow()
+                );
+            }
+        }
+        if all {
+            println!(
+                "Total: {}, counting mods shared by several presets once",
+                format_size(footprint.total_size)
+            );
+        }
+    }
+
+    if args.health {
+        let report = beammm::health::check(&beamng_mod_cfg, &mods_dir)?;
+        println!(
+            "Mods directory: {} ({})",
+            mods_dir.display(),
+            format_size(report.total_size)
+        );
+        if !report.orphaned.is_empty() {
+            println!(
+                "{}",
+                "Archives and unpacked mods not registered in db.json:".yellow()
+            );
+            for archive in report.orphaned.iter() {
+                println!("  - {}", archive.display());
+            }
+            println!(
+                "  Fix: start the game, or move them to {} to register them now.",
+                inbox_dir(&beammm_dir).display()
+            );
+        }
+        if !report.stale.is_empty() {
+            println!("{}", "Mods in db.json whose archive is missing:".yellow());
+            for mod_name in report.stale.iter() {
+                println!("  - {}", mod_name);
+            }
+            println!("  Fix: beammm --restore-from-mirror, or start the game to forget them.");
+        }
+        if !report.corrupt.is_empty() {
+            println!("{}", "Corrupt archives:".yellow());
+            for (archive, reason) in report.corrupt.iter() {
+                println!("  - {} - {}", archive.display(), reason);
+            }
+            println!("  Fix: beammm --check-archives --quarantine");
+        }
+        if !report.empty.is_empty() {
+            println!("{}", "Empty files:".yellow());
+            for file in report.empty.iter() {
+                println!("  - {}", file.display());
+            }
+            println!("  Fix: delete them.");
+        }
+        if !report.duplicates.is_empty() {
+            println!("{}", "Mods with identical archives:".yellow());
+            for mod_names in report.duplicates.iter() {
+                println!("  - {}", mod_names.join(", "));
+            }
+            println!("  Fix: beammm --dedup");
+        }
+        if !report.repo_duplicates.is_empty() {
+            println!(
+                "{}",
+                "Mods installed both from the repository and by hand:".yellow()
+            );
+            for (repo_mod, manual_mod) in report.repo_duplicates.iter() {
+                println!("  - {} (repo), {} (manual)", repo_mod, manual_mod);
+            }
+            println!("  Fix: beammm --dedup-repo");
+        }
+        if !report.non_unicode.is_empty() {
+            println!(
+                "{}",
+                "Mods whose names aren't valid Unicode, which the game can't name:".yellow()
+            );
+            for path in report.non_unicode.iter() {
+                println!("  - {}", path.display());
+            }
+            println!("  Fix: rename them.");
+        }
+        if report.is_healthy() {
+            println!("{}", "No problems found.".green());
+        }
+    }
+
+    if args.check_archives {
+        let corrupt = beammm::archive::find_corrupt(&mods_dir)?;
+        if corrupt.is_empty() {
+            println!("No corrupt mod archives found.");
+        }
+        for (archive, reason) in corrupt {
+            println!("{} {} - {}", "corrupt".red(), archive.display(), reason);
+            if !args.quarantine {
+                continue;
+            }
+            let confirmation = beammm::confirm_cli(
+                &format!("Move {} to the quarantine?", archive.display()),
+                true,
+                auto_answer,
+            )?;
+            if confirmation {
+                // The game would keep looking for the archive, so its mod is removed from db.json.
+                let mod_name = beamng_mod_cfg
+                    .get_mods()
+                    .find(|m| beamng_mod_cfg.archive_path(m, &mods_dir).as_ref() == Some(&archive))
+                    .cloned();
+                let target = beammm::archive::quarantine(&archive, &quarantine_dir(&beammm_dir)?)?;
+                // Archives aren't snapshotted, so the archive is moved back if a later step fails.
+                transaction.record_move(&archive, &target);
+                if let Some(mod_name) = mod_name {
+                    beamng_mod_cfg.remove_mod(&mod_name)?;
+                }
+                println!("Moved to {}", target.display());
+            }
+        }
+    }
+
+    if args.dedup {
+        let duplicates = beammm::archive::find_duplicates(&beamng_mod_cfg, &mods_dir)?;
+        if duplicates.is_empty() {
+            println!("No duplicate mods found.");
+        }
+        for mod_names in duplicates {
+            // Groups of duplicates always have at least two mods.
+            let (keep, extras) = mod_names.split_first().unwrap();
+            println!("'{}' has identical copies: {}", keep, extras.join(", "));
+            let confirmation = beammm::confirm_cli(
+                &format!("Remove the copies and keep '{}'?", keep),
+                true,
+                auto_answer,
+            )?;
+            if !confirmation {
+                continue;
+            }
+            let active = extras
+                .iter()
+                .any(|m| beamng_mod_cfg.is_mod_active(m) == Some(true));
+            for extra in extras {
+                // Like with --dedup-repo, the copy is only deleted once db.json is saved.
+                let archive = beamng_mod_cfg.archive_path(extra, &mods_dir);
+                beamng_mod_cfg.remove_mod(extra)?;
+                if let Some(archive) = archive.filter(|a| a.exists()) {
+                    transaction.remove_on_commit(archive, args.permanent);
+                }
+                // Presets that reference the copy keep working through the alias.
+                config.set_mod_alias(extra, keep);
+            }
+            if active {
+                // We don't care if the kept mod isn't allowed to be enabled.
+                let _ = beamng_mod_cfg.set_mod_active(keep, true);
+            }
+            println!("Removed {} duplicate(s) of '{}'.", extras.len(), keep);
+        }
+        config.save_to_path(&beammm_dir)?;
+        beamng_mod_cfg.set_aliases(config.mod_aliases().clone());
+    }
+
+    if args.dedup_repo {
+        let duplicates = beammm::archive::find_repo_duplicates(&beamng_mod_cfg, &mods_dir)?;
+        if duplicates.is_empty() {
+            println!("No mods are installed both from the repository and by hand.");
+        }
+        for (repo_mod, manual_mod) in duplicates {
+            println!(
+                "'{}' is installed from the repository and by hand as '{}'.",
+                repo_mod, manual_mod
+            );
+            // The repository copy is the one worth keeping, since it can be updated.
+            let choice = match auto_answer {
+                AutoAnswer::Yes => 0,
+                AutoAnswer::No => 2,
+                _ => match beammm::select_cli(
+                    "Which copy do you want to keep?",
+                    &[
+                        format!("The repository copy '{}'", repo_mod),
+                        format!("The manual copy '{}'", manual_mod),
+                        "Both, for now".into(),
+                    ],
+                    auto_answer,
+                ) {
+                    Ok(choice) => choice,
+                    Err(beammm::Error::IO(e)) if e.kind() == std::io::ErrorKind::TimedOut => {
+                        println!("No answer, so keeping both.");
+                        2
+                    }
+                    Err(e) => return Err(e),
+                },
+            };
+            let (keep, remove) = match choice {
+                0 => (repo_mod, manual_mod),
+                1 => (manual_mod, repo_mod),
+                _ => continue,
+            };
+            let active = beamng_mod_cfg.is_mod_active(&remove) == Some(true);
+            // The archive is only deleted once db.json is saved, so a failed run can't lose both
+            // copies.
+            let archive = beamng_mod_cfg.archive_path(&remove, &mods_dir);
+            beamng_mod_cfg.remove_mod(&remove)?;
+            if let Some(archive) = archive.filter(|a| a.exists()) {
+                transaction.remove_on_commit(archive, args.permanent);
+            }
+            // Presets that reference the removed copy keep working through the alias.
+            config.set_mod_alias(&remove, &keep);
+            if active {
+                // We don't care if the kept mod isn't allowed to be enabled.
+                let _ = beamng_mod_cfg.set_mod_active(&keep, true);
+            }
+            println!("Removed '{}' and kept '{}'.", remove, keep);
+        }
+        config.save_to_path(&beammm_dir)?;
+        beamng_mod_cfg.set_aliases(config.mod_aliases().clone());
+    }
+
+    if args.normalize_names {
+        let mut renames = vec![];
+        let mut taken: HashSet<String> = beamng_mod_cfg.get_mods().cloned().collect();
+        // The game tracks subscribed mods by their archive names, so those are left alone.
+        let mod_names: Vec<String> = beamng_mod_cfg
+            .iter_mods()
+            .filter(|entry| !entry.entry().is_subscribed())
+            .map(|entry| entry.name().to_string())
+            .collect();
+        for mod_name in mod_names {
+            let Some(archive) = beamng_mod_cfg.archive_path(&mod_name, &mods_dir) else {
+                continue;
+            };
+            let Some(stem) = archive.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let new_name = beammm::archive::normalized_name(stem);
+            if new_name == stem && new_name == mod_name {
+                continue;
+            }
+            if new_name != mod_name && !taken.insert(new_name.clone()) {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "Can't rename '{}' to '{}', another mod has that name.",
+                        mod_name, new_name
+                    )
+                    .yellow()
+                );
+                continue;
+            }
+            renames.push((mod_name, new_name));
+        }
+
+        if renames.is_empty() {
+            println!("All mod archives already have normalized names.");
+        } else {
+            println!("Mods to rename:");
+            for (mod_name, new_name) in renames.iter() {
+                println!("  - {} -> {}", mod_name, new_name);
+            }
+            let confirmation = beammm::confirm_cli(
+                &format!("Rename {} mod(s)?", renames.len()),
+                true,
+                auto_answer,
+            )?;
+            if confirmation {
+                for (mod_name, new_name) in renames.iter() {
+                    // Archives aren't snapshotted, so renames are undone if a later step fails.
+                    if let Some((from, to)) =
+                        beamng_mod_cfg.rename_mod(mod_name, new_name, &mods_dir)?
+                    {
+                        transaction.record_move(from, to);
+                    }
+                    if mod_name == new_name {
+                        continue;
+                    }
+                    beammm::Preset::rename_mod_in_all(mod_name, new_name, &presets_dir)?;
+                    metadata.rename_mod(mod_name, new_name);
+                    // Scripts that use the old name keep working through the alias.
+                    config.set_mod_alias(mod_name, new_name);
+                }
+                config.save_to_path(&beammm_dir)?;
+                beamng_mod_cfg.set_aliases(config.mod_aliases().clone());
+                println!("Renamed {} mod(s).", renames.len());
+            }
+        }
+    }
+
+    if let Some(preset_name) = args.resolve {
+        let mut resolved = namespaces.load(&preset_name)?;
+        resolved.flatten_with(|name| namespaces.load(name))?;
+        let missing = beamng_mod_cfg.missing_mods(resolved.get_mods());
+        if missing.is_empty() {
+            namespaces.set_many_enabled([&preset_name], true, &mut beamng_mod_cfg)?;
+            println!("All mods are installed. Preset '{}' enabled.", preset_name);
+        } else {
+            let mirror_dir = match args.mirror_dir {
+                Some(dir) => dir,
+                None => mirror_dir(&beammm_dir)?,
+            };
+            let mut unresolved = vec![];
+            let mut mismatched = vec![];
+            for mod_name in missing {
+                let Some(repo_version) = resolved.repo_version(&mod_name) else {
+                    unresolved.push(mod_name);
+                    continue;
+                };
+                let Some(installed) =
+                    beammm::mirror::install(repo_version, &mods_dir, &mirror_dir)?
+                else {
+                    unresolved.push(mod_name);
+                    continue;
+                };
+                // Registered right away, so the preset can be enabled before the game starts.
+                for archive in installed.archives.iter() {
+                    beamng_mod_cfg.register_archive(archive);
+                }
+                println!("Installed '{}' from the mirror.", mod_name);
+                if installed.version_id != repo_version.version_id {
+                    mismatched.push((mod_name, repo_version.version_id, installed.version_id));
+                }
+            }
+            if !unresolved.is_empty() {
+                eprintln!(
+                    "{}",
+                    "These mods have no repository ID or aren't in the mirror:".red()
+                );
+                for mod_name in unresolved.iter() {
+                    eprintln!("  - {}", mod_name);
+                }
+            }
+            if !mismatched.is_empty() {
+                eprintln!(
+                    "{}",
+                    "These mods were installed in a different version than the preset's:".yellow()
+                );
+                for (mod_name, wanted, installed) in mismatched.iter() {
+                    eprintln!(
+                        "  - {} (version {} instead of {})",
+                        mod_name, installed, wanted
+                    );
+                }
+            }
+            if unresolved.is_empty() && mismatched.is_empty() {
+                namespaces.set_many_enabled([&preset_name], true, &mut beamng_mod_cfg)?;
+                println!(
+                    "All mods are installed. Preset '{}' enabled. The game finishes registering the installed mods the next time it starts.",
+                    preset_name
+                );
+            } else {
+                println!(
+                    "Preset '{}' was left disabled. Enable it with the --enable-preset flag once its mods are in order.",
+                    preset_name
+                );
+            }
+        }
+    }
+
+    if let Some(preset_name) = args.list_preset_mods {
+        let preset = namespaces.load(&preset_name)?;
+        let status = if preset.is_enabled() {
+            "enabled ".green()
+        } else {
+            "disabled".red()
+        };
+        println!("Mods in preset '{}' ({}):", preset_name, status);
+        for mod_name in preset.get_mods() {
+            match preset.repo_version(mod_name) {
+                Some(repo_version) => println!("{} ({})", mod_name, repo_version),
+                None => println!("{}", mod_name),
+            }
+        }
+        if let Some(parent) = preset.extends() {
+            println!("Also includes the mods of preset '{}':", parent);
+            for mod_name in preset.resolve_mods_with(|name| namespaces.load(name))? {
+                if !preset.get_mods().contains(&mod_name) {
+                    println!("{}", mod_name);
+                }
+            }
+        }
+        if !preset.launch_args().is_empty() {
+            println!("Launch arguments: {}", preset.launch_args().join(" "));
+        }
+        if !preset.requirements().is_empty() {
+            println!("Requirements:");
+            for (mod_name, required) in preset.requirements() {
+                println!("{} requires {}", mod_name, required.join(", "));
+            }
+        }
+    }
+
+    if args.list_presets {
+        let mut table = Table::new(["STATUS", "NAME", "MODS", "EXTENDS"]);
+        for (preset_name, preset) in PresetIndex::load_from_path(&presets_dir)?.iter() {
+            let status = if preset.is_enabled() {
+                "enabled".green()
+            } else {
+                "disabled".red()
+            };
+            table.add_row([
+                status.to_string(),
+                preset_name.into(),
+                preset.mod_count().to_string(),
+                preset.extends().unwrap_or("-").into(),
+            ]);
+        }
+        for preset_name in namespaces.list_shared()? {
+            let preset = namespaces.load(&preset_name)?;
+            let status = if preset.is_enabled() {
+                "enabled".green()
+            } else {
+                "disabled".red()
+            };
+            table.add_row([
+                status.to_string(),
+                preset_name,
+                preset.get_mods().len().to_string(),
+                preset.extends().unwrap_or("-").into(),
+            ]);
+        }
+        table.print()?;
+    }
+    if let Some(path) = &args.import {
+        // Names of imported files are made valid, but names chosen by the user are only checked.
+        let preset_name = match &args.import_as {
+            Some(name) => name.clone(),
+            None => beammm::Preset::sanitize_name(
+                &path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy())
+                    .unwrap_or_default(),
+            ),
+        };
+        beammm::Preset::validate_name(&preset_name)?;
+        if beammm::Preset::exists(&preset_name, &presets_dir) {
+            return Err(beammm::Error::PresetExists {
+                preset: preset_name,
+            });
+        }
+
+        let source = beammm::import::ImportSource::detect(path);
+        let imported = beammm::import::import(path, source, &mods_dir, &mut beamng_mod_cfg)?;
+        for archive in imported.installed.iter() {
+            // Archives aren't snapshotted, so copies are removed if a later step fails.
+            transaction.record_created(archive);
+            println!("{} {}", "installed".green(), archive.display());
+        }
+        for (archive, reason) in imported.rejected.iter() {
+            println!("{} {} - {}", "rejected".red(), archive.display(), reason);
+        }
+        let mut preset = beammm::Preset::new(preset_name.clone(), imported.mods.clone())?;
+        preset.record_repo_versions(&beamng_mod_cfg);
+        preset.save_to_path(&presets_dir)?;
+        write_log(
+            log,
+            &format!("Imported {} into preset {}", path.display(), preset_name),
+        );
+        println!(
+            "Created preset '{}' with {} mod(s).",
+            preset_name,
+            imported.mods.len()
+        );
+        if !imported.missing.is_empty() {
+            println!("{}", "These mods aren't installed:".yellow());
+            for mod_name in imported.missing.iter() {
+                println!("  - {}", mod_name);
+            }
+            println!(
+                "  Install them, then add them with --preset-add {}",
+                preset_name
+            );
+        }
+    }
+
+    if let Some(path) = &args.import_presets {
+        let imported = beammm::import::import_presets(path, &presets_dir)?;
+        for (file, name) in imported.imported.iter() {
+            println!("{} {} as '{}'", "imported".green(), file.display(), name);
+        }
+        for (file, name, new_name) in imported.renamed.iter() {
+            println!(
+                "{} {} as '{}' ('{}' is taken)",
+                "renamed".yellow(),
+                file.display(),
+                new_name,
+                name
+            );
+        }
+        for (file, reason) in imported.rejected.iter() {
+            println!("{} {} - {}", "rejected".red(), file.display(), reason);
+        }
+        let count = imported.imported.len() + imported.renamed.len();
+        write_log(
+            log,
+            &format!("Imported {} preset(s) from {}", count, path.display()),
+        );
+        println!(
+            "Imported {} preset(s), {} rejected. Use the --enable-preset flag to enable them.",
+            count,
+            imported.rejected.len()
+        );
+    }
+
+    if let Some(preset_name) = args.create_preset {
+        namespace::check_writable(&preset_name)?;
+        beammm::Preset::validate_name(&preset_name)?;
+        // Check if the preset already exists
+        if beammm::Preset::exists(&preset_name, &presets_dir) {
+            return Err(beammm::Error::PresetExists {
+                preset: preset_name,
+            });
+        }
+
+        let mut preset =
+            beammm::Preset::new(preset_name.clone(), args.mods.clone().unwrap_or(vec![]))?;
+        preset.record_repo_versions(&beamng_mod_cfg);
+        preset.save_to_path(&presets_dir)?;
+        println!("Preset '{}' created successfully.", preset_name);
+        if let Some(_mods) = args.mods.clone() {
+            println!("With mods:");
+            for mod_name in preset.get_mods() {
+                println!("  - {}", mod_name);
+            }
+        } else {
+            println!("No mods added to the preset.");
+        }
+        println!(
+            "Use the --enable-preset and --disable-preset flags to enable or disable the preset."
+        );
+        println!(
+            "Use the --preset-add and --preset-remove flags to add or remove mods from the preset."
+        );
+    }
+    if args.backup_deleted_presets || args.no_backup_deleted_presets {
+        config.set_backup_deleted_presets(args.backup_deleted_presets);
+        config.save_to_path(&beammm_dir)?;
+        if args.backup_deleted_presets {
+            println!("Presets will be backed up before they are deleted.");
+        } else {
+            println!("Presets will no longer be backed up before they are deleted.");
+        }
+    }
+    if let Some(preset) = args.delete_preset {
+        namespace::check_writable(&preset)?;
+        let confirmation = beammm::confirm_cli(
+            &format!("Are you sure you want to delete preset '{}'?", preset),
+            false,
+            auto_answer,
+        )?;
+        if confirmation {
+            // A missing preset doesn't stop the run, so the other flags are still handled and
+            // everything is saved as usual.
+            let mut missing = false;
+            if args.backup_preset || config.is_backup_deleted_presets() {
+                match beammm::preset_backups::create(
+                    &preset_backups_dir,
+                    &preset,
+                    &presets_dir,
+                    now,
+                ) {
+                    Ok(backup) => {
+                        println!("Backed up preset '{}' to {}.", preset, backup.display())
+                    }
+                    Err(beammm::Error::MissingPreset { .. }) => missing = true,
+                    Err(e) => return Err(e),
+                }
+            }
+            if !missing {
+                match beammm::Preset::delete(&preset, &presets_dir, args.permanent) {
+                    Ok(_) => (),
+                    Err(beammm::Error::IO(e)) => match e.kind() {
+                        std::io::ErrorKind::NotFound => missing = true,
+                        _ => return Err(beammm::Error::IO(e)),
+                    },
+                    Err(e) => {
+                        return Err(e);
+                    }
+                }
+            }
+            if missing {
+                println!("Preset '{}' does not exist.", preset);
+            } else {
+                println!("Preset '{}' deleted successfully.", preset);
+            }
+        } else {
+            println!("Preset '{}' was not deleted.", preset);
+        }
+    }
+    if let Some(preset) = args.restore_preset {
+        let restored = beammm::preset_backups::restore(&preset_backups_dir, &preset, &presets_dir)?;
+        println!("Preset '{}' restored successfully.", restored.name());
+        if restored.is_enabled() {
+            println!("It is enabled, so its mods will be enabled again.");
+        }
+    }
+    if let Some(preset) = args.preset_history {
+        let revisions = if namespace::split(&preset).is_some() {
+            // Shared presets are never saved over, so they have no history.
+            namespaces.load(&preset)?;
+            vec![]
+        } else {
+            beammm::history::revisions(&preset, &presets_dir)?
+        };
+        if revisions.is_empty() {
+            println!("Preset '{}' has no previous versions.", preset);
+        } else {
+            let mut table = Table::new(["REV", "REPLACED", "MODS"]);
+            for revision in revisions {
+                let mods = match revision.load() {
+                    Ok(old) => old.get_mods().len().to_string(),
+                    Err(_) => "unreadable".into(),
+                };
+                table.add_row([
+                    revision.number.to_string(),
+                    format_time(Some(revision.time), ""),
+                    mods,
+                ]);
+            }
+            table.print()?;
+            println!("Use --preset-revert {} <REV> to go back to one.", preset);
+        }
+    }
+    if let Some(values) = args.preset_revert {
+        // Clap guarantees exactly two values.
+        let (preset_name, revision) = (&values[0], &values[1]);
+        namespace::check_writable(preset_name)?;
+        let revision = revision.trim_start_matches('#').parse().map_err(|_| {
+            beammm::Error::InvalidRevision {
+                preset: preset_name.clone(),
+                revision: revision.clone(),
+            }
+        })?;
+        let preset = beammm::history::revert(preset_name, revision, &presets_dir)?;
+        println!(
+            "Preset '{}' reverted to revision {} ({} mod(s)).",
+            preset_name,
+            revision,
+            preset.get_mods().len()
+        );
+        println!("The version it replaced was kept, so this can be undone with --preset-revert.");
+    }
+    for (preset_names, enabled) in [(args.enable_preset, true), (args.disable_preset, false)] {
+        let Some(mut preset_names) = preset_names else {
+            continue;
+        };
+        let action = if enabled { "enable" } else { "disable" };
+        if preset_names.iter().any(|p| p == "all") {
+            let confirmation = beammm::confirm_cli(
+                &format!("Are you sure you would like to {} all presets?", action),
+                enabled,
+                auto_answer,
+            )?;
+            if !confirmation {
+                continue;
+            }
+            preset_names = beammm::Preset::list(&presets_dir)?
+                .chain(namespaces.list_shared()?)
+                .collect();
+        }
+        namespaces.set_many_enabled(&preset_names, enabled, &mut beamng_mod_cfg)?;
+        for preset_name in preset_names.iter() {
+            println!("Preset '{}' {}d.", preset_name, action);
+        }
+    }
+    if let Some(values) = args.map_preset {
+        // Clap guarantees exactly two values.
+        let (level, preset) = (&values[0], values[1].trim());
+        if preset.is_empty() {
+            config.set_map_preset(level, None);
+            println!("Removed the preset for {}.", level);
+        } else {
+            if namespace::split(preset).is_none() {
+                beammm::Preset::validate_name(preset)?;
+            }
+            if !namespaces.exists(preset) {
+                println!(
+                    "{}",
+                    format!("Preset '{}' doesn't exist yet.", preset).yellow()
+                );
+            }
+            config.set_map_preset(level, Some(preset.into()));
+            println!("{} now uses preset '{}'.", level, preset);
+        }
+        config.save_to_path(&beammm_dir)?;
+    }
+    if args.list_map_presets {
+        if config.map_presets().is_empty() {
+            println!("No levels are mapped to presets. Map one with --map-preset.");
+        }
+        for (level, preset) in config.map_presets() {
+            println!("{} -> {}", level, preset);
+        }
+    }
+    if let Some(level) = args.for_map {
+        match config.preset_for_map(&level) {
+            Some(preset) => {
+                let others: Vec<String> = PresetIndex::load_from_path(&presets_dir)?
+                    .enabled()
+                    .into_iter()
+                    .map(String::from)
+                    .chain(namespaces.enabled_shared()?)
+                    .filter(|p| p != preset)
+                    .collect();
+                namespaces.set_many_enabled(&others, false, &mut beamng_mod_cfg)?;
+                namespaces.set_many_enabled([preset], true, &mut beamng_mod_cfg)?;
+                for other in others.iter() {
+                    println!("Preset '{}' disabled.", other);
+                }
+                println!("Preset '{}' enabled for {}.", preset, level);
+            }
+            None => println!(
+                "No preset is mapped to {}. Map one with --map-preset {} <PRESET>.",
+                level, level
+            ),
+        }
+    }
+
+    if let Some(values) = args.preset_missing_mods {
+        // Clap guarantees exactly two values.
+        let (preset_name, policy) = (&values[0], &values[1]);
+        namespace::check_writable(preset_name)?;
+        let mut preset = beammm::Preset::load_from_path(preset_name, &presets_dir)?;
+        if policy.to_lowercase() == "default" {
+            preset.set_missing_mod_policy(None);
+            println!(
+                "Preset '{}' now uses the default missing mod policy.",
+                preset_name
+            );
+        } else {
+            preset.set_missing_mod_policy(Some(policy.parse()?));
+            println!(
+                "Preset '{}' missing mod policy set to '{}'.",
+                preset_name,
+                policy.to_lowercase()
+            );
+        }
+        preset.save_to_path(&presets_dir)?;
+    }
+
+    if let Some(preset_name) = args.share {
+        let mut preset = namespaces.load(&preset_name)?;
+        // Extended presets won't exist for whoever imports the code.
+        preset.flatten_with(|name| namespaces.load(name))?;
+        if let Some((_, name)) = namespace::split(&preset_name) {
+            // Namespaces are local, so the code carries the plain name.
+            preset.set_name(name.into())?;
+        }
+        println!("{}", preset.to_share_code()?);
+    }
+    if let Some(code) = args.import_code {
+        let preset = beammm::Preset::from_share_code(&code)?;
+        let preset_name = preset.name().to_string();
+        if beammm::Preset::exists(&preset_name, &presets_dir) {
+            return Err(beammm::Error::PresetExists {
+                preset: preset_name,
+            });
+        }
+        preset.save_to_path(&presets_dir)?;
+        println!("Preset '{}' imported with mods:", preset_name);
+        for mod_name in preset.get_mods() {
+            println!("  - {}", mod_name);
+        }
+        let missing = beamng_mod_cfg.missing_mods(preset.get_mods());
+        if !missing.is_empty() {
+            println!("{}", "These mods aren't installed:".yellow());
+            for mod_name in missing {
+                println!("  - {}", mod_name);
+            }
+        }
+        println!("Use the --enable-preset flag to enable it.");
+    }
+
+    if let Some(values) = args.preset_extends {
+        // Clap guarantees exactly two values.
+        let (preset_name, parent) = (&values[0], &values[1]);
+        namespace::check_writable(preset_name)?;
+        let mut preset = beammm::Preset::load_from_path(preset_name, &presets_dir)?;
+        if parent.to_lowercase() == "none" {
+            preset.set_extends(None);
+            println!("Preset '{}' no longer extends another preset.", preset_name);
+        } else {
+            preset.set_extends(Some(parent.clone()));
+            // Make sure the parent exists and doesn't lead back to this preset.
+            preset.resolve_mods_with(|name| namespaces.load(name))?;
+            println!("Preset '{}' now extends preset '{}'.", preset_name, parent);
+        }
+        preset.save_to_path(&presets_dir)?;
+    }
+
+    if let Some(values) = args.preset_launch_args {
+        // Clap guarantees exactly two values.
+        let (preset_name, launch_args) = (&values[0], &values[1]);
+        namespace::check_writable(preset_name)?;
+        let mut preset = beammm::Preset::load_from_path(preset_name, &presets_dir)?;
+        preset.set_launch_args(beammm::config::split_args(launch_args));
+        preset.save_to_path(&presets_dir)?;
+        if preset.launch_args().is_empty() {
+            println!("Launch arguments cleared for preset '{}'.", preset_name);
+        } else {
+            println!(
+                "Launch arguments for preset '{}' set to: {}",
+                preset_name,
+                preset.launch_args().join(" ")
+            );
+        }
+    }
+
+    if let Some(values) = args.preset_requires {
+        // Clap guarantees at least two values.
+        let (preset_name, mod_name, required) = (&values[0], &values[1], &values[2..]);
+        namespace::check_writable(preset_name)?;
+        let mut preset = beammm::Preset::load_from_path(preset_name, &presets_dir)?;
+        preset.set_requirements(mod_name, required.to_vec());
+        preset.save_to_path(&presets_dir)?;
+        if required.is_empty() {
+            println!(
+                "Requirements of '{}' cleared in preset '{}'.",
+                mod_name, preset_name
+            );
+        } else {
+            println!(
+                "'{}' now requires {} in preset '{}'.",
+                mod_name,
+                required.join(", "),
+                preset_name
+            );
+        }
+    }
+
+    if let Some(mod_name) = args.preset_remove_all {
+        let changed = beammm::Preset::remove_mod_from_all(&mod_name, &presets_dir)?;
+        if changed.is_empty() {
+            println!("No presets contain mod '{}'.", mod_name);
+        } else {
+            println!("Mod '{}' removed from presets:", mod_name);
+            for preset_name in changed.iter() {
+                println!("  - {}", preset_name);
+            }
+        }
+    }
+
+    if args.prune_presets {
+        let mut pruned_any = false;
+        for preset_name in beammm::Preset::list(&presets_dir)? {
+            let mut preset = beammm::Preset::load_from_path(&preset_name, &presets_dir)?;
+            let removed = preset.prune_missing(&beamng_mod_cfg);
+            if removed.is_empty() {
+                continue;
+            }
+            pruned_any = true;
+            println!("Preset '{}' references missing mods:", preset_name);
+            for mod_name in removed.iter() {
+                println!("  - {}", mod_name);
+            }
+            let confirmation = beammm::confirm_cli(
+                &format!("Remove them from preset '{}'?", preset_name),
+                true,
+                auto_answer,
+            )?;
+            if confirmation {
+                preset.save_to_path(&presets_dir)?;
+                println!("Preset '{}' pruned.", preset_name);
+            }
+        }
+        if !pruned_any {
+            println!("No presets reference missing mods.");
+        }
+    }
+
+    if args.prune_all_presets {
+        let pruned = beammm::Preset::prune_all(&presets_dir, &beamng_mod_cfg)?;
+        if pruned.is_empty() {
+            println!("No presets reference missing mods.");
+        }
+        for (preset_name, removed) in pruned {
+            println!("Preset '{}' pruned:", preset_name);
+            for mod_name in removed.iter() {
+                println!("  - {}", mod_name);
+            }
+        }
+    }
+
+    if args.stash {
+        Stash::push(&beammm_dir, &mut beamng_mod_cfg)?;
+        println!("Mod state stashed and all mods disabled.");
+        println!("Presets won't be applied until the stash is restored.");
+        println!("Use the --stash-pop flag to restore it.");
+    }
+    if args.stash_pop {
+        Stash::pop(&beammm_dir, &mut beamng_mod_cfg)?;
+        println!("Stashed mod state restored.");
+    }
+
+    if args.enforce_whitelist || args.no_enforce_whitelist {
+        config.set_whitelist_enforced(args.enforce_whitelist);
+        config.save_to_path(&beammm_dir)?;
+        beamng_mod_cfg.set_whitelist(config.enforced_whitelist());
+        if args.enforce_whitelist {
+            println!("Whitelist enforced. Only whitelisted mods may be active.");
+        } else {
+            println!("Whitelist no longer enforced.");
+        }
+    }
+
+    if args.retries.is_some() || args.retry_backoff.is_some() || args.network_timeout.is_some() {
+        let mut policy = config.retry_policy();
+        if let Some(retries) = args.retries {
+            policy = policy.with_retries(retries);
+        }
+        if let Some(backoff) = args.retry_backoff {
+            policy = policy.with_backoff(Duration::from_millis(backoff));
+        }
+        if let Some(timeout) = args.network_timeout {
+            policy = policy.with_timeout(Duration::from_secs(timeout));
+        }
+        config.set_retry_policy(Some(policy));
+        config.save_to_path(&beammm_dir)?;
+        println!("Network requests will be retried with {}.", policy);
+    }
+
+    if let Some(url) = args.rules_url {
+        if url.eq_ignore_ascii_case("none") {
+            config.set_rules_url(None);
+            println!("Compatibility rules will no longer be downloaded.");
+        } else {
+            config.set_rules_url(Some(url.clone()));
+            println!("Compatibility rules will be downloaded from {}.", url);
+        }
+        config.save_to_path(&beammm_dir)?;
+    }
+    if args.update_rules {
+        match config.rules_url() {
+            Some(url) => {
+                let rules = Rules::fetch(url, &config.retry_policy())?;
+                rules.save_to_path(&beammm_dir)?;
+                println!("Downloaded {} compatibility rules.", rules.len());
+            }
+            None => eprintln!(
+                "{}",
+                "No rules URL is set. Set one with --rules-url first.".yellow()
+            ),
+        }
+    }
+
+    if args.check_for_updates || args.no_check_for_updates {
+        config.set_check_for_updates(args.check_for_updates);
+        config.save_to_path(&beammm_dir)?;
+        if args.check_for_updates {
+            println!("BeamMM will check for newer releases once a day.");
+        } else {
+            println!("BeamMM will no longer check for newer releases.");
+        }
+    }
+
+    if let Some(values) = args.alias {
+        // Clap guarantees exactly two values.
+        let (name, alias_args) = (&values[0], values[1].trim());
+        if alias_args.is_empty() {
+            config.set_alias(name, None);
+            println!("Removed alias {}.", name);
+        } else {
+            config.set_alias(name, Some(alias_args.into()));
+            println!("{} now stands for: {}", name, alias_args);
+        }
+        config.save_to_path(&beammm_dir)?;
+    }
+    if args.list_unassigned {
+        let unassigned = namespaces.unmanaged_mods(&beamng_mod_cfg)?;
+        if unassigned.is_empty() {
+            println!("Every mod is in a preset.");
+        }
+        for mod_name in unassigned {
+            println!("{}", mod_name);
+        }
+    }
+
+    let management_changes = [
+        (
+            args.manage.take(),
+            Management::Managed,
+            "managed by presets",
+        ),
+        (args.ignore.take(), Management::Ignored, "ignored"),
+        (args.observe.take(), Management::Observed, "observed"),
+    ];
+    for (mods, management, description) in management_changes {
+        let Some(mods) = mods else {
+            continue;
+        };
+        for mod_name in mods.iter() {
+            metadata.set_management(mod_name, management);
+        }
+        write_log(
+            log,
+            &format!("Set {} to be {}", mods.join(", "), description),
+        );
+        println!("These mods are now {}:", description);
+        for mod_name in mods.iter() {
+            println!("  - {}", mod_name);
+        }
+    }
+    beamng_mod_cfg.set_management(metadata.management());
+
+    if args.list_management {
+        let management = metadata.management();
+        if management.is_empty() {
+            println!("Every mod is observed. Change that with --manage or --ignore.");
+        }
+        for (mod_name, management) in management {
+            let state = match management {
+                Management::Managed => "managed by presets".green(),
+                Management::Ignored => "ignored".yellow(),
+                Management::Observed => "observed".normal(),
+            };
+            println!("{} is {}", mod_name, state);
+        }
+    }
+
+    if args.list_overrides {
+        let overrides = metadata.overrides();
+        if overrides.is_empty() {
+            println!("No overrides. Add one with --enable or --disable and --override.");
+        }
+        for (mod_name, active) in overrides {
+            let state = if active { "enabled" } else { "disabled" };
+            println!("{} is kept {}", mod_name, state);
+        }
+    }
+
+    if args.list_aliases {
+        if config.aliases().is_empty() {
+            println!("No aliases. Add one with --alias.");
+        }
+        for (name, alias_args) in config.aliases() {
+            println!("{} = {}", name, alias_args);
+        }
+    }
+
+    if args.compact_db_json || args.no_compact_db_json {
+        config.set_compact_db_json(args.compact_db_json);
+        config.save_to_path(&beammm_dir)?;
+        beamng_mod_cfg.set_compact(args.compact_db_json);
+        if args.compact_db_json {
+            println!("db.json will be saved as compact JSON.");
+        } else {
+            println!("db.json will be saved pretty-printed.");
+        }
+    }
+
+    if args.sorted_json || args.no_sorted_json {
+        config.set_sorted_json(args.sorted_json);
+        config.save_to_path(&beammm_dir)?;
+        beamng_mod_cfg.set_sorted(args.sorted_json);
+        if args.sorted_json {
+            println!("db.json and presets will be saved sorted.");
         } else {
-            println!("No mods added to the preset.");
+            println!("db.json and presets will be saved as they are.");
         }
-        println!(
-            "Use the --enable-preset and --disable-preset flags to enable or disable the preset."
-        );
-        println!(
-            "Use the --preset-add and --preset-remove flags to add or remove mods from the preset."
-        );
     }
-    if let Some(preset) = args.delete_preset {
-        let confirmation = beammm::confirm_cli(
-            &format!("Are you sure you want to delete preset '{}'?", preset),
-            false,
-            args.confirm_all,
-        )?;
-        if confirmation {
-            match beammm::Preset::delete(&preset, &presets_dir) {
-                Ok(_) => (),
-                Err(beammm::Error::IO(e)) => match e.kind() {
-                    std::io::ErrorKind::NotFound => {
-                        println!("Preset '{}' does not exist.", preset);
-                        return Ok(());
-                    }
-                    _ => return Err(beammm::Error::IO(e)),
-                },
-                Err(e) => {
-                    return Err(e);
-                }
+
+    let db_backups_dir = db_backups_dir(&beammm_dir, &beamng_version);
+    if let Some(retention) = args.keep_backups {
+        config.set_backup_retention(Some(retention));
+        config.save_to_path(&beammm_dir)?;
+        println!("Keeping {} of db.json.", retention);
+    }
+    if args.list_backups {
+        let backups = beammm::db_backups::list(&db_backups_dir)?;
+        if backups.is_empty() {
+            println!("No db.json backups yet.");
+        } else {
+            let mut table = Table::new(["TIME", "SIZE", "FILE"]);
+            for backup in backups {
+                table.add_row([
+                    format_time(Some(backup.time), ""),
+                    format_size(backup.size),
+                    backup.path.display().to_string(),
+                ]);
             }
-            println!("Preset '{}' deleted successfully.", preset);
+            table.print()?;
+        }
+        println!("Keeping {}.", config.backup_retention());
+    }
+
+    let mut restored = None;
+    if args.restore_db {
+        let backup = if args.at.is_some() || args.latest {
+            beammm::db_backups::latest(&db_backups_dir, args.at.map(Into::into))?
         } else {
-            println!("Preset '{}' was not deleted.", preset);
+            let backups = beammm::db_backups::list(&db_backups_dir)?;
+            if backups.is_empty() {
+                None
+            } else {
+                let options: Vec<String> = backups
+                    .iter()
+                    .map(|b| {
+                        format!(
+                            "{} ({})",
+                            format_time(Some(b.time), ""),
+                            format_size(b.size)
+                        )
+                    })
+                    .collect();
+                let choice = beammm::select_cli(
+                    "Choose the db.json backup to restore:",
+                    &options,
+                    auto_answer,
+                )?;
+                backups.into_iter().nth(choice)
+            }
+        };
+        let backup = backup.ok_or(beammm::Error::MissingDbBackup {
+            dir: db_backups_dir.clone(),
+        })?;
+        // Enabled presets would change the mods right back, so they are disabled like with
+        // --rollback-to-good.
+        let disabled = namespaces.force_disable_all(&mut beamng_mod_cfg)?;
+        // The restored mod config is saved at the end, after the current db.json is backed up.
+        let mut restored_cfg = backup.load()?;
+        restored_cfg.copy_settings_from(&beamng_mod_cfg);
+        beamng_mod_cfg = restored_cfg;
+        restored = Some((backup.time, disabled));
+    }
+
+    if args.rollback_to_good {
+        let good = beammm::Preset::load_last_known_good(&last_known_good_file)?;
+        // Enabled presets, personal or shared, would change the mods right back.
+        let enabled = namespaces.force_disable_all(&mut beamng_mod_cfg)?;
+        let missing = good.restore(&mut beamng_mod_cfg);
+        println!("Restored the last known good mods:");
+        for mod_name in good.get_mods().iter().filter(|m| !missing.contains(m)) {
+            println!("  - {}", mod_name);
+        }
+        if !missing.is_empty() {
+            eprintln!("{}", "Skipped mods that are no longer installed:".yellow());
+            for mod_name in missing.iter() {
+                eprintln!("  - {}", mod_name);
+            }
+        }
+        if !enabled.is_empty() {
+            println!("Disabled presets: {}", enabled.join(", "));
         }
     }
-    if let Some(preset_name) = args.enable_preset {
-        if preset_name == "all" {
+
+    if !args.disable_where.is_empty() {
+        let matching: Vec<String> =
+            beammm::filter::matching(&args.disable_where, &beamng_mod_cfg, &mods_dir)
+                .into_iter()
+                .filter(|m| beamng_mod_cfg.is_mod_active(m) == Some(true))
+                .collect();
+        if matching.is_empty() {
+            println!("No active mods match the filters.");
+        } else {
+            println!("Active mods matching the filters:");
+            for mod_name in matching.iter() {
+                match beammm::filter::archive_size(&beamng_mod_cfg, mod_name, &mods_dir) {
+                    Some(size) => println!("  - {} ({})", mod_name, format_size(size)),
+                    None => println!("  - {}", mod_name),
+                }
+            }
             let confirmation = beammm::confirm_cli(
-                "Are you sure you would like to enable all presets?",
-                true,
-                args.confirm_all,
+                &format!("Disable these {} mod(s)?", matching.len()),
+                false,
+                auto_answer,
             )?;
             if confirmation {
-                for preset_name in beammm::Preset::list(&presets_dir)? {
-                    let mut preset = beammm::Preset::load_from_path(&preset_name, &presets_dir)?;
-                    preset.enable();
-                    preset.save_to_path(&presets_dir)?;
-                    println!("Preset '{}' enabled.", preset_name);
-                }
+                beamng_mod_cfg.set_mods_active(&matching, false)?;
+                println!("Mods disabled.");
             }
+        }
+    }
+
+    if let Some([mod_name, label]) = args.label.as_deref() {
+        if beamng_mod_cfg.is_mod_active(mod_name).is_none() {
+            return Err(beamng_mod_cfg.missing_mods_error(vec![mod_name.clone()]));
+        }
+        metadata.set_label(mod_name, label, true);
+        println!("Label '{}' added to mod '{}'.", label, mod_name);
+    }
+    if let Some([mod_name, label]) = args.unlabel.as_deref() {
+        metadata.set_label(mod_name, label, false);
+        println!("Label '{}' removed from mod '{}'.", label, mod_name);
+    }
+    if let Some([label]) = args.label.as_deref() {
+        let labeled = metadata.with_label(label);
+        if labeled.is_empty() {
+            println!("No mods have the label '{}'.", label);
         } else {
-            let mut preset = beammm::Preset::load_from_path(&preset_name, &presets_dir)?;
-            preset.enable();
-            preset.save_to_path(&presets_dir)?;
-            println!("Preset '{}' enabled.", preset_name);
+            args.mods.get_or_insert_with(Vec::new).extend(labeled);
         }
     }
-    if let Some(preset_name) = args.disable_preset {
-        if preset_name == "all" {
-            let confirmation = beammm::confirm_cli(
-                "Are you sure you would like to disable all presets?",
-                false,
-                args.confirm_all,
-            )?;
-            if confirmation {
-                for preset_name in beammm::Preset::list(&presets_dir)? {
-                    let mut preset = beammm::Preset::load_from_path(&preset_name, &presets_dir)?;
-                    preset.disable(&mut beamng_mod_cfg)?;
-                    preset.save_to_path(&presets_dir)?;
-                    println!("Preset '{}' disabled.", preset_name);
+
+    if args.interactive {
+        let mut mods: Vec<String> = match &args.mods {
+            Some(mods) if !mods.first().is_some_and(|m| m.eq_ignore_ascii_case("all")) => {
+                let missing = beamng_mod_cfg.missing_mods(mods);
+                if !missing.is_empty() {
+                    return Err(beamng_mod_cfg.missing_mods_error(missing));
                 }
+                mods.clone()
+            }
+            _ => beamng_mod_cfg.get_mods().cloned().collect(),
+        };
+        mods.sort();
+        let active: Vec<bool> = mods
+            .iter()
+            .map(|m| beamng_mod_cfg.is_mod_active(m).unwrap_or(false))
+            .collect();
+        let checked = beammm::multi_select_cli("Check the mods to enable:", &mods, &active)?;
+
+        let (mut to_enable, mut to_disable) = (vec![], vec![]);
+        for ((mod_name, was_active), now_active) in mods.iter().zip(active).zip(checked) {
+            match (was_active, now_active) {
+                (false, true) => to_enable.push(mod_name),
+                (true, false) => to_disable.push(mod_name),
+                _ => (),
             }
+        }
+        beamng_mod_cfg.set_mods_active(&to_disable, false)?;
+        beamng_mod_cfg.set_mods_active(&to_enable, true)?;
+        println!(
+            "{} mod(s) enabled and {} mod(s) disabled.",
+            to_enable.len(),
+            to_disable.len()
+        );
+
+        // The checklist replaces the usual enabling and disabling of the given mods.
+        args.enable = false;
+        args.disable = false;
+    }
+
+    if let Some(clear) = args.clear_overrides {
+        let cleared: Vec<String> = metadata
+            .overrides()
+            .into_iter()
+            .map(|(mod_name, _)| mod_name)
+            .filter(|m| clear.is_empty() || clear.contains(m))
+            .collect();
+        for mod_name in cleared.iter() {
+            metadata.set_override(mod_name, None);
+        }
+        beamng_mod_cfg.set_overrides(metadata.overrides());
+        if cleared.is_empty() {
+            println!("No overrides to clear.");
         } else {
-            let mut preset = beammm::Preset::load_from_path(&preset_name, &presets_dir)?;
-            preset.disable(&mut beamng_mod_cfg)?;
-            preset.save_to_path(&presets_dir)?;
-            println!("Preset '{}' disabled.", preset_name);
+            println!("Presets may enable these mods again:");
+            for mod_name in cleared.iter() {
+                println!("  - {}", mod_name);
+            }
         }
-        // let mut preset = beammm::Preset::load_from_path(&preset_name, &presets_dir)?;
-        // preset.disable(&mut beamng_mod_cfg)?;
-        // preset.save_to_path(&presets_dir)?;
-        // println!("Preset '{}' disabled.", preset_name);
     }
 
     // Handle operations that require args.mods to exist.
@@ -222,12 +2724,53 @@ fn run() -> beammm::Result<()> {
         // Check of mods argument is "all"
         let all_mods = Some(String::from("all")) == mods.first().map(|s| s.to_lowercase());
 
+        if args.whitelist || args.unwhitelist {
+            let missing = beamng_mod_cfg.missing_mods(&mods);
+            if !missing.is_empty() {
+                return Err(beamng_mod_cfg.missing_mods_error(missing));
+            }
+            for mod_name in mods.iter() {
+                config.set_whitelisted(mod_name, args.whitelist);
+            }
+            config.save_to_path(&beammm_dir)?;
+            beamng_mod_cfg.set_whitelist(config.enforced_whitelist());
+            if args.whitelist {
+                println!("Mods added to the whitelist:");
+            } else {
+                println!("Mods removed from the whitelist:");
+            }
+            for mod_name in mods.iter() {
+                println!("  - {}", mod_name);
+            }
+        }
+        if args.blacklist || args.unblacklist {
+            let missing = beamng_mod_cfg.missing_mods(&mods);
+            if !missing.is_empty() {
+                return Err(beamng_mod_cfg.missing_mods_error(missing));
+            }
+            if args.blacklist {
+                beamng_mod_cfg.set_mods_active(&mods, false)?;
+            }
+            for mod_name in mods.iter() {
+                config.set_blacklisted(mod_name, args.blacklist);
+            }
+            config.save_to_path(&beammm_dir)?;
+            beamng_mod_cfg.set_blacklist(config.blacklist().iter().cloned());
+            if args.blacklist {
+                println!("Mods blacklisted and disabled:");
+            } else {
+                println!("Mods removed from the blacklist:");
+            }
+            for mod_name in mods.iter() {
+                println!("  - {}", mod_name);
+            }
+        }
         if args.enable {
             if all_mods {
                 let confirmation = beammm::confirm_cli(
                     "Are you sure you would like to enable all mods?",
                     true,
-                    args.confirm_all,
+                    auto_answer,
                 )?;
                 if confirmation {
                     beamng_mod_cfg.set_all_mods_active(true)?;
@@ -235,7 +2778,14 @@ fn run() -> beammm::Result<()> {
                 }
             } else {
                 beamng_mod_cfg.set_mods_active(&mods, true)?;
-                println!("Mods enabled:");
+                if args.override_presets {
+                    for mod_name in mods.iter() {
+                        metadata.set_override(mod_name, Some(true));
+                    }
+                    println!("Mods enabled, and kept enabled when presets are applied:");
+                } else {
+                    println!("Mods enabled:");
+                }
                 for mod_name in mods.iter() {
                     println!("  - {}", mod_name);
                 }
@@ -246,27 +2796,118 @@ fn run() -> beammm::Result<()> {
                 let confirmation = beammm::confirm_cli(
                     "Are you sure you would like to disable all mods?",
                     false,
-                    args.confirm_all,
+                    auto_answer,
                 )?;
                 if confirmation {
-                    beamng_mod_cfg.set_all_mods_active(false)?;
-                    println!("All mods disabled.");
+                    if args.spare_favorites {
+                        let mods: Vec<String> = beamng_mod_cfg
+                            .get_mods()
+                            .filter(|m| !metadata.is_favorite(m))
+                            .cloned()
+                            .collect();
+                        beamng_mod_cfg.set_mods_active(&mods, false)?;
+                        println!("All mods except favorites disabled.");
+                    } else {
+                        beamng_mod_cfg.set_all_mods_active(false)?;
+                        println!("All mods disabled.");
+                    }
                 }
             } else {
                 beamng_mod_cfg.set_mods_active(&mods, false)?;
-                println!("Mods disabled:");
+                if args.override_presets {
+                    for mod_name in mods.iter() {
+                        metadata.set_override(mod_name, Some(false));
+                    }
+                    println!("Mods disabled, and kept disabled when presets are applied:");
+                } else {
+                    println!("Mods disabled:");
+                }
                 for mod_name in mods.iter() {
                     println!("  - {}", mod_name);
                 }
             }
         }
+        beamng_mod_cfg.set_overrides(metadata.overrides());
+        if args.favorite || args.unfavorite {
+            let missing = beamng_mod_cfg.missing_mods(&mods);
+            if !missing.is_empty() {
+                return Err(beamng_mod_cfg.missing_mods_error(missing));
+            }
+            for mod_name in mods.iter() {
+                metadata.set_favorite(mod_name, args.favorite);
+            }
+            if args.favorite {
+                println!("Mods added to favorites:");
+            } else {
+                println!("Mods removed from favorites:");
+            }
+            for mod_name in mods.iter() {
+                println!("  - {}", mod_name);
+            }
+        }
+        if args.pin || args.unpin {
+            let missing = beamng_mod_cfg.missing_mods(&mods);
+            if !missing.is_empty() {
+                return Err(beamng_mod_cfg.missing_mods_error(missing));
+            }
+            for mod_name in mods.iter() {
+                metadata.set_pinned(mod_name, args.pin);
+            }
+            if args.pin {
+                println!("Mods pinned:");
+            } else {
+                println!("Mods unpinned:");
+            }
+            for mod_name in mods.iter() {
+                println!("  - {}", mod_name);
+            }
+        }
+        if args.mark_working {
+            let missing = beamng_mod_cfg.missing_mods(&mods);
+            if !missing.is_empty() {
+                return Err(beamng_mod_cfg.missing_mods_error(missing));
+            }
+            for mod_name in mods.iter() {
+                metadata.set_confirmed_version(mod_name, Some(beamng_version));
+            }
+            println!("Mods marked as working on {}:", beamng_version);
+            for mod_name in mods.iter() {
+                println!("  - {}", mod_name);
+            }
+        }
         if let Some(preset_name) = args.preset_add {
+            namespace::check_writable(&preset_name)?;
             let mut preset = beammm::Preset::load_from_path(&preset_name, &presets_dir)?;
+            // Catch typos now rather than when the preset fails to apply.
+            let missing = beamng_mod_cfg.missing_mods(&mods);
+            if args.strict && !missing.is_empty() {
+                return Err(beamng_mod_cfg.missing_mods_error(missing));
+            }
             preset.add_mods(&mods);
+            preset.record_repo_versions(&beamng_mod_cfg);
             preset.save_to_path(&presets_dir)?;
             println!("Mods added to preset '{}':", preset_name);
+            for mod_name in mods.iter() {
+                println!("  - {}", mod_name);
+            }
+            if !missing.is_empty() {
+                eprintln!(
+                    "{}",
+                    "These mods aren't installed, so the preset won't apply until they are:"
+                        .yellow()
+                );
+                for mod_name in missing.iter() {
+                    match beamng_mod_cfg.suggest_mod(mod_name) {
+                        Some(suggestion) => {
+                            eprintln!("  - {} (did you mean {}?)", mod_name, suggestion)
+                        }
+                        None => eprintln!("  - {}", mod_name),
+                    }
+                }
+            }
         }
         if let Some(preset_name) = args.preset_remove {
+            namespace::check_writable(&preset_name)?;
             let mut preset = beammm::Preset::load_from_path(&preset_name, &presets_dir)?;
             preset.remove_mods(&mods);
             preset.save_to_path(&presets_dir)?;
@@ -277,41 +2918,631 @@ fn run() -> beammm::Result<()> {
         }
     }
 
+    if let Some(values) = args.note {
+        // Clap guarantees exactly two values.
+        let (mod_name, note) = (&values[0], values[1].trim());
+        if beamng_mod_cfg.is_mod_active(mod_name).is_none() {
+            return Err(beamng_mod_cfg.missing_mods_error(vec![mod_name.clone()]));
+        }
+        if note.is_empty() {
+            metadata.set_note(mod_name, None);
+            println!("Note removed from mod '{}'.", mod_name);
+        } else {
+            metadata.set_note(mod_name, Some(note.into()));
+            println!("Note added to mod '{}'.", mod_name);
+        }
+    }
+
     if args.list_mods {
-        for beamng_mod in beamng_mod_cfg.get_mods() {
-            let status = beamng_mod_cfg.is_mod_active(beamng_mod).unwrap(); // Safe to unwrap because we just
-                                                                            // got the mods from the config.
-            let status_str = if status {
-                "enabled ".green()
+        let mut listed_mods: Vec<_> = beamng_mod_cfg.iter_mods().collect();
+        // Sorting by time puts the most recent first and mods without the time last. The sort is
+        // stable so ties stay alphabetical.
+        match args.sort {
+            ModSort::Name => (),
+            ModSort::Installed => listed_mods.sort_by_key(|m| {
+                std::cmp::Reverse(metadata.get(m.name()).and_then(|m| m.installed()))
+            }),
+            ModSort::Updated => listed_mods.sort_by_key(|m| {
+                std::cmp::Reverse(metadata.get(m.name()).and_then(|m| m.updated()))
+            }),
+            ModSort::Enabled => listed_mods.sort_by_key(|m| {
+                std::cmp::Reverse(metadata.get(m.name()).and_then(|m| m.last_enabled()))
+            }),
+        }
+        let mut headers = vec!["STATUS", "NAME", "SIZE", "SOURCE"];
+        if args.show_notes {
+            headers.push("NOTE");
+        }
+        let mod_row = |entry: &beammm::game::ModEntry| {
+            let beamng_mod = entry.name();
+            let status_str = if entry.is_active() {
+                "enabled".green()
             } else {
                 "disabled".red()
             };
 
-            println!("{} {}", status_str, beamng_mod);
+            let name = if beamng_mod_cfg.is_blacklisted(beamng_mod) {
+                format!("{} {}", beamng_mod, "(blacklisted)".dimmed())
+            } else {
+                beamng_mod.to_string()
+            };
+            let size = entry.entry().file_size().map(format_size);
+            let source = entry.entry().root().to_string();
+
+            let mut row = vec![
+                status_str.to_string(),
+                name,
+                size.unwrap_or("-".into()),
+                source,
+            ];
+            if args.show_notes {
+                let note = metadata.note(beamng_mod).unwrap_or_default();
+                row.push(note.italic().to_string());
+            }
+            row
+        };
+        listed_mods.retain(|entry| !args.favorites || metadata.is_favorite(entry.name()));
+
+        match args.group_by {
+            None => {
+                let mut table = Table::new(headers);
+                for entry in listed_mods.iter() {
+                    table.add_row(mod_row(entry));
+                }
+                table.print()?;
+            }
+            Some(ModGroup::Preset) => {
+                // Every section is printed at once so they are paged together.
+                // The tables are indented under their section.
+                let width = beammm::table::terminal_width().map(|w| w.saturating_sub(2));
+                let mut lines = vec![];
+                let mut assigned = HashSet::new();
+                let index = PresetIndex::load_from_path(&presets_dir)?;
+                for (preset_name, preset) in index.iter() {
+                    let status = if preset.is_enabled() {
+                        "enabled".green()
+                    } else {
+                        "disabled".red()
+                    };
+                    lines.push(format!("{} ({})", preset_name.bold(), status));
+                    let mut table = Table::new(headers.clone());
+                    for mod_name in preset.mods() {
+                        assigned.insert(mod_name.as_str());
+                        match listed_mods.iter().find(|m| m.name() == mod_name) {
+                            Some(entry) => table.add_row(mod_row(entry)),
+                            // Installed mods that were filtered out, e.g. by --favorites.
+                            None if beamng_mod_cfg.is_mod_active(mod_name).is_some() => (),
+                            None => {
+                                table.add_row(["missing".yellow().to_string(), mod_name.into()])
+                            }
+                        }
+                    }
+                    if table.is_empty() {
+                        lines.push("  No mods.".into());
+                    } else {
+                        lines.extend(table.render(width).into_iter().map(|l| format!("  {}", l)));
+                    }
+                    lines.push(String::new());
+                }
+
+                lines.push(format!("{}", "Unassigned".bold()));
+                let mut table = Table::new(headers);
+                for entry in listed_mods.iter() {
+                    if !assigned.contains(entry.name()) {
+                        table.add_row(mod_row(entry));
+                    }
+                }
+                if table.is_empty() {
+                    lines.push("  Every mod is in a preset.".into());
+                } else {
+                    lines.extend(table.render(width).into_iter().map(|l| format!("  {}", l)));
+                }
+                beammm::pager::print(&lines)?;
+            }
         }
     }
 
-    match beamng_mod_cfg.apply_presets(&presets_dir) {
-        Ok(_) => (),
-        Err(beammm::Error::PresetsFailed { mods, presets }) => {
-            eprintln!("{}", "Failed to apply presets:".red());
-            for preset in presets.iter() {
-                eprintln!("  - {}", preset);
+    if args.why_broken {
+        let version_dir = beamng_dir.join(beamng_version.to_string());
+        let log = beammm::logscan::log_path(&version_dir);
+        if !log.try_exists()? {
+            println!(
+                "No game log found at {}. Start the game first.",
+                log.display()
+            );
+        } else {
+            let issues = beammm::logscan::scan_path(&version_dir, &beamng_mod_cfg)?;
+            if issues.is_empty() {
+                println!("No mod errors found in {}.", log.display());
             }
-            eprintln!("Because of the following missing mods:");
-            for mod_name in mods {
-                eprintln!("  - {}", mod_name);
+            for issue in issues {
+                println!(
+                    "{} {} ({}, line {})",
+                    issue.kind.to_string().red(),
+                    issue.mod_name,
+                    issue.message,
+                    issue.line
+                );
             }
-            eprintln!("{}", "Disabling these presets.".red());
-            for preset in presets.iter() {
-                let mut preset = beammm::Preset::load_from_path(preset, &presets_dir)?;
-                preset.force_disable(&mut beamng_mod_cfg);
-                preset.save_to_path(&presets_dir)?;
+        }
+    }
+
+    if let Some(mod_name) = args.open {
+        if beamng_mod_cfg.is_mod_active(&mod_name).is_none() {
+            return Err(beamng_mod_cfg.missing_mods_error(vec![mod_name]));
+        }
+        let repo_version =
+            beamng_mod_cfg
+                .repo_version(&mod_name)
+                .ok_or_else(|| beammm::Error::NotRepoMod {
+                    mod_name: mod_name.clone(),
+                })?;
+        open::that(repo_version.url())?;
+        println!("Opened the repository page of '{}'.", mod_name);
+    }
+
+    if let Some(mod_name) = args.peek {
+        let archive = match beamng_mod_cfg.archive_path(&mod_name, &mods_dir) {
+            Some(archive) => archive,
+            // Downloads can be peeked into before they are installed.
+            None if Path::new(&mod_name).is_file() => PathBuf::from(&mod_name),
+            None => return Err(beamng_mod_cfg.missing_mods_error(vec![mod_name])),
+        };
+        let contents = beammm::archive::peek(&archive)?;
+        let mut lines = vec![format!(
+            "{}: {} file(s), {} uncompressed",
+            archive.display(),
+            contents.files.len(),
+            format_size(contents.size())
+        )];
+        if contents.holds.is_empty() {
+            lines.push(format!(
+                "{}",
+                "Holds nothing the game loads, like vehicles/ or levels/.".yellow()
+            ));
+        } else {
+            lines.push(format!("Holds: {}", contents.holds.join(", ")));
+        }
+        if let Some(wrapper) = &contents.wrapped_in {
+            lines.push(format!(
+                "{}",
+                format!(
+                    "Everything is inside {}, so the game won't find it. Zip the folder's contents instead.",
+                    wrapper
+                )
+                .yellow()
+            ));
+        }
+        for nested in contents.nested_archives.iter() {
+            lines.push(format!(
+                "{}",
+                format!(
+                    "{} is a zip inside the zip, which the game won't open.",
+                    nested
+                )
+                .yellow()
+            ));
+        }
+        lines.push(String::new());
+        let mut table = Table::new(["TOP LEVEL", "FILES", "SIZE"]);
+        for entry in contents.top_level.iter() {
+            table.add_row([
+                entry.name.clone(),
+                entry.files.to_string(),
+                format_size(entry.size),
+            ]);
+        }
+        lines.extend(table.render(beammm::table::terminal_width()));
+        lines.push(String::new());
+        lines.push("Files:".into());
+        for file in contents.files.iter() {
+            lines.push(format!("  {} ({})", file.path, format_size(file.size)));
+        }
+        beammm::pager::print(&lines)?;
+    }
+
+    if let Some(mod_name) = args.mod_info {
+        let status = match beamng_mod_cfg.is_mod_active(&mod_name) {
+            Some(true) => "enabled".green(),
+            Some(false) => "disabled".red(),
+            None => return Err(beamng_mod_cfg.missing_mods_error(vec![mod_name])),
+        };
+        println!("Mod '{}' ({}):", mod_name, status);
+        println!("  Note: {}", metadata.note(&mod_name).unwrap_or("none"));
+        let mod_metadata = metadata.get(&mod_name);
+        if let Some(repo_version) = metadata.repo_version(&mod_name) {
+            println!("  Repository version: {}", repo_version);
+        }
+        if metadata.is_pinned(&mod_name) {
+            println!("  Pinned: yes");
+        }
+        if let Some(confirmed) = metadata.confirmed_version(&mod_name) {
+            println!("  Confirmed working on: {}", confirmed);
+        }
+        if let Some(mod_metadata) = mod_metadata.filter(|m| m.labels().next().is_some()) {
+            let labels: Vec<&str> = mod_metadata.labels().map(String::as_str).collect();
+            println!("  Labels: {}", labels.join(", "));
+        }
+        // Credits are a nice extra, so a missing or unreadable archive just leaves them out.
+        let info = beamng_mod_cfg
+            .archive_path(&mod_name, &mods_dir)
+            .and_then(|archive| beammm::archive::read_info(&archive).ok())
+            .unwrap_or_default();
+        if let Some(title) = info.title {
+            println!("  Title: {}", title);
+        }
+        println!("  Author: {}", info.author.as_deref().unwrap_or("unknown"));
+        println!(
+            "  License: {}",
+            info.license.as_deref().unwrap_or("unknown")
+        );
+        println!(
+            "  Installed: {}",
+            format_time(mod_metadata.and_then(|m| m.installed()), "unknown")
+        );
+        println!(
+            "  Updated: {}",
+            format_time(mod_metadata.and_then(|m| m.updated()), "never")
+        );
+        println!(
+            "  Last enabled: {}",
+            format_time(mod_metadata.and_then(|m| m.last_enabled()), "never")
+        );
+    }
+
+    // Presets would re-enable stashed mods, so they wait until the stash is popped. A restored
+    // db.json is saved exactly as it was backed up.
+    let mut presets_changed_mods = false;
+    if !Stash::exists(&beammm_dir) && restored.is_none() {
+        let before_presets = beamng_mod_cfg.clone();
+        match namespaces.apply_presets(&mut beamng_mod_cfg, args.missing_mods) {
+            Ok(_) => {
+                presets_changed_mods =
+                    !beammm::game::ChangeSet::between(&before_presets, &beamng_mod_cfg).is_empty()
+            }
+            Err(beammm::Error::PresetsFailed {
+                mods,
+                presets,
+                requirements,
+            }) => {
+                eprintln!("{}", "Failed to apply presets:".red());
+                for preset in presets.iter() {
+                    eprintln!("  - {}", preset);
+                }
+                if !mods.is_empty() {
+                    eprintln!("Because of the following missing mods:");
+                    for mod_name in mods {
+                        eprintln!("  - {}", mod_name);
+                    }
+                }
+                if !requirements.is_empty() {
+                    eprintln!("Because of the following unmet mod requirements:");
+                    for (mod_name, required) in requirements {
+                        eprintln!("  - {} requires {}", mod_name, required);
+                    }
+                }
+                eprintln!("{}", "Disabling these presets.".red());
+                for preset in presets.iter() {
+                    namespaces.force_disable(preset, &mut beamng_mod_cfg)?;
+                }
             }
+            Err(e) => return Err(e),
+        }
+    }
+
+    let not_whitelisted = beamng_mod_cfg.enforce_whitelist();
+    if !not_whitelisted.is_empty() {
+        eprintln!("{}", "Disabling mods that aren't whitelisted:".yellow());
+        for mod_name in not_whitelisted {
+            eprintln!("  - {}", mod_name);
+        }
+    }
+
+    // Rules are only advice, so a broken rules file is reported without stopping the run.
+    let rules = Rules::load_from_path(&beammm_dir).unwrap_or_else(|e| {
+        eprintln!(
+            "{}",
+            format!(
+                "Warning: the compatibility rules couldn't be loaded, so they aren't checked: {}",
+                e
+            )
+            .yellow()
+        );
+        Rules::default()
+    });
+    let violations = rules.violations(&beamng_mod_cfg, &beamng_version);
+    if !violations.is_empty() {
+        eprintln!("{}", "Active mods break compatibility rules:".yellow());
+        for violation in violations {
+            eprintln!("  - {}", violation);
+        }
+    }
+
+    if args.status {
+        let changes = beamng_mod_cfg.diff_against_path(&mods_dir)?;
+        let preset_changes =
+            index_before.membership_changes(&PresetIndex::load_from_path(&presets_dir)?);
+        if !preset_changes.is_empty() {
+            println!("Presets changed in this run:");
+            print_diff(&[], &[], &preset_changes);
+        }
+        if changes.is_empty() {
+            println!("db.json is up to date.");
+        } else {
+            print_diff(&changes.enabled, &changes.disabled, &[]);
+            println!("Run without --status to save these changes.");
+        }
+        // Presets saved by other flags or while applying presets are put back, so nothing is
+        // saved.
+        transaction.rollback()?;
+        return Ok(());
+    }
+
+    let changes = beamng_mod_cfg.diff_against_path(&mods_dir)?;
+    metadata.record_enabled(&changes.enabled, now);
+    let outdated = metadata.unconfirmed_on(&changes.enabled, &beamng_version);
+    if !outdated.is_empty() {
+        eprintln!(
+            "{}",
+            format!(
+                "Enabled mods haven't been confirmed to work on {}:",
+                beamng_version
+            )
+            .yellow()
+        );
+        for (mod_name, confirmed) in outdated {
+            eprintln!("  - {} (last confirmed on {})", mod_name, confirmed);
+        }
+        eprintln!("Use --mark-working once they work.");
+    }
+
+    if let Some(path) = &args.plan {
+        // Everything is saved to find what changed, then put back. db.json is only saved if
+        // something changed it, like at the end of a normal run, so it isn't just reformatted.
+        if beamng_mod_cfg.is_dirty() {
+            beamng_mod_cfg.save_to_path(&mods_dir)?;
+        }
+        metadata.save_to_path(&beammm_dir)?;
+        // The metadata and the preset index change on most runs, which would make plans go stale
+        // right away, so only db.json, the presets and the list of enabled shared presets are
+        // planned, without their history and backups.
+        let file_changes = transaction
+            .changes()?
+            .into_iter()
+            .filter(|change| {
+                change.path == mods_dir.join("db.json")
+                    || change.path == presets_dir.join(beammm::namespace::ENABLED_FILE)
+                    || change.path.parent() == Some(presets_dir.as_path())
+                        && !change
+                            .path
+                            .file_name()
+                            .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+            })
+            .collect();
+        let mut plan = beammm::plan::Plan::new(&changes, file_changes);
+        plan.preset_changes =
+            index_before.membership_changes(&PresetIndex::load_from_path(&presets_dir)?);
+        transaction.rollback()?;
+        plan.save_to_path(path)?;
+        if plan.is_empty() {
+            println!("Nothing would change.");
+        } else {
+            print_plan(&plan);
+            println!("Saved the plan to {}.", path.display());
+            println!("Apply it with --apply-plan {}", path.display());
+        }
+        return Ok(());
+    }
+
+    if let Ok(changes) = beamng_mod_cfg.diff_against_path(&mods_dir) {
+        if !changes.enabled.is_empty() {
+            write_log(
+                log,
+                &format!("Enabled mods: {}", changes.enabled.join(", ")),
+            );
+        }
+        if !changes.disabled.is_empty() {
+            write_log(
+                log,
+                &format!("Disabled mods: {}", changes.disabled.join(", ")),
+            );
+        }
+    }
+    // When presets applied cleanly and changed the mods, the mods enabled before this run are
+    // kept as the last known good state, unless this run is going back to it.
+    if presets_changed_mods && !changes.is_empty() && !args.rollback_to_good {
+        let previous = beammm::game::ModCfg::load_or_default(&mods_dir)?;
+        beammm::Preset::last_known_good(&previous).save_last_known_good(&last_known_good_file)?;
+    }
+    if config.is_sorted_json() {
+        beammm::Preset::sort_all(&presets_dir)?;
+    }
+    // db.json is only rewritten if something changed it, so runs that just read, like
+    // --list-mods, leave the game's file alone. It is backed up as it was before this run first,
+    // unless it is already the latest backup.
+    if beamng_mod_cfg.is_dirty() {
+        beammm::db_backups::create(&db_backups_dir, &mods_dir, now)?;
+        beammm::db_backups::prune(&db_backups_dir, config.backup_retention(), now)?;
+        beamng_mod_cfg.save_to_path(&mods_dir)?;
+    }
+    metadata.save_to_path(&beammm_dir)?;
+    transaction.commit()?;
+    if let Some((time, disabled)) = restored {
+        println!(
+            "Restored db.json from the backup taken at {}.",
+            format_time(Some(time), "")
+        );
+        println!("The previous db.json was backed up first, so --restore-db can undo this.");
+        if !disabled.is_empty() {
+            println!("Disabled presets: {}", disabled.join(", "));
+        }
+    }
+
+    // The update check must never get in the way, so failures are ignored.
+    if config.check_for_updates() {
+        if let Ok(Some(latest)) = UpdateCheck::run(&beammm_dir, now, &config.retry_policy()) {
+            println!(
+                "{}",
+                format!(
+                    "BeamMM {} is available (you have {}).",
+                    latest,
+                    env!("CARGO_PKG_VERSION")
+                )
+                .yellow()
+            );
         }
-        Err(e) => return Err(e),
     }
-    beamng_mod_cfg.save_to_path(&mods_dir)?;
+
+    if args.watch_inbox {
+        // Watching takes the lock only while installing, so other processes can run meanwhile.
+        drop(lock);
+        watch_inbox(&beammm_dir, &mods_dir, &mut beamng_mod_cfg, &mut metadata)?;
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Run BeamMM with the given arguments on a BeamMM directory and mods directory in `dir`.
+    fn run_in(dir: &Path, args: &[&str]) -> beammm::Result<()> {
+        let mods_dir = dir.join("mods");
+        let mut all_args = vec!["beammm", "--mods-dir", mods_dir.to_str().unwrap()];
+        all_args.extend(["--game-version", "0.32"]);
+        all_args.extend(args);
+        let args = Args::parse_from(all_args);
+        let beammm_dir = dir.join("BeamMM");
+        fs::create_dir_all(&beammm_dir).unwrap();
+        run(args, Ok(beammm_dir), None)
+    }
+
+    /// Set up a mods directory with the identical mods `a` and `b`.
+    fn mods_with_duplicates(dir: &Path) -> PathBuf {
+        let mods_dir = dir.join("mods");
+        fs::create_dir_all(&mods_dir).unwrap();
+        fs::write(mods_dir.join("a.zip"), "same").unwrap();
+        fs::write(mods_dir.join("b.zip"), "same").unwrap();
+        let db_json = r#"{
+            "mods": {
+                "a": { "active": true, "filename": "a.zip" },
+                "b": { "active": true, "filename": "b.zip" }
+            }
+        }"#;
+        fs::write(mods_dir.join("db.json"), db_json).unwrap();
+        mods_dir
+    }
+
+    #[test]
+    fn dedup_with_missing_preset() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mods_dir = mods_with_duplicates(tmp.path());
+
+        run_in(
+            tmp.path(),
+            &["--dedup", "--delete-preset", "nosuch", "--permanent", "-y"],
+        )
+        .unwrap();
+
+        // The copy is only deleted along with saving db.json without it.
+        assert!(!mods_dir.join("b.zip").exists());
+        let mod_cfg = beammm::game::ModCfg::load_from_path(&mods_dir).unwrap();
+        let mods: Vec<&String> = mod_cfg.get_mods().collect();
+        assert_eq!(mods, vec!["a"]);
+    }
+
+    #[test]
+    fn dedup_rolled_back() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mods_dir = mods_with_duplicates(tmp.path());
+
+        // Shared presets can't be deleted, so the run fails after deduplicating.
+        let result = run_in(
+            tmp.path(),
+            &[
+                "--dedup",
+                "--delete-preset",
+                "shared/racing",
+                "--permanent",
+                "-y",
+            ],
+        );
+        assert!(matches!(result, Err(beammm::Error::ReadOnlyPreset { .. })));
+
+        assert!(mods_dir.join("b.zip").exists());
+        let mod_cfg = beammm::game::ModCfg::load_from_path(&mods_dir).unwrap();
+        assert_eq!(mod_cfg.get_mods().count(), 2);
+        let config = Config::load_from_path(tmp.path().join("BeamMM")).unwrap();
+        assert!(config.mod_aliases().is_empty());
+    }
+
+    #[test]
+    fn inbox_rolled_back() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mods_dir = mods_with_duplicates(tmp.path());
+        run_in(tmp.path(), &["--create-profile", "racing", "-y"]).unwrap();
+        let inbox_dir = tmp.path().join("BeamMM").join("inbox");
+        fs::create_dir_all(&inbox_dir).unwrap();
+        let mut zip = zip::ZipWriter::new(File::create(inbox_dir.join("new_mod.zip")).unwrap());
+        zip.start_file("info.json", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.finish().unwrap();
+
+        let result = run_in(tmp.path(), &["--create-profile", "racing", "-y"]);
+        assert!(matches!(result, Err(beammm::Error::ProfileExists { .. })));
+
+        // The archive is back in the inbox, so the next run registers it.
+        assert!(inbox_dir.join("new_mod.zip").exists());
+        assert!(!mods_dir.join("new_mod.zip").exists());
+        run_in(tmp.path(), &["-y"]).unwrap();
+        assert!(mods_dir.join("new_mod.zip").exists());
+        let mod_cfg = beammm::game::ModCfg::load_from_path(&mods_dir).unwrap();
+        assert!(mod_cfg.is_mod_active("new_mod").unwrap());
+    }
+
+    #[test]
+    fn quarantining_archives() {
+        let tmp = tempfile::tempdir().unwrap();
+        // The archives aren't valid zips.
+        let mods_dir = mods_with_duplicates(tmp.path());
+        let quarantine_dir = tmp.path().join("BeamMM").join("quarantine");
+
+        // Shared presets can't be deleted, so the run fails after quarantining.
+        let result = run_in(
+            tmp.path(),
+            &[
+                "--check-archives",
+                "--quarantine",
+                "--delete-preset",
+                "shared/racing",
+                "-y",
+            ],
+        );
+        assert!(matches!(result, Err(beammm::Error::ReadOnlyPreset { .. })));
+        assert!(mods_dir.join("a.zip").exists());
+        assert!(!quarantine_dir.join("a.zip").exists());
+
+        run_in(tmp.path(), &["--check-archives", "--quarantine", "-y"]).unwrap();
+        assert!(!mods_dir.join("a.zip").exists());
+        assert!(quarantine_dir.join("a.zip").exists());
+        // The game no longer looks for the quarantined archives.
+        let mod_cfg = beammm::game::ModCfg::load_from_path(&mods_dir).unwrap();
+        assert_eq!(mod_cfg.get_mods().count(), 0);
+    }
+
+    #[test]
+    fn quoted_launch_args() {
+        let tmp = tempfile::tempdir().unwrap();
+        mods_with_duplicates(tmp.path());
+        let launch_args = r#"-console -level "west coast""#;
+
+        run_in(tmp.path(), &["--create-preset", "racing", "-y"]).unwrap();
+        run_in(tmp.path(), &["--preset-launch-args", "racing", launch_args]).unwrap();
+
+        let presets_dir = tmp.path().join("BeamMM").join("presets");
+        let preset = beammm::Preset::load_from_path("racing", presets_dir).unwrap();
+        assert_eq!(preset.launch_args(), &["-console", "-level", "west coast"]);
+    }
+}
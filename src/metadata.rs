@@ -0,0 +1,774 @@
+use crate::{
+    game::{Management, ModCfg, RepoVersion},
+    GameVersion, Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// BeamMM's own per-mod metadata.
+///
+/// This data is stored in the BeamMM directory, separate from the game's db.json, so it never
+/// interferes with the game.
+///
+/// # Examples
+/// ```rust
+/// use beammm::metadata::MetadataDb;
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let beammm_dir = temp_dir.path();
+/// let mut metadata = MetadataDb::load_from_path(&beammm_dir).unwrap();
+/// metadata.set_note("mod1", Some("breaks with career mode".into()));
+/// metadata.save_to_path(&beammm_dir).unwrap();
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct MetadataDb {
+    /// Metadata for each mod, keyed by the mod's name in db.json.
+    #[serde(default)]
+    mods: HashMap<String, ModMetadata>,
+}
+
+/// BeamMM's metadata for a single mod.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct ModMetadata {
+    /// A free-text note attached to the mod.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+    /// Whether the mod is marked as a favorite.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    favorite: bool,
+    /// When the mod was installed, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    installed: Option<u64>,
+    /// When the mod was last updated, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    updated: Option<u64>,
+    /// When the mod was last enabled, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_enabled: Option<u64>,
+    /// The installed repository version, for mods installed from the repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    repo_version: Option<RepoVersion>,
+    /// Whether the mod is pinned to its installed version and must not be updated.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pinned: bool,
+    /// The last game version the mod was confirmed to work on, e.g. `0.32`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    confirmed_version: Option<String>,
+    /// Labels grouping the mod with other mods, e.g. `drift`.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    labels: BTreeSet<String>,
+    /// Whether the mod is kept enabled or disabled when presets are applied, regardless of what
+    /// the presets say.
+    #[serde(default, rename = "override", skip_serializing_if = "Option::is_none")]
+    override_active: Option<bool>,
+    /// How much say presets have over the mod, unless it is just observed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    management: Option<Management>,
+}
+
+/// Convert a time to seconds since the Unix epoch for storage.
+fn to_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
+
+/// Convert stored seconds since the Unix epoch back to a time.
+fn from_timestamp(timestamp: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(timestamp)
+}
+
+impl ModMetadata {
+    /// Get the note attached to the mod, if any.
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_deref()
+    }
+
+    /// Whether the mod is marked as a favorite.
+    pub fn is_favorite(&self) -> bool {
+        self.favorite
+    }
+
+    /// When the mod was installed. For mods installed outside of BeamMM, this is when BeamMM first
+    /// saw the mod.
+    pub fn installed(&self) -> Option<SystemTime> {
+        self.installed.map(from_timestamp)
+    }
+
+    /// When the mod was last updated.
+    pub fn updated(&self) -> Option<SystemTime> {
+        self.updated.map(from_timestamp)
+    }
+
+    /// When the mod was last enabled through BeamMM.
+    pub fn last_enabled(&self) -> Option<SystemTime> {
+        self.last_enabled.map(from_timestamp)
+    }
+
+    /// The installed repository version, for mods installed from the repository.
+    pub fn repo_version(&self) -> Option<&RepoVersion> {
+        self.repo_version.as_ref()
+    }
+
+    /// Whether the mod is pinned to its installed version.
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// The last game version the mod was confirmed to work on.
+    pub fn confirmed_version(&self) -> Option<GameVersion> {
+        self.confirmed_version.as_deref()?.parse().ok()
+    }
+
+    /// The labels attached to the mod, sorted.
+    pub fn labels(&self) -> impl Iterator<Item = &String> {
+        self.labels.iter()
+    }
+
+    /// Whether the mod is kept enabled or disabled when presets are applied, if it is.
+    pub fn override_active(&self) -> Option<bool> {
+        self.override_active
+    }
+
+    /// How much say presets have over the mod.
+    pub fn management(&self) -> Management {
+        self.management.unwrap_or_default()
+    }
+
+    /// Whether this metadata holds no data and therefore doesn't need to be stored.
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl MetadataDb {
+    /// The filename of the metadata file.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    fn filename() -> PathBuf {
+        PathBuf::from("metadata.json")
+    }
+
+    /// Load the metadata from a reader.
+    ///
+    /// # Arguments
+    ///
+    /// `reader`: The reader to load the metadata from.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue reading or deserializing the metadata.
+    pub fn load<R: BufRead>(reader: R) -> Result<Self> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Load the metadata from a file. If the file doesn't exist yet, empty metadata is returned.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The BeamMM directory where the metadata file is stored.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading the file or serde_json errors if there is
+    /// an issue deserializing the metadata.
+    pub fn load_from_path(beammm_dir: impl AsRef<Path>) -> Result<Self> {
+        let beammm_dir = beammm_dir.as_ref();
+        let path = beammm_dir.join(Self::filename());
+        if path.try_exists()? {
+            let file = File::open(path)?;
+            let reader = BufReader::new(file);
+            Self::load(reader)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Serialize and save the metadata to a writer.
+    ///
+    /// # Arguments
+    ///
+    /// `writer`: The writer to save the metadata to.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue serializing the metadata or writing.
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Serialize and save the metadata to a file.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The BeamMM directory where the metadata file will be saved.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue creating the file or writing to it.
+    /// Possible serde_json errors if there is an issue serializing the metadata.
+    pub fn save_to_path(&self, beammm_dir: impl AsRef<Path>) -> Result<()> {
+        let beammm_dir = beammm_dir.as_ref();
+        let file = File::create(beammm_dir.join(Self::filename()))?;
+        let writer = BufWriter::new(file);
+        self.save(writer)
+    }
+
+    /// Get the metadata of a mod.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    ///
+    /// # Returns
+    ///
+    /// `Some(&ModMetadata)`: The mod's metadata if any is stored.
+    /// `None`: If no metadata is stored for the mod.
+    pub fn get(&self, mod_name: &str) -> Option<&ModMetadata> {
+        self.mods.get(mod_name)
+    }
+
+    /// Modify the metadata of a mod, creating it if needed. Entries left empty are removed.
+    fn update(&mut self, mod_name: &str, f: impl FnOnce(&mut ModMetadata)) {
+        let entry = self.mods.entry(mod_name.into()).or_default();
+        f(entry);
+        if entry.is_empty() {
+            self.mods.remove(mod_name);
+        }
+    }
+
+    /// Get the note attached to a mod, if any.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    pub fn note(&self, mod_name: &str) -> Option<&str> {
+        self.get(mod_name).and_then(ModMetadata::note)
+    }
+
+    /// Attach a note to a mod, replacing any existing note. Pass `None` to remove the note.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    /// `note`: The note to attach.
+    pub fn set_note(&mut self, mod_name: &str, note: Option<String>) {
+        self.update(mod_name, |m| m.note = note)
+    }
+
+    /// Check whether a mod is marked as a favorite.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    pub fn is_favorite(&self, mod_name: &str) -> bool {
+        self.get(mod_name).is_some_and(ModMetadata::is_favorite)
+    }
+
+    /// Mark or unmark a mod as a favorite.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    /// `favorite`: Whether the mod should be a favorite.
+    pub fn set_favorite(&mut self, mod_name: &str, favorite: bool) {
+        self.update(mod_name, |m| m.favorite = favorite)
+    }
+
+    /// Attach a label to a mod or remove it.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    /// `label`: The label.
+    /// `labeled`: Whether the mod should have the label.
+    pub fn set_label(&mut self, mod_name: &str, label: &str, labeled: bool) {
+        self.update(mod_name, |m| {
+            if labeled {
+                m.labels.insert(label.into());
+            } else {
+                m.labels.remove(label);
+            }
+        })
+    }
+
+    /// Get the names of the mods with a label, sorted.
+    ///
+    /// # Arguments
+    ///
+    /// `label`: The label.
+    pub fn with_label(&self, label: &str) -> Vec<String> {
+        let mut mods: Vec<String> = self
+            .mods
+            .iter()
+            .filter(|(_, m)| m.labels.contains(label))
+            .map(|(name, _)| name.clone())
+            .collect();
+        mods.sort();
+        mods
+    }
+
+    /// Record when mods were installed. Mods that already have an install time recorded keep it.
+    ///
+    /// Call this with every installed mod whenever the mod configuration is loaded so mods
+    /// installed outside of BeamMM get the time BeamMM first saw them.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_names`: The names of the installed mods.
+    /// `time`: The time to record.
+    pub fn record_installed<'a>(
+        &mut self,
+        mod_names: impl IntoIterator<Item = &'a String>,
+        time: SystemTime,
+    ) {
+        for mod_name in mod_names {
+            self.update(mod_name, |m| {
+                m.installed.get_or_insert(to_timestamp(time));
+            })
+        }
+    }
+
+    /// Record when a mod was last updated.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    /// `time`: The time to record.
+    pub fn record_updated(&mut self, mod_name: &str, time: SystemTime) {
+        self.update(mod_name, |m| m.updated = Some(to_timestamp(time)))
+    }
+
+    /// Record when mods were last enabled.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_names`: The names of the mods that were enabled.
+    /// `time`: The time to record.
+    pub fn record_enabled<'a>(
+        &mut self,
+        mod_names: impl IntoIterator<Item = &'a String>,
+        time: SystemTime,
+    ) {
+        for mod_name in mod_names {
+            self.update(mod_name, |m| m.last_enabled = Some(to_timestamp(time)))
+        }
+    }
+
+    /// Get the installed repository version of a mod, if it was installed from the repository.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    pub fn repo_version(&self, mod_name: &str) -> Option<&RepoVersion> {
+        self.get(mod_name).and_then(ModMetadata::repo_version)
+    }
+
+    /// Set the installed repository version of a mod. Pass `None` to forget it.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    /// `repo_version`: The installed repository version.
+    pub fn set_repo_version(&mut self, mod_name: &str, repo_version: Option<RepoVersion>) {
+        self.update(mod_name, |m| m.repo_version = repo_version)
+    }
+
+    /// Check whether a mod is pinned to its installed version. Updates must skip pinned mods.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    pub fn is_pinned(&self, mod_name: &str) -> bool {
+        self.get(mod_name).is_some_and(ModMetadata::is_pinned)
+    }
+
+    /// Pin or unpin a mod to its installed version.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    /// `pinned`: Whether the mod should be pinned.
+    pub fn set_pinned(&mut self, mod_name: &str, pinned: bool) {
+        self.update(mod_name, |m| m.pinned = pinned)
+    }
+
+    /// Get whether a mod is kept enabled or disabled when presets are applied, if it is.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    pub fn override_of(&self, mod_name: &str) -> Option<bool> {
+        self.get(mod_name).and_then(|m| m.override_active)
+    }
+
+    /// Keep a mod enabled or disabled when presets are applied, or pass `None` to let presets
+    /// change it again.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    /// `active`: Whether the mod is kept enabled or disabled.
+    pub fn set_override(&mut self, mod_name: &str, active: Option<bool>) {
+        self.update(mod_name, |m| m.override_active = active)
+    }
+
+    /// Get the mods presets must leave alone along with whether they are kept enabled or
+    /// disabled, sorted by name. Suitable for `ModCfg::set_overrides`.
+    pub fn overrides(&self) -> Vec<(String, bool)> {
+        let mut overrides: Vec<(String, bool)> = self
+            .mods
+            .iter()
+            .filter_map(|(name, m)| Some((name.clone(), m.override_active?)))
+            .collect();
+        overrides.sort();
+        overrides
+    }
+
+    /// Set how much say presets have over a mod.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    /// `management`: How the mod is managed.
+    pub fn set_management(&mut self, mod_name: &str, management: Management) {
+        let management = (management != Management::Observed).then_some(management);
+        self.update(mod_name, |m| m.management = management)
+    }
+
+    /// Get the mods that aren't just observed along with how they are managed, sorted by name.
+    /// Suitable for `ModCfg::set_management`.
+    pub fn management(&self) -> Vec<(String, Management)> {
+        let mut management: Vec<(String, Management)> = self
+            .mods
+            .iter()
+            .filter_map(|(name, m)| Some((name.clone(), m.management?)))
+            .collect();
+        management.sort_by(|a, b| a.0.cmp(&b.0));
+        management
+    }
+
+    /// Get the last game version a mod was confirmed to work on.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    pub fn confirmed_version(&self, mod_name: &str) -> Option<GameVersion> {
+        self.get(mod_name).and_then(ModMetadata::confirmed_version)
+    }
+
+    /// Set the last game version a mod was confirmed to work on. Pass `None` to forget it.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    /// `version`: The game version the mod works on.
+    pub fn set_confirmed_version(&mut self, mod_name: &str, version: Option<GameVersion>) {
+        self.update(mod_name, |m| {
+            m.confirmed_version = version.map(|v| v.to_string())
+        })
+    }
+
+    /// Get the mods that were last confirmed to work on an older game version than the current
+    /// one. Mods that were never confirmed are left out.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_names`: The names of the mods to check.
+    /// `version`: The current game version.
+    ///
+    /// # Returns
+    ///
+    /// The outdated mods with the version they were last confirmed on.
+    pub fn unconfirmed_on<'a>(
+        &self,
+        mod_names: impl IntoIterator<Item = &'a String>,
+        version: &GameVersion,
+    ) -> Vec<(String, GameVersion)> {
+        mod_names
+            .into_iter()
+            .filter_map(|m| {
+                let confirmed = self.confirmed_version(m)?;
+                (confirmed < *version).then(|| (m.clone(), confirmed))
+            })
+            .collect()
+    }
+
+    /// Record the repository versions the game reports for installed repository mods.
+    ///
+    /// Mods whose repository version changed since it was last recorded are marked as updated.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg to read the repository versions from.
+    /// `time`: The time to record as the update time for updated mods.
+    ///
+    /// # Returns
+    ///
+    /// The mods that were newly installed or updated from the repository.
+    pub fn sync_repo_versions(&mut self, mod_config: &ModCfg, time: SystemTime) -> Vec<String> {
+        let mut downloaded = vec![];
+        for mod_name in mod_config.get_mods() {
+            let Some(repo_version) = mod_config.repo_version(mod_name) else {
+                continue;
+            };
+            match self.repo_version(mod_name) {
+                Some(known) if known.version_id != repo_version.version_id => {
+                    self.record_updated(mod_name, time);
+                    downloaded.push(mod_name.clone());
+                }
+                Some(known) if *known == repo_version => continue,
+                Some(_) => (),
+                None => downloaded.push(mod_name.clone()),
+            }
+            self.set_repo_version(mod_name, Some(repo_version));
+        }
+        downloaded
+    }
+
+    /// Merge metadata from another MetadataDb into this one. A mod's metadata in `other` replaces
+    /// the metadata stored for it here.
+    ///
+    /// # Arguments
+    ///
+    /// `other`: The metadata to merge in.
+    pub fn merge(&mut self, other: MetadataDb) {
+        self.mods.extend(other.mods);
+    }
+
+    /// Move a mod's metadata to a new name after the mod was renamed.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The old name of the mod.
+    /// `new_name`: The new name of the mod.
+    pub fn rename_mod(&mut self, mod_name: &str, new_name: &str) {
+        if let Some(metadata) = self.mods.remove(mod_name) {
+            self.mods.insert(new_name.into(), metadata);
+        }
+    }
+
+    /// Get an iterator over the names of all favorite mods.
+    pub fn favorites(&self) -> impl Iterator<Item = &String> {
+        self.mods
+            .iter()
+            .filter(|(_, m)| m.is_favorite())
+            .map(|(name, _)| name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_missing_metadata() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let metadata = MetadataDb::load_from_path(tmp.path()).unwrap();
+
+        assert!(metadata.mods.is_empty());
+    }
+
+    #[test]
+    fn saving_and_loading_notes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let beammm_dir = tmp.path();
+
+        let mut metadata = MetadataDb::default();
+        metadata.set_note("mod1", Some("breaks with career mode".into()));
+        metadata.save_to_path(beammm_dir).unwrap();
+
+        let metadata = MetadataDb::load_from_path(beammm_dir).unwrap();
+        assert_eq!(metadata.note("mod1"), Some("breaks with career mode"));
+        assert_eq!(metadata.note("mod2"), None);
+    }
+
+    #[test]
+    fn removing_note_removes_empty_entry() {
+        let mut metadata = MetadataDb::default();
+
+        metadata.set_note("mod1", Some("note".into()));
+        metadata.set_note("mod1", None);
+
+        assert!(metadata.get("mod1").is_none());
+    }
+
+    #[test]
+    fn favorites() {
+        let mut metadata = MetadataDb::default();
+
+        metadata.set_favorite("mod1", true);
+        metadata.set_favorite("mod2", true);
+        metadata.set_note("mod3", Some("not a favorite".into()));
+        metadata.set_favorite("mod2", false);
+
+        assert!(metadata.is_favorite("mod1"));
+        assert!(!metadata.is_favorite("mod2"));
+        assert!(!metadata.is_favorite("mod3"));
+        assert_eq!(metadata.favorites().collect::<Vec<_>>(), vec!["mod1"]);
+        // Unfavoriting should leave no empty entry behind.
+        assert!(metadata.get("mod2").is_none());
+    }
+
+    #[test]
+    fn recording_timestamps() {
+        let tmp = tempfile::tempdir().unwrap();
+        let beammm_dir = tmp.path();
+        let first = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let later = first + Duration::from_secs(60);
+        let mods: Vec<String> = vec!["mod1".into(), "mod2".into()];
+
+        let mut metadata = MetadataDb::default();
+        metadata.record_installed(&mods, first);
+        // Recording again shouldn't overwrite the original install time.
+        metadata.record_installed(&mods, later);
+        metadata.record_updated("mod1", later);
+        metadata.record_enabled(&mods[1..], later);
+        metadata.save_to_path(beammm_dir).unwrap();
+
+        let metadata = MetadataDb::load_from_path(beammm_dir).unwrap();
+        let mod1 = metadata.get("mod1").unwrap();
+        let mod2 = metadata.get("mod2").unwrap();
+        assert_eq!(mod1.installed(), Some(first));
+        assert_eq!(mod1.updated(), Some(later));
+        assert_eq!(mod1.last_enabled(), None);
+        assert_eq!(mod2.updated(), None);
+        assert_eq!(mod2.last_enabled(), Some(later));
+    }
+
+    #[test]
+    fn syncing_repo_versions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mods_dir = tmp.path();
+        let first = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let later = first + Duration::from_secs(60);
+        let db_json = |version_id: u64| {
+            format!(
+                r#"{{"mods":{{"mod1":{{"active":true,"modData":{{"resource_id":1,"current_version_id":{},"version_string":"1.{}"}}}},"mod2":{{"active":true}}}}}}"#,
+                version_id, version_id
+            )
+        };
+
+        let mut metadata = MetadataDb::default();
+
+        std::fs::write(mods_dir.join("db.json"), db_json(1)).unwrap();
+        let mod_cfg = ModCfg::load_from_path(mods_dir).unwrap();
+        assert_eq!(metadata.sync_repo_versions(&mod_cfg, first), vec!["mod1"]);
+
+        assert_eq!(metadata.repo_version("mod1").unwrap().version_id, 1);
+        assert!(metadata.repo_version("mod2").is_none());
+        // The first sighting isn't an update.
+        assert!(metadata.get("mod1").unwrap().updated().is_none());
+
+        std::fs::write(mods_dir.join("db.json"), db_json(2)).unwrap();
+        let mod_cfg = ModCfg::load_from_path(mods_dir).unwrap();
+        assert_eq!(metadata.sync_repo_versions(&mod_cfg, later), vec!["mod1"]);
+        assert!(metadata.sync_repo_versions(&mod_cfg, later).is_empty());
+
+        let repo_version = metadata.repo_version("mod1").unwrap();
+        assert_eq!(repo_version.version_id, 2);
+        assert_eq!(repo_version.version.as_deref(), Some("1.2"));
+        assert_eq!(metadata.get("mod1").unwrap().updated(), Some(later));
+    }
+
+    #[test]
+    fn confirmed_versions() {
+        let mut metadata = MetadataDb::default();
+        let mods = vec!["mod1".to_string(), "mod2".into(), "mod3".into()];
+
+        metadata.set_confirmed_version("mod1", Some("0.31".parse().unwrap()));
+        metadata.set_confirmed_version("mod2", Some("0.32".parse().unwrap()));
+        assert_eq!(
+            metadata.confirmed_version("mod1"),
+            Some("0.31".parse().unwrap())
+        );
+
+        assert_eq!(
+            metadata.unconfirmed_on(&mods, &"0.32".parse().unwrap()),
+            vec![("mod1".to_string(), "0.31".parse().unwrap())]
+        );
+        assert!(metadata
+            .unconfirmed_on(&mods, &"0.31".parse().unwrap())
+            .is_empty());
+
+        metadata.set_confirmed_version("mod1", None);
+        assert!(metadata.get("mod1").is_none());
+    }
+
+    #[test]
+    fn labels() {
+        let mut metadata = MetadataDb::default();
+
+        metadata.set_label("mod2", "drift", true);
+        metadata.set_label("mod1", "drift", true);
+        metadata.set_label("mod1", "maps", true);
+        assert_eq!(metadata.with_label("drift"), vec!["mod1", "mod2"]);
+        assert_eq!(
+            metadata.get("mod1").unwrap().labels().collect::<Vec<_>>(),
+            vec!["drift", "maps"]
+        );
+
+        metadata.set_label("mod2", "drift", false);
+        assert_eq!(metadata.with_label("drift"), vec!["mod1"]);
+        assert!(metadata.get("mod2").is_none());
+    }
+
+    #[test]
+    fn management() {
+        let mut metadata = MetadataDb::default();
+        metadata.set_management("mod2", Management::Ignored);
+        metadata.set_management("mod1", Management::Managed);
+        metadata.set_management("mod3", Management::Observed);
+        assert_eq!(
+            metadata.management(),
+            vec![
+                ("mod1".to_string(), Management::Managed),
+                ("mod2".to_string(), Management::Ignored)
+            ]
+        );
+
+        metadata.set_management("mod1", Management::Observed);
+        assert_eq!(metadata.management().len(), 1);
+        assert!(metadata.get("mod1").is_none());
+    }
+
+    #[test]
+    fn overrides() {
+        let mut metadata = MetadataDb::default();
+        metadata.set_override("mod2", Some(false));
+        metadata.set_override("mod1", Some(true));
+        assert_eq!(metadata.override_of("mod2"), Some(false));
+        assert_eq!(
+            metadata.overrides(),
+            vec![("mod1".to_string(), true), ("mod2".to_string(), false)]
+        );
+
+        metadata.set_override("mod1", None);
+        metadata.set_override("mod2", None);
+        assert!(metadata.overrides().is_empty());
+        assert!(metadata.mods.is_empty());
+    }
+
+    #[test]
+    fn pinning() {
+        let mut metadata = MetadataDb::default();
+
+        metadata.set_pinned("mod1", true);
+        assert!(metadata.is_pinned("mod1"));
+        assert!(!metadata.is_pinned("mod2"));
+
+        metadata.set_pinned("mod1", false);
+        assert!(!metadata.is_pinned("mod1"));
+        assert!(metadata.get("mod1").is_none());
+    }
+}
@@ -0,0 +1,249 @@
+use crate::{Error::*, Preset, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// The version of the BeamMM directory layout written by this version of BeamMM.
+pub const DATA_VERSION: u32 = 1;
+
+/// The name of the file in the BeamMM directory that holds its version.
+const MARKER: &str = "data-version";
+
+/// Entries of the BeamMM directory that aren't backed up before migrating, since they can be
+/// large and migrations don't change them, or, like the lock file, are held by the running
+/// BeamMM.
+const NOT_BACKED_UP: [&str; 7] = [
+    "backups",
+    "beammm.lock",
+    "crash-reports",
+    "inbox",
+    "logs",
+    "mirror",
+    "quarantine",
+];
+
+/// Upgrades from each older BeamMM directory version to the next, indexed by the version they
+/// upgrade from.
+const MIGRATIONS: [fn(&Path) -> Result<()>; DATA_VERSION as usize] = [upgrade_presets];
+
+/// A migration of the BeamMM directory done by `migrate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration {
+    /// The version the directory was migrated from.
+    pub from: u32,
+    /// The version the directory was migrated to.
+    pub to: u32,
+    /// Where the directory was backed up to before migrating.
+    pub backup: PathBuf,
+}
+
+/// Get the version of a BeamMM directory. Directories from before the version was recorded are
+/// version 0.
+///
+/// # Arguments
+///
+/// `beammm_dir`: The path to the BeamMM directory.
+///
+/// # Errors
+///
+/// * `std::io::Error`: If there is an issue reading the version.
+/// * `UnsupportedDataVersion`: If the version can't be parsed.
+pub fn data_version(beammm_dir: impl AsRef<Path>) -> Result<u32> {
+    let beammm_dir = beammm_dir.as_ref();
+    let marker = beammm_dir.join(MARKER);
+    if !marker.try_exists()? {
+        return Ok(0);
+    }
+    let version = fs::read_to_string(&marker)?;
+    version.trim().parse().map_err(|_| UnsupportedDataVersion {
+        dir: beammm_dir.into(),
+        version: version.trim().into(),
+    })
+}
+
+/// Upgrade a BeamMM directory written by an older version of BeamMM to the current layout.
+///
+/// Everything migrations may change is copied to `backups/migrations` first, so the old state
+/// can be recovered by hand if a migration goes wrong. New directories are only marked with the
+/// current version.
+///
+/// # Arguments
+///
+/// `beammm_dir`: The path to the BeamMM directory.
+/// `now`: The current time, used to name the backup.
+///
+/// # Returns
+///
+/// The migration that was done, or `None` if the directory was already up to date or new.
+///
+/// # Errors
+///
+/// * `UnsupportedDataVersion`: If the directory was written by a newer version of BeamMM.
+/// * `std::io::Error`: If there is an issue backing up or migrating the directory.
+/// * `serde_json::Error`: If there is an issue upgrading a file.
+///
+/// # Examples
+/// ```rust
+/// use beammm::migrations::{self, DATA_VERSION};
+/// use std::time::SystemTime;
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let beammm_dir = temp_dir.path();
+/// std::fs::write(beammm_dir.join("config.json"), "{}").unwrap();
+///
+/// let migration = migrations::migrate(&beammm_dir, SystemTime::now()).unwrap().unwrap();
+/// assert_eq!(migration.from, 0);
+/// assert!(migration.backup.join("config.json").exists());
+/// assert_eq!(migrations::data_version(&beammm_dir).unwrap(), DATA_VERSION);
+/// assert!(migrations::migrate(&beammm_dir, SystemTime::now()).unwrap().is_none());
+/// ```
+pub fn migrate(beammm_dir: impl AsRef<Path>, now: SystemTime) -> Result<Option<Migration>> {
+    let beammm_dir = beammm_dir.as_ref();
+    let from = data_version(beammm_dir)?;
+    if from > DATA_VERSION {
+        return Err(UnsupportedDataVersion {
+            dir: beammm_dir.into(),
+            version: from.to_string(),
+        });
+    }
+    if from == DATA_VERSION {
+        return Ok(None);
+    }
+
+    let mut entries = vec![];
+    if beammm_dir.try_exists()? {
+        for entry in fs::read_dir(beammm_dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if !NOT_BACKED_UP.iter().any(|n| name == *n) {
+                entries.push(entry.path());
+            }
+        }
+    }
+    if entries.is_empty() {
+        fs::create_dir_all(beammm_dir)?;
+        fs::write(beammm_dir.join(MARKER), DATA_VERSION.to_string())?;
+        return Ok(None);
+    }
+
+    // Colons aren't allowed in filenames on Windows.
+    let stamp = humantime::format_rfc3339_seconds(now)
+        .to_string()
+        .replace(':', "");
+    let backup = beammm_dir
+        .join("backups")
+        .join("migrations")
+        .join(format!("v{}-{}", from, stamp));
+    for entry in entries {
+        if let Some(name) = entry.file_name() {
+            copy_all(&entry, &backup.join(name))?;
+        }
+    }
+
+    for migration in MIGRATIONS.iter().skip(from as usize) {
+        migration(beammm_dir)?;
+    }
+    fs::write(beammm_dir.join(MARKER), DATA_VERSION.to_string())?;
+    Ok(Some(Migration {
+        from,
+        to: DATA_VERSION,
+        backup,
+    }))
+}
+
+/// Copy a file, or a directory and everything in it.
+fn copy_all(from: &Path, to: &Path) -> Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_all(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+/// Version 0 to 1: save every preset, including those of other profiles, in the current preset
+/// file format. Presets that can't be read are left as they are, to be reported and fixed like
+/// any other broken preset.
+fn upgrade_presets(beammm_dir: &Path) -> Result<()> {
+    let mut presets_dirs = vec![beammm_dir.join("presets")];
+    let profiles_dir = beammm_dir.join("profiles");
+    if profiles_dir.try_exists()? {
+        for entry in fs::read_dir(profiles_dir)? {
+            presets_dirs.push(entry?.path().join("presets"));
+        }
+    }
+    for presets_dir in presets_dirs {
+        if !presets_dir.is_dir() {
+            continue;
+        }
+        for name in Preset::list(&presets_dir)? {
+            if let Ok(preset) = Preset::load_from_path(&name, &presets_dir) {
+                preset.save_to_path(&presets_dir)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn migrating_data_dirs() {
+        let temp_dir = tempdir().unwrap();
+        let beammm_dir = temp_dir.path();
+        let presets_dir = beammm_dir.join("profiles").join("racing").join("presets");
+        fs::create_dir_all(&presets_dir).unwrap();
+        let unversioned = r#"{"name":"old","mods":["mod1"],"enabled":true}"#;
+        fs::write(presets_dir.join("old.json"), unversioned).unwrap();
+        fs::write(presets_dir.join("broken.json"), "{").unwrap();
+        fs::create_dir_all(beammm_dir.join("mirror")).unwrap();
+        let now = SystemTime::now();
+
+        let migration = migrate(beammm_dir, now).unwrap().unwrap();
+        assert_eq!((migration.from, migration.to), (0, DATA_VERSION));
+        // The old state is kept, except for what migrations don't touch.
+        let backed_up = migration.backup.join("profiles/racing/presets/old.json");
+        assert_eq!(fs::read_to_string(backed_up).unwrap(), unversioned);
+        assert!(!migration.backup.join("mirror").exists());
+        // The preset is saved in the current format.
+        let upgraded = fs::read_to_string(presets_dir.join("old.json")).unwrap();
+        assert!(upgraded.contains("format_version"));
+        // Broken presets don't stop the migration.
+        assert_eq!(
+            fs::read_to_string(presets_dir.join("broken.json")).unwrap(),
+            "{"
+        );
+        assert_eq!(data_version(beammm_dir).unwrap(), DATA_VERSION);
+        assert!(migrate(beammm_dir, now).unwrap().is_none());
+
+        // Directories of newer versions aren't touched.
+        fs::write(beammm_dir.join(MARKER), "999").unwrap();
+        assert!(matches!(
+            migrate(beammm_dir, now),
+            Err(UnsupportedDataVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn marking_new_data_dirs() {
+        let temp_dir = tempdir().unwrap();
+        let beammm_dir = temp_dir.path().join("BeamMM");
+
+        assert!(migrate(&beammm_dir, SystemTime::now()).unwrap().is_none());
+        assert_eq!(data_version(&beammm_dir).unwrap(), DATA_VERSION);
+        assert!(!beammm_dir.join("backups").exists());
+    }
+}
@@ -0,0 +1,330 @@
+use crate::{
+    game::{ModCfg, RepoVersion},
+    Result,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Copy the archive of every installed repository mod into the mirror.
+///
+/// The game downloads repository mods into `mods/repo`. The mirror keeps a copy of every version
+/// of those archives, laid out as `<mirror_dir>/<resource_id>/<version_id>/<archive>`, so they can
+/// be reinstalled without the repository, e.g. when offline or when a mod is delisted. Versions
+/// that are already mirrored are left alone, so older versions accumulate over time.
+/// Repository mods whose archive can't be found are skipped.
+///
+/// # Arguments
+///
+/// `mod_config`: The ModCfg listing the installed mods.
+/// `mods_dir`: The mods directory of the current game version.
+/// `mirror_dir`: The directory the mirror is kept in.
+///
+/// # Returns
+///
+/// The paths of the newly mirrored archives.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue creating mirror directories or copying archives.
+pub fn sync(
+    mod_config: &ModCfg,
+    mods_dir: impl AsRef<Path>,
+    mirror_dir: impl AsRef<Path>,
+) -> Result<Vec<PathBuf>> {
+    let mods_dir = mods_dir.as_ref();
+    let mirror_dir = mirror_dir.as_ref();
+    let mut mirrored = vec![];
+    for mod_name in mod_config.get_mods() {
+        let Some(repo_version) = mod_config.repo_version(mod_name) else {
+            continue;
+        };
+        let Some(archive) = mod_config.archive_path(mod_name, mods_dir) else {
+            continue;
+        };
+        let Some(file_name) = archive.file_name() else {
+            continue;
+        };
+        if !archive.is_file() {
+            continue;
+        }
+
+        let version_dir = mirror_dir
+            .join(repo_version.resource_id.to_string())
+            .join(repo_version.version_id.to_string());
+        let target = version_dir.join(file_name);
+        if target.try_exists()? {
+            continue;
+        }
+        fs::create_dir_all(&version_dir)?;
+        fs::copy(&archive, &target)?;
+        mirrored.push(target);
+    }
+    mirrored.sort();
+    Ok(mirrored)
+}
+
+/// Reinstall repository mods from the mirror into `mods/repo`.
+///
+/// For each mirrored mod, the version the game last recorded as installed is restored if it is
+/// mirrored, otherwise the newest mirrored version is. Archives that already exist in the mods
+/// directory are never overwritten. The game registers restored mods the next time it starts.
+///
+/// # Arguments
+///
+/// `mod_config`: The ModCfg used to find the versions last installed.
+/// `mods_dir`: The mods directory of the current game version.
+/// `mirror_dir`: The directory the mirror is kept in.
+///
+/// # Returns
+///
+/// The paths of the restored archives.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading the mirror or copying archives.
+pub fn restore(
+    mod_config: &ModCfg,
+    mods_dir: impl AsRef<Path>,
+    mirror_dir: impl AsRef<Path>,
+) -> Result<Vec<PathBuf>> {
+    let mods_dir = mods_dir.as_ref();
+    let mirror_dir = mirror_dir.as_ref();
+    let repo_dir = mods_dir.join("repo");
+    let mut restored = vec![];
+
+    for (resource_id, resource_dir) in numbered_dirs(mirror_dir)? {
+        let versions = numbered_dirs(&resource_dir)?;
+        let installed_version = mod_config
+            .get_mods()
+            .filter_map(|m| mod_config.repo_version(m))
+            .find(|v| v.resource_id == resource_id)
+            .map(|v| v.version_id);
+        let version_dir = versions
+            .iter()
+            .find(|(id, _)| Some(*id) == installed_version)
+            .or(versions.last()); // Versions are sorted, so the last is the newest.
+        let Some((_, version_dir)) = version_dir else {
+            continue;
+        };
+
+        restored.extend(copy_archives(version_dir, &repo_dir)?);
+    }
+    restored.sort();
+    Ok(restored)
+}
+
+/// A repository mod version installed from the mirror by `install`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Installed {
+    /// The repository's ID of the installed version, which differs from the one asked for if that
+    /// version isn't mirrored.
+    pub version_id: u64,
+    /// The paths of the installed archives.
+    pub archives: Vec<PathBuf>,
+}
+
+/// Install a specific repository mod version from the mirror into `mods/repo`.
+///
+/// If the exact version isn't mirrored, the newest mirrored version of the mod is installed
+/// instead, so compare the installed version with the one asked for. Archives that already exist
+/// in the mods directory are never overwritten. The game registers installed mods the next time
+/// it starts, or register them with `ModCfg::register_archive` to manage them right away.
+///
+/// # Arguments
+///
+/// `repo_version`: The repository mod and version to install.
+/// `mods_dir`: The mods directory of the current game version.
+/// `mirror_dir`: The directory the mirror is kept in.
+///
+/// # Returns
+///
+/// `Some(Installed)`: The installed version and archives if the mod is mirrored.
+/// `None`: If no version of the mod is mirrored.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading the mirror or copying archives.
+pub fn install(
+    repo_version: &RepoVersion,
+    mods_dir: impl AsRef<Path>,
+    mirror_dir: impl AsRef<Path>,
+) -> Result<Option<Installed>> {
+    let mods_dir = mods_dir.as_ref();
+    let mirror_dir = mirror_dir.as_ref();
+    let resource_dir = mirror_dir.join(repo_version.resource_id.to_string());
+    if !resource_dir.is_dir() {
+        return Ok(None);
+    }
+    let versions = numbered_dirs(&resource_dir)?;
+    let version_dir = versions
+        .iter()
+        .find(|(id, _)| *id == repo_version.version_id)
+        .or(versions.last()); // Versions are sorted, so the last is the newest.
+    match version_dir {
+        Some((version_id, version_dir)) => Ok(Some(Installed {
+            version_id: *version_id,
+            archives: copy_archives(version_dir, &mods_dir.join("repo"))?,
+        })),
+        None => Ok(None),
+    }
+}
+
+/// Copy every archive in a mirrored version directory into `repo_dir`, skipping archives that
+/// already exist there.
+fn copy_archives(version_dir: &Path, repo_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut copied = vec![];
+    for archive in fs::read_dir(version_dir)? {
+        let archive = archive?.path();
+        let Some(file_name) = archive.file_name() else {
+            continue;
+        };
+        let target = repo_dir.join(file_name);
+        if !archive.is_file() || target.try_exists()? {
+            continue;
+        }
+        fs::create_dir_all(repo_dir)?;
+        fs::copy(&archive, &target)?;
+        copied.push(target);
+    }
+    Ok(copied)
+}
+
+/// Get the subdirectories of `dir` whose names are numeric IDs, sorted by ID.
+fn numbered_dirs(dir: &Path) -> Result<Vec<(u64, PathBuf)>> {
+    let mut dirs: Vec<(u64, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|d| d.ok().map(|d| d.path())) // Get rid of errors and map to path type
+        .filter(|d| d.is_dir())
+        .filter_map(|d| {
+            let id = d.file_name()?.to_str()?.parse().ok()?;
+            Some((id, d))
+        })
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a db.json with a single repository mod at the given version, along with its archive.
+    fn install_repo_mod(mods_dir: &Path, version_id: u64) -> ModCfg {
+        let db_json = format!(
+            r#"{{"mods":{{"repo_mod":{{"active":true,"fullpath":"/mods/repo/repo_mod.zip","modData":{{"resource_id":7,"current_version_id":{}}}}}}}}}"#,
+            version_id
+        );
+        fs::write(mods_dir.join("db.json"), db_json).unwrap();
+        fs::create_dir_all(mods_dir.join("repo")).unwrap();
+        fs::write(
+            mods_dir.join("repo").join("repo_mod.zip"),
+            format!("version {}", version_id),
+        )
+        .unwrap();
+        ModCfg::load_from_path(mods_dir).unwrap()
+    }
+
+    #[test]
+    fn syncing_keeps_every_version() {
+        let mods_tmp = tempfile::tempdir().unwrap();
+        let mirror_tmp = tempfile::tempdir().unwrap();
+        let (mods_dir, mirror_dir) = (mods_tmp.path(), mirror_tmp.path());
+
+        let mod_cfg = install_repo_mod(mods_dir, 1);
+        let mirrored = sync(&mod_cfg, mods_dir, mirror_dir).unwrap();
+        assert_eq!(mirrored, vec![mirror_dir.join("7/1/repo_mod.zip")]);
+
+        // Syncing again without changes mirrors nothing new.
+        assert!(sync(&mod_cfg, mods_dir, mirror_dir).unwrap().is_empty());
+
+        let mod_cfg = install_repo_mod(mods_dir, 2);
+        sync(&mod_cfg, mods_dir, mirror_dir).unwrap();
+        assert!(mirror_dir.join("7/1/repo_mod.zip").exists());
+        assert!(mirror_dir.join("7/2/repo_mod.zip").exists());
+    }
+
+    #[test]
+    fn restoring_installed_version() {
+        let mods_tmp = tempfile::tempdir().unwrap();
+        let mirror_tmp = tempfile::tempdir().unwrap();
+        let (mods_dir, mirror_dir) = (mods_tmp.path(), mirror_tmp.path());
+
+        let mod_cfg = install_repo_mod(mods_dir, 2);
+        sync(&mod_cfg, mods_dir, mirror_dir).unwrap();
+        let mod_cfg = install_repo_mod(mods_dir, 1);
+        sync(&mod_cfg, mods_dir, mirror_dir).unwrap();
+
+        // Restoring doesn't overwrite existing archives.
+        assert!(restore(&mod_cfg, mods_dir, mirror_dir).unwrap().is_empty());
+
+        // With the archive gone, the version the game last recorded is restored.
+        let archive = mods_dir.join("repo").join("repo_mod.zip");
+        fs::remove_file(&archive).unwrap();
+        assert_eq!(
+            restore(&mod_cfg, mods_dir, mirror_dir).unwrap(),
+            vec![archive.clone()]
+        );
+        assert_eq!(fs::read_to_string(&archive).unwrap(), "version 1");
+    }
+
+    #[test]
+    fn restoring_newest_version_of_unknown_mod() {
+        let mods_tmp = tempfile::tempdir().unwrap();
+        let mirror_tmp = tempfile::tempdir().unwrap();
+        let (mods_dir, mirror_dir) = (mods_tmp.path(), mirror_tmp.path());
+
+        let mod_cfg = install_repo_mod(mods_dir, 1);
+        sync(&mod_cfg, mods_dir, mirror_dir).unwrap();
+        let mod_cfg = install_repo_mod(mods_dir, 2);
+        sync(&mod_cfg, mods_dir, mirror_dir).unwrap();
+
+        // The game forgot about the mod entirely, e.g. after it was delisted.
+        fs::remove_dir_all(mods_dir.join("repo")).unwrap();
+        fs::write(mods_dir.join("db.json"), r#"{"mods":{}}"#).unwrap();
+        let mod_cfg = ModCfg::load_from_path(mods_dir).unwrap();
+
+        restore(&mod_cfg, mods_dir, mirror_dir).unwrap();
+        assert_eq!(
+            fs::read_to_string(mods_dir.join("repo").join("repo_mod.zip")).unwrap(),
+            "version 2"
+        );
+    }
+
+    #[test]
+    fn installing_specific_version() {
+        let mods_tmp = tempfile::tempdir().unwrap();
+        let mirror_tmp = tempfile::tempdir().unwrap();
+        let (mods_dir, mirror_dir) = (mods_tmp.path(), mirror_tmp.path());
+
+        let mod_cfg = install_repo_mod(mods_dir, 1);
+        sync(&mod_cfg, mods_dir, mirror_dir).unwrap();
+        let mod_cfg = install_repo_mod(mods_dir, 2);
+        sync(&mod_cfg, mods_dir, mirror_dir).unwrap();
+        fs::remove_dir_all(mods_dir.join("repo")).unwrap();
+
+        let mut repo_version = mod_cfg.repo_version("repo_mod").unwrap();
+        repo_version.version_id = 1;
+        let archive = mods_dir.join("repo").join("repo_mod.zip");
+        assert_eq!(
+            install(&repo_version, mods_dir, mirror_dir).unwrap(),
+            Some(Installed {
+                version_id: 1,
+                archives: vec![archive.clone()]
+            })
+        );
+        assert_eq!(fs::read_to_string(&archive).unwrap(), "version 1");
+
+        // Versions that aren't mirrored fall back to the newest one.
+        fs::remove_dir_all(mods_dir.join("repo")).unwrap();
+        repo_version.version_id = 3;
+        let installed = install(&repo_version, mods_dir, mirror_dir)
+            .unwrap()
+            .unwrap();
+        assert_eq!(installed.version_id, 2);
+
+        // Mods that aren't mirrored can't be installed.
+        repo_version.resource_id = 8;
+        assert_eq!(install(&repo_version, mods_dir, mirror_dir).unwrap(), None);
+    }
+}
@@ -0,0 +1,567 @@
+use crate::{
+    game::{MissingModPolicy, ModCfg},
+    index::PresetIndex,
+    Error::*,
+    Preset, Result,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Separates a namespace from the name of one of its presets, e.g. `shared/racing`.
+pub const SEPARATOR: char = '/';
+
+/// The file in the personal presets directory listing the shared presets that are enabled, since
+/// shared presets are read-only and can't record it themselves. Like the index, it doesn't end in
+/// `.json`, so it isn't listed as a preset.
+pub const ENABLED_FILE: &str = ".shared-enabled";
+
+/// Join a namespace and the name of one of its presets, e.g. `shared` and `racing` into
+/// `shared/racing`.
+///
+/// # Arguments
+///
+/// `namespace`: The name of the namespace.
+/// `name`: The name of the preset within the namespace.
+pub fn qualified(namespace: &str, name: &str) -> String {
+    format!("{}{}{}", namespace, SEPARATOR, name)
+}
+
+/// Split a namespace-qualified preset name into its namespace and the name of the preset within
+/// it.
+///
+/// # Arguments
+///
+/// `name`: The name of a preset, e.g. `shared/racing`.
+///
+/// # Returns
+///
+/// `Some((&str, &str))`: The namespace and the name within it, e.g. `("shared", "racing")`.
+/// `None`: If the name is of a personal preset.
+pub fn split(name: &str) -> Option<(&str, &str)> {
+    name.split_once(SEPARATOR)
+}
+
+/// Fail if a preset is in a shared namespace, for commands that change presets.
+///
+/// # Arguments
+///
+/// `name`: The name of the preset.
+///
+/// # Errors
+///
+/// ReadOnlyPreset: If the name is namespace-qualified.
+pub fn check_writable(name: &str) -> Result<()> {
+    match split(name) {
+        Some(_) => Err(ReadOnlyPreset {
+            preset: name.into(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The personal presets directory along with the shared presets directories, like a team's or a
+/// modpack's, configured with `Config::set_preset_namespace`.
+///
+/// Presets in a shared directory are named after its namespace, like `shared/racing`, and are
+/// never written to, so the directory can be read-only. Which of them are enabled is recorded in
+/// the personal presets directory instead, so every profile chooses its own. A shared preset that
+/// extends another extends the one in the same namespace, while a personal preset can extend a
+/// shared one by its qualified name.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{game::{MissingModPolicy, ModCfg}, namespace::Namespaces, Preset};
+/// use std::collections::BTreeMap;
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let presets_dir = temp_dir.path().join("presets");
+/// # let team_dir = temp_dir.path().join("team");
+/// # std::fs::create_dir_all(&presets_dir).unwrap();
+/// # std::fs::create_dir_all(&team_dir).unwrap();
+/// let mut mod_cfg =
+///     ModCfg::load(r#"{"mods":{"mod1":{"active":false}}}"#.as_bytes()).unwrap();
+/// Preset::new("racing".into(), vec!["mod1".into()]).unwrap().save_to_path(&team_dir).unwrap();
+///
+/// let namespaces = Namespaces::new(&presets_dir, BTreeMap::from([("team".into(), team_dir)]));
+/// namespaces.set_many_enabled(["team/racing"], true, &mut mod_cfg).unwrap();
+/// namespaces.apply_presets(&mut mod_cfg, MissingModPolicy::Fail).unwrap();
+/// assert!(mod_cfg.is_mod_active("mod1").unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Namespaces {
+    /// The personal presets directory.
+    presets_dir: PathBuf,
+    /// The shared presets directories by namespace.
+    shared: BTreeMap<String, PathBuf>,
+}
+
+impl Namespaces {
+    /// Collect the presets directories.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: The personal presets directory.
+    /// `shared`: The shared presets directories by namespace.
+    pub fn new(presets_dir: impl AsRef<Path>, shared: BTreeMap<String, PathBuf>) -> Self {
+        Self {
+            presets_dir: presets_dir.as_ref().into(),
+            shared,
+        }
+    }
+
+    /// Get the directory of a shared namespace.
+    ///
+    /// # Errors
+    ///
+    /// UnknownNamespace: If the namespace isn't configured.
+    fn dir(&self, namespace: &str) -> Result<&Path> {
+        self.shared
+            .get(namespace)
+            .map(PathBuf::as_path)
+            .ok_or_else(|| UnknownNamespace {
+                namespace: namespace.into(),
+            })
+    }
+
+    /// Check if a personal or shared preset exists.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The name of the preset, qualified with its namespace if it's shared.
+    pub fn exists(&self, name: &str) -> bool {
+        match split(name) {
+            Some((namespace, name)) => self
+                .dir(namespace)
+                .is_ok_and(|dir| Preset::exists(name, dir)),
+            None => Preset::exists(name, &self.presets_dir),
+        }
+    }
+
+    /// Load a personal or shared preset. Shared presets are named after their namespace and are
+    /// enabled if they were enabled with `Namespaces::set_many_enabled`.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The name of the preset, qualified with its namespace if it's shared.
+    ///
+    /// # Errors
+    ///
+    /// * `UnknownNamespace`: If the namespace isn't configured.
+    /// * `MissingPreset`: If the preset doesn't exist.
+    /// * `std::io::Error`: If there is an issue reading the preset.
+    /// * `serde_json::Error`: If the preset can't be parsed.
+    pub fn load(&self, name: &str) -> Result<Preset> {
+        let Some((namespace, preset_name)) = split(name) else {
+            return Preset::load_from_path(name, &self.presets_dir);
+        };
+        let mut preset = Preset::load_from_path(preset_name, self.dir(namespace)?)?;
+        preset.qualify(namespace);
+        if self.enabled_shared()?.contains(name) {
+            preset.enable();
+        } else {
+            // The preset was only just loaded, so it has no mods to disable.
+            preset.force_disable(&mut ModCfg::default());
+        }
+        Ok(preset)
+    }
+
+    /// List the presets of every shared namespace, qualified with their namespace and sorted.
+    /// Namespaces whose directory doesn't exist, e.g. an unmounted network drive, are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading a shared presets directory.
+    pub fn list_shared(&self) -> Result<Vec<String>> {
+        let mut names = vec![];
+        for (namespace, dir) in self.shared.iter() {
+            if !dir.is_dir() {
+                continue;
+            }
+            names.extend(Preset::list(dir)?.map(|name| qualified(namespace, &name)));
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Get the names of the enabled shared presets, sorted.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO or serde_json errors if there is an issue reading the list of enabled presets.
+    pub fn enabled_shared(&self) -> Result<BTreeSet<String>> {
+        let path = self.presets_dir.join(ENABLED_FILE);
+        if !path.try_exists()? {
+            return Ok(BTreeSet::new());
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Get the enabled shared presets whose namespace is no longer configured, sorted. They are
+    /// skipped when presets are applied, and can still be disabled.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO or serde_json errors if there is an issue reading the list of enabled presets.
+    pub fn unknown_enabled(&self) -> Result<Vec<String>> {
+        Ok(self
+            .enabled_shared()?
+            .into_iter()
+            .filter(|name| split(name).is_some_and(|(ns, _)| !self.shared.contains_key(ns)))
+            .collect())
+    }
+
+    /// Save the names of the enabled shared presets.
+    fn save_enabled_shared(&self, enabled: &BTreeSet<String>) -> Result<()> {
+        let path = self.presets_dir.join(ENABLED_FILE);
+        if enabled.is_empty() {
+            if path.try_exists()? {
+                fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+        fs::write(path, serde_json::to_string_pretty(enabled)?)?;
+        Ok(())
+    }
+
+    /// Enable or disable many personal and shared presets at once, like
+    /// `Preset::set_many_enabled`. The mods of extended presets are looked up in every namespace.
+    ///
+    /// Every preset is loaded before any is changed, so nothing changes if one doesn't exist.
+    /// Shared presets whose namespace is no longer configured can still be disabled.
+    ///
+    /// # Arguments
+    ///
+    /// `names`: The names of the presets, qualified with their namespace if they're shared.
+    /// `enabled`: Whether the presets should be enabled or disabled.
+    /// `mod_config`: The ModCfg to disable the presets' mods in.
+    ///
+    /// # Errors
+    ///
+    /// * `UnknownNamespace`: If a namespace isn't configured.
+    /// * `MissingPreset`: If one of the presets doesn't exist.
+    /// * `MissingMods`: If a preset being disabled contains mods that don't exist in the ModCfg.
+    /// * Possible IO or serde_json errors if there is an issue loading or saving a preset.
+    pub fn set_many_enabled(
+        &self,
+        names: impl IntoIterator<Item = impl AsRef<str>>,
+        enabled: bool,
+        mod_config: &mut ModCfg,
+    ) -> Result<()> {
+        let (shared, personal): (Vec<String>, Vec<String>) = names
+            .into_iter()
+            .map(|name| name.as_ref().to_string())
+            .partition(|name| split(name).is_some());
+        let mut enabled_shared = self.enabled_shared()?;
+        let mut presets = vec![];
+        for name in personal.iter() {
+            presets.push(Preset::load_from_path(name, &self.presets_dir)?);
+        }
+        let mut unknown = vec![];
+        for name in shared.iter() {
+            match self.load(name) {
+                Ok(preset) => presets.push(preset),
+                // Their mods can't be found anymore, so they are only forgotten.
+                Err(UnknownNamespace { .. }) if !enabled && enabled_shared.contains(name) => {
+                    unknown.push(name)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        for name in unknown {
+            enabled_shared.remove(name);
+        }
+        for preset in presets.iter_mut() {
+            if enabled {
+                preset.enable();
+                continue;
+            }
+            preset.disable(mod_config)?;
+            for mod_name in preset.resolve_mods_with(|name| self.load(name))? {
+                if mod_config.is_ignored(&mod_name) {
+                    continue;
+                }
+                // Inherited mods that don't exist are already handled by the parent preset.
+                let _ = mod_config.set_mod_active(&mod_name, false);
+            }
+        }
+        for preset in presets.iter() {
+            if split(preset.name()).is_none() {
+                preset.save_to_path(&self.presets_dir)?;
+            } else if enabled {
+                enabled_shared.insert(preset.name().into());
+            } else {
+                enabled_shared.remove(preset.name());
+            }
+        }
+        self.save_enabled_shared(&enabled_shared)
+    }
+
+    /// Disable a personal or shared preset without checking that its mods exist, like
+    /// `Preset::force_disable`, e.g. after it failed to apply.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The name of the preset, qualified with its namespace if it's shared.
+    /// `mod_config`: The ModCfg to disable the preset's mods in.
+    ///
+    /// # Errors
+    ///
+    /// The same as `Namespaces::load`, and possible IO or serde_json errors if there is an issue
+    /// saving the preset.
+    pub fn force_disable(&self, name: &str, mod_config: &mut ModCfg) -> Result<()> {
+        let mut preset = self.load(name)?;
+        preset.force_disable(mod_config);
+        if split(name).is_none() {
+            return preset.save_to_path(&self.presets_dir);
+        }
+        let mut enabled_shared = self.enabled_shared()?;
+        enabled_shared.remove(name);
+        self.save_enabled_shared(&enabled_shared)
+    }
+
+    /// Disable every enabled personal and shared preset with `Namespaces::force_disable`, e.g.
+    /// before going back to an earlier set of mods that the presets would otherwise change right
+    /// back. Enabled shared presets whose namespace is no longer configured are left alone, since
+    /// they are skipped anyway.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg to disable the presets' mods in.
+    ///
+    /// # Returns
+    ///
+    /// The names of the disabled presets, personal ones first.
+    ///
+    /// # Errors
+    ///
+    /// The same as `Namespaces::force_disable`, and possible IO or serde_json errors if there is
+    /// an issue reading the preset index.
+    pub fn force_disable_all(&self, mod_config: &mut ModCfg) -> Result<Vec<String>> {
+        let mut disabled: Vec<String> = PresetIndex::load_from_path(&self.presets_dir)?
+            .enabled()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        disabled.extend(
+            self.enabled_shared()?
+                .into_iter()
+                .filter(|name| self.exists(name)),
+        );
+        for name in disabled.iter() {
+            self.force_disable(name, mod_config)?;
+        }
+        Ok(disabled)
+    }
+
+    /// Load the enabled shared presets, the shared presets extended by personal presets, and the
+    /// presets they extend, ready for `ModCfg::apply_presets_with_shared`. Presets that were
+    /// removed from their namespace, or whose namespace is no longer configured, are skipped.
+    /// Find the enabled ones among the latter with `Namespaces::unknown_enabled`.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingPreset`: If a shared preset extends a preset that doesn't exist.
+    /// * `std::io::Error`: If there is an issue reading a preset.
+    /// * `serde_json::Error`: If a preset can't be parsed.
+    pub fn shared_presets(&self) -> Result<Vec<Preset>> {
+        let index = PresetIndex::load_from_path(&self.presets_dir)?;
+        let extended: Vec<String> = index
+            .iter()
+            .filter_map(|(_, entry)| entry.extends())
+            .map(String::from)
+            .collect();
+        let mut presets: Vec<Preset> = vec![];
+        for name in self.enabled_shared()?.into_iter().chain(extended) {
+            if split(&name).is_none() || !self.exists(&name) {
+                continue;
+            }
+            let mut next = Some(name);
+            // Stopping at presets already loaded also stops at cycles.
+            while let Some(name) = next.filter(|n| presets.iter().all(|p| p.name() != n)) {
+                let preset = self.load(&name)?;
+                next = preset.extends().map(String::from);
+                presets.push(preset);
+            }
+        }
+        Ok(presets)
+    }
+
+    /// Apply the enabled personal and shared presets together, like `ModCfg::apply_presets`.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg to apply the presets to.
+    /// `policy`: The default policy for presets that reference missing mods.
+    ///
+    /// # Errors
+    ///
+    /// The same as `ModCfg::apply_presets` and `Namespaces::shared_presets`.
+    pub fn apply_presets(&self, mod_config: &mut ModCfg, policy: MissingModPolicy) -> Result<()> {
+        mod_config.apply_presets_with_shared(&self.presets_dir, self.shared_presets()?, policy)
+    }
+
+    /// Get the names of the mods neither a personal nor a shared preset contains, sorted, like
+    /// `ModCfg::unmanaged_mods`.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg of the installed mods.
+    ///
+    /// # Errors
+    ///
+    /// The same as `ModCfg::unmanaged_mods` and `Namespaces::load`.
+    pub fn unmanaged_mods<'a>(&self, mod_config: &'a ModCfg) -> Result<Vec<&'a str>> {
+        let mut shared = BTreeSet::new();
+        for name in self.list_shared()? {
+            for mod_name in self.load(&name)?.get_mods() {
+                shared.insert(mod_config.resolve_alias(mod_name).to_string());
+            }
+        }
+        let mut unmanaged = mod_config.unmanaged_mods(&self.presets_dir)?;
+        unmanaged.retain(|mod_name| !shared.contains(*mod_name));
+        Ok(unmanaged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+    use tempfile::tempdir;
+
+    #[test]
+    fn applying_shared_presets() {
+        let mock = MockData::new();
+        let mut mod_cfg = mock.modcfg;
+        let temp_dir = tempdir().unwrap();
+        let team_dir = temp_dir.path().to_path_buf();
+        Preset::new("base".into(), vec!["mod2".into()])
+            .unwrap()
+            .save_to_path(&team_dir)
+            .unwrap();
+        let mut racing = Preset::new("racing".into(), vec!["mod3".into()]).unwrap();
+        racing.set_extends(Some("base".into()));
+        racing.save_to_path(&team_dir).unwrap();
+        let namespaces = Namespaces::new(
+            &mock.presets_dir,
+            BTreeMap::from([("team".into(), team_dir.clone())]),
+        );
+
+        assert_eq!(
+            namespaces.list_shared().unwrap(),
+            vec!["team/base", "team/racing"]
+        );
+        assert!(namespaces.exists("team/racing"));
+        assert!(namespaces.exists("preset1"));
+        assert!(!namespaces.exists("other/racing"));
+        assert!(matches!(
+            namespaces.load("other/racing"),
+            Err(UnknownNamespace { .. })
+        ));
+        assert!(matches!(
+            check_writable("team/racing"),
+            Err(ReadOnlyPreset { .. })
+        ));
+
+        namespaces
+            .set_many_enabled(["team/racing"], true, &mut mod_cfg)
+            .unwrap();
+        let loaded = namespaces.load("team/racing").unwrap();
+        assert!(loaded.is_enabled());
+        assert_eq!(loaded.extends(), Some("team/base"));
+        let shared: Vec<_> = namespaces
+            .shared_presets()
+            .unwrap()
+            .iter()
+            .map(|p| p.name().to_string())
+            .collect();
+        assert_eq!(shared, vec!["team/racing", "team/base"]);
+
+        // The personal preset1 and the shared presets are merged.
+        namespaces
+            .apply_presets(&mut mod_cfg, MissingModPolicy::Fail)
+            .unwrap();
+        assert_eq!(mod_cfg.is_mod_active("mod1"), Some(true));
+        assert_eq!(mod_cfg.is_mod_active("mod2"), Some(true));
+        // The shared directory is never written to.
+        assert_eq!(fs::read_dir(&team_dir).unwrap().count(), 2);
+
+        namespaces
+            .set_many_enabled(["team/racing"], false, &mut mod_cfg)
+            .unwrap();
+        assert!(namespaces.enabled_shared().unwrap().is_empty());
+        assert_eq!(mod_cfg.is_mod_active("mod2"), Some(false));
+        assert_eq!(mod_cfg.is_mod_active("mod3"), Some(false));
+
+        // Removing the namespace leaves its enabled presets behind, which are skipped.
+        namespaces
+            .set_many_enabled(["team/racing"], true, &mut mod_cfg)
+            .unwrap();
+        let removed = Namespaces::new(&mock.presets_dir, BTreeMap::new());
+        assert_eq!(removed.unknown_enabled().unwrap(), vec!["team/racing"]);
+        assert!(removed.shared_presets().unwrap().is_empty());
+        removed
+            .apply_presets(&mut mod_cfg, MissingModPolicy::Fail)
+            .unwrap();
+        removed
+            .set_many_enabled(["team/racing"], false, &mut mod_cfg)
+            .unwrap();
+        assert!(removed.unknown_enabled().unwrap().is_empty());
+
+        // Disabling every preset includes the shared ones and the mods they enabled.
+        namespaces
+            .set_many_enabled(["team/racing"], true, &mut mod_cfg)
+            .unwrap();
+        namespaces
+            .apply_presets(&mut mod_cfg, MissingModPolicy::Fail)
+            .unwrap();
+        assert_eq!(mod_cfg.is_mod_active("mod3"), Some(true));
+        let disabled = namespaces.force_disable_all(&mut mod_cfg).unwrap();
+        assert_eq!(disabled, vec!["preset1", "team/racing"]);
+        assert!(namespaces.enabled_shared().unwrap().is_empty());
+        assert_eq!(mod_cfg.is_mod_active("mod3"), Some(false));
+        namespaces
+            .apply_presets(&mut mod_cfg, MissingModPolicy::Fail)
+            .unwrap();
+        assert_eq!(mod_cfg.is_mod_active("mod3"), Some(false));
+    }
+
+    #[test]
+    fn extending_shared_presets() {
+        let mock = MockData::new();
+        let mut mod_cfg = mock.modcfg;
+        let temp_dir = tempdir().unwrap();
+        Preset::new("base".into(), vec!["mod3".into()])
+            .unwrap()
+            .save_to_path(temp_dir.path())
+            .unwrap();
+        let namespaces = Namespaces::new(
+            &mock.presets_dir,
+            BTreeMap::from([("team".into(), temp_dir.path().to_path_buf())]),
+        );
+        assert_eq!(
+            namespaces.unmanaged_mods(&mod_cfg).unwrap(),
+            Vec::<&str>::new()
+        );
+
+        let mut preset = Preset::load_from_path("preset1", &mock.presets_dir).unwrap();
+        preset.set_extends(Some("team/base".into()));
+        preset.save_to_path(&mock.presets_dir).unwrap();
+        namespaces
+            .apply_presets(&mut mod_cfg, MissingModPolicy::Fail)
+            .unwrap();
+        assert_eq!(mod_cfg.is_mod_active("mod3"), Some(true));
+        // The shared preset itself stays disabled.
+        assert!(namespaces.enabled_shared().unwrap().is_empty());
+
+        namespaces
+            .set_many_enabled(["preset1"], false, &mut mod_cfg)
+            .unwrap();
+        assert_eq!(mod_cfg.is_mod_active("mod3"), Some(false));
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{config::RetryPolicy, Error::*, Result};
+use attohttpc::ErrorKind;
+use std::time::{Duration, Instant};
+
+/// Download a URL, retrying failed requests according to a retry policy.
+///
+/// Requests the server refuses with a client error, like `404 Not Found`, aren't retried since
+/// asking again won't change the answer.
+///
+/// # Arguments
+///
+/// `url`: The URL to download.
+/// `headers`: Extra headers to send, as names paired with values.
+/// `policy`: How failed requests are retried.
+///
+/// # Errors
+///
+/// Download: If the URL couldn't be downloaded within the policy's retries and timeout.
+pub fn get(url: &str, headers: &[(&'static str, &str)], policy: &RetryPolicy) -> Result<Vec<u8>> {
+    let start = Instant::now();
+    let mut retry = 0;
+    loop {
+        let remaining = policy.timeout().saturating_sub(start.elapsed());
+        let error = match request(url, headers, remaining.max(Duration::from_millis(1))) {
+            Ok(bytes) => return Ok(bytes),
+            Err(e) => e,
+        };
+        let client_error =
+            matches!(error.kind(), ErrorKind::StatusCode(status) if status.is_client_error());
+        let delay = policy.delay(retry);
+        if client_error || retry >= policy.retries() || start.elapsed() + delay >= policy.timeout()
+        {
+            return Err(Download {
+                url: url.into(),
+                reason: error.to_string(),
+            });
+        }
+        std::thread::sleep(delay);
+        retry += 1;
+    }
+}
+
+/// Make a single request for a URL and read the whole response.
+fn request(
+    url: &str,
+    headers: &[(&'static str, &str)],
+    timeout: Duration,
+) -> std::result::Result<Vec<u8>, attohttpc::Error> {
+    let mut request = attohttpc::get(url).timeout(timeout);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    request.send()?.error_for_status()?.bytes()
+}
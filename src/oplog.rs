@@ -0,0 +1,232 @@
+use crate::Result;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// A log of what BeamMM did, kept in the logs directory so there is a record to look at when mods
+/// change unexpectedly.
+///
+/// Lines are appended to `beammm.log`. When it grows past the size limit it is rotated to
+/// `beammm.1.log`, pushing older logs up to `beammm.2.log` and so on. Rotated logs past the file
+/// limit or older than the age limit are deleted.
+///
+/// # Examples
+/// ```rust
+/// use beammm::oplog::OperationLog;
+/// use std::time::SystemTime;
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let logs_dir = temp_dir.path().join("logs");
+/// let log = OperationLog::new(logs_dir.clone());
+/// log.write("Disabled mods: mod1", SystemTime::now()).unwrap();
+///
+/// let contents = std::fs::read_to_string(logs_dir.join("beammm.log")).unwrap();
+/// assert!(contents.ends_with("Disabled mods: mod1\n"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct OperationLog {
+    /// The directory the logs are kept in.
+    dir: PathBuf,
+    /// How big the current log may grow before it is rotated, in bytes.
+    max_size: u64,
+    /// How many rotated logs are kept.
+    max_files: usize,
+    /// How old rotated logs may get before they are deleted.
+    max_age: Duration,
+}
+
+impl OperationLog {
+    /// The default size limit of the current log, 1 MB.
+    pub const MAX_SIZE: u64 = 1 << 20;
+    /// The default number of rotated logs kept.
+    pub const MAX_FILES: usize = 5;
+    /// The default age limit of rotated logs, 30 days.
+    pub const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+    /// Create a log in a directory with the default limits. The directory is created when the
+    /// first line is written.
+    ///
+    /// # Arguments
+    ///
+    /// `dir`: The directory the logs are kept in.
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            max_size: Self::MAX_SIZE,
+            max_files: Self::MAX_FILES,
+            max_age: Self::MAX_AGE,
+        }
+    }
+
+    /// Set how big the current log may grow before it is rotated.
+    ///
+    /// # Arguments
+    ///
+    /// `max_size`: The size limit in bytes.
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Set how many rotated logs are kept.
+    ///
+    /// # Arguments
+    ///
+    /// `max_files`: The number of rotated logs to keep.
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// Set how old rotated logs may get before they are deleted.
+    ///
+    /// # Arguments
+    ///
+    /// `max_age`: The age limit.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Get the path to the current log.
+    pub fn path(&self) -> PathBuf {
+        self.dir.join("beammm.log")
+    }
+
+    /// Get the last lines of the current log, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// `count`: How many lines to get at most.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if the log exists but can't be read.
+    pub fn recent(&self, count: usize) -> Result<Vec<String>> {
+        let path = self.path();
+        if !path.try_exists()? {
+            return Ok(vec![]);
+        }
+        let contents = fs::read_to_string(path)?;
+        let lines: Vec<&str> = contents.lines().collect();
+        let start = lines.len().saturating_sub(count);
+        Ok(lines[start..].iter().map(|l| l.to_string()).collect())
+    }
+
+    /// Get the path to a rotated log, where 1 is the most recent.
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        self.dir.join(format!("beammm.{}.log", n))
+    }
+
+    /// Append a line to the log, starting with the time, rotating the log first if needed.
+    ///
+    /// # Arguments
+    ///
+    /// `message`: What happened.
+    /// `time`: When it happened.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue creating the directory, rotating the logs, or
+    /// writing the line.
+    pub fn write(&self, message: &str, time: SystemTime) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let line = format!(
+            "{} [{}] {}\n",
+            humantime::format_rfc3339_seconds(time),
+            std::process::id(),
+            message
+        );
+        self.rotate(line.len() as u64, time)?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path())?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Rotate the current log if adding `incoming` bytes would take it past the size limit, then
+    /// delete rotated logs past the file or age limit.
+    fn rotate(&self, incoming: u64, time: SystemTime) -> Result<()> {
+        let current = self.path();
+        let size = match fs::metadata(&current) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        };
+        if size > 0 && size + incoming > self.max_size {
+            for n in (1..self.max_files).rev() {
+                rename_if_exists(&self.rotated_path(n), &self.rotated_path(n + 1))?;
+            }
+            if self.max_files > 0 {
+                fs::rename(&current, self.rotated_path(1))?;
+            } else {
+                fs::remove_file(&current)?;
+            }
+        }
+
+        for n in 1.. {
+            let path = self.rotated_path(n);
+            let Ok(metadata) = fs::metadata(&path) else {
+                break;
+            };
+            let too_old = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| time.duration_since(modified).ok())
+                .is_some_and(|age| age > self.max_age);
+            if n > self.max_files || too_old {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rename a file, doing nothing if it doesn't exist.
+fn rename_if_exists(from: &Path, to: &Path) -> Result<()> {
+    if from.try_exists()? {
+        fs::rename(from, to)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn rotating_logs() {
+        let temp_dir = tempdir().unwrap();
+        let log = OperationLog::new(temp_dir.path().join("logs"))
+            .with_max_size(100)
+            .with_max_files(2);
+        let now = SystemTime::now();
+
+        for i in 0..10 {
+            log.write(&format!("line {i} with some padding"), now)
+                .unwrap();
+        }
+        let current = fs::read_to_string(log.path()).unwrap();
+        assert!(current.contains("line 9"));
+        assert!(fs::metadata(log.path()).unwrap().len() <= 100);
+        assert!(log.rotated_path(1).exists());
+        assert!(log.rotated_path(2).exists());
+        assert!(!log.rotated_path(3).exists());
+        let recent = log.recent(1).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert!(recent[0].ends_with("line 9 with some padding"));
+
+        // Rotated logs past the age limit are deleted.
+        let log = log.with_max_age(Duration::from_secs(60));
+        log.write("later", now + Duration::from_secs(120)).unwrap();
+        assert!(!log.rotated_path(2).exists());
+        assert!(log.path().exists());
+    }
+}
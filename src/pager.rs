@@ -0,0 +1,140 @@
+use crate::Result;
+use std::{
+    env,
+    io::{self, BufRead, IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+/// Print lines to stdout, paging them if stdout is a terminal and they don't fit on one screen.
+///
+/// The pager in the `PAGER` environment variable is used if it is set, otherwise the built-in
+/// pager from `page` is. Setting `PAGER` to nothing turns paging off. If the pager can't be
+/// started, the lines are printed as usual.
+///
+/// # Arguments
+///
+/// `lines`: The lines to print.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue writing to stdout or the pager.
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn print(lines: &[String]) -> Result<()> {
+    let height = io::stdout()
+        .is_terminal()
+        .then(terminal_size::terminal_size)
+        .flatten()
+        .map(|(_, height)| height.0 as usize);
+    let pager = env::var("PAGER").ok();
+
+    match (height, pager.as_deref()) {
+        (Some(height), pager) if lines.len() >= height && pager != Some("") => {
+            if let Some(pager) = pager {
+                if page_with(pager, lines).is_ok() {
+                    return Ok(());
+                }
+            }
+            page(io::stdin().lock(), io::stdout(), lines, height)
+        }
+        _ => {
+            let mut stdout = io::stdout().lock();
+            for line in lines {
+                writeln!(stdout, "{}", line)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Pipe lines through an external pager command, such as `less -R`, and wait for it to exit.
+#[cfg_attr(coverage_nightly, coverage(off))]
+fn page_with(pager: &str, lines: &[String]) -> Result<()> {
+    let mut parts = pager.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or(io::Error::from(io::ErrorKind::NotFound))?;
+    let mut child = Command::new(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        for line in lines {
+            // The user quitting the pager early closes the pipe, which isn't an error.
+            if writeln!(stdin, "{}", line).is_err() {
+                break;
+            }
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// Show lines a screen at a time, waiting for the user between screens.
+///
+/// Entering nothing shows the next screen, and `q` stops early.
+///
+/// # Arguments
+///
+/// `reader`: Thing to read from e.g. stdin.
+/// `writer`: Thing to write to e.g. stdout.
+/// `lines`: The lines to show.
+/// `height`: The number of lines that fit on the screen, including the prompt.
+///
+/// # Errors
+///
+/// IO errors are possible from read and write operations.
+pub fn page<R: BufRead, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    lines: &[String],
+    height: usize,
+) -> Result<()> {
+    let screen = height.saturating_sub(1).max(1);
+    let mut shown = 0;
+    for chunk in lines.chunks(screen) {
+        for line in chunk {
+            writeln!(&mut writer, "{}", line)?;
+        }
+        shown += chunk.len();
+        if shown == lines.len() {
+            break;
+        }
+
+        write!(
+            &mut writer,
+            "-- More ({}/{}) -- press enter to continue or q to quit: ",
+            shown,
+            lines.len()
+        )?;
+        writer.flush()?;
+        let mut input = String::new();
+        if reader.read_line(&mut input)? == 0 || input.trim().eq_ignore_ascii_case("q") {
+            writeln!(&mut writer)?;
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paging_lines() {
+        let lines: Vec<String> = (1..=5).map(|n| format!("line {n}")).collect();
+
+        let mut writer = Vec::new();
+        page(&b"\n\n"[..], &mut writer, &lines, 3).unwrap();
+        let output = String::from_utf8(writer).unwrap();
+        assert!(output.contains("line 5"));
+        assert_eq!(output.matches("-- More").count(), 2);
+
+        // Quitting stops after the first screen.
+        let mut writer = Vec::new();
+        page(&b"q\n"[..], &mut writer, &lines, 3).unwrap();
+        let output = String::from_utf8(writer).unwrap();
+        assert!(output.contains("line 2"));
+        assert!(!output.contains("line 3"));
+    }
+}
@@ -1,7 +1,14 @@
-use crate::{Error::*, Result};
+use crate::{
+    game::ModRoot,
+    Error::{self, *},
+    GameVersion, Result,
+};
 use dirs;
+use serde::Serialize;
 use std::{
+    collections::BTreeMap,
     fs::{self},
+    io,
     path::{Path, PathBuf},
 };
 
@@ -17,10 +24,10 @@ use std::{
 /// * `std::io::Error`: If there is a permission issue when checking if the directory exists or
 ///     creating the directory.
 fn validate_dir(dir: PathBuf) -> Result<PathBuf> {
-    if dir.try_exists()? {
+    if dir.try_exists().map_err(Error::io(&dir))? {
         Ok(dir)
     } else {
-        fs::create_dir_all(&dir)?;
+        fs::create_dir_all(&dir).map_err(Error::io(&dir))?;
         Ok(dir)
     }
 }
@@ -41,17 +48,186 @@ pub fn beamng_dir(possible_dirs: impl Iterator<Item = PathBuf>) -> Result<PathBu
         .ok_or(GameDirNotFound)
 }
 
+/// Get the paths to every BeamNG.drive data directory that exists, in the order they were given.
+///
+/// # Arguments
+///
+/// * `possible_dirs`: An iterator of possible directories to check for the game's data directory.
+pub fn beamng_dirs(possible_dirs: impl Iterator<Item = PathBuf>) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = vec![];
+    for dir in possible_dirs.map(|d| d.join("BeamNG.drive")) {
+        if dir.try_exists().unwrap_or(false) && !found.contains(&dir) {
+            found.push(dir);
+        }
+    }
+    found
+}
+
 /// Get the BeamNG.drive data directory based on the game's default data directories.
 ///
+/// If the game's `startup.ini` relocates the user folder, the relocated folder is used instead.
+///
 /// # Errors
 ///
 /// * `GameDirNotFound`: When the game's data directory cannot be found automatically.
 #[cfg_attr(coverage_nightly, coverage(off))]
 pub fn beamng_dir_default() -> Result<PathBuf> {
+    beamng_dirs_default()
+        .into_iter()
+        .next()
+        .ok_or(GameDirNotFound)
+}
+
+/// Get every BeamNG.drive data directory found in the game's default data directories.
+///
+/// A user folder relocated by the game's `startup.ini` comes first, followed by the default data
+/// directories.
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn beamng_dirs_default() -> Vec<PathBuf> {
+    let mut found = vec![];
+    if let Ok(install_dir) = install_dir_default() {
+        if let Ok(Some(user_dir)) = startup_ini_user_dir(&install_dir) {
+            if user_dir.try_exists().unwrap_or(false) {
+                found.push(user_dir);
+            }
+        }
+    }
+
     let possible_dirs = vec![dirs::data_local_dir(), dirs::data_dir()]
         .into_iter()
         .flatten();
-    beamng_dir(possible_dirs)
+    for dir in beamng_dirs(possible_dirs) {
+        if !found.contains(&dir) {
+            found.push(dir);
+        }
+    }
+    found
+}
+
+/// BeamNG.drive's Steam app ID.
+const STEAM_APP_ID: &str = "284160";
+
+/// Get the path to the BeamNG.drive installation directory through Steam.
+///
+/// Every Steam library of each Steam installation is checked for the game's app manifest.
+///
+/// # Arguments
+///
+/// * `steam_dirs`: An iterator of possible Steam installation directories.
+///
+/// # Errors
+///
+/// * `InstallDirNotFound`: When the game's installation directory cannot be found.
+pub fn install_dir(steam_dirs: impl Iterator<Item = PathBuf>) -> Result<PathBuf> {
+    steam_dirs
+        .flat_map(|steam_dir| {
+            let library_folders =
+                fs::read_to_string(steam_dir.join("steamapps").join("libraryfolders.vdf"))
+                    .unwrap_or_default();
+            let mut libraries = vec![steam_dir];
+            libraries.extend(vdf_values(&library_folders, "path").map(PathBuf::from));
+            libraries
+        })
+        .find_map(|library| {
+            let steamapps = library.join("steamapps");
+            let manifest =
+                fs::read_to_string(steamapps.join(format!("appmanifest_{}.acf", STEAM_APP_ID)))
+                    .ok()?;
+            let install_dir = steamapps
+                .join("common")
+                .join(vdf_values(&manifest, "installdir").next()?);
+            install_dir
+                .try_exists()
+                .unwrap_or(false)
+                .then_some(install_dir)
+        })
+        .ok_or(InstallDirNotFound)
+}
+
+/// Get the path to the BeamNG.drive installation directory based on where Steam is installed.
+///
+/// On Windows, Steam's location is read from the registry. The default Steam locations are
+/// checked as well.
+///
+/// # Errors
+///
+/// * `InstallDirNotFound`: When the game's installation directory cannot be found.
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn install_dir_default() -> Result<PathBuf> {
+    let mut steam_dirs = vec![];
+    #[cfg(windows)]
+    {
+        use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+        if let Ok(steam_path) = RegKey::predef(HKEY_CURRENT_USER)
+            .open_subkey(r"Software\Valve\Steam")
+            .and_then(|key| key.get_value::<String, _>("SteamPath"))
+        {
+            steam_dirs.push(PathBuf::from(steam_path));
+        }
+    }
+    steam_dirs.push(PathBuf::from(r"C:\Program Files (x86)\Steam"));
+    steam_dirs.push(PathBuf::from(r"C:\Program Files\Steam"));
+    // Steam on Linux, for running the game through Proton.
+    if let Some(data_dir) = dirs::data_dir() {
+        steam_dirs.push(data_dir.join("Steam"));
+    }
+    if let Some(home_dir) = dirs::home_dir() {
+        steam_dirs.push(home_dir.join(".steam").join("steam"));
+    }
+    install_dir(steam_dirs.into_iter())
+}
+
+/// Get every value of `key` in the contents of a Steam VDF/ACF file.
+///
+/// Only simple `"key" "value"` lines are understood, which is all that's needed to read library
+/// folders and app manifests.
+fn vdf_values<'a>(contents: &'a str, key: &'a str) -> impl Iterator<Item = String> + 'a {
+    contents.lines().filter_map(move |line| {
+        let mut parts = line.split('"').map(str::trim).filter(|p| !p.is_empty());
+        if parts.next()? == key {
+            Some(parts.next()?.replace(r"\\", r"\"))
+        } else {
+            None
+        }
+    })
+}
+
+/// Get the user folder configured in the game's `startup.ini`, if the user folder was relocated.
+///
+/// # Arguments
+///
+/// * `install_dir`: The game's installation directory, where `startup.ini` is located.
+///
+/// # Errors
+///
+/// * `std::io::Error`: If `startup.ini` exists but there is an issue reading it.
+pub fn startup_ini_user_dir(install_dir: impl AsRef<Path>) -> Result<Option<PathBuf>> {
+    let install_dir = install_dir.as_ref();
+    let ini_path = install_dir.join("startup.ini");
+    if !ini_path.try_exists()? {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(ini_path)?;
+    Ok(parse_startup_ini(&contents).map(|dir| install_dir.join(dir)))
+}
+
+/// Parse the user folder out of the contents of a `startup.ini` file.
+///
+/// The returned path may be relative to the installation directory. Joining it onto the
+/// installation directory handles both relative and absolute paths.
+fn parse_startup_ini(contents: &str) -> Option<PathBuf> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.starts_with(';') && !l.starts_with('#'))
+        .filter_map(|l| l.split_once('='))
+        .find(|(key, _)| {
+            let key = key.trim();
+            key.eq_ignore_ascii_case("UserPath") || key.eq_ignore_ascii_case("UserFolder")
+        })
+        .map(|(_, value)| value.trim().trim_matches('"').trim())
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
 }
 
 /// Get the BeamNG.drive mods folder based on the game's base data dir and the game's version.
@@ -75,11 +251,12 @@ pub fn beamng_dir_default() -> Result<PathBuf> {
 ///
 /// # let temp_dir = tempdir().unwrap();
 /// # let data_dir = temp_dir.path();
-/// # let version = "0.32";
-/// # std::fs::create_dir_all(data_dir.join(version).join("mods")).unwrap();
+/// # let version = "0.32".parse().unwrap();
+/// # std::fs::create_dir_all(data_dir.join("0.32").join("mods")).unwrap();
 /// let mods_dir = mods_dir(&data_dir, &version).unwrap();
 /// ```
-pub fn mods_dir(data_dir: &Path, version: &str) -> Result<PathBuf> {
+pub fn mods_dir(data_dir: impl AsRef<Path>, version: &GameVersion) -> Result<PathBuf> {
+    let data_dir = data_dir.as_ref();
     // Confirm data_dir even exists.
     if !data_dir.try_exists()? {
         Err(DirNotFound {
@@ -88,7 +265,7 @@ pub fn mods_dir(data_dir: &Path, version: &str) -> Result<PathBuf> {
     } else {
         // Find the mods_dir. To do this, we need to find the game version, enter that version.
         // folder, and return the mods dir inside that folder after verifying it exists.
-        let mods_dir_ = data_dir.join(version).join("mods");
+        let mods_dir_ = data_dir.join(version.to_string()).join("mods");
         if mods_dir_.try_exists()? {
             Ok(mods_dir_)
         } else {
@@ -97,7 +274,282 @@ pub fn mods_dir(data_dir: &Path, version: &str) -> Result<PathBuf> {
     }
 }
 
-/// Get the path to the beammm directory and create it if it doesn't exist.
+/// Get the game version a mods directory is for. The game keeps each version's mods in
+/// `<data dir>/<version>/mods`, so it is the name of the folder the mods directory is in.
+///
+/// # Arguments
+///
+/// `mods_dir`: The mods directory.
+///
+/// # Returns
+///
+/// `Some(GameVersion)`: The version the mods directory is for.
+/// `None`: If the folder the mods directory is in isn't named after a version.
+///
+/// # Examples
+///
+/// ```rust
+/// use beammm::{path::mods_dir_version, GameVersion};
+///
+/// let version = mods_dir_version("BeamNG.drive/0.32/mods");
+/// assert_eq!(version, Some(GameVersion { major: 0, minor: 32, patch: None }));
+/// assert_eq!(mods_dir_version("backup/mods"), None);
+/// ```
+pub fn mods_dir_version(mods_dir: impl AsRef<Path>) -> Option<GameVersion> {
+    mods_dir
+        .as_ref()
+        .parent()?
+        .file_name()?
+        .to_str()?
+        .parse()
+        .ok()
+}
+
+/// Get the mods roots that exist in a mods directory: the mods directory itself, `repo`, and
+/// `unpacked`.
+///
+/// # Arguments
+///
+/// `mods_dir`: The mods directory of a game version.
+///
+/// # Examples
+///
+/// ```rust
+/// use beammm::{game::ModRoot, path::mods_roots};
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let mods_dir = temp_dir.path();
+/// std::fs::create_dir(mods_dir.join("repo")).unwrap();
+/// let roots: Vec<ModRoot> = mods_roots(&mods_dir).into_iter().map(|(root, _)| root).collect();
+/// assert_eq!(roots, vec![ModRoot::Mods, ModRoot::Repo]);
+/// ```
+pub fn mods_roots(mods_dir: impl AsRef<Path>) -> Vec<(ModRoot, PathBuf)> {
+    let mods_dir = mods_dir.as_ref();
+    ModRoot::ALL
+        .into_iter()
+        .map(|root| match root.dir_name() {
+            Some(dir_name) => (root, mods_dir.join(dir_name)),
+            None => (root, mods_dir.to_path_buf()),
+        })
+        .filter(|(_, dir)| dir.is_dir())
+        .collect()
+}
+
+/// Get every file in a mods root and its subdirectories, leaving out the other roots inside it.
+///
+/// # Arguments
+///
+/// `mods_dir`: The mods directory of a game version.
+/// `root`: The root to list the files of.
+///
+/// # Errors
+///
+/// * `std::io::Error`: If there is an issue reading a directory.
+pub fn root_files(mods_dir: impl AsRef<Path>, root: ModRoot) -> Result<Vec<PathBuf>> {
+    let roots = mods_roots(mods_dir);
+    let Some((_, dir)) = roots.iter().find(|(r, _)| *r == root) else {
+        return Ok(vec![]);
+    };
+    let mut found = vec![];
+    let mut dirs = vec![dir.clone()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !path.is_dir() {
+                found.push(path);
+            } else if !roots.iter().any(|(_, root_dir)| *root_dir == path) {
+                dirs.push(path);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Get the entries of a directory whose names aren't valid Unicode. Mods and presets are named
+/// after their files, and names are stored as JSON strings, so these entries can't be named and
+/// are left out wherever BeamMM lists a directory. This lets them be reported instead of hidden.
+///
+/// Names in any script, like Cyrillic or Chinese, are valid Unicode. Invalid names are usually
+/// left by archives or copies made with a different encoding.
+///
+/// # Arguments
+///
+/// `dir`: The directory to check. A directory that doesn't exist has no such entries.
+///
+/// # Returns
+///
+/// The paths of the entries, sorted.
+///
+/// # Errors
+///
+/// * `std::io::Error`: If there is an issue reading the directory.
+pub fn non_unicode_entries(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    if !dir.try_exists()? {
+        return Ok(vec![]);
+    }
+    let mut found = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_str().is_none() {
+            found.push(entry.path());
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Make a path usable beyond Windows' 260 character `MAX_PATH` limit, which deeply nested user
+/// folders and long mod filenames can exceed.
+///
+/// On Windows, the path is made absolute and given the `\\?\` extended-length prefix, so every
+/// path joined onto it can be as long as the filesystem allows. Extended-length paths are used as
+/// they are, without resolving `/` or `..`, so only join single components or paths split with
+/// `join_relative` onto them. On other platforms, the path is returned unchanged.
+///
+/// # Arguments
+///
+/// `path`: The path to a directory BeamMM works in.
+///
+/// # Errors
+///
+/// * `std::io::Error`: If the path can't be made absolute, e.g. because it's empty.
+pub fn long_path(path: impl AsRef<Path>) -> Result<PathBuf> {
+    let path = path.as_ref();
+    if cfg!(windows) {
+        let absolute = std::path::absolute(path)?;
+        Ok(match absolute.to_str() {
+            Some(absolute) => PathBuf::from(extended_length(absolute)),
+            // Only paths from other platforms aren't valid Unicode on Windows.
+            None => absolute,
+        })
+    } else {
+        Ok(path.to_path_buf())
+    }
+}
+
+/// Add the extended-length prefix to an absolute Windows path, e.g. `C:\mods` to `\\?\C:\mods`
+/// and `\\server\share` to `\\?\UNC\server\share`. Paths that already have a prefix are returned
+/// unchanged.
+fn extended_length(absolute: &str) -> String {
+    if absolute.starts_with(r"\\?\") || absolute.starts_with(r"\\.\") {
+        absolute.into()
+    } else if let Some(unc) = absolute.strip_prefix(r"\\") {
+        format!(r"\\?\UNC\{}", unc)
+    } else {
+        format!(r"\\?\{}", absolute)
+    }
+}
+
+/// Join a relative path with `/` separators, like those the game stores in db.json, onto a base
+/// path one component at a time. Unlike `Path::join`, this keeps working when the base is an
+/// extended-length path from `long_path`, where `/` isn't a separator.
+///
+/// # Arguments
+///
+/// `base`: The path to join onto.
+/// `relative`: The relative path, e.g. `repo/mod.zip`.
+///
+/// # Examples
+/// ```rust
+/// use beammm::path::join_relative;
+/// use std::path::Path;
+///
+/// let archive = join_relative("mods", "repo/mod.zip");
+/// assert_eq!(archive, Path::new("mods").join("repo").join("mod.zip"));
+/// ```
+pub fn join_relative(base: impl AsRef<Path>, relative: &str) -> PathBuf {
+    relative
+        .split(['/', '\\'])
+        .filter(|component| !component.is_empty() && *component != ".")
+        .fold(base.as_ref().to_path_buf(), |path, component| {
+            path.join(component)
+        })
+}
+
+/// Delete a file, sending it to the system trash unless it should be removed permanently, so an
+/// accidental deletion can be undone from the recycle bin.
+///
+/// # Arguments
+///
+/// `path`: The path to the file to delete.
+/// `permanent`: Whether to remove the file permanently instead of sending it to the trash.
+///
+/// # Errors
+///
+/// * `std::io::Error`: If the file doesn't exist or there is an issue deleting it, including when
+///   the platform has no trash to send it to.
+pub fn remove_file(path: impl AsRef<Path>, permanent: bool) -> Result<()> {
+    let path = path.as_ref();
+    // Checked first, since the trash reports missing files with its own error kind.
+    fs::symlink_metadata(path).map_err(Error::io(path))?;
+    if permanent {
+        fs::remove_file(path).map_err(Error::io(path))?;
+    } else {
+        trash::delete(path).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+/// The file that turns on portable mode when it is placed next to the BeamMM executable.
+pub const PORTABLE_FLAG: &str = "portable.flag";
+
+/// Get the directory holding the BeamMM executable.
+///
+/// # Errors
+///
+/// * `std::io::Error` if the path of the executable can't be found
+#[cfg_attr(coverage_nightly, coverage(off))]
+fn exe_dir() -> Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+    Ok(exe.parent().map(Path::to_path_buf).unwrap_or_default())
+}
+
+/// Whether BeamMM runs in portable mode, which is when a `portable.flag` file is next to the
+/// executable.
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn is_portable() -> bool {
+    exe_dir().is_ok_and(|dir| dir.join(PORTABLE_FLAG).is_file())
+}
+
+/// Get the path to the beammm directory used in portable mode, next to the executable, and create
+/// it if it doesn't exist.
+///
+/// # Errors
+///
+/// * `std::io::Error` if the path of the executable can't be found, if there is a permissions
+///   issue when checking if the dir exists or if there is an issue creating the dir
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn portable_beammm_dir() -> Result<PathBuf> {
+    validate_dir(find_beammm_dir(true)?)
+}
+
+/// Get the path to the beammm directory without creating it, e.g. to read the config before the
+/// command line is parsed.
+///
+/// # Arguments
+///
+/// `portable`: Whether to use the portable beammm directory next to the executable, which is also
+/// used in portable mode.
+///
+/// # Errors
+///
+/// * `MissingLocalAppdata` if there is a problem retrieving the `%LocalAppData%` Windows variable
+/// * `std::io::Error` if the path of the executable can't be found
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn find_beammm_dir(portable: bool) -> Result<PathBuf> {
+    if portable || is_portable() {
+        return Ok(exe_dir()?.join("BeamMM"));
+    }
+    Ok(dirs::data_local_dir()
+        .ok_or(MissingLocalAppdata)?
+        .join("BeamMM"))
+}
+
+/// Get the path to the beammm directory and create it if it doesn't exist. In portable mode, this
+/// is the portable beammm directory next to the executable.
 ///
 /// # Errors
 ///
@@ -106,11 +558,7 @@ pub fn mods_dir(data_dir: &Path, version: &str) -> Result<PathBuf> {
 ///     an issue creating the dir
 #[cfg_attr(coverage_nightly, coverage(off))]
 pub fn beammm_dir() -> Result<PathBuf> {
-    let dir = dirs::data_local_dir()
-        .ok_or(MissingLocalAppdata)?
-        .join("BeamMM");
-
-    validate_dir(dir)
+    validate_dir(find_beammm_dir(false)?)
 }
 
 /// Get the path to the presets directory and create it if it doesn't exist.
@@ -135,15 +583,306 @@ pub fn beammm_dir() -> Result<PathBuf> {
 /// let presets_dir = presets_dir(&beammm_dir).unwrap();
 /// ```
 #[cfg_attr(coverage_nightly, coverage(off))]
-pub fn presets_dir(beammm_dir: &Path) -> Result<PathBuf> {
+pub fn presets_dir(beammm_dir: impl AsRef<Path>) -> Result<PathBuf> {
+    let beammm_dir = beammm_dir.as_ref();
     let dir = beammm_dir.join("presets");
     validate_dir(dir)
 }
 
+/// Get the path to the mirror directory, where copies of repository mods are kept, and create it
+/// if it doesn't exist.
+///
+/// # Arguments
+///
+/// `beammm_dir`: The path to the beammm directory.
+///
+/// # Errors
+///
+/// * `std::io::Error` if there is a permissions issue when checking if the dir exists or if there
+///   is an issue creating the dir
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn mirror_dir(beammm_dir: impl AsRef<Path>) -> Result<PathBuf> {
+    let beammm_dir = beammm_dir.as_ref();
+    let dir = beammm_dir.join("mirror");
+    validate_dir(dir)
+}
+
+/// Get the path to the quarantine directory, where corrupt mod archives are moved to, and create it
+/// if it doesn't exist.
+///
+/// # Arguments
+///
+/// `beammm_dir`: The path to the beammm directory.
+///
+/// # Errors
+///
+/// * `std::io::Error` if there is a permissions issue when checking if the dir exists or if there
+///   is an issue creating the dir
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn quarantine_dir(beammm_dir: impl AsRef<Path>) -> Result<PathBuf> {
+    let beammm_dir = beammm_dir.as_ref();
+    let dir = beammm_dir.join("quarantine");
+    validate_dir(dir)
+}
+
+/// Get the path to the inbox directory, where mod archives can be dropped to be installed.
+///
+/// The inbox is optional, so unlike the other directories it isn't created here.
+///
+/// # Arguments
+///
+/// `beammm_dir`: The path to the beammm directory.
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn inbox_dir(beammm_dir: impl AsRef<Path>) -> PathBuf {
+    let beammm_dir = beammm_dir.as_ref();
+    beammm_dir.join("inbox")
+}
+
+/// Get the path to the logs directory, where the operation log is kept.
+///
+/// The directory is created when the log is first written, so it isn't created here.
+///
+/// # Arguments
+///
+/// `beammm_dir`: The path to the beammm directory.
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn logs_dir(beammm_dir: impl AsRef<Path>) -> PathBuf {
+    let beammm_dir = beammm_dir.as_ref();
+    beammm_dir.join("logs")
+}
+
+/// Get the path to the directory crash reports are written to.
+///
+/// The directory is created when the first report is written, so it isn't created here.
+///
+/// # Arguments
+///
+/// `beammm_dir`: The path to the beammm directory.
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn crash_reports_dir(beammm_dir: impl AsRef<Path>) -> PathBuf {
+    let beammm_dir = beammm_dir.as_ref();
+    beammm_dir.join("crash-reports")
+}
+
+/// Get the path to the directory the automatic db.json backups of a game version are kept in.
+///
+/// The directory is created when the first backup is taken, so it isn't created here.
+///
+/// # Arguments
+///
+/// `beammm_dir`: The path to the beammm directory.
+/// `version`: The game version the backups are of.
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn db_backups_dir(beammm_dir: impl AsRef<Path>, version: &GameVersion) -> PathBuf {
+    let beammm_dir = beammm_dir.as_ref();
+    beammm_dir
+        .join("backups")
+        .join("db")
+        .join(version.to_string())
+}
+
+/// Get the path to the file holding the mods that were enabled in a game version before presets
+/// last changed its db.json, as saved by `Preset::save_last_known_good`.
+///
+/// The file is created when presets first change db.json, so it isn't created here.
+///
+/// # Arguments
+///
+/// `beammm_dir`: The path to the beammm directory.
+/// `version`: The game version the state is of.
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn last_known_good_file(beammm_dir: impl AsRef<Path>, version: &GameVersion) -> PathBuf {
+    let beammm_dir = beammm_dir.as_ref();
+    beammm_dir
+        .join("last-known-good")
+        .join(format!("{}.json", version))
+}
+
+/// Get the path to the directory copies of deleted presets are kept in.
+///
+/// The directory is created when the first preset is backed up, so it isn't created here.
+///
+/// # Arguments
+///
+/// `beammm_dir`: The path to the beammm directory.
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn preset_backups_dir(beammm_dir: impl AsRef<Path>) -> PathBuf {
+    let beammm_dir = beammm_dir.as_ref();
+    beammm_dir.join("backups").join("presets")
+}
+
+/// Get the path to the profiles directory and create it if it doesn't exist.
+///
+/// # Arguments
+///
+/// `beammm_dir`: The path to the beammm directory.
+///
+/// # Errors
+///
+/// * `std::io::Error` if there is a permissions issue when checking if the dir exists or if there
+///   is an issue creating the dir
+pub fn profiles_dir(beammm_dir: impl AsRef<Path>) -> Result<PathBuf> {
+    let beammm_dir = beammm_dir.as_ref();
+    let dir = beammm_dir.join("profiles");
+    validate_dir(dir)
+}
+
+/// Get the path to a profile's presets directory and create it if it doesn't exist.
+///
+/// The default profile uses the regular presets directory so that presets created before
+/// profiles existed keep working.
+///
+/// # Arguments
+///
+/// `beammm_dir`: The path to the beammm directory.
+/// `profile`: The name of the profile.
+///
+/// # Errors
+///
+/// * `InvalidProfileName` if the name can't be used as a profile directory name, e.g. from an
+///   active profile file written by hand
+/// * `std::io::Error` if there is a permissions issue when checking if the dir exists or if there
+///   is an issue creating the dir
+pub fn profile_presets_dir(beammm_dir: impl AsRef<Path>, profile: &str) -> Result<PathBuf> {
+    crate::Profile::validate_name(profile)?;
+    let beammm_dir = beammm_dir.as_ref();
+    if profile == crate::Profile::DEFAULT_NAME {
+        presets_dir(beammm_dir)
+    } else {
+        let dir = profiles_dir(beammm_dir)?.join(profile).join("presets");
+        validate_dir(dir)
+    }
+}
+
+/// Every directory BeamMM resolved for the current game version, so frontends and support
+/// helpers don't have to work them out again.
+///
+/// None of the directories are created, so some of them may not exist yet.
+///
+/// # Examples
+///
+/// ```rust
+/// use beammm::{path::ResolvedPaths, GameVersion};
+///
+/// let version = GameVersion { major: 0, minor: 32, patch: None };
+/// let paths = ResolvedPaths::new(
+///     "BeamMM",
+///     "BeamNG.drive",
+///     &version,
+///     "BeamNG.drive/0.32/mods",
+///     "BeamMM/presets",
+/// );
+/// assert_eq!(paths.mirror_dir, std::path::Path::new("BeamMM").join("mirror"));
+/// ```
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ResolvedPaths {
+    /// The game data directory.
+    pub game_data_dir: PathBuf,
+    /// The current game version.
+    pub game_version: String,
+    /// The mods directory of the current game version.
+    pub mods_dir: PathBuf,
+    /// The mods roots that exist in the mods directory, by name, e.g. `repo`.
+    pub mods_roots: BTreeMap<String, PathBuf>,
+    /// The BeamMM directory.
+    pub beammm_dir: PathBuf,
+    /// The presets directory of the active profile.
+    pub presets_dir: PathBuf,
+    /// The profiles directory.
+    pub profiles_dir: PathBuf,
+    /// Where copies of repository mods are kept.
+    pub mirror_dir: PathBuf,
+    /// Where the automatic db.json backups of the current game version are kept.
+    pub db_backups_dir: PathBuf,
+    /// Where copies of deleted presets are kept.
+    pub preset_backups_dir: PathBuf,
+    /// Where mod archives can be dropped to be installed.
+    pub inbox_dir: PathBuf,
+    /// Where corrupt mod archives are moved to.
+    pub quarantine_dir: PathBuf,
+    /// Where the operation log is kept.
+    pub logs_dir: PathBuf,
+    /// Where crash reports are written to.
+    pub crash_reports_dir: PathBuf,
+}
+
+impl ResolvedPaths {
+    /// Collect the directories derived from the ones BeamMM resolved.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The path to the beammm directory.
+    /// `game_data_dir`: The game data directory.
+    /// `version`: The current game version.
+    /// `mods_dir`: The mods directory of the current game version.
+    /// `presets_dir`: The presets directory of the active profile.
+    pub fn new(
+        beammm_dir: impl AsRef<Path>,
+        game_data_dir: impl AsRef<Path>,
+        version: &GameVersion,
+        mods_dir: impl AsRef<Path>,
+        presets_dir: impl AsRef<Path>,
+    ) -> Self {
+        let beammm_dir = beammm_dir.as_ref();
+        let mods_dir = mods_dir.as_ref();
+        Self {
+            game_data_dir: game_data_dir.as_ref().into(),
+            game_version: version.to_string(),
+            mods_dir: mods_dir.into(),
+            mods_roots: mods_roots(mods_dir)
+                .into_iter()
+                .map(|(root, dir)| (root.to_string(), dir))
+                .collect(),
+            beammm_dir: beammm_dir.into(),
+            presets_dir: presets_dir.as_ref().into(),
+            // These are the same as `profiles_dir`, `mirror_dir` and `quarantine_dir`, without
+            // creating them.
+            profiles_dir: beammm_dir.join("profiles"),
+            mirror_dir: beammm_dir.join("mirror"),
+            db_backups_dir: db_backups_dir(beammm_dir, version),
+            preset_backups_dir: preset_backups_dir(beammm_dir),
+            inbox_dir: inbox_dir(beammm_dir),
+            quarantine_dir: beammm_dir.join("quarantine"),
+            logs_dir: logs_dir(beammm_dir),
+            crash_reports_dir: crash_reports_dir(beammm_dir),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn extending_path_length() {
+        assert_eq!(extended_length(r"C:\Users\me"), r"\\?\C:\Users\me");
+        assert_eq!(
+            extended_length(r"\\server\share\mods"),
+            r"\\?\UNC\server\share\mods"
+        );
+        assert_eq!(extended_length(r"\\?\C:\mods"), r"\\?\C:\mods");
+        if !cfg!(windows) {
+            assert_eq!(long_path("mods").unwrap(), PathBuf::from("mods"));
+        }
+    }
+
+    #[test]
+    fn removing_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("preset.json");
+        fs::write(&file, "{}").unwrap();
+
+        remove_file(&file, true).unwrap();
+        assert!(!file.exists());
+        // Missing files are reported the same way whether they'd be trashed or not.
+        for permanent in [true, false] {
+            match remove_file(&file, permanent) {
+                Err(IO(e)) => assert_eq!(e.kind(), std::io::ErrorKind::NotFound),
+                other => panic!("Expected a NotFound error, got {:?}", other),
+            }
+        }
+    }
+
     #[test]
     fn test_validate_dir() {
         let tmp = tempfile::tempdir().unwrap();
@@ -188,15 +927,44 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_beamng_dirs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let temp_dir = tmp.path();
+
+        let first = temp_dir.join("first");
+        fs::create_dir_all(first.join("BeamNG.drive")).unwrap();
+        let second = temp_dir.join("second");
+        fs::create_dir_all(second.join("BeamNG.drive")).unwrap();
+        let without_beamng = temp_dir.join("without_beamng");
+        fs::create_dir(&without_beamng).unwrap();
+
+        // Every existing data dir is returned once, in order.
+        let possible_dirs = vec![
+            second.clone(),
+            without_beamng,
+            first.clone(),
+            second.clone(),
+        ];
+        assert_eq!(
+            beamng_dirs(possible_dirs.into_iter()),
+            vec![second.join("BeamNG.drive"), first.join("BeamNG.drive")]
+        );
+    }
+
     #[test]
     fn test_mods_dir() {
         let not_exists = PathBuf::from("not_exists");
-        let version = "0.32";
+        let version = &GameVersion {
+            major: 0,
+            minor: 32,
+            patch: None,
+        };
 
         let tmp = tempfile::tempdir().unwrap();
         let data_dir = tmp.path();
 
-        let version_dir = data_dir.join(version);
+        let version_dir = data_dir.join("0.32");
         fs::create_dir(&version_dir).unwrap();
 
         // Check that it returns an error when the data_dir doesn't exist.
@@ -217,4 +985,106 @@ mod tests {
         // Check that it returns the correct mods dir.
         assert_eq!(mods_dir(data_dir, version).unwrap(), mods_dir_path);
     }
+
+    #[test]
+    fn test_profile_presets_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let beammm_dir = tmp.path();
+
+        // The default profile shares the regular presets dir.
+        assert_eq!(
+            profile_presets_dir(beammm_dir, "default").unwrap(),
+            beammm_dir.join("presets")
+        );
+
+        let racing_presets = profile_presets_dir(beammm_dir, "racing").unwrap();
+        assert_eq!(
+            racing_presets,
+            beammm_dir.join("profiles").join("racing").join("presets")
+        );
+        assert!(racing_presets.exists());
+    }
+
+    #[test]
+    fn test_parse_startup_ini() {
+        let ini = "[filesystem]\n; UserPath = commented/out\nUserPath = \"D:/BeamNG User\"\n";
+        assert_eq!(
+            parse_startup_ini(ini).unwrap(),
+            PathBuf::from("D:/BeamNG User")
+        );
+
+        assert!(parse_startup_ini("[filesystem]\nUserPath =\n").is_none());
+        assert!(parse_startup_ini("").is_none());
+    }
+
+    #[test]
+    fn test_startup_ini_user_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let install_dir = tmp.path();
+
+        // No startup.ini means the user folder wasn't relocated.
+        assert!(startup_ini_user_dir(install_dir).unwrap().is_none());
+
+        let user_dir = install_dir.join("userfolder");
+        fs::write(
+            install_dir.join("startup.ini"),
+            format!("UserPath = {}", user_dir.display()),
+        )
+        .unwrap();
+        assert_eq!(
+            startup_ini_user_dir(install_dir).unwrap().unwrap(),
+            user_dir
+        );
+
+        // Relative paths are relative to the installation directory.
+        fs::write(install_dir.join("startup.ini"), "UserFolder = relative").unwrap();
+        assert_eq!(
+            startup_ini_user_dir(install_dir).unwrap().unwrap(),
+            install_dir.join("relative")
+        );
+    }
+
+    #[test]
+    fn test_install_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let steam_dir = tmp.path().join("Steam");
+        let library_dir = tmp.path().join("SteamLibrary");
+        fs::create_dir_all(steam_dir.join("steamapps")).unwrap();
+        fs::create_dir_all(library_dir.join("steamapps/common/BeamNG.drive")).unwrap();
+
+        // The game isn't in any library yet.
+        assert!(matches!(
+            install_dir(vec![steam_dir.clone()].into_iter()).unwrap_err(),
+            InstallDirNotFound
+        ));
+
+        let library_folders = format!(
+            "\"libraryfolders\"\n{{\n\t\"1\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t}}\n}}\n",
+            library_dir.display().to_string().replace('\\', "\\\\")
+        );
+        fs::write(
+            steam_dir.join("steamapps/libraryfolders.vdf"),
+            library_folders,
+        )
+        .unwrap();
+        fs::write(
+            library_dir.join("steamapps/appmanifest_284160.acf"),
+            "\"AppState\"\n{\n\t\"appid\"\t\t\"284160\"\n\t\"installdir\"\t\t\"BeamNG.drive\"\n}\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            install_dir(vec![steam_dir].into_iter()).unwrap(),
+            library_dir.join("steamapps/common/BeamNG.drive")
+        );
+    }
+
+    #[test]
+    fn test_vdf_values() {
+        let vdf = "\"0\"\n{\n\t\"path\"\t\t\"C:\\\\Steam\"\n}\n\"1\"\n{\n\t\"path\"\t\t\"D:\\\\Games\"\n}";
+        assert_eq!(
+            vdf_values(vdf, "path").collect::<Vec<_>>(),
+            vec![r"C:\Steam", r"D:\Games"]
+        );
+    }
 }
@@ -0,0 +1,310 @@
+use crate::{
+    game::ChangeSet,
+    index::{MembershipChange, PresetIndex},
+    transaction::{FileChange, Transaction},
+    Error::*,
+    Preset, Result,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// The changes a run of BeamMM would make, saved to be reviewed and then applied exactly as
+/// planned, the way `terraform plan` and `terraform apply` work.
+///
+/// A plan records every file it writes along with a hash of what the file held when the plan was
+/// made. Applying a plan refuses to touch anything if any of those files changed since, so a
+/// plan never overwrites changes it wasn't made from.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{game::ChangeSet, plan::Plan, transaction::Transaction};
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let db_json = temp_dir.path().join("db.json");
+/// std::fs::write(&db_json, "before").unwrap();
+///
+/// let mut transaction = Transaction::new();
+/// transaction.snapshot(&db_json).unwrap();
+/// std::fs::write(&db_json, "after").unwrap();
+/// let plan = Plan::new(&ChangeSet::default(), transaction.changes().unwrap());
+/// transaction.rollback().unwrap();
+///
+/// plan.apply(temp_dir.path().join("presets")).unwrap();
+/// assert_eq!(std::fs::read_to_string(&db_json).unwrap(), "after");
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Plan {
+    /// The mods the plan enables, sorted.
+    pub enabled: Vec<String>,
+    /// The mods the plan disables, sorted.
+    pub disabled: Vec<String>,
+    /// The mods the plan adds to and removes from presets, sorted by preset.
+    #[serde(default)]
+    pub preset_changes: Vec<MembershipChange>,
+    /// The files the plan writes or removes.
+    pub files: Vec<PlannedFile>,
+}
+
+/// A file written or removed by a `Plan`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PlannedFile {
+    /// The path to the file.
+    pub path: PathBuf,
+    /// The SHA-256 hash of the file when the plan was made, or `None` if it didn't exist.
+    pub before: Option<String>,
+    /// What the plan writes to the file, or `None` if the plan removes it.
+    pub contents: Option<String>,
+}
+
+impl Plan {
+    /// Make a plan from the mods a run would toggle and the files it changed.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_changes`: The mods the run would enable and disable.
+    /// `file_changes`: The files the run changed, from `Transaction::changes`.
+    pub fn new(mod_changes: &ChangeSet, file_changes: Vec<FileChange>) -> Self {
+        let files = file_changes
+            .into_iter()
+            .map(|change| PlannedFile {
+                path: change.path,
+                before: change
+                    .before
+                    .map(|before| format!("{:x}", Sha256::digest(before))),
+                contents: change
+                    .after
+                    .map(|after| String::from_utf8_lossy(&after).into_owned()),
+            })
+            .collect();
+        Self {
+            enabled: mod_changes.enabled.clone(),
+            disabled: mod_changes.disabled.clone(),
+            preset_changes: vec![],
+            files,
+        }
+    }
+
+    /// Whether the plan changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Load a plan from a reader.
+    ///
+    /// # Arguments
+    ///
+    /// `reader`: Thing to read from e.g. a file.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if the plan can't be parsed.
+    pub fn load<R: BufRead>(reader: R) -> Result<Self> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Load a plan from a file.
+    ///
+    /// # Arguments
+    ///
+    /// `path`: The path to the plan.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue opening the file, or serde_json errors if the plan
+    /// can't be parsed.
+    pub fn load_from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::load(BufReader::new(File::open(path)?))
+    }
+
+    /// Save the plan to a writer.
+    ///
+    /// # Arguments
+    ///
+    /// `writer`: Thing to write to e.g. a file.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue writing the plan.
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Save the plan to a file.
+    ///
+    /// # Arguments
+    ///
+    /// `path`: The path to save the plan to.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue creating the file, or serde_json errors if there
+    /// is an issue writing the plan.
+    pub fn save_to_path(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.save(BufWriter::new(File::create(path)?))
+    }
+
+    /// Write and remove the planned files, after checking that none of them changed since the
+    /// plan was made. Presets are saved with `Preset::save_to_path`, so their history is recorded,
+    /// and the preset index is refreshed. If a file can't be written, every file is put back.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: Where preset config files are stored.
+    ///
+    /// # Errors
+    ///
+    /// * `StalePlan`: If a file changed since the plan was made. Nothing is written.
+    /// * `std::io::Error`: If there is an issue reading, writing, or removing a file.
+    /// * `serde_json::Error`: If a planned preset can't be parsed.
+    pub fn apply(&self, presets_dir: impl AsRef<Path>) -> Result<()> {
+        let presets_dir = presets_dir.as_ref();
+        for file in &self.files {
+            let current = if file.path.try_exists()? {
+                Some(format!("{:x}", Sha256::digest(fs::read(&file.path)?)))
+            } else {
+                None
+            };
+            if current != file.before {
+                return Err(StalePlan {
+                    path: file.path.clone(),
+                });
+            }
+        }
+
+        let mut transaction = Transaction::new();
+        if presets_dir.try_exists()? {
+            transaction.snapshot_dir(presets_dir)?;
+        }
+        for file in &self.files {
+            transaction.snapshot(&file.path)?;
+        }
+        for file in &self.files {
+            match &file.contents {
+                Some(contents) => match Self::preset_name(&file.path, presets_dir) {
+                    Some(name) => {
+                        let preset = Preset::load(contents.as_bytes())?;
+                        if preset.name() == name {
+                            preset.save_to_path(presets_dir)?;
+                        } else {
+                            fs::write(&file.path, contents)?;
+                        }
+                    }
+                    None => fs::write(&file.path, contents)?,
+                },
+                None if file.path.try_exists()? => fs::remove_file(&file.path)?,
+                None => (),
+            }
+        }
+        if presets_dir.try_exists()? {
+            PresetIndex::load_from_path(presets_dir)?;
+        }
+        transaction.commit()
+    }
+
+    /// Get the name of the preset a planned file is, if it is a preset file in the presets
+    /// directory.
+    fn preset_name<'a>(path: &'a Path, presets_dir: &Path) -> Option<&'a str> {
+        if path.parent() != Some(presets_dir) || path.extension().is_none_or(|e| e != "json") {
+            return None;
+        }
+        path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .filter(|name| Preset::validate_name(name).is_ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::MockData, transaction::Transaction};
+
+    #[test]
+    fn planning_and_applying() {
+        let mock = MockData::new();
+        let db_json = mock.mods_dir.join("db.json");
+        let plan_path = mock.mods_dir.join("plan.json");
+        let before = fs::read_to_string(&db_json).unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction.snapshot(&db_json).unwrap();
+        transaction.snapshot_dir(&mock.presets_dir).unwrap();
+        let mut mod_cfg = mock.modcfg.clone();
+        mod_cfg.set_mod_active("mod1", false).unwrap();
+        let mod_changes = mod_cfg.diff_against_path(&mock.mods_dir).unwrap();
+        mod_cfg.save_to_path(&mock.mods_dir).unwrap();
+        crate::Preset::delete("preset2", &mock.presets_dir, true).unwrap();
+        let plan = Plan::new(&mod_changes, transaction.changes().unwrap());
+        transaction.rollback().unwrap();
+        plan.save_to_path(&plan_path).unwrap();
+
+        // Making the plan changed nothing.
+        assert_eq!(fs::read_to_string(&db_json).unwrap(), before);
+        assert!(mock.presets_dir.join("preset2.json").exists());
+
+        let plan = Plan::load_from_path(&plan_path).unwrap();
+        assert_eq!(plan.disabled, vec!["mod1"]);
+        assert_eq!(plan.files.len(), 2);
+        plan.apply(&mock.presets_dir).unwrap();
+        let applied = crate::game::ModCfg::load_from_path(&mock.mods_dir).unwrap();
+        assert_eq!(applied.is_mod_active("mod1"), Some(false));
+        assert!(!mock.presets_dir.join("preset2.json").exists());
+
+        // The plan was made from db.json as it was before, so it can't be applied again.
+        assert!(matches!(
+            plan.apply(&mock.presets_dir),
+            Err(StalePlan { .. })
+        ));
+    }
+
+    /// Plan a change to preset1 without making it.
+    fn plan_preset1(mock: &MockData, change: impl FnOnce(&mut Preset)) -> Plan {
+        let mut transaction = Transaction::new();
+        transaction.snapshot_dir(&mock.presets_dir).unwrap();
+        let mut preset1 = Preset::load_from_path("preset1", &mock.presets_dir).unwrap();
+        change(&mut preset1);
+        preset1.save_to_path(&mock.presets_dir).unwrap();
+        let changes = transaction
+            .changes()
+            .unwrap()
+            .into_iter()
+            .filter(|c| c.path == mock.presets_dir.join("preset1.json"))
+            .collect();
+        transaction.rollback().unwrap();
+        Plan::new(&ChangeSet::default(), changes)
+    }
+
+    #[test]
+    fn applying_presets() {
+        let mock = MockData::new();
+        let preset1_path = mock.presets_dir.join("preset1.json");
+
+        // Presets are saved like any other change, so the old version is kept in the history.
+        let plan = plan_preset1(&mock, |preset| preset.add_mod("mod3"));
+        plan.apply(&mock.presets_dir).unwrap();
+        let history = crate::history::revisions("preset1", &mock.presets_dir).unwrap();
+        assert_eq!(history.len(), 1);
+        let index = PresetIndex::load_from_path(&mock.presets_dir).unwrap();
+        assert_eq!(index.get("preset1").unwrap().mods().len(), 2);
+
+        // A file that can't be written puts back the ones written before it.
+        let before = fs::read(&preset1_path).unwrap();
+        let mut plan = plan_preset1(&mock, |preset| preset.add_mod("mod2"));
+        plan.files.push(PlannedFile {
+            path: mock.mods_dir.join("missing").join("file.json"),
+            before: None,
+            contents: Some("{}".into()),
+        });
+        assert!(plan.apply(&mock.presets_dir).is_err());
+        assert_eq!(fs::read(&preset1_path).unwrap(), before);
+        let history = crate::history::revisions("preset1", &mock.presets_dir).unwrap();
+        assert_eq!(history.len(), 1);
+    }
+}
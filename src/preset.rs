@@ -1,13 +1,58 @@
-use crate::{game::ModCfg, Error::*, Result};
+#[cfg(feature = "native")]
+use crate::index::PresetIndex;
+use crate::{
+    game::{MissingModPolicy, ModCfg, RepoVersion},
+    Error::*,
+    Result,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+#[cfg(feature = "native")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{BufRead, Read, Write},
+};
+#[cfg(feature = "native")]
+use std::{
     ffi::OsStr,
     fs::{self, File},
-    io::{BufRead, BufReader, BufWriter, Write},
-    path::Path,
+    io::BufReader,
+    path::{Path, PathBuf},
 };
 
+/// The prefix of preset share codes, marking the share code format version.
+const SHARE_CODE_PREFIX: &str = "bmm1:";
+
+/// The most bytes of JSON a share code may decompress to. Share codes are pasted in by other
+/// people, and a short code could otherwise decompress to gigabytes.
+const MAX_SHARE_CODE_JSON_LEN: u64 = 1024 * 1024;
+
+/// Characters that can't be used in file names on Windows, and so not in preset names.
+const INVALID_NAME_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Names Windows reserves for devices, which can't be used as file names even with an extension.
+const RESERVED_FILE_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// The parts of a preset included in share codes.
+#[derive(Serialize, Deserialize)]
+struct SharedPreset {
+    name: String,
+    mods: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    missing_mod_policy: Option<MissingModPolicy>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    launch_args: Vec<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    repo_versions: HashMap<String, RepoVersion>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    requires: BTreeMap<String, Vec<String>>,
+}
+
 /// A preset of mods suitable for enabling/disabling groups of mods.
 ///
 /// Presets are stored as JSON files in the BeamMM/presets directory.
@@ -23,7 +68,7 @@ use std::{
 /// let mods: Vec<String> = vec!["mod1".into(), "mod2".into()];
 ///
 /// // Create a preset
-/// let mut new_preset = Preset::new("preset_name".into(), mods.clone());
+/// let mut new_preset = Preset::new("preset_name".into(), mods.clone()).unwrap();
 /// new_preset.save_to_path(&presets_dir).unwrap();
 ///
 /// // Load a preset
@@ -32,7 +77,8 @@ use std::{
 /// ```
 ///
 /// See additional preset examples in each function's documentation.
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "PresetFile", into = "PresetFile")]
 pub struct Preset {
     /// The name of the preset.
     name: String,
@@ -40,10 +86,175 @@ pub struct Preset {
     mods: Vec<String>,
     /// Whether the preset is enabled.
     enabled: bool,
+    /// How to handle this preset's mods that are missing from the ModCfg. Overrides the policy
+    /// passed to `ModCfg::apply_presets`.
+    missing_mod_policy: Option<MissingModPolicy>,
+    /// Arguments to launch the game with when this preset is selected.
+    launch_args: Vec<String>,
+    /// The preset this preset is a variant of. Its mods are included in this preset's mods.
+    extends: Option<String>,
+    /// The repository versions of the preset's mods that were installed from the repository.
+    repo_versions: HashMap<String, RepoVersion>,
+    /// Mods mapped to the mods they need to work, e.g. a skin pack to its vehicle.
+    requires: BTreeMap<String, Vec<String>>,
+}
+
+/// A preset as it is stored in its JSON file.
+///
+/// The doc comments of the fields are the descriptions in the preset JSON Schema.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "native", derive(JsonSchema))]
+#[cfg_attr(feature = "native", schemars(rename = "Preset"))]
+struct PresetFile {
+    /// The version of the preset file format. Files from before the format was versioned don't
+    /// have one.
+    #[serde(default)]
+    format_version: u32,
+    /// The name of the preset. It matches the name of the file, without `.json`.
+    name: String,
+    /// The mods in the preset.
+    mods: Vec<ModEntry>,
+    /// Whether the preset is enabled.
+    enabled: bool,
+    /// How to handle this preset's mods that aren't installed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    missing_mod_policy: Option<MissingModPolicy>,
+    /// Arguments to launch the game with when this preset is selected.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    launch_args: Vec<String>,
+    /// The preset this preset is a variant of. Its mods are included in this preset's mods.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    extends: Option<String>,
+    /// Mods mapped to the mods they need to work, e.g. a skin pack to its vehicle.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    requires: BTreeMap<String, Vec<String>>,
+}
+
+/// A mod in a preset file. Mods without a known repository version are stored as plain names,
+/// which is also how every mod was stored before repository versions were recorded.
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "native", derive(JsonSchema))]
+#[serde(untagged)]
+enum ModEntry {
+    /// The name of a mod.
+    Name(String),
+    /// A mod installed from the repository, with the version that was installed.
+    Repo {
+        /// The name of the mod.
+        name: String,
+        /// The repository's ID of the mod.
+        resource_id: u64,
+        /// The repository's ID of the installed version.
+        version_id: u64,
+        /// The installed version as the mod's author wrote it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+    },
+}
+
+/// Upgrades from each older preset file format to the next, indexed by the version they upgrade
+/// from. Loading a preset runs every upgrade from the file's version on.
+const MIGRATIONS: [fn(&mut serde_json::Value); Preset::FORMAT_VERSION as usize] = [
+    // Version 0 is every file from before the format was versioned. Version 1 only adds the
+    // version itself.
+    |_| (),
+];
+
+impl From<PresetFile> for Preset {
+    fn from(file: PresetFile) -> Self {
+        let mut mods = vec![];
+        let mut repo_versions = HashMap::new();
+        for entry in file.mods {
+            match entry {
+                ModEntry::Name(name) => mods.push(name),
+                ModEntry::Repo {
+                    name,
+                    resource_id,
+                    version_id,
+                    version,
+                } => {
+                    let repo_version = RepoVersion {
+                        resource_id,
+                        version_id,
+                        version,
+                    };
+                    repo_versions.insert(name.clone(), repo_version);
+                    mods.push(name);
+                }
+            }
+        }
+        Preset {
+            name: file.name,
+            mods,
+            enabled: file.enabled,
+            missing_mod_policy: file.missing_mod_policy,
+            launch_args: file.launch_args,
+            extends: file.extends,
+            repo_versions,
+            requires: file.requires,
+        }
+    }
+}
+
+impl From<Preset> for PresetFile {
+    fn from(mut preset: Preset) -> Self {
+        let mods = preset
+            .mods
+            .into_iter()
+            .map(|name| match preset.repo_versions.remove(&name) {
+                Some(repo_version) => ModEntry::Repo {
+                    name,
+                    resource_id: repo_version.resource_id,
+                    version_id: repo_version.version_id,
+                    version: repo_version.version,
+                },
+                None => ModEntry::Name(name),
+            })
+            .collect();
+        PresetFile {
+            format_version: Preset::FORMAT_VERSION,
+            name: preset.name,
+            mods,
+            enabled: preset.enabled,
+            missing_mod_policy: preset.missing_mod_policy,
+            launch_args: preset.launch_args,
+            extends: preset.extends,
+            requires: preset.requires,
+        }
+    }
 }
 
 impl Preset {
-    /// Get an iterator over currently saved presets.
+    /// The name of the preset holding the mods that were enabled before presets last changed
+    /// db.json. It is kept outside the presets directory, so it is never listed or applied like
+    /// the other presets.
+    pub const LAST_KNOWN_GOOD: &'static str = "_last_known_good";
+
+    /// The version of the preset file format written by this version of BeamMM.
+    pub const FORMAT_VERSION: u32 = 1;
+
+    /// Get the JSON Schema of preset files, so editors and other tools can check a preset file
+    /// before BeamMM loads it.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::Preset;
+    ///
+    /// let schema = Preset::json_schema();
+    /// assert_eq!(schema["title"], "Preset");
+    /// assert_eq!(schema["required"], serde_json::json!(["enabled", "mods", "name"]));
+    /// ```
+    #[cfg(feature = "native")]
+    pub fn json_schema() -> serde_json::Value {
+        // Serializing a schema to a Value never fails.
+        serde_json::to_value(schemars::schema_for!(PresetFile)).unwrap_or_default()
+    }
+
+    /// Get an iterator over currently saved presets, sorted by name.
+    ///
+    /// Preset files whose names aren't valid Unicode are left out. Find them with
+    /// `Preset::list_unreadable`. So are files whose names fail `Preset::validate_name`, e.g.
+    /// ones created before names were checked. Find them with `Preset::list_invalid`.
     ///
     /// # Arguments
     ///
@@ -53,8 +264,10 @@ impl Preset {
     ///
     /// Possible IO errors if the path doesn't exist, there is a permission issue,
     /// or if the path is not a directory.
-    pub fn list(presets_dir: &Path) -> Result<impl Iterator<Item = String>> {
-        Ok(fs::read_dir(presets_dir)?
+    #[cfg(feature = "native")]
+    pub fn list(presets_dir: impl AsRef<Path>) -> Result<impl Iterator<Item = String>> {
+        let presets_dir = presets_dir.as_ref();
+        let mut names: Vec<String> = fs::read_dir(presets_dir)?
             .filter_map(|f| f.ok().map(|f| f.path())) // Get rid of errors and map to path type
             .filter(|f| f.is_file() && f.extension().unwrap_or(OsStr::new("")) == "json") // Filter out dirs and non-json files
             // Map to remove the json extension so we just have the preset name and convert to String
@@ -64,23 +277,225 @@ impl Preset {
                     .file_name()
                     .and_then(OsStr::to_str)
                     .map(|f| f.to_string())
-            }))
+            })
+            .filter(|name| Self::validate_name(name).is_ok())
+            .collect();
+        // The directory is read in whatever order the file system keeps it.
+        names.sort();
+        Ok(names.into_iter())
+    }
+
+    /// Get the preset files that `Preset::list` leaves out because their names aren't valid
+    /// Unicode, so they can be reported rather than silently ignored.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: Where preset config files are stored.
+    ///
+    /// # Returns
+    ///
+    /// The paths of the files, sorted.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading the presets directory.
+    #[cfg(feature = "native")]
+    pub fn list_unreadable(presets_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        Ok(crate::path::non_unicode_entries(presets_dir)?
+            .into_iter()
+            .filter(|f| f.is_file() && f.extension().unwrap_or(OsStr::new("")) == "json")
+            .collect())
+    }
+
+    /// Get the preset files that `Preset::list` leaves out because their names fail
+    /// `Preset::validate_name`, e.g. `what?.json` created before names were checked, so they can
+    /// be reported rather than silently ignored. They can still be deleted with `Preset::delete`.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: Where preset config files are stored.
+    ///
+    /// # Returns
+    ///
+    /// The paths of the files, sorted.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading the presets directory.
+    #[cfg(feature = "native")]
+    pub fn list_invalid(presets_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let mut found = vec![];
+        for entry in fs::read_dir(presets_dir)? {
+            let path = entry?.path();
+            if !path.is_file() || path.extension().unwrap_or(OsStr::new("")) != "json" {
+                continue;
+            }
+            let name = path.with_extension("");
+            let Some(name) = name.file_name().and_then(OsStr::to_str) else {
+                continue;
+            };
+            if Self::validate_name(name).is_err() {
+                found.push(path);
+            }
+        }
+        found.sort();
+        Ok(found)
     }
 
     /// Create a new preset.
     ///
     /// # Arguments
     ///
-    /// `name`: The name of the preset.
+    /// `name`: The name of the preset. It must pass `Preset::validate_name`.
     /// `mods`: The mods to include in the preset.
-    pub fn new(name: String, mods: Vec<String>) -> Self {
-        Preset {
+    ///
+    /// # Errors
+    ///
+    /// InvalidPresetName: If the name can't be used as a preset file name.
+    pub fn new(name: String, mods: Vec<String>) -> Result<Self> {
+        Self::validate_name(&name)?;
+        Ok(Preset {
             name,
             mods,
             enabled: false,
+            missing_mod_policy: None,
+            launch_args: vec![],
+            extends: None,
+            repo_versions: HashMap::new(),
+            requires: BTreeMap::new(),
+        })
+    }
+
+    /// Check that a name can be used for a preset. Preset names are used as file names, so they
+    /// must be a single file name that is valid on every platform the game runs on.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The name to check.
+    ///
+    /// # Errors
+    ///
+    /// InvalidPresetName: If the name is empty, refers to a directory, contains characters file
+    /// names can't, ends with a dot or space, or is a name Windows reserves for devices.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::Preset;
+    ///
+    /// assert!(Preset::validate_name("racing v1.2").is_ok());
+    /// assert!(Preset::validate_name("../evil").is_err());
+    /// assert!(Preset::validate_name("con").is_err());
+    /// ```
+    pub fn validate_name(name: &str) -> Result<()> {
+        let invalid = |reason: String| {
+            Err(InvalidPresetName {
+                preset: name.into(),
+                reason,
+            })
+        };
+        if name.is_empty() {
+            return invalid("it is empty".into());
+        }
+        if name == "." || name == ".." {
+            return invalid("it refers to a directory".into());
+        }
+        if let Some(c) = name
+            .chars()
+            .find(|c| INVALID_NAME_CHARS.contains(c) || c.is_control())
+        {
+            return invalid(format!("it contains {:?}, which file names can't", c));
+        }
+        if name.ends_with(['.', ' ']) {
+            return invalid("it ends with a dot or space, which Windows drops".into());
+        }
+        let stem = name.split('.').next().unwrap_or_default().trim_end();
+        if RESERVED_FILE_NAMES
+            .iter()
+            .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+        {
+            return invalid("it is reserved by Windows".into());
+        }
+        Ok(())
+    }
+
+    /// Check that a name can refer to an existing preset file. Unlike `Preset::validate_name`,
+    /// this only rejects names that would lead out of the presets directory, so files saved before
+    /// names were checked can still be loaded and deleted. Windows can't hold such files, so the
+    /// full check is used there.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The name to check.
+    ///
+    /// # Errors
+    ///
+    /// InvalidPresetName: If the name isn't a single file name.
+    #[cfg(feature = "native")]
+    pub(crate) fn check_file_name(name: &str) -> Result<()> {
+        if cfg!(windows) {
+            return Self::validate_name(name);
+        }
+        let mut components = std::path::Path::new(name).components();
+        match (components.next(), components.next()) {
+            (Some(std::path::Component::Normal(part)), None) if part == name => Ok(()),
+            _ => Err(InvalidPresetName {
+                preset: name.into(),
+                reason: "it isn't a single file name".into(),
+            }),
+        }
+    }
+
+    /// Turn any text into a valid preset name, e.g. to name a preset after an imported file.
+    ///
+    /// Characters file names can't contain are replaced with underscores, trailing dots and
+    /// spaces are dropped, and names Windows reserves get an underscore in front. Valid names are
+    /// returned unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The text to make a name from.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::Preset;
+    ///
+    /// assert_eq!(Preset::sanitize_name("../evil"), ".._evil");
+    /// assert_eq!(Preset::sanitize_name("what? "), "what_");
+    /// assert_eq!(Preset::sanitize_name("aux"), "_aux");
+    /// ```
+    pub fn sanitize_name(name: &str) -> String {
+        let name: String = name
+            .chars()
+            .map(|c| {
+                if INVALID_NAME_CHARS.contains(&c) || c.is_control() {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .collect();
+        let name = name.trim_end_matches(['.', ' ']);
+        if name.is_empty() {
+            return "preset".into();
+        }
+        match Self::validate_name(name) {
+            Ok(()) => name.into(),
+            Err(_) => format!("_{}", name),
         }
     }
 
+    /// Get the path of a preset's file.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The name of the preset.
+    /// `presets_dir`: The directory where the preset is stored.
+    #[cfg(feature = "native")]
+    pub fn path(name: &str, presets_dir: impl AsRef<Path>) -> PathBuf {
+        // Not `with_extension`, which would replace anything after a dot in the name.
+        presets_dir.as_ref().join(format!("{}.json", name))
+    }
+
     /// Serialize and save the preset to a writer.
     ///
     /// # Arguments
@@ -99,30 +514,73 @@ impl Preset {
 
     /// Serialize and save the preset to a file.
     ///
+    /// The file isn't rewritten if it already holds this exact preset. Otherwise, the version
+    /// being replaced is recorded in the preset's history, so it can be brought back with
+    /// `history::revert`, unless the preset was only enabled or disabled.
+    ///
     /// # Arguments
     ///
     /// `presets_dir`: The directory where the preset will be saved.
     ///
     /// # Errors
     ///
-    /// Possible IO errors if there is an issue creating the file or writing to it.
-    pub fn save_to_path(&self, presets_dir: &Path) -> Result<()> {
-        let file = File::create(presets_dir.join(&self.name).with_extension("json"))?;
-        let writer = BufWriter::new(file);
-        self.save(writer)
+    /// * `InvalidPresetName`: If the preset's name, e.g. from a file written by hand, can't be
+    ///   used as a file name.
+    /// * `PermissionDenied`: If BeamMM isn't allowed to write the file.
+    /// * `std::io::Error`: If there is an issue recording the history, creating the file, or
+    ///   writing to it.
+    #[cfg(feature = "native")]
+    pub fn save_to_path(&self, presets_dir: impl AsRef<Path>) -> Result<()> {
+        let presets_dir = presets_dir.as_ref();
+        Self::validate_name(&self.name)?;
+        let path = Self::path(&self.name, presets_dir);
+        let mut bytes = vec![];
+        self.save(&mut bytes)?;
+        if let Ok(on_disk) = fs::read(&path) {
+            if on_disk == bytes {
+                return Ok(());
+            }
+            let toggled = Self::load(on_disk.as_slice()).is_ok_and(|mut old| {
+                old.enabled = self.enabled;
+                old == *self
+            });
+            if !toggled {
+                crate::history::record(&self.name, presets_dir, &on_disk)?;
+            }
+        }
+        fs::write(&path, bytes).map_err(crate::Error::io(&path))
     }
 
     /// Deserialize and load a preset from a reader.
     ///
+    /// Presets saved in an older file format are upgraded to the current one as they are loaded.
+    /// They are saved in the current format the next time they are saved.
+    ///
     /// # Arguments
     ///
     /// `reader`: The reader to load the preset from.
     ///
     /// # Errors
     ///
-    /// Possible serde_json errors if there is an issue reading or deserializing the preset.
+    /// * `UnsupportedPresetFormat`: If the preset was saved by a newer version of BeamMM.
+    /// * `serde_json::Error`: If there is an issue reading or deserializing the preset.
     pub fn load<R: BufRead>(reader: R) -> Result<Self> {
-        Ok(serde_json::from_reader(reader)?)
+        let mut value: serde_json::Value = serde_json::from_reader(reader)?;
+        let format_version = value
+            .get("format_version")
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or(0);
+        if format_version > u64::from(Self::FORMAT_VERSION) {
+            let name = value.get("name").and_then(serde_json::Value::as_str);
+            return Err(UnsupportedPresetFormat {
+                preset: name.unwrap_or_default().into(),
+                format_version,
+            });
+        }
+        for migration in MIGRATIONS.iter().skip(format_version as usize) {
+            migration(&mut value);
+        }
+        Ok(serde_json::from_value(value)?)
     }
 
     /// Deserialize and load a preset from a file.
@@ -134,12 +592,16 @@ impl Preset {
     ///
     /// # Errors
     ///
+    /// InvalidPresetName: If the name isn't a single file name.
     /// Possible IO errors if there is an issue reading the file or serde_json errors if there is
     /// an issue deserializing the preset.
-    pub fn load_from_path(name: &str, presets_dir: &Path) -> Result<Self> {
-        let preset_path = presets_dir.join(name).with_extension("json");
+    #[cfg(feature = "native")]
+    pub fn load_from_path(name: &str, presets_dir: impl AsRef<Path>) -> Result<Self> {
+        Self::check_file_name(name)?;
+        let presets_dir = presets_dir.as_ref();
+        let preset_path = Self::path(name, presets_dir);
         if preset_path.try_exists()? {
-            let file = File::open(preset_path)?;
+            let file = File::open(&preset_path).map_err(crate::Error::io(&preset_path))?;
             let reader = BufReader::new(file);
             Self::load(reader)
         } else {
@@ -150,19 +612,23 @@ impl Preset {
         }
     }
 
-    /// Delete a preset.
+    /// Delete a preset, sending its file to the system trash unless it should be removed
+    /// permanently.
     ///
     /// # Arguments
     ///
     /// `name`: The name of the preset to delete.
     /// `presets_dir`: The directory where the preset is stored.
+    /// `permanent`: Whether to remove the file permanently instead of sending it to the trash.
     ///
     /// # Errors
     ///
-    /// Possible IO errors if there is an issue deleting the file.
-    pub fn delete(name: &str, presets_dir: &Path) -> Result<()> {
-        fs::remove_file(presets_dir.join(name).with_extension("json"))?;
-        Ok(())
+    /// InvalidPresetName: If the name isn't a single file name.
+    /// Possible IO errors if the preset doesn't exist or there is an issue deleting the file.
+    #[cfg(feature = "native")]
+    pub fn delete(name: &str, presets_dir: impl AsRef<Path>, permanent: bool) -> Result<()> {
+        Self::check_file_name(name)?;
+        crate::path::remove_file(Self::path(name, presets_dir), permanent)
     }
 
     /// Add a mod to the preset.
@@ -179,8 +645,9 @@ impl Preset {
     /// # Arguments
     ///
     /// `mods`: The mods to add.
-    pub fn add_mods(&mut self, mods: &[String]) {
-        self.mods.extend(mods.iter().cloned())
+    pub fn add_mods(&mut self, mods: impl IntoIterator<Item = impl AsRef<str>>) {
+        self.mods
+            .extend(mods.into_iter().map(|m| m.as_ref().to_owned()))
     }
 
     /// Remove a mod from the preset.
@@ -192,7 +659,45 @@ impl Preset {
     ///
     /// `mod_name`: The name of the mod to remove.
     pub fn remove_mod(&mut self, mod_name: &str) {
-        self.mods.retain(|m| m != mod_name)
+        self.mods.retain(|m| m != mod_name);
+        self.repo_versions.remove(mod_name);
+    }
+
+    /// Rename a mod in the preset, keeping its place, recorded repository version, and
+    /// requirements.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod to rename.
+    /// `new_name`: The new name of the mod.
+    ///
+    /// # Returns
+    ///
+    /// Whether the preset referenced the mod.
+    pub fn rename_mod(&mut self, mod_name: &str, new_name: &str) -> bool {
+        let mut renamed = false;
+        for m in self.mods.iter_mut().filter(|m| *m == mod_name) {
+            *m = new_name.into();
+            renamed = true;
+        }
+        if let Some(version) = self.repo_versions.remove(mod_name) {
+            self.repo_versions.insert(new_name.into(), version);
+            renamed = true;
+        }
+        if let Some(required) = self.requires.remove(mod_name) {
+            self.requires.insert(new_name.into(), required);
+            renamed = true;
+        }
+        for m in self
+            .requires
+            .values_mut()
+            .flatten()
+            .filter(|m| *m == mod_name)
+        {
+            *m = new_name.into();
+            renamed = true;
+        }
+        renamed
     }
 
     /// Remove multiple mods from the preset.
@@ -203,11 +708,238 @@ impl Preset {
     /// # Arguments
     ///
     /// `mods`: The mods to remove.
-    pub fn remove_mods(&mut self, mods: &[String]) {
+    pub fn remove_mods(&mut self, mods: impl IntoIterator<Item = impl AsRef<str>>) {
         // Convert to HashSet so we can O(1) check if a mod is in the mods to remove.
-        let values_to_remove: HashSet<&String> = mods.iter().collect();
+        let values_to_remove: HashSet<String> =
+            mods.into_iter().map(|m| m.as_ref().to_owned()).collect();
+
+        self.mods.retain(|m| !values_to_remove.contains(m));
+        self.repo_versions
+            .retain(|m, _| !values_to_remove.contains(m));
+    }
+
+    /// Replace the preset's mods with a new list, e.g. after editing the preset in a dialog.
+    ///
+    /// Repository versions recorded for mods that are no longer in the preset are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// `mods`: The preset's new mods.
+    pub fn set_mods(&mut self, mods: Vec<String>) {
+        self.mods = mods;
+        let mods: HashSet<&String> = self.mods.iter().collect();
+        self.repo_versions.retain(|m, _| mods.contains(m));
+    }
+
+    /// Remove every mod from the preset.
+    pub fn clear_mods(&mut self) {
+        self.set_mods(vec![]);
+    }
+
+    /// Remove a mod from every preset that contains it, saving the changed presets.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod to remove.
+    /// `presets_dir`: The directory where the presets are stored.
+    ///
+    /// # Returns
+    ///
+    /// The names of the presets the mod was removed from, sorted.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors when reading the presets directory or serde_json errors if there is an
+    /// issue loading or saving a preset.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::Preset;
+    /// # use tempfile::tempdir;
+    ///
+    /// # let temp_presets_dir = tempdir().unwrap();
+    /// # let presets_dir = temp_presets_dir.path();
+    /// Preset::new("preset1".into(), vec!["mod1".into()]).unwrap().save_to_path(&presets_dir).unwrap();
+    /// Preset::new("preset2".into(), vec!["mod2".into()]).unwrap().save_to_path(&presets_dir).unwrap();
+    ///
+    /// let changed = Preset::remove_mod_from_all("mod1", &presets_dir).unwrap();
+    /// assert_eq!(changed, vec!["preset1"]);
+    /// ```
+    #[cfg(feature = "native")]
+    pub fn remove_mod_from_all(
+        mod_name: &str,
+        presets_dir: impl AsRef<Path>,
+    ) -> Result<Vec<String>> {
+        let presets_dir = presets_dir.as_ref();
+        let mut changed = vec![];
+        for preset_name in PresetIndex::load_from_path(presets_dir)?.containing(mod_name) {
+            let mut preset = Self::load_from_path(preset_name, presets_dir)?;
+            preset.remove_mod(mod_name);
+            preset.save_to_path(presets_dir)?;
+            changed.push(preset_name.to_string());
+        }
+        Ok(changed)
+    }
+
+    /// Rename a mod in every preset that references it.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod to rename.
+    /// `new_name`: The new name of the mod.
+    /// `presets_dir`: The directory where the presets are stored.
+    ///
+    /// # Returns
+    ///
+    /// The names of the presets that were changed, sorted.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors when reading the presets directory or serde_json errors if there is an
+    /// issue loading or saving a preset.
+    #[cfg(feature = "native")]
+    pub fn rename_mod_in_all(
+        mod_name: &str,
+        new_name: &str,
+        presets_dir: impl AsRef<Path>,
+    ) -> Result<Vec<String>> {
+        let presets_dir = presets_dir.as_ref();
+        let mut changed = vec![];
+        // Requirements aren't in the index, so every preset is checked.
+        for preset_name in PresetIndex::load_from_path(presets_dir)?
+            .iter()
+            .map(|(n, _)| n)
+        {
+            let mut preset = Self::load_from_path(preset_name, presets_dir)?;
+            if preset.rename_mod(mod_name, new_name) {
+                preset.save_to_path(presets_dir)?;
+                changed.push(preset_name.to_string());
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Remove every mod from the preset that doesn't exist in the ModCfg.
+    ///
+    /// Only the preset in memory is changed. Save the preset to persist the removal.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg to check the preset's mods against.
+    ///
+    /// # Returns
+    ///
+    /// The mods that were removed from the preset.
+    pub fn prune_missing(&mut self, mod_config: &ModCfg) -> Vec<String> {
+        let mut missing: Vec<String> = vec![];
+        for mod_name in &self.mods {
+            if mod_config.is_mod_active(mod_name).is_none() && !missing.contains(mod_name) {
+                missing.push(mod_name.clone());
+            }
+        }
+        self.remove_mods(&missing);
+        missing
+    }
+
+    /// Remove every mod that doesn't exist in the ModCfg from every preset, saving the pruned
+    /// presets.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: The directory where the presets are stored.
+    /// `mod_config`: The ModCfg to check the presets' mods against.
+    ///
+    /// # Returns
+    ///
+    /// Each pruned preset's name along with the mods removed from it, sorted by preset name.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors when reading the presets directory or serde_json errors if there is an
+    /// issue loading or saving a preset.
+    #[cfg(feature = "native")]
+    pub fn prune_all(
+        presets_dir: impl AsRef<Path>,
+        mod_config: &ModCfg,
+    ) -> Result<Vec<(String, Vec<String>)>> {
+        let presets_dir = presets_dir.as_ref();
+        let mut pruned = vec![];
+        for preset_name in Self::list(presets_dir)? {
+            let mut preset = Self::load_from_path(&preset_name, presets_dir)?;
+            let removed = preset.prune_missing(mod_config);
+            if removed.is_empty() {
+                continue;
+            }
+            preset.save_to_path(presets_dir)?;
+            pruned.push((preset_name, removed));
+        }
+        pruned.sort();
+        Ok(pruned)
+    }
 
-        self.mods.retain(|m| !values_to_remove.contains(m))
+    /// Sort the preset's mods and the mods each of them requires, dropping duplicates, so the
+    /// preset is saved the same way no matter the order its mods were added in.
+    ///
+    /// Only the preset in memory is changed. Save the preset to persist the new order.
+    ///
+    /// # Returns
+    ///
+    /// Whether the order changed.
+    pub fn sort(&mut self) -> bool {
+        let before = (self.mods.clone(), self.requires.clone());
+        self.mods.sort();
+        self.mods.dedup();
+        for required in self.requires.values_mut() {
+            required.sort();
+            required.dedup();
+        }
+        before != (self.mods.clone(), self.requires.clone())
+    }
+
+    /// Sort every preset with `Preset::sort`, saving those whose order changed. Together with the
+    /// fixed order of the keys in preset files, this makes saving the same presets always give
+    /// the same bytes, which keeps git-tracked or synced presets folders free of noise.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: The directory where the presets are stored.
+    ///
+    /// # Returns
+    ///
+    /// The names of the presets that were sorted, sorted.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors when reading the presets directory or serde_json errors if there is an
+    /// issue loading or saving a preset.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::Preset;
+    /// # use tempfile::tempdir;
+    ///
+    /// # let temp_presets_dir = tempdir().unwrap();
+    /// # let presets_dir = temp_presets_dir.path();
+    /// Preset::new("preset1".into(), vec!["mod2".into(), "mod1".into()]).unwrap().save_to_path(&presets_dir).unwrap();
+    ///
+    /// assert_eq!(Preset::sort_all(&presets_dir).unwrap(), vec!["preset1"]);
+    /// let preset = Preset::load_from_path("preset1", &presets_dir).unwrap();
+    /// assert_eq!(preset.get_mods(), &vec!["mod1".to_string(), "mod2".to_string()]);
+    /// assert!(Preset::sort_all(&presets_dir).unwrap().is_empty());
+    /// ```
+    #[cfg(feature = "native")]
+    pub fn sort_all(presets_dir: impl AsRef<Path>) -> Result<Vec<String>> {
+        let presets_dir = presets_dir.as_ref();
+        let mut sorted = vec![];
+        for preset_name in Self::list(presets_dir)? {
+            let mut preset = Self::load_from_path(&preset_name, presets_dir)?;
+            if preset.sort() {
+                preset.save_to_path(presets_dir)?;
+                sorted.push(preset_name);
+            }
+        }
+        sorted.sort();
+        Ok(sorted)
     }
 
     /// Enable the preset.
@@ -224,7 +956,7 @@ impl Preset {
     /// # Examples
     ///
     /// ```rust
-    /// use beammm::{Preset, game::ModCfg};
+    /// use beammm::{Preset, game::{MissingModPolicy, ModCfg}};
     /// # use tempfile::tempdir;
     ///
     /// # // Set up temp mock directories
@@ -236,12 +968,12 @@ impl Preset {
     /// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{\"mod1\":{\"active\":false},\"mod2\":{\"active\":false}}}").unwrap();
     /// #
     /// let mut mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
-    /// let mut preset = Preset::new("preset_name".into(), vec!["mod1".into(), "mod2".into()]);
+    /// let mut preset = Preset::new("preset_name".into(), vec!["mod1".into(), "mod2".into()]).unwrap();
     ///
     /// preset.enable();
     /// preset.save_to_path(&presets_dir).unwrap();
     ///
-    /// mod_cfg.apply_presets(&presets_dir).unwrap();
+    /// mod_cfg.apply_presets(&presets_dir, MissingModPolicy::Fail).unwrap();
     /// mod_cfg.save_to_path(&mods_dir).unwrap();
     /// ```
     pub fn enable(&mut self) {
@@ -273,7 +1005,7 @@ impl Preset {
     /// # Examples
     ///
     /// ```rust
-    /// use beammm::{Preset, game::ModCfg};
+    /// use beammm::{Preset, game::{MissingModPolicy, ModCfg}};
     /// # use tempfile::tempdir;
     ///
     /// # // Set up temp mock directories
@@ -285,16 +1017,21 @@ impl Preset {
     /// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{\"mod1\":{\"active\":true},\"mod2\":{\"active\":true}}}").unwrap();
     /// #
     /// let mut mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
-    /// let mut preset = Preset::new("preset_name".into(), vec!["mod1".into(), "mod2".into()]);
+    /// let mut preset = Preset::new("preset_name".into(), vec!["mod1".into(), "mod2".into()]).unwrap();
     ///
     /// preset.disable(&mut mod_cfg).unwrap();
     /// preset.save_to_path(&presets_dir).unwrap();
     ///
-    /// mod_cfg.apply_presets(&presets_dir).unwrap();
+    /// mod_cfg.apply_presets(&presets_dir, MissingModPolicy::Fail).unwrap();
     /// mod_cfg.save_to_path(&mods_dir).unwrap();
     /// ```
     pub fn disable(&mut self, mod_config: &mut ModCfg) -> Result<()> {
-        mod_config.set_mods_active(&self.mods, false)?;
+        let mods: Vec<&String> = self
+            .mods
+            .iter()
+            .filter(|m| !mod_config.is_ignored(m))
+            .collect();
+        mod_config.set_mods_active(mods, false)?;
         self.enabled = false;
         Ok(())
     }
@@ -308,49 +1045,643 @@ impl Preset {
     pub fn force_disable(&mut self, mod_config: &mut ModCfg) {
         self.enabled = false;
         for mod_name in &self.mods {
+            if mod_config.is_ignored(mod_name) {
+                continue;
+            }
             // We don't care if the mod is already disabled or doesn't exist.
             let _ = mod_config.set_mod_active(mod_name, false);
         }
     }
 
-    /// Get the enabled status of the preset.
-    pub fn is_enabled(&self) -> bool {
-        self.enabled
-    }
-
-    /// Get a list of mods in the preset.
-    pub fn get_mods(&self) -> &Vec<String> {
-        &self.mods
-    }
-
-    /// Check if a preset already exists.
+    /// Enable or disable several presets at once.
+    ///
+    /// Every preset is loaded before any is changed, so a missing preset leaves all of them
+    /// untouched. Disabling a preset disables its mods in the ModCfg, including the mods it
+    /// inherits from the presets it extends. As with `Preset::enable` and `Preset::disable`, call
+    /// `ModCfg::apply_presets` once afterwards and save the ModCfg.
     ///
     /// # Arguments
     ///
-    /// `name`: The name of the preset to check for.
+    /// `names`: The names of the presets.
+    /// `enabled`: Whether the presets should be enabled or disabled.
     /// `presets_dir`: The directory where the presets are stored.
-    #[cfg_attr(coverage_nightly, coverage(off))]
-    pub fn exists(name: &str, presets_dir: &Path) -> bool {
-        presets_dir.join(name).with_extension("json").exists()
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::test_utils::MockData;
-
-    #[test]
+    /// `mod_config`: The ModCfg to disable the presets' mods in.
+    ///
+    /// # Errors
+    ///
+    /// MissingPreset: If one of the presets doesn't exist.
+    /// MissingMods: If a preset being disabled contains mods that don't exist in the ModCfg.
+    /// Possible IO or serde_json errors if there is an issue loading or saving a preset.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::{Preset, game::{MissingModPolicy, ModCfg}};
+    /// # use tempfile::tempdir;
+    ///
+    /// # let temp_presets_dir = tempdir().unwrap();
+    /// # let presets_dir = temp_presets_dir.path();
+    /// # let temp_mods_dir = tempdir().unwrap();
+    /// # let mods_dir = temp_mods_dir.path();
+    /// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{\"mod1\":{\"active\":false},\"mod2\":{\"active\":false}}}").unwrap();
+    /// let mut mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+    /// Preset::new("preset1".into(), vec!["mod1".into()]).unwrap().save_to_path(&presets_dir).unwrap();
+    /// Preset::new("preset2".into(), vec!["mod2".into()]).unwrap().save_to_path(&presets_dir).unwrap();
+    ///
+    /// Preset::set_many_enabled(["preset1", "preset2"], true, &presets_dir, &mut mod_cfg).unwrap();
+    /// mod_cfg.apply_presets(&presets_dir, MissingModPolicy::Fail).unwrap();
+    /// mod_cfg.save_to_path(&mods_dir).unwrap();
+    /// ```
+    #[cfg(feature = "native")]
+    pub fn set_many_enabled(
+        names: impl IntoIterator<Item = impl AsRef<str>>,
+        enabled: bool,
+        presets_dir: impl AsRef<Path>,
+        mod_config: &mut ModCfg,
+    ) -> Result<()> {
+        let presets_dir = presets_dir.as_ref();
+        let mut presets = vec![];
+        for name in names {
+            presets.push(Self::load_from_path(name.as_ref(), presets_dir)?);
+        }
+
+        for preset in presets.iter_mut() {
+            if enabled {
+                preset.enable();
+            } else {
+                preset.disable(mod_config)?;
+                for mod_name in preset.resolve_mods(presets_dir)? {
+                    if mod_config.is_ignored(&mod_name) {
+                        continue;
+                    }
+                    // Inherited mods that don't exist are already handled by the parent preset.
+                    let _ = mod_config.set_mod_active(&mod_name, false);
+                }
+            }
+        }
+        for preset in presets.iter() {
+            preset.save_to_path(presets_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Get the name of the preset.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Change the name of the preset.
+    ///
+    /// Only the preset in memory is changed. Saving it writes a file under the new name and
+    /// leaves the file under the old name alone.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The new name of the preset.
+    ///
+    /// # Errors
+    ///
+    /// InvalidPresetName: If the name can't be used as a preset file name.
+    pub fn set_name(&mut self, name: String) -> Result<()> {
+        Self::validate_name(&name)?;
+        self.name = name;
+        Ok(())
+    }
+
+    /// Name the preset, and the preset it extends, after the shared namespace it was loaded from,
+    /// e.g. `racing` to `shared/racing`, so it can be applied alongside the personal presets.
+    /// Qualified names aren't valid file names, so the preset can't be saved afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// `namespace`: The name of the namespace.
+    #[cfg(feature = "native")]
+    pub(crate) fn qualify(&mut self, namespace: &str) {
+        self.name = crate::namespace::qualified(namespace, &self.name);
+        self.extends = self
+            .extends
+            .as_deref()
+            .map(|parent| crate::namespace::qualified(namespace, parent));
+    }
+
+    /// Set whether the preset is enabled without touching its mods, for replacing a preset with
+    /// another version of itself.
+    ///
+    /// # Arguments
+    ///
+    /// `enabled`: Whether the preset is enabled.
+    #[cfg(feature = "native")]
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Get the enabled status of the preset.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Get a list of mods in the preset.
+    pub fn get_mods(&self) -> &Vec<String> {
+        &self.mods
+    }
+
+    /// Get the preset's own missing mod policy, if it has one.
+    pub fn missing_mod_policy(&self) -> Option<MissingModPolicy> {
+        self.missing_mod_policy
+    }
+
+    /// Set the preset's own missing mod policy. Pass `None` to fall back to the policy passed to
+    /// `ModCfg::apply_presets`.
+    ///
+    /// # Arguments
+    ///
+    /// `policy`: The policy to use for this preset.
+    pub fn set_missing_mod_policy(&mut self, policy: Option<MissingModPolicy>) {
+        self.missing_mod_policy = policy
+    }
+
+    /// Get the arguments the game should be launched with for this preset.
+    pub fn launch_args(&self) -> &[String] {
+        &self.launch_args
+    }
+
+    /// Set the arguments the game should be launched with for this preset, e.g. `-console`.
+    ///
+    /// # Arguments
+    ///
+    /// `launch_args`: The launch arguments. Pass an empty list to launch the game normally.
+    pub fn set_launch_args(&mut self, launch_args: Vec<String>) {
+        self.launch_args = launch_args
+    }
+
+    /// Get the mods in the preset mapped to the mods they require.
+    pub fn requirements(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.requires
+    }
+
+    /// Set the mods a mod needs to work. When the preset is applied, the required mods are enabled
+    /// along with the mod, and the preset fails if any of them isn't installed.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The mod that needs other mods.
+    /// `required`: The mods it needs. Pass an empty list to remove the requirement.
+    pub fn set_requirements(&mut self, mod_name: &str, required: Vec<String>) {
+        if required.is_empty() {
+            self.requires.remove(mod_name);
+        } else {
+            self.requires.insert(mod_name.into(), required);
+        }
+    }
+
+    /// Get the requirements of the preset including the requirements of every preset it extends,
+    /// loading the extended presets with `load`.
+    ///
+    /// # Arguments
+    ///
+    /// `load`: Loads a preset by name, like for `Preset::resolve_mods_with`.
+    ///
+    /// # Errors
+    ///
+    /// Same as `Preset::resolve_mods_with`.
+    pub fn resolve_requirements_with(
+        &self,
+        load: impl FnMut(&str) -> Result<Preset>,
+    ) -> Result<BTreeMap<String, Vec<String>>> {
+        let ancestors = self.ancestors(load)?;
+        Ok(Self::merge_requires(&ancestors, self))
+    }
+
+    /// Collect the requirements of a preset and its ancestors, from the root preset down to the
+    /// preset.
+    fn merge_requires(ancestors: &[Preset], preset: &Preset) -> BTreeMap<String, Vec<String>> {
+        let mut requires: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for preset in ancestors.iter().rev().chain([preset]) {
+            for (mod_name, required) in &preset.requires {
+                let merged = requires.entry(mod_name.clone()).or_default();
+                for required in required {
+                    if !merged.contains(required) {
+                        merged.push(required.clone());
+                    }
+                }
+            }
+        }
+        requires
+    }
+
+    /// Get the recorded repository version of a mod in the preset.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_name`: The name of the mod.
+    ///
+    /// # Returns
+    ///
+    /// `Some(&RepoVersion)`: If a repository version is recorded for the mod.
+    /// `None`: If the mod isn't in the preset or isn't known to be from the repository.
+    pub fn repo_version(&self, mod_name: &str) -> Option<&RepoVersion> {
+        self.repo_versions.get(mod_name)
+    }
+
+    /// Record the repository version of every mod in the preset that was installed from the
+    /// repository, so the preset can be reconstructed on another computer.
+    ///
+    /// Mods the ModCfg doesn't know to be from the repository keep any version already recorded.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg to read the installed repository versions from.
+    pub fn record_repo_versions(&mut self, mod_config: &ModCfg) {
+        for mod_name in &self.mods {
+            if let Some(repo_version) = mod_config.repo_version(mod_name) {
+                self.repo_versions.insert(mod_name.clone(), repo_version);
+            }
+        }
+    }
+
+    /// Get the name of the preset this preset extends, if any.
+    pub fn extends(&self) -> Option<&str> {
+        self.extends.as_deref()
+    }
+
+    /// Set the preset this preset extends. Pass `None` to stop extending another preset.
+    ///
+    /// The parent isn't checked until the preset's mods are resolved with `Preset::resolve_mods`.
+    ///
+    /// # Arguments
+    ///
+    /// `parent`: The name of the preset to extend.
+    pub fn set_extends(&mut self, parent: Option<String>) {
+        self.extends = parent
+    }
+
+    /// Get the mods of the preset including the mods of every preset it extends.
+    ///
+    /// The parent's mods come first, followed by this preset's own mods. Each mod is only listed
+    /// once.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: The directory where the presets are stored.
+    ///
+    /// # Errors
+    ///
+    /// MissingPreset: If an extended preset doesn't exist.
+    /// PresetCycle: If presets extend each other in a cycle.
+    /// Possible IO or serde_json errors if there is an issue loading an extended preset.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use beammm::Preset;
+    /// # use tempfile::tempdir;
+    ///
+    /// # let temp_dir = tempdir().unwrap();
+    /// # let presets_dir = temp_dir.path();
+    /// let base = Preset::new("base".into(), vec!["mod1".into()]).unwrap();
+    /// base.save_to_path(&presets_dir).unwrap();
+    ///
+    /// let mut variant = Preset::new("variant".into(), vec!["mod2".into()]).unwrap();
+    /// variant.set_extends(Some("base".into()));
+    ///
+    /// assert_eq!(variant.resolve_mods(&presets_dir).unwrap(), vec!["mod1", "mod2"]);
+    /// ```
+    #[cfg(feature = "native")]
+    pub fn resolve_mods(&self, presets_dir: impl AsRef<Path>) -> Result<Vec<String>> {
+        let presets_dir = presets_dir.as_ref();
+        self.resolve_mods_with(|name| Self::load_from_path(name, presets_dir))
+    }
+
+    /// Get the mods of the preset including the mods of every preset it extends, loading the
+    /// extended presets with `load`. Doesn't touch the file system itself, so presets can be kept
+    /// anywhere.
+    ///
+    /// # Arguments
+    ///
+    /// `load`: Loads a preset by name. It should return `MissingPreset` if there is no such
+    ///   preset.
+    ///
+    /// # Errors
+    ///
+    /// PresetCycle: If presets extend each other in a cycle.
+    /// Any error returned by `load`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use beammm::Preset;
+    ///
+    /// let base = Preset::new("base".into(), vec!["mod1".into()]).unwrap();
+    /// let mut variant = Preset::new("variant".into(), vec!["mod2".into()]).unwrap();
+    /// variant.set_extends(Some("base".into()));
+    ///
+    /// let mods = variant.resolve_mods_with(|_| Ok(base.clone())).unwrap();
+    /// assert_eq!(mods, vec!["mod1", "mod2"]);
+    /// ```
+    pub fn resolve_mods_with(
+        &self,
+        load: impl FnMut(&str) -> Result<Preset>,
+    ) -> Result<Vec<String>> {
+        let ancestors = self.ancestors(load)?;
+        Ok(Self::merge_mods(&ancestors, self))
+    }
+
+    /// Collect the mods of a preset and its ancestors, from the root preset down to the preset.
+    fn merge_mods(ancestors: &[Preset], preset: &Preset) -> Vec<String> {
+        let mut mods: Vec<String> = vec![];
+        for preset in ancestors.iter().rev().chain([preset]) {
+            for mod_name in &preset.mods {
+                if !mods.contains(mod_name) {
+                    mods.push(mod_name.clone());
+                }
+            }
+        }
+        mods
+    }
+
+    /// Load every preset this preset extends, starting from its parent.
+    ///
+    /// # Errors
+    ///
+    /// Same as `Preset::resolve_mods_with`.
+    fn ancestors(&self, mut load: impl FnMut(&str) -> Result<Preset>) -> Result<Vec<Preset>> {
+        let mut chain = vec![self.name.clone()];
+        let mut ancestors = vec![];
+        let mut parent = self.extends.clone();
+        while let Some(parent_name) = parent {
+            if chain.contains(&parent_name) {
+                chain.push(parent_name);
+                return Err(PresetCycle { presets: chain });
+            }
+            let parent_preset = load(&parent_name)?;
+            parent = parent_preset.extends.clone();
+            chain.push(parent_name);
+            ancestors.push(parent_preset);
+        }
+        Ok(ancestors)
+    }
+
+    /// Replace the preset's mods with its resolved mods and stop extending another preset.
+    ///
+    /// Repository versions recorded by extended presets are taken over as well. Useful before
+    /// sharing a preset, since the extended presets won't exist elsewhere.
+    ///
+    /// # Arguments
+    ///
+    /// `presets_dir`: The directory where the presets are stored.
+    ///
+    /// # Errors
+    ///
+    /// Same as `Preset::resolve_mods`.
+    #[cfg(feature = "native")]
+    pub fn flatten(&mut self, presets_dir: impl AsRef<Path>) -> Result<()> {
+        let presets_dir = presets_dir.as_ref();
+        self.flatten_with(|name| Self::load_from_path(name, presets_dir))
+    }
+
+    /// Same as `Preset::flatten`, loading the extended presets with `load`.
+    ///
+    /// # Arguments
+    ///
+    /// `load`: Loads a preset by name. It should return `MissingPreset` if there is no such
+    ///   preset.
+    ///
+    /// # Errors
+    ///
+    /// Same as `Preset::resolve_mods_with`.
+    pub fn flatten_with(&mut self, load: impl FnMut(&str) -> Result<Preset>) -> Result<()> {
+        let ancestors = self.ancestors(load)?;
+        self.mods = Self::merge_mods(&ancestors, self);
+        self.requires = Self::merge_requires(&ancestors, self);
+        for ancestor in ancestors {
+            for (mod_name, repo_version) in ancestor.repo_versions {
+                // This preset's own versions win over inherited ones.
+                self.repo_versions.entry(mod_name).or_insert(repo_version);
+            }
+        }
+        self.extends = None;
+        Ok(())
+    }
+
+    /// Encode the preset as a compact share code that can be pasted in a message.
+    ///
+    /// The share code holds the preset's name, mods, repository versions, missing mod policy, and
+    /// launch arguments.
+    /// Whether the preset is enabled and which preset it extends are left out, so flatten the
+    /// preset with `Preset::flatten` first to include the mods of extended presets.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO or serde_json errors if there is an issue encoding the preset.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use beammm::Preset;
+    ///
+    /// let preset = Preset::new("preset_name".into(), vec!["mod1".into(), "mod2".into()]).unwrap();
+    /// let code = preset.to_share_code().unwrap();
+    ///
+    /// let imported = Preset::from_share_code(&code).unwrap();
+    /// assert_eq!(imported.get_mods(), &["mod1", "mod2"]);
+    /// ```
+    pub fn to_share_code(&self) -> Result<String> {
+        let shared = SharedPreset {
+            name: self.name.clone(),
+            mods: self.mods.clone(),
+            missing_mod_policy: self.missing_mod_policy,
+            launch_args: self.launch_args.clone(),
+            repo_versions: self.repo_versions.clone(),
+            requires: self.requires.clone(),
+        };
+        let mut encoder = DeflateEncoder::new(vec![], Compression::best());
+        serde_json::to_writer(&mut encoder, &shared)?;
+        let compressed = encoder.finish()?;
+        Ok(format!(
+            "{}{}",
+            SHARE_CODE_PREFIX,
+            URL_SAFE_NO_PAD.encode(compressed)
+        ))
+    }
+
+    /// Decode a preset from a share code made by `Preset::to_share_code`.
+    ///
+    /// The decoded preset is disabled and isn't saved.
+    ///
+    /// # Arguments
+    ///
+    /// `code`: The share code. Surrounding whitespace is ignored.
+    ///
+    /// # Errors
+    ///
+    /// InvalidShareCode: If the share code is malformed, or decompresses to more than 1 MiB.
+    pub fn from_share_code(code: &str) -> Result<Self> {
+        let invalid = || InvalidShareCode;
+        let encoded = code
+            .trim()
+            .strip_prefix(SHARE_CODE_PREFIX)
+            .ok_or_else(invalid)?;
+        let compressed = URL_SAFE_NO_PAD.decode(encoded).map_err(|_| invalid())?;
+        let mut json = vec![];
+        DeflateDecoder::new(&compressed[..])
+            .take(MAX_SHARE_CODE_JSON_LEN + 1)
+            .read_to_end(&mut json)
+            .map_err(|_| invalid())?;
+        if json.len() as u64 > MAX_SHARE_CODE_JSON_LEN {
+            return Err(invalid());
+        }
+        let shared: SharedPreset = serde_json::from_slice(&json).map_err(|_| invalid())?;
+
+        let mut preset = Self::new(shared.name, shared.mods)?;
+        preset.missing_mod_policy = shared.missing_mod_policy;
+        preset.launch_args = shared.launch_args;
+        preset.repo_versions = shared.repo_versions;
+        preset.requires = shared.requires;
+        Ok(preset)
+    }
+
+    /// Make the `Preset::LAST_KNOWN_GOOD` preset from the mods enabled in a ModCfg.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg whose enabled mods are recorded, usually as it was before this
+    /// run changed it.
+    pub fn last_known_good(mod_config: &ModCfg) -> Self {
+        let mods = mod_config.active_mods().map(String::from).collect();
+        // The reserved name is known to be valid.
+        Self::new(Self::LAST_KNOWN_GOOD.into(), mods).unwrap()
+    }
+
+    /// Save the preset as the last known good state, to a file outside the presets directory.
+    ///
+    /// # Arguments
+    ///
+    /// `path`: The file to save to, usually from `path::last_known_good_file`. Its directory is
+    /// created if needed.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue creating the directory or writing the file.
+    #[cfg(feature = "native")]
+    pub fn save_last_known_good(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut bytes = vec![];
+        self.save(&mut bytes)?;
+        fs::write(path, bytes).map_err(crate::Error::io(path))
+    }
+
+    /// Load the last known good state saved with `Preset::save_last_known_good`.
+    ///
+    /// # Arguments
+    ///
+    /// `path`: The file it was saved to.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingPreset`: If no last known good state was saved yet.
+    /// * `std::io::Error`: If there is an issue reading the file.
+    /// * `serde_json::Error`: If there is an issue deserializing the preset.
+    #[cfg(feature = "native")]
+    pub fn load_last_known_good(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.try_exists()? {
+            return Err(MissingPreset {
+                dir: path.parent().unwrap_or(path).into(),
+                preset: Self::LAST_KNOWN_GOOD.into(),
+            });
+        }
+        Self::load(BufReader::new(File::open(path)?))
+    }
+
+    /// Enable exactly the mods in this preset and disable every other mod.
+    ///
+    /// Mods that no longer exist, are blacklisted, aren't on an enforced whitelist, or are ignored
+    /// are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg to change.
+    ///
+    /// # Returns
+    ///
+    /// The mods in the preset that no longer exist.
+    pub fn restore(&self, mod_config: &mut ModCfg) -> Vec<String> {
+        // Ignored mods are left alone, like with other bulk changes.
+        let mods: Vec<String> = mod_config
+            .get_mods()
+            .filter(|m| !mod_config.is_ignored(m))
+            .cloned()
+            .collect();
+        for mod_name in mods.iter() {
+            // The mods were just read from the config.
+            mod_config.set_mod_active(mod_name, false).unwrap();
+        }
+        for mod_name in self.mods.iter() {
+            if mod_config.is_ignored(mod_name) {
+                continue;
+            }
+            // Missing mods are returned and disallowed mods are simply skipped.
+            let _ = mod_config.set_mod_active(mod_name, true);
+        }
+        mod_config.missing_mods(&self.mods)
+    }
+
+    /// Check if a preset already exists.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The name of the preset to check for.
+    /// `presets_dir`: The directory where the presets are stored.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    #[cfg(feature = "native")]
+    pub fn exists(name: &str, presets_dir: impl AsRef<Path>) -> bool {
+        Self::check_file_name(name).is_ok() && Self::path(name, presets_dir).exists()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+
+    #[test]
     fn listing_presets() {
         let mock = MockData::new();
         let presets = Preset::list(&mock.presets_dir).unwrap().collect::<Vec<_>>();
         assert_eq!(presets, vec!["preset1", "preset2"]);
     }
 
+    #[test]
+    fn skipping_invalid_preset_files() {
+        let mock = MockData::new();
+        let legacy = mock.presets_dir.join("what?.json");
+        fs::write(
+            &legacy,
+            r#"{"name":"what?","mods":["mod1"],"enabled":true}"#,
+        )
+        .unwrap();
+
+        let presets: Vec<_> = Preset::list(&mock.presets_dir).unwrap().collect();
+        assert!(!presets.contains(&"what?".to_string()));
+        assert_eq!(
+            Preset::list_invalid(&mock.presets_dir).unwrap(),
+            vec![legacy]
+        );
+        // The file doesn't stop the other presets from being indexed.
+        let index = PresetIndex::load_from_path(&mock.presets_dir).unwrap();
+        assert_eq!(index.iter().count(), 2);
+
+        assert!(Preset::load_from_path("what?", &mock.presets_dir).is_ok());
+        Preset::delete("what?", &mock.presets_dir, true).unwrap();
+        assert!(Preset::list_invalid(&mock.presets_dir).unwrap().is_empty());
+    }
+
     #[test]
     fn creating_preset() {
         let mods = vec!["mod1".into(), "mod2".into()];
-        let preset = Preset::new("preset3".into(), mods.clone());
+        let preset = Preset::new("preset3".into(), mods.clone()).unwrap();
 
         assert_eq!(preset.get_mods(), &mods);
     }
@@ -359,7 +1690,7 @@ mod tests {
     fn saving_and_loading_preset() {
         let mock = MockData::new();
         let mods = vec!["mod1".into(), "mod2".into()];
-        let preset = Preset::new("preset3".into(), mods);
+        let preset = Preset::new("preset3".into(), mods).unwrap();
         preset.save_to_path(&mock.presets_dir).unwrap();
 
         // Check that there is now a `preset3.json` file in the presets directory.
@@ -369,6 +1700,40 @@ mod tests {
         assert_eq!(loaded_preset, preset);
     }
 
+    #[test]
+    fn validating_preset_names() {
+        let mock = MockData::new();
+        for name in [
+            "", "..", "../evil", "a/b", "a\\b", "what?", "tab\t", "end.", "NUL.txt",
+        ] {
+            assert!(
+                matches!(
+                    Preset::new(name.into(), vec![]),
+                    Err(InvalidPresetName { .. })
+                ),
+                "{:?}",
+                name
+            );
+            assert!(Preset::validate_name(&Preset::sanitize_name(name)).is_ok());
+        }
+        assert!(matches!(
+            Preset::load_from_path("../preset1", &mock.presets_dir),
+            Err(InvalidPresetName { .. })
+        ));
+        assert!(!Preset::exists("../presets/preset1", &mock.presets_dir));
+
+        // Dots inside names are kept in the file name.
+        Preset::new("racing v1.2".into(), vec!["mod1".into()])
+            .unwrap()
+            .save_to_path(&mock.presets_dir)
+            .unwrap();
+        assert!(mock.presets_dir.join("racing v1.2.json").exists());
+        assert!(Preset::list(&mock.presets_dir)
+            .unwrap()
+            .any(|name| name == "racing v1.2"));
+        assert!(Preset::load_from_path("racing v1.2", &mock.presets_dir).is_ok());
+    }
+
     #[test]
     fn load_missing_preset() {
         let mock = MockData::new();
@@ -379,7 +1744,7 @@ mod tests {
     #[test]
     fn deleting_preset() {
         let mock = MockData::new();
-        Preset::delete("preset1", &mock.presets_dir).unwrap();
+        Preset::delete("preset1", &mock.presets_dir, true).unwrap();
         let presets = Preset::list(&mock.presets_dir).unwrap().collect::<Vec<_>>();
         assert_eq!(presets, vec!["preset2"]);
     }
@@ -390,7 +1755,7 @@ mod tests {
         let mut preset = mock.preset1;
 
         preset.add_mod("mod2");
-        preset.add_mods(&["mod3".into(), "mod4".into()]);
+        preset.add_mods(["mod3", "mod4"]);
 
         assert_eq!(preset.get_mods(), &["mod1", "mod2", "mod3", "mod4"]);
     }
@@ -400,15 +1765,28 @@ mod tests {
         let mut preset = Preset::new(
             "preset5".into(),
             vec!["mod1".into(), "mod2".into(), "mod3".into()],
-        );
+        )
+        .unwrap();
         preset.remove_mod("mod2");
         // Also remove mod that isn't already in the preset to verify we don't get an error of
         // sorts.
-        preset.remove_mods(&["mod1".into(), "mod4".into()]);
+        preset.remove_mods(["mod1", "mod4"]);
 
         assert_eq!(preset.get_mods(), &["mod3"]);
     }
 
+    #[test]
+    fn replacing_mods() {
+        let mock = MockData::new();
+        let mut preset = mock.preset2;
+
+        preset.set_mods(vec!["mod3".into(), "mod1".into()]);
+        assert_eq!(preset.get_mods(), &["mod3", "mod1"]);
+
+        preset.clear_mods();
+        assert!(preset.get_mods().is_empty());
+    }
+
     #[test]
     fn enabling_preset() {
         let mock = MockData::new();
@@ -454,4 +1832,412 @@ mod tests {
         assert!(!preset.is_enabled());
         assert!(!mod_cfg.is_mod_active("mod1").unwrap());
     }
+
+    #[test]
+    fn missing_mod_policy_roundtrip() {
+        let mock = MockData::new();
+        let mut preset = mock.preset1;
+
+        // Presets without a policy field should load with no policy.
+        assert_eq!(preset.missing_mod_policy(), None);
+
+        preset.set_missing_mod_policy(Some(MissingModPolicy::Skip));
+        preset.save_to_path(&mock.presets_dir).unwrap();
+
+        let loaded_preset = Preset::load_from_path("preset1", &mock.presets_dir).unwrap();
+        assert_eq!(
+            loaded_preset.missing_mod_policy(),
+            Some(MissingModPolicy::Skip)
+        );
+    }
+
+    #[test]
+    fn pruning_missing_mods() {
+        let mock = MockData::new();
+        let mut preset = mock.preset2;
+
+        preset.add_mods(["fake_mod", "mod3"]);
+
+        let removed = preset.prune_missing(&mock.modcfg);
+
+        assert_eq!(removed, vec!["fake_mod"]);
+        assert_eq!(preset.get_mods(), &["mod1", "mod2", "mod3"]);
+        assert!(preset.prune_missing(&mock.modcfg).is_empty());
+    }
+
+    #[test]
+    fn requirements_roundtrip() {
+        let mock_data = MockData::new();
+        let presets_dir = &mock_data.presets_dir;
+
+        let mut base = mock_data.preset1;
+        base.set_requirements("mod1", vec!["vehicle".into()]);
+        base.save_to_path(presets_dir).unwrap();
+        let loaded = Preset::load_from_path("preset1", presets_dir).unwrap();
+        assert_eq!(loaded.requirements()["mod1"], vec!["vehicle"]);
+
+        let mut variant = Preset::new("variant".into(), vec!["skin".into()]).unwrap();
+        variant.set_extends(Some("preset1".into()));
+        variant.set_requirements("skin", vec!["mod1".into()]);
+        variant.set_requirements("mod1", vec!["vehicle".into(), "map".into()]);
+        let requirements = variant
+            .resolve_requirements_with(|name| Preset::load_from_path(name, presets_dir))
+            .unwrap();
+        assert_eq!(requirements["mod1"], vec!["vehicle", "map"]);
+        assert_eq!(requirements["skin"], vec!["mod1"]);
+
+        variant.set_requirements("skin", vec![]);
+        assert!(!variant.requirements().contains_key("skin"));
+    }
+
+    #[test]
+    fn launch_args_roundtrip() {
+        let mock = MockData::new();
+        let mut preset = mock.preset1;
+
+        assert!(preset.launch_args().is_empty());
+
+        preset.set_launch_args(vec!["-console".into(), "-level".into(), "utah".into()]);
+        preset.save_to_path(&mock.presets_dir).unwrap();
+
+        let loaded_preset = Preset::load_from_path("preset1", &mock.presets_dir).unwrap();
+        assert_eq!(loaded_preset.launch_args(), &["-console", "-level", "utah"]);
+    }
+
+    #[test]
+    fn resolving_extended_mods() {
+        let mock = MockData::new();
+        let mut preset = Preset::new("preset3".into(), vec!["mod3".into(), "mod1".into()]).unwrap();
+        preset.set_extends(Some("preset2".into()));
+        preset.save_to_path(&mock.presets_dir).unwrap();
+
+        let loaded_preset = Preset::load_from_path("preset3", &mock.presets_dir).unwrap();
+        assert_eq!(loaded_preset.extends(), Some("preset2"));
+        assert_eq!(
+            loaded_preset.resolve_mods(&mock.presets_dir).unwrap(),
+            vec!["mod1", "mod2", "mod3"]
+        );
+
+        // Presets without a parent just resolve to their own mods.
+        assert_eq!(
+            mock.preset1.resolve_mods(&mock.presets_dir).unwrap(),
+            vec!["mod1"]
+        );
+    }
+
+    #[test]
+    fn resolving_missing_parent() {
+        let mock = MockData::new();
+        let mut preset = mock.preset1;
+        preset.set_extends(Some("missing_preset".into()));
+
+        let result = preset.resolve_mods(&mock.presets_dir);
+        assert!(matches!(result, Err(MissingPreset { .. })));
+    }
+
+    #[test]
+    fn resolving_extends_cycle() {
+        let mock = MockData::new();
+        let mut preset1 = mock.preset1;
+        let mut preset2 = mock.preset2;
+        preset1.set_extends(Some("preset2".into()));
+        preset2.set_extends(Some("preset1".into()));
+        preset1.save_to_path(&mock.presets_dir).unwrap();
+        preset2.save_to_path(&mock.presets_dir).unwrap();
+
+        match preset1.resolve_mods(&mock.presets_dir) {
+            Err(PresetCycle { presets }) => {
+                assert_eq!(presets, vec!["preset1", "preset2", "preset1"])
+            }
+            result => panic!("Expected a PresetCycle error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn share_code_roundtrip() {
+        let mock = MockData::new();
+        let mut preset = mock.preset2;
+        preset.set_missing_mod_policy(Some(MissingModPolicy::Skip));
+        preset.set_launch_args(vec!["-console".into()]);
+
+        let code = preset.to_share_code().unwrap();
+        assert!(code.starts_with(SHARE_CODE_PREFIX));
+
+        let imported = Preset::from_share_code(&format!("  {}\n", code)).unwrap();
+        assert_eq!(imported.name, "preset2");
+        assert_eq!(imported.get_mods(), &["mod1", "mod2"]);
+        assert_eq!(imported.missing_mod_policy(), Some(MissingModPolicy::Skip));
+        assert_eq!(imported.launch_args(), &["-console"]);
+        assert!(!imported.is_enabled());
+    }
+
+    #[test]
+    fn invalid_share_codes() {
+        for code in ["", "mod1,mod2", "bmm1:not base64!", "bmm1:AAAA"] {
+            assert!(matches!(
+                Preset::from_share_code(code),
+                Err(InvalidShareCode)
+            ));
+        }
+    }
+
+    #[test]
+    fn oversized_share_code() {
+        // A valid preset whose mods compress well, so the code is short but decompresses past
+        // the limit.
+        let mods = vec!["mod1".to_string(); MAX_SHARE_CODE_JSON_LEN as usize / 6];
+        let code = Preset::new("huge".into(), mods)
+            .unwrap()
+            .to_share_code()
+            .unwrap();
+        assert!(code.len() < 4096);
+
+        assert!(matches!(
+            Preset::from_share_code(&code),
+            Err(InvalidShareCode)
+        ));
+    }
+
+    #[test]
+    fn flattening_preset() {
+        let mock = MockData::new();
+        let mut preset = Preset::new("preset3".into(), vec!["mod3".into()]).unwrap();
+        preset.set_extends(Some("preset1".into()));
+
+        let mut preset1 = mock.preset1;
+        let repo_version = RepoVersion {
+            resource_id: 7,
+            version_id: 3,
+            version: None,
+        };
+        preset1
+            .repo_versions
+            .insert("mod1".into(), repo_version.clone());
+        preset1.save_to_path(&mock.presets_dir).unwrap();
+
+        preset.flatten(&mock.presets_dir).unwrap();
+
+        assert_eq!(preset.extends(), None);
+        assert_eq!(preset.get_mods(), &["mod1", "mod3"]);
+        assert_eq!(preset.repo_version("mod1"), Some(&repo_version));
+    }
+
+    #[test]
+    fn repo_versions_roundtrip() {
+        let mock = MockData::new();
+        let mut preset = mock.preset2;
+        let repo_version = RepoVersion {
+            resource_id: 7,
+            version_id: 3,
+            version: Some("1.2".into()),
+        };
+        preset
+            .repo_versions
+            .insert("mod2".into(), repo_version.clone());
+        preset.save_to_path(&mock.presets_dir).unwrap();
+
+        // Mods without a repository version stay plain names in the file.
+        let json: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(mock.presets_dir.join("preset2.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json["mods"][0], "mod1");
+        assert_eq!(json["mods"][1]["name"], "mod2");
+        assert_eq!(json["mods"][1]["resource_id"], 7);
+
+        let loaded_preset = Preset::load_from_path("preset2", &mock.presets_dir).unwrap();
+        assert_eq!(loaded_preset, preset);
+        assert_eq!(loaded_preset.get_mods(), &["mod1", "mod2"]);
+        assert_eq!(loaded_preset.repo_version("mod2"), Some(&repo_version));
+        assert_eq!(loaded_preset.repo_version("mod1"), None);
+
+        let imported = Preset::from_share_code(&preset.to_share_code().unwrap()).unwrap();
+        assert_eq!(imported.repo_version("mod2"), Some(&repo_version));
+    }
+
+    #[test]
+    fn renaming_mods() {
+        let mock = MockData::new();
+        let mut preset = mock.preset2;
+        assert!(preset.rename_mod("mod2", "mod4"));
+        assert_eq!(preset.get_mods(), &["mod1", "mod4"]);
+        assert!(!preset.rename_mod("mod2", "mod5"));
+
+        // A repository version alone still refers to the mod.
+        let repo_version = RepoVersion {
+            resource_id: 7,
+            version_id: 3,
+            version: None,
+        };
+        preset
+            .repo_versions
+            .insert("old".into(), repo_version.clone());
+        assert!(preset.rename_mod("old", "new"));
+        assert_eq!(preset.repo_version("new"), Some(&repo_version));
+    }
+
+    #[test]
+    fn recording_repo_versions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mods_dir = tmp.path();
+        fs::write(
+            mods_dir.join("db.json"),
+            r#"{"mods":{"mod1":{"active":true},"mod2":{"active":true,"modData":{"resource_id":"7","current_version_id":3}}}}"#,
+        )
+        .unwrap();
+        let mod_cfg = ModCfg::load_from_path(mods_dir).unwrap();
+        let mut preset = Preset::new("preset".into(), vec!["mod1".into(), "mod2".into()]).unwrap();
+
+        preset.record_repo_versions(&mod_cfg);
+
+        assert_eq!(preset.repo_version("mod1"), None);
+        assert_eq!(preset.repo_version("mod2").unwrap().resource_id, 7);
+
+        // Removing a mod forgets its repository version.
+        preset.remove_mod("mod2");
+        assert!(preset.repo_versions.is_empty());
+    }
+
+    #[test]
+    fn migrating_preset_formats() {
+        // Presets from before the format was versioned are upgraded as they load.
+        let unversioned = r#"{"name":"old","mods":["mod1"],"enabled":true}"#;
+        let preset = Preset::load(unversioned.as_bytes()).unwrap();
+        assert_eq!(preset.get_mods(), &vec!["mod1".to_string()]);
+        assert!(preset.is_enabled());
+
+        let mut saved = vec![];
+        preset.save(&mut saved).unwrap();
+        let saved: serde_json::Value = serde_json::from_slice(&saved).unwrap();
+        assert_eq!(saved["format_version"], Preset::FORMAT_VERSION);
+
+        let newer = r#"{"format_version":999,"name":"new","mods":[],"enabled":false}"#;
+        assert!(matches!(
+            Preset::load(newer.as_bytes()),
+            Err(UnsupportedPresetFormat { preset, format_version: 999 }) if preset == "new"
+        ));
+    }
+
+    #[test]
+    fn removing_mod_from_all_presets() {
+        let mock = MockData::new();
+
+        let changed = Preset::remove_mod_from_all("mod1", &mock.presets_dir).unwrap();
+
+        assert_eq!(changed, vec!["preset1", "preset2"]);
+        let preset1 = Preset::load_from_path("preset1", &mock.presets_dir).unwrap();
+        let preset2 = Preset::load_from_path("preset2", &mock.presets_dir).unwrap();
+        assert!(preset1.get_mods().is_empty());
+        assert_eq!(preset2.get_mods(), &vec!["mod2"]);
+
+        let changed = Preset::remove_mod_from_all("mod1", &mock.presets_dir).unwrap();
+        assert!(changed.is_empty());
+    }
+
+    #[test]
+    fn pruning_all_presets() {
+        let mock = MockData::new();
+        let mut preset = Preset::new("preset3".into(), vec!["mod3".into(), "gone".into()]).unwrap();
+        preset.add_mod("gone_too");
+        preset.save_to_path(&mock.presets_dir).unwrap();
+
+        let pruned = Preset::prune_all(&mock.presets_dir, &mock.modcfg).unwrap();
+
+        assert_eq!(
+            pruned,
+            vec![(
+                "preset3".to_string(),
+                vec!["gone".into(), "gone_too".into()]
+            )]
+        );
+        let preset3 = Preset::load_from_path("preset3", &mock.presets_dir).unwrap();
+        assert_eq!(preset3.get_mods(), &vec!["mod3"]);
+    }
+
+    #[test]
+    fn sorting_all_presets() {
+        let mock = MockData::new();
+        let mut preset = Preset::new("preset3".into(), vec!["mod3".into(), "mod1".into()]).unwrap();
+        preset.add_mod("mod2");
+        preset.set_requirements("mod3", vec!["mod2".into(), "mod1".into()]);
+        preset.save_to_path(&mock.presets_dir).unwrap();
+
+        let sorted = Preset::sort_all(&mock.presets_dir).unwrap();
+
+        assert_eq!(sorted, vec!["preset3"]);
+        let path = mock.presets_dir.join("preset3.json");
+        let saved = fs::read(&path).unwrap();
+        let preset3 = Preset::load_from_path("preset3", &mock.presets_dir).unwrap();
+        assert_eq!(preset3.get_mods(), &vec!["mod1", "mod2", "mod3"]);
+        assert_eq!(preset3.requirements()["mod3"], vec!["mod1", "mod2"]);
+        // Sorted presets are left alone.
+        assert!(Preset::sort_all(&mock.presets_dir).unwrap().is_empty());
+        assert_eq!(fs::read(path).unwrap(), saved);
+    }
+
+    #[test]
+    fn restoring_last_known_good() {
+        let mock = MockData::new();
+        let mut mod_cfg = mock.modcfg;
+        mod_cfg.set_mod_active("mod2", false).unwrap();
+
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("last-known-good").join("0.32.json");
+        assert!(matches!(
+            Preset::load_last_known_good(&file),
+            Err(MissingPreset { .. })
+        ));
+        Preset::last_known_good(&mod_cfg)
+            .save_last_known_good(&file)
+            .unwrap();
+        let mut good = Preset::load_last_known_good(&file).unwrap();
+        assert_eq!(good.name(), Preset::LAST_KNOWN_GOOD);
+        assert_eq!(good.get_mods(), &["mod1", "mod3"]);
+        assert!(!good.is_enabled());
+        // It isn't one of the presets.
+        assert_eq!(Preset::list(&mock.presets_dir).unwrap().count(), 2);
+
+        mod_cfg.set_all_mods_active(false).unwrap();
+        mod_cfg.set_mod_active("mod2", true).unwrap();
+        good.add_mod("fake_mod");
+        assert_eq!(good.restore(&mut mod_cfg), vec!["fake_mod"]);
+        assert_eq!(mod_cfg.is_mod_active("mod1"), Some(true));
+        assert_eq!(mod_cfg.is_mod_active("mod2"), Some(false));
+        assert_eq!(mod_cfg.is_mod_active("mod3"), Some(true));
+    }
+
+    #[test]
+    fn toggling_many_presets() {
+        let mock = MockData::new();
+        let mut mod_cfg = mock.modcfg;
+        let names = ["preset1", "preset2"];
+
+        Preset::set_many_enabled(names, true, &mock.presets_dir, &mut mod_cfg).unwrap();
+        for name in names.iter() {
+            assert!(Preset::load_from_path(name, &mock.presets_dir)
+                .unwrap()
+                .is_enabled());
+        }
+
+        Preset::set_many_enabled(names, false, &mock.presets_dir, &mut mod_cfg).unwrap();
+        assert!(!mod_cfg.is_mod_active("mod1").unwrap());
+        assert!(mod_cfg.is_mod_active("mod3").unwrap());
+        for name in names.iter() {
+            assert!(!Preset::load_from_path(name, &mock.presets_dir)
+                .unwrap()
+                .is_enabled());
+        }
+
+        // Nothing changes if one of the presets is missing.
+        let result = Preset::set_many_enabled(
+            ["preset1", "missing"],
+            true,
+            &mock.presets_dir,
+            &mut mod_cfg,
+        );
+        assert!(matches!(result, Err(MissingPreset { .. })));
+        assert!(!Preset::load_from_path("preset1", &mock.presets_dir)
+            .unwrap()
+            .is_enabled());
+    }
 }
@@ -0,0 +1,198 @@
+use crate::{
+    db_backups::{parse_stamp, stamp},
+    Error::*,
+    Preset, Result,
+};
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Copy a preset's file into the preset backups directory, so it can be brought back with
+/// `restore` once the preset is deleted. Backups are named after the preset and the time,
+/// e.g. `racing-20241016T120348.123Z.json`, so every deletion of a preset is kept.
+///
+/// # Arguments
+///
+/// `backups_dir`: Where copies of deleted presets are kept.
+/// `name`: The name of the preset to back up.
+/// `presets_dir`: Where preset config files are stored.
+/// `time`: The current time.
+///
+/// # Returns
+///
+/// The path to the backup.
+///
+/// # Errors
+///
+/// * `InvalidPresetName`: If the name isn't a single file name.
+/// * `MissingPreset`: If the preset doesn't exist.
+/// * `std::io::Error`: If there is an issue copying the preset.
+pub fn create(
+    backups_dir: impl AsRef<Path>,
+    name: &str,
+    presets_dir: impl AsRef<Path>,
+    time: SystemTime,
+) -> Result<PathBuf> {
+    let backups_dir = backups_dir.as_ref();
+    let presets_dir = presets_dir.as_ref();
+    Preset::check_file_name(name)?;
+    if !Preset::exists(name, presets_dir) {
+        return Err(MissingPreset {
+            dir: presets_dir.into(),
+            preset: name.into(),
+        });
+    }
+    fs::create_dir_all(backups_dir)?;
+    let path = backups_dir.join(format!("{}-{}.json", name, stamp(time)));
+    fs::copy(Preset::path(name, presets_dir), &path)?;
+    Ok(path)
+}
+
+/// List the backups of a preset, most recent first, paired with when they were taken.
+///
+/// # Arguments
+///
+/// `backups_dir`: Where copies of deleted presets are kept.
+/// `name`: The name of the preset.
+///
+/// # Errors
+///
+/// Possible IO errors if there is an issue reading the backups directory.
+pub fn list(backups_dir: impl AsRef<Path>, name: &str) -> Result<Vec<(PathBuf, SystemTime)>> {
+    let backups_dir = backups_dir.as_ref();
+    if !backups_dir.try_exists()? {
+        return Ok(vec![]);
+    }
+    let mut backups = vec![];
+    for entry in fs::read_dir(backups_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        // Stamps have no dashes, so the last one separates the preset's name from the stamp.
+        let Some(time) = file_name
+            .to_str()
+            .and_then(|n| n.strip_suffix(".json"))
+            .and_then(|n| n.rsplit_once('-'))
+            .filter(|(preset, _)| *preset == name)
+            .and_then(|(_, stamp)| parse_stamp(stamp))
+        else {
+            continue;
+        };
+        backups.push((entry.path(), time));
+    }
+    backups.sort_by_key(|(_, time)| std::cmp::Reverse(*time));
+    Ok(backups)
+}
+
+/// Bring back a deleted preset from its most recent backup. The preset is restored exactly as it
+/// was when it was backed up, including whether it was enabled.
+///
+/// # Arguments
+///
+/// `backups_dir`: Where copies of deleted presets are kept.
+/// `name`: The name of the preset to restore.
+/// `presets_dir`: Where preset config files are stored.
+///
+/// # Returns
+///
+/// The restored preset.
+///
+/// # Errors
+///
+/// * `InvalidPresetName`: If the name can't be used as a preset file name.
+/// * `PresetExists`: If a preset with the name exists, so restoring would overwrite it.
+/// * `MissingPreset`: If the preset has no backups.
+/// * `std::io::Error`: If there is an issue reading or copying the backup.
+/// * `serde_json::Error`: If the backup isn't a valid preset.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{preset_backups, Preset};
+/// use std::time::SystemTime;
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let presets_dir = temp_dir.path().join("presets");
+/// # let backups_dir = temp_dir.path().join("backups");
+/// # std::fs::create_dir_all(&presets_dir).unwrap();
+/// Preset::new("racing".into(), vec!["mod1".into()]).unwrap().save_to_path(&presets_dir).unwrap();
+/// preset_backups::create(&backups_dir, "racing", &presets_dir, SystemTime::now()).unwrap();
+/// Preset::delete("racing", &presets_dir, true).unwrap();
+///
+/// let preset = preset_backups::restore(&backups_dir, "racing", &presets_dir).unwrap();
+/// assert_eq!(preset.get_mods(), &vec!["mod1"]);
+/// assert!(Preset::exists("racing", &presets_dir));
+/// ```
+pub fn restore(
+    backups_dir: impl AsRef<Path>,
+    name: &str,
+    presets_dir: impl AsRef<Path>,
+) -> Result<Preset> {
+    let backups_dir = backups_dir.as_ref();
+    let presets_dir = presets_dir.as_ref();
+    Preset::validate_name(name)?;
+    if Preset::exists(name, presets_dir) {
+        return Err(PresetExists {
+            preset: name.into(),
+        });
+    }
+    let Some((backup, _)) = list(backups_dir, name)?.into_iter().next() else {
+        return Err(MissingPreset {
+            dir: backups_dir.into(),
+            preset: name.into(),
+        });
+    };
+    // Loaded first, so a damaged backup isn't restored.
+    let preset = Preset::load(BufReader::new(File::open(&backup)?))?;
+    fs::copy(&backup, Preset::path(name, presets_dir))?;
+    Ok(preset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    #[test]
+    fn backing_up_presets() {
+        let mock = MockData::new();
+        let temp_dir = tempdir().unwrap();
+        let backups_dir = temp_dir.path().join("presets");
+        let now = SystemTime::now();
+        // A preset whose name starts with another's doesn't get mixed up with it.
+        Preset::new("preset1-2".into(), vec!["mod3".into()])
+            .unwrap()
+            .save_to_path(&mock.presets_dir)
+            .unwrap();
+
+        create(&backups_dir, "preset1", &mock.presets_dir, now).unwrap();
+        let mut preset = Preset::load_from_path("preset1", &mock.presets_dir).unwrap();
+        preset.add_mod("mod2");
+        preset.save_to_path(&mock.presets_dir).unwrap();
+        let later = now + Duration::from_secs(1);
+        create(&backups_dir, "preset1", &mock.presets_dir, later).unwrap();
+        create(&backups_dir, "preset1-2", &mock.presets_dir, later).unwrap();
+        assert_eq!(list(&backups_dir, "preset1").unwrap().len(), 2);
+        assert!(matches!(
+            create(&backups_dir, "missing", &mock.presets_dir, now),
+            Err(MissingPreset { .. })
+        ));
+
+        assert!(matches!(
+            restore(&backups_dir, "preset1", &mock.presets_dir),
+            Err(PresetExists { .. })
+        ));
+        Preset::delete("preset1", &mock.presets_dir, true).unwrap();
+        let restored = restore(&backups_dir, "preset1", &mock.presets_dir).unwrap();
+        assert_eq!(restored.get_mods(), &vec!["mod1", "mod2"]);
+        assert!(Preset::exists("preset1", &mock.presets_dir));
+        assert!(matches!(
+            restore(&backups_dir, "preset3", &mock.presets_dir),
+            Err(MissingPreset { .. })
+        ));
+    }
+}
@@ -0,0 +1,421 @@
+use crate::{game::ModCfg, Error::*, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// A profile with its own presets and its own saved mod activation state.
+///
+/// Profiles are stored as directories in the BeamMM/profiles directory. Each profile directory
+/// holds the profile's saved state and, except for the default profile, its presets directory.
+/// Only one profile is active at a time. Switching profiles saves the current activation state
+/// into the active profile and restores the activation state of the new one.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{Profile, game::ModCfg};
+/// # use tempfile::tempdir;
+///
+/// # let temp_profiles_dir = tempdir().unwrap();
+/// # let profiles_dir = temp_profiles_dir.path();
+/// # let temp_mods_dir = tempdir().unwrap();
+/// # let mods_dir = temp_mods_dir.path();
+/// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{\"mod1\":{\"active\":true}}}").unwrap();
+/// let mut mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+///
+/// // Create a new profile starting from the current mod state.
+/// let mut profile = Profile::new("racing".into()).unwrap();
+/// profile.capture(&mod_cfg);
+/// profile.save_to_path(&profiles_dir).unwrap();
+///
+/// // Switch to it.
+/// Profile::switch("racing", &profiles_dir, &mut mod_cfg).unwrap();
+/// assert_eq!(Profile::active(&profiles_dir).unwrap(), "racing");
+/// ```
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Profile {
+    /// The name of the profile.
+    name: String,
+    /// The saved activation state of each mod.
+    mods: HashMap<String, bool>,
+}
+
+impl Profile {
+    /// The name of the profile used when no other profile has been selected.
+    pub const DEFAULT_NAME: &'static str = "default";
+
+    /// The filename of a profile's saved state inside its directory.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    fn filename() -> PathBuf {
+        PathBuf::from("profile.json")
+    }
+
+    /// The filename of the file recording the active profile.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    fn active_filename() -> PathBuf {
+        PathBuf::from("active")
+    }
+
+    /// Get an iterator over currently saved profiles.
+    ///
+    /// # Arguments
+    ///
+    /// `profiles_dir`: Where profile directories are stored.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if the path doesn't exist, there is a permission issue,
+    /// or if the path is not a directory.
+    pub fn list(profiles_dir: impl AsRef<Path>) -> Result<impl Iterator<Item = String>> {
+        let profiles_dir = profiles_dir.as_ref();
+        Ok(fs::read_dir(profiles_dir)?
+            .filter_map(|f| f.ok().map(|f| f.path())) // Get rid of errors and map to path type
+            .filter(|f| f.join(Self::filename()).is_file()) // Only dirs holding a profile
+            .filter_map(|f| f.file_name().and_then(OsStr::to_str).map(String::from)))
+    }
+
+    /// Create a new profile with no saved mod state.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The name of the profile. It must pass `Profile::validate_name`.
+    ///
+    /// # Errors
+    ///
+    /// InvalidProfileName: If the name can't be used as a profile directory name.
+    pub fn new(name: String) -> Result<Self> {
+        Self::validate_name(&name)?;
+        Ok(Profile {
+            name,
+            mods: HashMap::new(),
+        })
+    }
+
+    /// Check that a name can be used for a profile. Profile names are used as directory names, so
+    /// they follow the same rules as preset names.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The name to check.
+    ///
+    /// # Errors
+    ///
+    /// InvalidProfileName: If the name is empty, refers to a directory, contains characters file
+    /// names can't, ends with a dot or space, or is a name Windows reserves for devices.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::Profile;
+    ///
+    /// assert!(Profile::validate_name("racing").is_ok());
+    /// assert!(Profile::validate_name("../evil").is_err());
+    /// ```
+    pub fn validate_name(name: &str) -> Result<()> {
+        crate::Preset::validate_name(name).map_err(|e| match e {
+            InvalidPresetName { reason, .. } => InvalidProfileName {
+                profile: name.into(),
+                reason,
+            },
+            e => e,
+        })
+    }
+
+    /// Get the name of the profile.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Serialize and save the profile to a writer.
+    ///
+    /// # Arguments
+    ///
+    /// `writer`: The writer to save the profile to.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue writing to the writer.
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Serialize and save the profile to its directory, creating the directory if needed.
+    ///
+    /// # Arguments
+    ///
+    /// `profiles_dir`: The directory where profiles are stored.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidProfileName`: If the profile's name, e.g. from a file written by hand, can't be
+    ///   used as a directory name.
+    /// * `std::io::Error`: If there is an issue creating the directory or file, or writing to it.
+    pub fn save_to_path(&self, profiles_dir: impl AsRef<Path>) -> Result<()> {
+        Self::validate_name(&self.name)?;
+        let profiles_dir = profiles_dir.as_ref();
+        let dir = profiles_dir.join(&self.name);
+        fs::create_dir_all(&dir)?;
+        let file = File::create(dir.join(Self::filename()))?;
+        let writer = BufWriter::new(file);
+        self.save(writer)
+    }
+
+    /// Deserialize and load a profile from a reader.
+    ///
+    /// # Arguments
+    ///
+    /// `reader`: The reader to load the profile from.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue reading or deserializing the profile.
+    pub fn load<R: BufRead>(reader: R) -> Result<Self> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Deserialize and load a profile from its directory.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The name of the profile to load.
+    /// `profiles_dir`: The directory where profiles are stored.
+    ///
+    /// # Errors
+    ///
+    /// InvalidProfileName: If the name can't be used as a profile directory name.
+    /// MissingProfile: If the profile doesn't exist.
+    /// Possible IO errors if there is an issue reading the file or serde_json errors if there is
+    /// an issue deserializing the profile.
+    pub fn load_from_path(name: &str, profiles_dir: impl AsRef<Path>) -> Result<Self> {
+        Self::validate_name(name)?;
+        let profiles_dir = profiles_dir.as_ref();
+        let profile_path = profiles_dir.join(name).join(Self::filename());
+        if profile_path.try_exists()? {
+            let file = File::open(profile_path)?;
+            let reader = BufReader::new(file);
+            Self::load(reader)
+        } else {
+            Err(MissingProfile {
+                dir: profiles_dir.into(),
+                profile: name.into(),
+            })
+        }
+    }
+
+    /// Check if a profile already exists.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The name of the profile to check for.
+    /// `profiles_dir`: The directory where profiles are stored.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    pub fn exists(name: &str, profiles_dir: impl AsRef<Path>) -> bool {
+        let profiles_dir = profiles_dir.as_ref();
+        Self::validate_name(name).is_ok() && profiles_dir.join(name).join(Self::filename()).exists()
+    }
+
+    /// Record the current activation state of every mod in the ModCfg into this profile.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg to record the state of.
+    pub fn capture(&mut self, mod_config: &ModCfg) {
+        self.mods = mod_config
+            .get_mods()
+            .filter_map(|m| {
+                mod_config
+                    .is_mod_active(m)
+                    .map(|active| (m.clone(), active))
+            })
+            .collect();
+    }
+
+    /// Restore this profile's saved activation state into the ModCfg.
+    ///
+    /// Saved mods that are no longer installed are ignored, and installed mods the profile has no
+    /// saved state for are left as they are.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg to restore the state into.
+    pub fn restore(&self, mod_config: &mut ModCfg) {
+        for (mod_name, active) in &self.mods {
+            // We don't care if the mod doesn't exist anymore.
+            let _ = mod_config.set_mod_active(mod_name, *active);
+        }
+    }
+
+    /// Get the name of the active profile.
+    ///
+    /// # Arguments
+    ///
+    /// `profiles_dir`: The directory where profiles are stored.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading the active profile file.
+    pub fn active(profiles_dir: impl AsRef<Path>) -> Result<String> {
+        let profiles_dir = profiles_dir.as_ref();
+        let active_path = profiles_dir.join(Self::active_filename());
+        if active_path.try_exists()? {
+            let name = fs::read_to_string(active_path)?.trim().to_string();
+            if !name.is_empty() {
+                return Ok(name);
+            }
+        }
+        Ok(Self::DEFAULT_NAME.into())
+    }
+
+    /// Switch the active profile.
+    ///
+    /// The current activation state is saved into the currently active profile, then the new
+    /// profile's saved state is restored into the ModCfg in memory. The new profile's presets
+    /// still need to be applied and the ModCfg saved for the switch to reach the game.
+    ///
+    /// # Arguments
+    ///
+    /// `name`: The name of the profile to switch to.
+    /// `profiles_dir`: The directory where profiles are stored.
+    /// `mod_config`: The ModCfg to save the state of and restore the new state into.
+    ///
+    /// # Errors
+    ///
+    /// MissingProfile: If the profile to switch to doesn't exist. The default profile always
+    /// exists.
+    /// Possible IO or serde_json errors if there is an issue reading or writing profiles.
+    pub fn switch(
+        name: &str,
+        profiles_dir: impl AsRef<Path>,
+        mod_config: &mut ModCfg,
+    ) -> Result<()> {
+        let profiles_dir = profiles_dir.as_ref();
+        let target = if name == Self::DEFAULT_NAME && !Self::exists(name, profiles_dir) {
+            Self::new(name.into())?
+        } else {
+            Self::load_from_path(name, profiles_dir)?
+        };
+
+        let current_name = Self::active(profiles_dir)?;
+        let mut current = match Self::load_from_path(&current_name, profiles_dir) {
+            Ok(profile) => profile,
+            Err(MissingProfile { .. }) => Self::new(current_name)?,
+            Err(e) => return Err(e),
+        };
+        current.capture(mod_config);
+        current.save_to_path(profiles_dir)?;
+
+        target.restore(mod_config);
+        target.save_to_path(profiles_dir)?;
+        fs::write(profiles_dir.join(Self::active_filename()), name)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+
+    #[test]
+    fn saving_and_loading_profile() {
+        let mock = MockData::new();
+        let tmp = tempfile::tempdir().unwrap();
+        let profiles_dir = tmp.path();
+
+        let mut profile = Profile::new("racing".into()).unwrap();
+        profile.capture(&mock.modcfg);
+        profile.save_to_path(profiles_dir).unwrap();
+
+        let loaded = Profile::load_from_path("racing", profiles_dir).unwrap();
+        assert_eq!(loaded, profile);
+        assert_eq!(
+            Profile::list(profiles_dir).unwrap().collect::<Vec<_>>(),
+            vec!["racing"]
+        );
+    }
+
+    #[test]
+    fn load_missing_profile() {
+        let tmp = tempfile::tempdir().unwrap();
+
+        let result = Profile::load_from_path("missing", tmp.path());
+        assert!(matches!(result, Err(MissingProfile { .. })));
+    }
+
+    #[test]
+    fn switching_profiles() {
+        let mock = MockData::new();
+        let tmp = tempfile::tempdir().unwrap();
+        let profiles_dir = tmp.path();
+        let mut mod_cfg = mock.modcfg;
+
+        assert_eq!(
+            Profile::active(profiles_dir).unwrap(),
+            Profile::DEFAULT_NAME
+        );
+
+        // Create a profile where every mod is disabled.
+        mod_cfg.set_all_mods_active(false).unwrap();
+        let mut profile = Profile::new("vanilla".into()).unwrap();
+        profile.capture(&mod_cfg);
+        profile.save_to_path(profiles_dir).unwrap();
+        mod_cfg.set_all_mods_active(true).unwrap();
+
+        Profile::switch("vanilla", profiles_dir, &mut mod_cfg).unwrap();
+
+        assert_eq!(Profile::active(profiles_dir).unwrap(), "vanilla");
+        assert!(!mod_cfg.is_mod_active("mod1").unwrap());
+
+        // Switching back restores the state the default profile had when we left it.
+        Profile::switch(Profile::DEFAULT_NAME, profiles_dir, &mut mod_cfg).unwrap();
+
+        assert_eq!(
+            Profile::active(profiles_dir).unwrap(),
+            Profile::DEFAULT_NAME
+        );
+        assert!(mod_cfg.is_mod_active("mod1").unwrap());
+        assert!(mod_cfg.is_mod_active("mod2").unwrap());
+    }
+
+    #[test]
+    fn switching_to_missing_profile() {
+        let mock = MockData::new();
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mod_cfg = mock.modcfg;
+
+        let result = Profile::switch("missing", tmp.path(), &mut mod_cfg);
+        assert!(matches!(result, Err(MissingProfile { .. })));
+        assert_eq!(Profile::active(tmp.path()).unwrap(), Profile::DEFAULT_NAME);
+    }
+
+    #[test]
+    fn validating_profile_names() {
+        let tmp = tempfile::tempdir().unwrap();
+        let profiles_dir = tmp.path().join("profiles");
+        for name in ["", "..", "../x", "a/b", "what?", "con"] {
+            assert!(
+                matches!(Profile::new(name.into()), Err(InvalidProfileName { .. })),
+                "{:?}",
+                name
+            );
+        }
+        assert!(matches!(
+            Profile::load_from_path("../x", &profiles_dir),
+            Err(InvalidProfileName { .. })
+        ));
+        assert!(!Profile::exists("../x", &profiles_dir));
+        assert!(matches!(
+            crate::path::profile_presets_dir(tmp.path(), "../x"),
+            Err(InvalidProfileName { .. })
+        ));
+        // Nothing is created outside the profiles directory.
+        assert!(!tmp.path().join("x").exists());
+    }
+}
@@ -0,0 +1,439 @@
+use crate::{
+    game::{MissingModPolicy, ModCfg},
+    index::PresetIndex,
+    namespace::Namespaces,
+    stats::Stats,
+    Result,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Write},
+    path::PathBuf,
+};
+
+/// JSON-RPC error code for requests that aren't valid JSON.
+const PARSE_ERROR: i64 = -32700;
+/// JSON-RPC error code for JSON that isn't a valid request.
+const INVALID_REQUEST: i64 = -32600;
+/// JSON-RPC error code for unknown methods.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code for invalid method parameters.
+const INVALID_PARAMS: i64 = -32602;
+/// JSON-RPC error code for errors returned by BeamMM itself.
+const BEAMMM_ERROR: i64 = -32000;
+
+/// A JSON-RPC 2.0 server exposing BeamMM's mod and preset operations, so frontends like the GUI
+/// can keep one process running instead of running the CLI for every command.
+///
+/// Requests and responses are JSON objects, one per line. Requests without an `id` are
+/// notifications and get no response. The mod configuration is kept in memory between requests
+/// and is only written to db.json by the `save` method.
+///
+/// # Methods
+///
+/// * `list_mods`: Returns `[{"name", "active"}]`, sorted by name.
+/// * `set_mods_active`: Takes `{"mods": [..], "active": bool}`.
+/// * `list_presets`: Returns `[{"name", "enabled", "mods"}]`, sorted by name.
+/// * `set_presets_enabled`: Takes `{"presets": [..], "enabled": bool}`.
+/// * `apply_presets`: Takes an optional `{"policy": "fail" | "skip" | "prune"}`.
+/// * `stats`: Returns the totals from `Stats::collect`.
+/// * `save`: Writes the mod configuration to db.json.
+/// * `reload`: Discards unsaved changes and reloads db.json.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{game::ModCfg, rpc::Server};
+/// # use tempfile::tempdir;
+///
+/// # let temp_presets_dir = tempdir().unwrap();
+/// # let presets_dir = temp_presets_dir.path();
+/// # let temp_mods_dir = tempdir().unwrap();
+/// # let mods_dir = temp_mods_dir.path();
+/// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{\"mod1\":{\"active\":true}}}").unwrap();
+/// let mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+/// let mut server = Server::new(mod_cfg, mods_dir.into(), presets_dir.into());
+///
+/// let response = server.handle(r#"{"jsonrpc": "2.0", "id": 1, "method": "list_mods"}"#);
+/// assert_eq!(
+///     response.unwrap(),
+///     r#"{"id":1,"jsonrpc":"2.0","result":[{"active":true,"name":"mod1"}]}"#
+/// );
+/// ```
+pub struct Server {
+    /// The mod configuration being managed.
+    mod_config: ModCfg,
+    /// The mods directory of the current game version.
+    mods_dir: PathBuf,
+    /// The directory where the presets are stored.
+    presets_dir: PathBuf,
+    /// The personal and shared presets directories.
+    namespaces: Namespaces,
+}
+
+/// A JSON-RPC request.
+#[derive(Deserialize)]
+struct Request {
+    /// The request ID, echoed in the response. Missing for notifications.
+    #[serde(default)]
+    id: Option<Value>,
+    /// The method to call.
+    method: String,
+    /// The method's parameters.
+    #[serde(default)]
+    params: Value,
+}
+
+/// An error to send back as the response to a request.
+struct RpcError {
+    /// The JSON-RPC error code.
+    code: i64,
+    /// A description of the error.
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        RpcError {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<crate::Error> for RpcError {
+    fn from(e: crate::Error) -> Self {
+        RpcError::new(BEAMMM_ERROR, e)
+    }
+}
+
+/// Parameters of `set_mods_active`.
+#[derive(Deserialize)]
+struct SetModsActive {
+    mods: Vec<String>,
+    active: bool,
+}
+
+/// Parameters of `set_presets_enabled`.
+#[derive(Deserialize)]
+struct SetPresetsEnabled {
+    presets: Vec<String>,
+    enabled: bool,
+}
+
+/// Parameters of `apply_presets`.
+#[derive(Deserialize)]
+struct ApplyPresets {
+    #[serde(default)]
+    policy: Option<String>,
+}
+
+impl Server {
+    /// Create a server managing a mod configuration.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The mod configuration to manage.
+    /// `mods_dir`: The mods directory of the current game version, where db.json is saved.
+    /// `presets_dir`: The directory where the presets are stored.
+    pub fn new(mod_config: ModCfg, mods_dir: PathBuf, presets_dir: PathBuf) -> Self {
+        Server {
+            namespaces: Namespaces::new(&presets_dir, BTreeMap::new()),
+            mod_config,
+            mods_dir,
+            presets_dir,
+        }
+    }
+
+    /// Set the shared preset namespaces, so their presets can be enabled, disabled and applied
+    /// like personal ones. There are none by default.
+    ///
+    /// # Arguments
+    ///
+    /// `shared`: The shared presets directories by namespace, like `Config::preset_namespaces`.
+    pub fn set_preset_namespaces(&mut self, shared: BTreeMap<String, PathBuf>) {
+        self.namespaces = Namespaces::new(&self.presets_dir, shared);
+    }
+
+    /// Serve requests from a reader until it is exhausted, writing responses to a writer.
+    ///
+    /// # Arguments
+    ///
+    /// `reader`: Where requests are read from, e.g. stdin.
+    /// `writer`: Where responses are written to, e.g. stdout.
+    ///
+    /// # Errors
+    ///
+    /// IO errors are possible from read and write operations. Errors from handling requests are
+    /// sent as responses instead.
+    pub fn serve<R: BufRead, W: Write>(&mut self, reader: R, mut writer: W) -> Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line) {
+                writeln!(writer, "{}", response)?;
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a single request.
+    ///
+    /// # Arguments
+    ///
+    /// `request`: The JSON-RPC request.
+    ///
+    /// # Returns
+    ///
+    /// The JSON-RPC response, or `None` if the request is a notification.
+    pub fn handle(&mut self, request: &str) -> Option<String> {
+        let value: Value = match serde_json::from_str(request) {
+            Ok(value) => value,
+            Err(e) => {
+                return Some(Self::response(
+                    Value::Null,
+                    Err(RpcError::new(PARSE_ERROR, e)),
+                ))
+            }
+        };
+        let request: Request = match serde_json::from_value(value) {
+            Ok(request) => request,
+            Err(e) => {
+                return Some(Self::response(
+                    Value::Null,
+                    Err(RpcError::new(INVALID_REQUEST, e)),
+                ))
+            }
+        };
+
+        let result = self.call(&request.method, request.params);
+        request.id.map(|id| Self::response(id, result))
+    }
+
+    /// Call a method.
+    fn call(&mut self, method: &str, params: Value) -> core::result::Result<Value, RpcError> {
+        match method {
+            "list_mods" => {
+                let mut mods: Vec<&String> = self.mod_config.get_mods().collect();
+                mods.sort();
+                Ok(mods
+                    .into_iter()
+                    .map(|m| json!({"name": m, "active": self.mod_config.is_mod_active(m)}))
+                    .collect())
+            }
+            "set_mods_active" => {
+                let params: SetModsActive = Self::params(params)?;
+                self.mod_config
+                    .set_mods_active(params.mods, params.active)?;
+                Ok(Value::Null)
+            }
+            "list_presets" => {
+                let listed: Vec<Value> = PresetIndex::load_from_path(&self.presets_dir)?
+                    .iter()
+                    .map(|(preset_name, preset)| {
+                        json!({
+                            "name": preset_name,
+                            "enabled": preset.is_enabled(),
+                            "mods": preset.mods(),
+                        })
+                    })
+                    .collect();
+                Ok(listed.into())
+            }
+            "set_presets_enabled" => {
+                let params: SetPresetsEnabled = Self::params(params)?;
+                self.namespaces.set_many_enabled(
+                    params.presets,
+                    params.enabled,
+                    &mut self.mod_config,
+                )?;
+                Ok(Value::Null)
+            }
+            "apply_presets" => {
+                let params: ApplyPresets = Self::params(params)?;
+                let policy = match params.policy {
+                    Some(policy) => policy.parse()?,
+                    None => MissingModPolicy::Fail,
+                };
+                self.namespaces
+                    .apply_presets(&mut self.mod_config, policy)?;
+                Ok(Value::Null)
+            }
+            "stats" => {
+                let stats = Stats::collect(&self.mod_config, &self.mods_dir, &self.presets_dir)?;
+                Ok(serde_json::to_value(stats).map_err(crate::Error::from)?)
+            }
+            "save" => {
+                self.mod_config.save_to_path(&self.mods_dir)?;
+                Ok(Value::Null)
+            }
+            "reload" => {
+                let mut mod_config = ModCfg::load_or_default(&self.mods_dir)?;
+                // Keep BeamMM's own settings, which aren't stored in db.json.
+                mod_config.copy_settings_from(&self.mod_config);
+                self.mod_config = mod_config;
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method `{}`.", method),
+            )),
+        }
+    }
+
+    /// Deserialize a method's parameters. Missing parameters are treated as an empty object.
+    fn params<T: DeserializeOwned>(params: Value) -> core::result::Result<T, RpcError> {
+        let params = if params.is_null() { json!({}) } else { params };
+        serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e))
+    }
+
+    /// Build a response to a request.
+    fn response(id: Value, result: core::result::Result<Value, RpcError>) -> String {
+        let response = match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": e.code, "message": e.message},
+            }),
+        };
+        response.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+
+    fn server() -> (MockData, Server) {
+        let mock = MockData::new();
+        let server = Server::new(
+            mock.modcfg.clone(),
+            mock.mods_dir.clone(),
+            mock.presets_dir.clone(),
+        );
+        (mock, server)
+    }
+
+    fn call(server: &mut Server, method: &str, params: Value) -> Value {
+        let request = json!({"jsonrpc": "2.0", "id": 7, "method": method, "params": params});
+        let response = server.handle(&request.to_string()).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn toggling_and_saving_mods() {
+        let (mock, mut server) = server();
+
+        let response = call(
+            &mut server,
+            "set_mods_active",
+            json!({"mods": ["mod2"], "active": true}),
+        );
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["result"], Value::Null);
+
+        let response = call(&mut server, "list_mods", Value::Null);
+        assert_eq!(
+            response["result"][1],
+            json!({"name": "mod2", "active": true})
+        );
+
+        call(&mut server, "save", Value::Null);
+        let mod_cfg = ModCfg::load_from_path(&mock.mods_dir).unwrap();
+        assert!(mod_cfg.is_mod_active("mod2").unwrap());
+    }
+
+    #[test]
+    fn presets() {
+        let (_mock, mut server) = server();
+
+        call(
+            &mut server,
+            "set_presets_enabled",
+            json!({"presets": ["preset2"], "enabled": true}),
+        );
+        call(&mut server, "apply_presets", json!({"policy": "skip"}));
+
+        let response = call(&mut server, "list_presets", Value::Null);
+        assert_eq!(response["result"][1]["name"], "preset2");
+        assert_eq!(response["result"][1]["enabled"], true);
+
+        let response = call(&mut server, "stats", Value::Null);
+        assert_eq!(response["result"]["enabled_presets"], 2);
+        let response = call(&mut server, "list_mods", Value::Null);
+        assert_eq!(response["result"][1]["active"], true);
+    }
+
+    #[test]
+    fn shared_presets() {
+        let (_mock, mut server) = server();
+        let team_dir = tempfile::tempdir().unwrap();
+        crate::Preset::new("racing".into(), vec!["mod2".into()])
+            .unwrap()
+            .save_to_path(team_dir.path())
+            .unwrap();
+        server.set_preset_namespaces(BTreeMap::from([("team".into(), team_dir.path().into())]));
+
+        call(
+            &mut server,
+            "set_presets_enabled",
+            json!({"presets": ["team/racing"], "enabled": true}),
+        );
+        call(&mut server, "apply_presets", Value::Null);
+        let response = call(&mut server, "list_mods", Value::Null);
+        assert_eq!(response["result"][1]["active"], true);
+    }
+
+    #[test]
+    fn errors() {
+        let (_mock, mut server) = server();
+
+        let response = call(
+            &mut server,
+            "set_mods_active",
+            json!({"mods": ["missing"], "active": true}),
+        );
+        assert_eq!(response["error"]["code"], BEAMMM_ERROR);
+        assert_eq!(response["id"], 7);
+
+        let response = call(&mut server, "set_mods_active", json!({"mods": "mod1"}));
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+
+        let response = call(&mut server, "fly", Value::Null);
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        let response: Value = serde_json::from_str(&server.handle("{").unwrap()).unwrap();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert_eq!(response["id"], Value::Null);
+
+        // Notifications get no response, even when they fail.
+        assert_eq!(
+            server.handle(r#"{"jsonrpc": "2.0", "method": "fly"}"#),
+            None
+        );
+    }
+
+    #[test]
+    fn serving() {
+        let (_mock, mut server) = server();
+        let requests = "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"save\"}\n\n\
+            {\"jsonrpc\":\"2.0\",\"method\":\"save\"}\n\
+            {\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"reload\"}\n";
+        let mut output = vec![];
+
+        server.serve(requests.as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output,
+            "{\"id\":1,\"jsonrpc\":\"2.0\",\"result\":null}\n\
+            {\"id\":2,\"jsonrpc\":\"2.0\",\"result\":null}\n"
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use crate::{config::RetryPolicy, game::ModCfg, net, GameVersion, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// A shared set of compatibility rules, such as mods known to break each other or mods known to be
+/// broken on specific game versions.
+///
+/// Rules are stored in the BeamMM directory, and can be downloaded from a URL so a community can
+/// maintain them together.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{game::ModCfg, rules::Rules};
+///
+/// let rules = Rules::load(
+///     r#"{"incompatible":[{"mods":["mod1","mod2"],"reason":"Both replace the same part."}]}"#
+///         .as_bytes(),
+/// )
+/// .unwrap();
+/// let mod_cfg = ModCfg::load(
+///     r#"{"mods":{"mod1":{"active":true},"mod2":{"active":true}}}"#.as_bytes(),
+/// )
+/// .unwrap();
+///
+/// for violation in rules.violations(&mod_cfg, &"0.32".parse().unwrap()) {
+///     println!("{}", violation);
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Rules {
+    /// Groups of mods that don't work when active together.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    incompatible: Vec<Incompatibility>,
+    /// Mods that don't work on some game versions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    broken: Vec<Breakage>,
+}
+
+/// Mods that don't work when active together.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Incompatibility {
+    /// The mods that conflict with each other.
+    pub mods: Vec<String>,
+    /// Why the mods conflict.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// A mod that doesn't work on some game versions.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Breakage {
+    /// The broken mod.
+    #[serde(rename = "mod")]
+    pub mod_name: String,
+    /// The game versions the mod is broken on, e.g. `0.32`.
+    pub versions: Vec<String>,
+    /// Why the mod is broken.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// A rule broken by the active mods.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    /// Incompatible mods are active together.
+    Incompatible {
+        /// The incompatible mods that are active.
+        mods: Vec<String>,
+        /// Why the mods conflict.
+        reason: Option<String>,
+    },
+    /// An active mod is broken on the current game version.
+    Broken {
+        /// The broken mod.
+        mod_name: String,
+        /// The current game version.
+        version: GameVersion,
+        /// Why the mod is broken.
+        reason: Option<String>,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            Violation::Incompatible { mods, reason } => {
+                write!(f, "{} are incompatible", mods.join(", "))?;
+                reason
+            }
+            Violation::Broken {
+                mod_name,
+                version,
+                reason,
+            } => {
+                write!(f, "{} is broken on {}", mod_name, version)?;
+                reason
+            }
+        };
+        match reason {
+            Some(reason) => write!(f, ": {}", reason),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Rules {
+    /// The filename of the rules file.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    fn filename() -> PathBuf {
+        PathBuf::from("rules.json")
+    }
+
+    /// Load the rules from a reader.
+    ///
+    /// # Arguments
+    ///
+    /// `reader`: The reader to load the rules from.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue reading or deserializing the rules.
+    pub fn load<R: BufRead>(reader: R) -> Result<Self> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Load the rules from the BeamMM directory. If there is no rules file, there are no rules.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The BeamMM directory where the rules file is stored.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading the file or serde_json errors if there is
+    /// an issue deserializing the rules.
+    pub fn load_from_path(beammm_dir: impl AsRef<Path>) -> Result<Self> {
+        let beammm_dir = beammm_dir.as_ref();
+        let path = beammm_dir.join(Self::filename());
+        if path.try_exists()? {
+            let file = File::open(path)?;
+            let reader = BufReader::new(file);
+            Self::load(reader)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Download the rules from a URL.
+    ///
+    /// # Arguments
+    ///
+    /// `url`: The URL of a rules file.
+    /// `policy`: How failed requests are retried.
+    ///
+    /// # Errors
+    ///
+    /// Download: If the rules couldn't be downloaded.
+    /// Possible serde_json errors if the downloaded rules can't be deserialized.
+    pub fn fetch(url: &str, policy: &RetryPolicy) -> Result<Self> {
+        let bytes = net::get(url, &[], policy)?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// Serialize and save the rules to a writer.
+    ///
+    /// # Arguments
+    ///
+    /// `writer`: The writer to save the rules to.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue serializing the rules or writing.
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Serialize and save the rules to the BeamMM directory.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The BeamMM directory where the rules file will be saved.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue creating the file or writing to it.
+    /// Possible serde_json errors if there is an issue serializing the rules.
+    pub fn save_to_path(&self, beammm_dir: impl AsRef<Path>) -> Result<()> {
+        let beammm_dir = beammm_dir.as_ref();
+        let file = File::create(beammm_dir.join(Self::filename()))?;
+        let writer = BufWriter::new(file);
+        self.save(writer)
+    }
+
+    /// Get the number of rules.
+    pub fn len(&self) -> usize {
+        self.incompatible.len() + self.broken.len()
+    }
+
+    /// Whether there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the rules broken by the active mods.
+    ///
+    /// An incompatibility is only broken when at least two of its mods are active.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg with the active mods.
+    /// `version`: The current game version.
+    pub fn violations(&self, mod_config: &ModCfg, version: &GameVersion) -> Vec<Violation> {
+        let is_active = |mod_name: &String| mod_config.is_mod_active(mod_name) == Some(true);
+        let mut violations = vec![];
+        for incompatibility in &self.incompatible {
+            let active: Vec<String> = incompatibility
+                .mods
+                .iter()
+                .filter(|m| is_active(m))
+                .cloned()
+                .collect();
+            if active.len() > 1 {
+                violations.push(Violation::Incompatible {
+                    mods: active,
+                    reason: incompatibility.reason.clone(),
+                });
+            }
+        }
+        for breakage in &self.broken {
+            let broken_here = breakage
+                .versions
+                .iter()
+                .any(|v| v.parse::<GameVersion>().is_ok_and(|v| v == *version));
+            if broken_here && is_active(&breakage.mod_name) {
+                violations.push(Violation::Broken {
+                    mod_name: breakage.mod_name.clone(),
+                    version: *version,
+                    reason: breakage.reason.clone(),
+                });
+            }
+        }
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+
+    const RULES: &str = r#"{
+        "incompatible": [
+            {"mods": ["mod1", "mod2", "mod3"], "reason": "Same part"},
+            {"mods": ["mod1", "mod2"]}
+        ],
+        "broken": [
+            {"mod": "mod1", "versions": ["0.31", "0.32"], "reason": "Uses removed Lua API"},
+            {"mod": "mod2", "versions": ["0.32"]}
+        ]
+    }"#;
+
+    #[test]
+    fn finding_violations() {
+        let mock = MockData::new();
+        let rules = Rules::load(RULES.as_bytes()).unwrap();
+        assert_eq!(rules.len(), 4);
+
+        // mod1 and mod3 are active, mod2 isn't.
+        let violations = rules.violations(&mock.modcfg, &"0.32".parse().unwrap());
+        assert_eq!(
+            violations,
+            vec![
+                Violation::Incompatible {
+                    mods: vec!["mod1".into(), "mod3".into()],
+                    reason: Some("Same part".into()),
+                },
+                Violation::Broken {
+                    mod_name: "mod1".into(),
+                    version: "0.32".parse().unwrap(),
+                    reason: Some("Uses removed Lua API".into()),
+                },
+            ]
+        );
+        assert_eq!(
+            violations[0].to_string(),
+            "mod1, mod3 are incompatible: Same part"
+        );
+
+        let violations = rules.violations(&mock.modcfg, &"0.33".parse().unwrap());
+        assert_eq!(violations.len(), 1);
+    }
+
+    #[test]
+    fn saving_and_loading_rules() {
+        let tmp = tempfile::tempdir().unwrap();
+        let beammm_dir = tmp.path();
+        assert!(Rules::load_from_path(beammm_dir).unwrap().is_empty());
+
+        let rules = Rules::load(RULES.as_bytes()).unwrap();
+        rules.save_to_path(beammm_dir).unwrap();
+        assert_eq!(Rules::load_from_path(beammm_dir).unwrap(), rules);
+    }
+}
@@ -0,0 +1,674 @@
+use crate::{
+    game::{MissingModPolicy, ModCfg},
+    index::PresetIndex,
+    namespace::Namespaces,
+    transaction::Transaction,
+    Error::*,
+    Preset, Result,
+};
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    validate::Validator, Context, Editor, Helper,
+};
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Write},
+    path::PathBuf,
+};
+
+/// The shell's commands, for tab completion.
+const COMMANDS: [&str; 14] = [
+    "list",
+    "presets",
+    "enable",
+    "disable",
+    "enable-preset",
+    "disable-preset",
+    "apply",
+    "complete",
+    "history",
+    "save",
+    "reload",
+    "exit",
+    "abort",
+    "help",
+];
+
+/// The help text listing the shell's commands.
+const HELP: &str = "\
+Commands:
+  list                      List the mods
+  presets                   List the presets
+  enable <MOD>...           Enable mods
+  disable <MOD>...          Disable mods
+  enable-preset <PRESET>    Enable a preset and save it
+  disable-preset <PRESET>   Disable a preset and save it
+  apply [POLICY]            Apply the enabled presets
+  complete <PREFIX>         List the mods and presets starting with a prefix
+  history                   List the commands run so far
+  !<N> or !!                Run command N from the history, or the last command
+  save                      Save db.json
+  reload                    Discard unsaved changes and reload db.json
+  exit                      Save db.json and exit
+  abort                     Exit without saving
+Mod and preset names can be shortened to any prefix that matches only one name.";
+
+/// An interactive shell that keeps the mod configuration loaded between commands, for long
+/// curation sessions.
+///
+/// Changes to mods are kept in memory until `save` or `exit`. Reaching the end of the input is the
+/// same as `exit`. Preset changes are saved right away, like in the CLI. In a terminal,
+/// `Shell::run_interactive` adds line editing, recalling earlier commands with the arrow keys and
+/// completing commands, mods and presets with tab.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{game::ModCfg, shell::Shell};
+/// # use tempfile::tempdir;
+///
+/// # let temp_presets_dir = tempdir().unwrap();
+/// # let presets_dir = temp_presets_dir.path();
+/// # let temp_mods_dir = tempdir().unwrap();
+/// # let mods_dir = temp_mods_dir.path();
+/// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{\"mod1\":{\"active\":true}}}").unwrap();
+/// let mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+/// let mut shell = Shell::new(mod_cfg, mods_dir.into(), presets_dir.into());
+///
+/// let mut output = vec![];
+/// shell.run("disable mod1\nexit\n".as_bytes(), &mut output).unwrap();
+///
+/// let mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+/// assert_eq!(mod_cfg.is_mod_active("mod1"), Some(false));
+/// ```
+pub struct Shell {
+    /// The mod configuration being managed.
+    mod_config: ModCfg,
+    /// The mods directory of the current game version.
+    mods_dir: PathBuf,
+    /// The directory where the presets are stored.
+    presets_dir: PathBuf,
+    /// The personal and shared presets directories.
+    namespaces: Namespaces,
+    /// The commands run so far, oldest first.
+    history: Vec<String>,
+    /// Whether the mod configuration has changes that aren't saved.
+    unsaved: bool,
+}
+
+impl Shell {
+    /// Create a shell managing a mod configuration.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The mod configuration to manage.
+    /// `mods_dir`: The mods directory of the current game version, where db.json is saved.
+    /// `presets_dir`: The directory where the presets are stored.
+    pub fn new(mod_config: ModCfg, mods_dir: PathBuf, presets_dir: PathBuf) -> Self {
+        Shell {
+            namespaces: Namespaces::new(&presets_dir, BTreeMap::new()),
+            mod_config,
+            mods_dir,
+            presets_dir,
+            history: vec![],
+            unsaved: false,
+        }
+    }
+
+    /// Set the shared preset namespaces, so their presets can be enabled, disabled and applied
+    /// like personal ones. There are none by default.
+    ///
+    /// # Arguments
+    ///
+    /// `shared`: The shared presets directories by namespace, like `Config::preset_namespaces`.
+    pub fn set_preset_namespaces(&mut self, shared: BTreeMap<String, PathBuf>) {
+        self.namespaces = Namespaces::new(&self.presets_dir, shared);
+    }
+
+    /// Run commands from a reader until `exit`, `abort`, or the end of the input, writing output
+    /// and prompts to a writer.
+    ///
+    /// # Arguments
+    ///
+    /// `reader`: Where commands are read from, e.g. stdin.
+    /// `writer`: Where output is written to, e.g. stdout.
+    ///
+    /// # Errors
+    ///
+    /// IO errors are possible from read and write operations, and saving db.json on exit. Errors
+    /// from commands are written to `writer` instead.
+    pub fn run<R: BufRead, W: Write>(&mut self, mut reader: R, mut writer: W) -> Result<()> {
+        loop {
+            write!(writer, "beammm> ")?;
+            writer.flush()?;
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                writeln!(writer)?;
+                return self.execute("exit", &mut writer).map(|_| ());
+            }
+            if !self.execute(&line, &mut writer)? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Run commands typed in the terminal until `exit`, `abort`, or Ctrl+D, like `Shell::run`
+    /// with line editing. Earlier commands are recalled with the arrow keys and commands, mods and
+    /// presets are completed with tab. Ctrl+C clears the line.
+    ///
+    /// # Errors
+    ///
+    /// IO errors are possible from reading the terminal, and saving db.json on exit. Errors from
+    /// commands are printed instead.
+    pub fn run_interactive(&mut self) -> Result<()> {
+        let mut editor: Editor<LineHelper, _> = Editor::new().map_err(readline_error)?;
+        let mut stdout = std::io::stdout();
+        loop {
+            // Mods and presets may have changed with the last command.
+            editor.set_helper(Some(LineHelper {
+                names: self.complete("")?,
+            }));
+            let line = match editor.readline("beammm> ") {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => {
+                    return self.execute("exit", &mut stdout).map(|_| ());
+                }
+                Err(e) => return Err(readline_error(e)),
+            };
+            editor
+                .add_history_entry(line.as_str())
+                .map_err(readline_error)?;
+            if !self.execute(&line, &mut stdout)? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Run a single command.
+    ///
+    /// # Arguments
+    ///
+    /// `line`: The command and its arguments.
+    /// `writer`: Where output is written to.
+    ///
+    /// # Returns
+    ///
+    /// Whether the shell should keep running.
+    ///
+    /// # Errors
+    ///
+    /// IO errors are possible from write operations, and saving db.json on exit. Errors from
+    /// commands are written to `writer` instead.
+    pub fn execute<W: Write>(&mut self, line: &str, writer: &mut W) -> Result<bool> {
+        let line = line.trim();
+        let line = match line {
+            "" => return Ok(true),
+            "!!" => self.history.last().cloned(),
+            _ => match line.strip_prefix('!') {
+                Some(n) => n
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| self.history.get(n.checked_sub(1)?).cloned()),
+                None => Some(line.to_string()),
+            },
+        };
+        let Some(line) = line else {
+            writeln!(writer, "No such command in the history.")?;
+            return Ok(true);
+        };
+        self.history.push(line.clone());
+
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let args: Vec<&str> = words.collect();
+        match command {
+            "exit" | "quit" => {
+                if self.unsaved {
+                    self.mod_config.save_to_path(&self.mods_dir)?;
+                    writeln!(writer, "db.json saved.")?;
+                }
+                return Ok(false);
+            }
+            "abort" => return Ok(false),
+            _ => (),
+        }
+        if let Err(e) = self.command(command, &args, writer) {
+            match e {
+                IO(_) => return Err(e),
+                e => writeln!(writer, "Error: {}", e)?,
+            }
+        }
+        Ok(true)
+    }
+
+    /// Get the mod and preset names starting with a prefix, sorted.
+    ///
+    /// # Arguments
+    ///
+    /// `prefix`: The start of the names.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading the presets directory.
+    pub fn complete(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut names: Vec<String> = self
+            .mod_config
+            .get_mods()
+            .cloned()
+            .chain(Preset::list(&self.presets_dir)?)
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// Run a script of commands, one per line, then save db.json. Empty lines and lines starting
+    /// with `#` are skipped. `exit` stops the script early, and `abort` stops it without saving.
+    /// The history commands aren't available in scripts.
+    ///
+    /// # Arguments
+    ///
+    /// `reader`: Where the script is read from.
+    /// `writer`: Where output is written to.
+    /// `all_or_nothing`: Whether to stop at the first failing command and undo the script's changes
+    ///   to the mods and presets. Otherwise, failing commands are reported and skipped.
+    ///
+    /// # Errors
+    ///
+    /// * `Error::ScriptFailed` if a command fails and `all_or_nothing` is set.
+    /// * IO errors are possible from read and write operations, and saving db.json.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use beammm::{game::ModCfg, shell::Shell};
+    /// # use tempfile::tempdir;
+    ///
+    /// # let temp_presets_dir = tempdir().unwrap();
+    /// # let presets_dir = temp_presets_dir.path();
+    /// # let temp_mods_dir = tempdir().unwrap();
+    /// # let mods_dir = temp_mods_dir.path();
+    /// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{\"mod1\":{\"active\":true}}}").unwrap();
+    /// let mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+    /// let mut shell = Shell::new(mod_cfg, mods_dir.into(), presets_dir.into());
+    ///
+    /// let script = "# Set up the race\ndisable mod1\nenable mod2\n";
+    /// assert!(shell.run_script(script.as_bytes(), vec![], true).is_err());
+    ///
+    /// let mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+    /// assert_eq!(mod_cfg.is_mod_active("mod1"), Some(true));
+    /// ```
+    pub fn run_script<R: BufRead, W: Write>(
+        &mut self,
+        reader: R,
+        mut writer: W,
+        all_or_nothing: bool,
+    ) -> Result<()> {
+        let mod_config = self.mod_config.clone();
+        let mut transaction = Transaction::new();
+        if all_or_nothing {
+            transaction.snapshot_dir(&self.presets_dir)?;
+        }
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let command = words.next().unwrap_or_default();
+            let args: Vec<&str> = words.collect();
+            match command {
+                "exit" | "quit" => break,
+                "abort" => {
+                    self.mod_config = mod_config;
+                    return transaction.commit();
+                }
+                _ => (),
+            }
+
+            match self.command(command, &args, &mut writer) {
+                Ok(()) => (),
+                Err(IO(e)) => return Err(IO(e)),
+                Err(e) if all_or_nothing => {
+                    self.mod_config = mod_config;
+                    transaction.rollback()?;
+                    return Err(ScriptFailed {
+                        line: i + 1,
+                        reason: e.to_string(),
+                    });
+                }
+                Err(e) => writeln!(writer, "Line {}: Error: {}", i + 1, e)?,
+            }
+        }
+
+        self.mod_config.save_to_path(&self.mods_dir)?;
+        self.unsaved = false;
+        transaction.commit()
+    }
+
+    /// Run a command other than `exit` and `abort`.
+    fn command<W: Write>(&mut self, command: &str, args: &[&str], writer: &mut W) -> Result<()> {
+        match command {
+            "help" => writeln!(writer, "{}", HELP)?,
+            "list" => {
+                for entry in self.mod_config.iter_mods() {
+                    let status = if entry.is_active() {
+                        "enabled "
+                    } else {
+                        "disabled"
+                    };
+                    writeln!(writer, "{} {}", status, entry.name())?;
+                }
+            }
+            "presets" => {
+                for (preset_name, preset) in PresetIndex::load_from_path(&self.presets_dir)?.iter()
+                {
+                    let status = if preset.is_enabled() {
+                        "enabled "
+                    } else {
+                        "disabled"
+                    };
+                    writeln!(writer, "{} {}", status, preset_name)?;
+                }
+            }
+            "enable" | "disable" => {
+                let mods = self.expand_mods(args);
+                self.mod_config
+                    .set_mods_active(&mods, command == "enable")?;
+                self.unsaved = true;
+                writeln!(writer, "{}d: {}", command, mods.join(", "))?;
+            }
+            "enable-preset" | "disable-preset" => {
+                let presets = self.expand_presets(args)?;
+                let enabled = command == "enable-preset";
+                self.namespaces
+                    .set_many_enabled(&presets, enabled, &mut self.mod_config)?;
+                if enabled {
+                    self.namespaces
+                        .apply_presets(&mut self.mod_config, MissingModPolicy::Fail)?;
+                }
+                self.unsaved = true;
+                writeln!(writer, "{}d: {}", command, presets.join(", "))?;
+            }
+            "apply" => {
+                let policy = match args.first() {
+                    Some(policy) => policy.parse()?,
+                    None => MissingModPolicy::Fail,
+                };
+                self.namespaces
+                    .apply_presets(&mut self.mod_config, policy)?;
+                self.unsaved = true;
+                writeln!(writer, "Presets applied.")?;
+            }
+            "complete" => {
+                for name in self.complete(args.first().unwrap_or(&""))? {
+                    writeln!(writer, "{}", name)?;
+                }
+            }
+            "history" => {
+                for (i, line) in self.history.iter().enumerate() {
+                    writeln!(writer, "{:>4}  {}", i + 1, line)?;
+                }
+            }
+            "save" => {
+                self.mod_config.save_to_path(&self.mods_dir)?;
+                self.unsaved = false;
+                writeln!(writer, "db.json saved.")?;
+            }
+            "reload" => {
+                let mut mod_config = ModCfg::load_or_default(&self.mods_dir)?;
+                // Keep BeamMM's own settings, which aren't stored in db.json.
+                mod_config.copy_settings_from(&self.mod_config);
+                self.mod_config = mod_config;
+                self.unsaved = false;
+                writeln!(writer, "db.json reloaded.")?;
+            }
+            _ => {
+                return Err(UnknownCommand {
+                    command: command.into(),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    /// Expand mod names shortened to a prefix that matches only one mod. Names that don't match
+    /// are kept, so they are reported as missing.
+    fn expand_mods(&self, names: &[&str]) -> Vec<String> {
+        names
+            .iter()
+            .map(|name| {
+                if self.mod_config.is_mod_active(name).is_some() {
+                    return name.to_string();
+                }
+                let mut matches = self.mod_config.get_mods().filter(|m| m.starts_with(name));
+                match (matches.next(), matches.next()) {
+                    (Some(only), None) => only.clone(),
+                    _ => name.to_string(),
+                }
+            })
+            .collect()
+    }
+
+    /// Expand preset names shortened to a prefix that matches only one personal or shared preset.
+    fn expand_presets(&self, names: &[&str]) -> Result<Vec<String>> {
+        let presets: Vec<String> = Preset::list(&self.presets_dir)?
+            .chain(self.namespaces.list_shared()?)
+            .collect();
+        Ok(names
+            .iter()
+            .map(|name| {
+                if presets.iter().any(|p| p == name) {
+                    return name.to_string();
+                }
+                let mut matches = presets.iter().filter(|p| p.starts_with(name));
+                match (matches.next(), matches.next()) {
+                    (Some(only), None) => only.clone(),
+                    _ => name.to_string(),
+                }
+            })
+            .collect())
+    }
+}
+
+/// Turn an error from reading the terminal into an IO error.
+fn readline_error(e: ReadlineError) -> crate::Error {
+    match e {
+        ReadlineError::Io(e) => IO(e),
+        e => IO(std::io::Error::other(e)),
+    }
+}
+
+/// Completes the commands of a `Shell`, and the mods and presets they take, in the terminal.
+struct LineHelper {
+    /// The names of the mods and presets.
+    names: Vec<String>,
+}
+
+impl LineHelper {
+    /// Get where the word being typed starts and what it could be. The first word is a command
+    /// and the others are mods or presets.
+    fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let start = line[..pos].rfind(' ').map_or(0, |i| i + 1);
+        let prefix = &line[start..pos];
+        let candidates = if line[..start].trim().is_empty() {
+            COMMANDS
+                .iter()
+                .filter(|c| c.starts_with(prefix))
+                .map(|c| c.to_string())
+                .collect()
+        } else {
+            self.names
+                .iter()
+                .filter(|n| n.starts_with(prefix))
+                .cloned()
+                .collect()
+        };
+        (start, candidates)
+    }
+}
+
+impl Completer for LineHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Hinter for LineHelper {
+    type Hint = String;
+}
+
+impl Highlighter for LineHelper {}
+
+impl Validator for LineHelper {}
+
+impl Helper for LineHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+
+    fn shell() -> (MockData, Shell) {
+        let mock = MockData::new();
+        let shell = Shell::new(
+            mock.modcfg.clone(),
+            mock.mods_dir.clone(),
+            mock.presets_dir.clone(),
+        );
+        (mock, shell)
+    }
+
+    fn run(shell: &mut Shell, input: &str) -> String {
+        let mut output = vec![];
+        shell.run(input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn running_commands() {
+        let (mock, mut shell) = shell();
+
+        let output = run(&mut shell, "enable mod2\ndisable mod\nhistory\n!1\nabort\n");
+        assert!(output.contains("enabled: mod2"));
+        assert!(output.contains("Error: Mods not found"));
+        assert!(output.contains("   1  enable mod2"));
+        // Aborting doesn't save.
+        let mod_cfg = ModCfg::load_from_path(&mock.mods_dir).unwrap();
+        assert_eq!(mod_cfg.is_mod_active("mod2"), Some(false));
+
+        // The end of the input saves, like exit.
+        run(&mut shell, "disable mod1\n");
+        let mod_cfg = ModCfg::load_from_path(&mock.mods_dir).unwrap();
+        assert_eq!(mod_cfg.is_mod_active("mod1"), Some(false));
+        assert_eq!(mod_cfg.is_mod_active("mod2"), Some(true));
+    }
+
+    #[test]
+    fn applying_presets_with_overrides() {
+        let (_mock, mut shell) = shell();
+        shell
+            .mod_config
+            .set_overrides([("mod2".to_string(), false)]);
+
+        // mod2 was disabled by hand, so enabling and applying preset2 leaves it disabled.
+        let output = run(&mut shell, "enable-preset preset2\napply\nlist\nabort\n");
+        assert!(output.contains("disabled mod2"));
+        assert!(output.contains("enabled  mod1"));
+    }
+
+    #[test]
+    fn applying_shared_presets() {
+        let (_mock, mut shell) = shell();
+        let team_dir = tempfile::tempdir().unwrap();
+        Preset::new("racing".into(), vec!["mod2".into()])
+            .unwrap()
+            .save_to_path(team_dir.path())
+            .unwrap();
+        shell.set_preset_namespaces(BTreeMap::from([("team".into(), team_dir.path().into())]));
+
+        // Shared presets can be shortened like personal ones.
+        let output = run(
+            &mut shell,
+            "enable-preset team/ra
+list
+",
+        );
+        assert!(output.contains(": team/racing"));
+        assert!(output.contains("enabled  mod2"));
+
+        let output = run(
+            &mut shell,
+            "disable-preset team/racing
+list
+abort
+",
+        );
+        assert!(output.contains("disabled mod2"));
+    }
+
+    #[test]
+    fn completing_names() {
+        let (_mock, mut shell) = shell();
+
+        assert_eq!(shell.complete("pre").unwrap(), vec!["preset1", "preset2"]);
+        assert_eq!(shell.complete("mod").unwrap(), vec!["mod1", "mod2", "mod3"]);
+
+        let output = run(&mut shell, "enable-preset preset2\nlist\nabort\n");
+        assert!(output.contains("enabled  mod2"));
+
+        // In the terminal, the first word is completed as a command and the others as names.
+        let helper = LineHelper {
+            names: shell.complete("").unwrap(),
+        };
+        assert_eq!(
+            helper.candidates("enable", 6),
+            (0, vec!["enable".into(), "enable-preset".into()])
+        );
+        assert_eq!(
+            helper.candidates("enable-preset preset1 pre", 25),
+            (22, vec!["preset1".into(), "preset2".into()])
+        );
+    }
+
+    #[test]
+    fn running_scripts() {
+        let (mock, mut shell) = shell();
+        let script = "\
+# Racing setup
+enable-preset preset2
+
+disable mod3
+enable mod4
+";
+
+        let result = shell.run_script(script.as_bytes(), vec![], true);
+        assert!(matches!(result, Err(ScriptFailed { line: 5, .. })));
+        let preset2 = Preset::load_from_path("preset2", &mock.presets_dir).unwrap();
+        assert!(!preset2.is_enabled());
+        let mod_cfg = ModCfg::load_from_path(&mock.mods_dir).unwrap();
+        assert_eq!(mod_cfg.is_mod_active("mod3"), Some(true));
+
+        let mut output = vec![];
+        shell
+            .run_script(script.as_bytes(), &mut output, false)
+            .unwrap();
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .contains("Line 5: Error: Mods not found"));
+        let preset2 = Preset::load_from_path("preset2", &mock.presets_dir).unwrap();
+        assert!(preset2.is_enabled());
+        let mod_cfg = ModCfg::load_from_path(&mock.mods_dir).unwrap();
+        assert_eq!(mod_cfg.is_mod_active("mod2"), Some(true));
+        assert_eq!(mod_cfg.is_mod_active("mod3"), Some(false));
+    }
+}
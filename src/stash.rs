@@ -0,0 +1,227 @@
+use crate::{game::ModCfg, Error::*, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// A stashed mod activation state.
+///
+/// Stashing records the activation state of every mod and disables them all, making it quick to
+/// test the game without mods. Popping the stash restores the recorded state. Only one stash is
+/// kept at a time, stored in the BeamMM directory.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{game::ModCfg, stash::Stash};
+/// # use tempfile::tempdir;
+///
+/// # let temp_beammm_dir = tempdir().unwrap();
+/// # let beammm_dir = temp_beammm_dir.path();
+/// # let temp_mods_dir = tempdir().unwrap();
+/// # let mods_dir = temp_mods_dir.path();
+/// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{\"mod1\":{\"active\":true}}}").unwrap();
+/// let mut mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+///
+/// Stash::push(&beammm_dir, &mut mod_cfg).unwrap();
+/// assert!(!mod_cfg.is_mod_active("mod1").unwrap());
+///
+/// Stash::pop(&beammm_dir, &mut mod_cfg).unwrap();
+/// assert!(mod_cfg.is_mod_active("mod1").unwrap());
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Stash {
+    /// The stashed activation state of each mod.
+    mods: HashMap<String, bool>,
+}
+
+impl Stash {
+    /// The filename of the stash file.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    fn filename() -> PathBuf {
+        PathBuf::from("stash.json")
+    }
+
+    /// Deserialize and load a stash from a reader.
+    ///
+    /// # Arguments
+    ///
+    /// `reader`: The reader to load the stash from.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue reading or deserializing the stash.
+    pub fn load<R: BufRead>(reader: R) -> Result<Self> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Deserialize and load the stash from the BeamMM directory.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The BeamMM directory where the stash is stored.
+    ///
+    /// # Errors
+    ///
+    /// MissingStash: If there is no stash.
+    /// Possible IO errors if there is an issue reading the file or serde_json errors if there is
+    /// an issue deserializing the stash.
+    pub fn load_from_path(beammm_dir: impl AsRef<Path>) -> Result<Self> {
+        let beammm_dir = beammm_dir.as_ref();
+        let path = beammm_dir.join(Self::filename());
+        if path.try_exists()? {
+            let file = File::open(path)?;
+            let reader = BufReader::new(file);
+            Self::load(reader)
+        } else {
+            Err(MissingStash)
+        }
+    }
+
+    /// Serialize and save the stash to a writer.
+    ///
+    /// # Arguments
+    ///
+    /// `writer`: The writer to save the stash to.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue serializing the stash or writing.
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Serialize and save the stash to the BeamMM directory.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The BeamMM directory where the stash will be saved.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue creating the file or writing to it.
+    /// Possible serde_json errors if there is an issue serializing the stash.
+    pub fn save_to_path(&self, beammm_dir: impl AsRef<Path>) -> Result<()> {
+        let beammm_dir = beammm_dir.as_ref();
+        let file = File::create(beammm_dir.join(Self::filename()))?;
+        let writer = BufWriter::new(file);
+        self.save(writer)
+    }
+
+    /// Check if there is a stash.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The BeamMM directory where the stash is stored.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    pub fn exists(beammm_dir: impl AsRef<Path>) -> bool {
+        let beammm_dir = beammm_dir.as_ref();
+        beammm_dir.join(Self::filename()).exists()
+    }
+
+    /// Record the current activation state of every mod, then disable every mod.
+    ///
+    /// The stash is written right away, but the mods stay enabled in db.json until the ModCfg is
+    /// saved.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The BeamMM directory where the stash will be saved.
+    /// `mod_config`: The ModCfg to stash the state of.
+    ///
+    /// # Errors
+    ///
+    /// StashExists: If there already is a stash. It must be popped first so it isn't lost.
+    /// Possible IO or serde_json errors if there is an issue saving the stash.
+    pub fn push(beammm_dir: impl AsRef<Path>, mod_config: &mut ModCfg) -> Result<()> {
+        let beammm_dir = beammm_dir.as_ref();
+        if Self::exists(beammm_dir) {
+            return Err(StashExists);
+        }
+
+        let stash = Stash {
+            mods: mod_config
+                .get_mods()
+                .filter_map(|m| {
+                    mod_config
+                        .is_mod_active(m)
+                        .map(|active| (m.clone(), active))
+                })
+                .collect(),
+        };
+        stash.save_to_path(beammm_dir)?;
+        mod_config.set_all_mods_active(false)
+    }
+
+    /// Restore the stashed activation state and remove the stash.
+    ///
+    /// Stashed mods that are no longer installed are ignored, and mods installed since stashing
+    /// are left as they are. The stash is deleted right away, so save the ModCfg afterwards or the
+    /// stashed state is lost.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The BeamMM directory where the stash is stored.
+    /// `mod_config`: The ModCfg to restore the state into.
+    ///
+    /// # Errors
+    ///
+    /// MissingStash: If there is no stash.
+    /// Possible IO or serde_json errors if there is an issue reading or removing the stash.
+    pub fn pop(beammm_dir: impl AsRef<Path>, mod_config: &mut ModCfg) -> Result<()> {
+        let beammm_dir = beammm_dir.as_ref();
+        let stash = Self::load_from_path(beammm_dir)?;
+        for (mod_name, active) in &stash.mods {
+            // We don't care if the mod doesn't exist anymore.
+            let _ = mod_config.set_mod_active(mod_name, *active);
+        }
+        fs::remove_file(beammm_dir.join(Self::filename()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+
+    #[test]
+    fn stashing_and_popping() {
+        let mock = MockData::new();
+        let tmp = tempfile::tempdir().unwrap();
+        let beammm_dir = tmp.path();
+        let mut mod_cfg = mock.modcfg;
+
+        Stash::push(beammm_dir, &mut mod_cfg).unwrap();
+        assert!(!mod_cfg.is_mod_active("mod1").unwrap());
+        assert!(!mod_cfg.is_mod_active("mod3").unwrap());
+
+        // A second stash would lose the first.
+        assert!(matches!(
+            Stash::push(beammm_dir, &mut mod_cfg),
+            Err(StashExists)
+        ));
+
+        Stash::pop(beammm_dir, &mut mod_cfg).unwrap();
+        assert!(mod_cfg.is_mod_active("mod1").unwrap());
+        assert!(!mod_cfg.is_mod_active("mod2").unwrap());
+        assert!(mod_cfg.is_mod_active("mod3").unwrap());
+        assert!(!Stash::exists(beammm_dir));
+    }
+
+    #[test]
+    fn popping_missing_stash() {
+        let mock = MockData::new();
+        let tmp = tempfile::tempdir().unwrap();
+        let mut mod_cfg = mock.modcfg;
+
+        let result = Stash::pop(tmp.path(), &mut mod_cfg);
+        assert!(matches!(result, Err(MissingStash)));
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{
+    game::{ModCfg, RepoVersion},
+    metadata::MetadataDb,
+    Preset, Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Write},
+    path::Path,
+};
+
+/// Everything BeamMM manages, without the mod archives, so a setup can be moved to another
+/// machine.
+///
+/// The state holds the presets, BeamMM's mod metadata, and whether each mod is active along with
+/// its repository version, so mods missing on the other machine can be downloaded again.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{game::ModCfg, metadata::MetadataDb, state::State};
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let presets_dir = temp_dir.path();
+/// let mod_cfg = ModCfg::load(r#"{"mods":{"mod1":{"active":true}}}"#.as_bytes()).unwrap();
+/// let state = State::capture(&mod_cfg, &MetadataDb::default(), &presets_dir).unwrap();
+///
+/// let mut exported = vec![];
+/// state.save(&mut exported).unwrap();
+///
+/// let mut other_cfg = ModCfg::load(r#"{"mods":{"mod1":{"active":false}}}"#.as_bytes()).unwrap();
+/// let mut other_metadata = MetadataDb::default();
+/// State::load(exported.as_slice())
+///     .unwrap()
+///     .apply(&mut other_cfg, &mut other_metadata, &presets_dir)
+///     .unwrap();
+/// assert_eq!(other_cfg.is_mod_active("mod1"), Some(true));
+/// ```
+#[derive(Serialize, Deserialize, Debug)]
+pub struct State {
+    /// The presets.
+    #[serde(default)]
+    presets: Vec<Preset>,
+    /// BeamMM's mod metadata.
+    #[serde(default)]
+    metadata: MetadataDb,
+    /// The state of each installed mod.
+    #[serde(default)]
+    mods: BTreeMap<String, ModState>,
+}
+
+/// The state of a single mod.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModState {
+    /// Whether the mod is active.
+    pub active: bool,
+    /// The repository version, for mods installed from the repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo_version: Option<RepoVersion>,
+}
+
+/// What was changed by applying a state.
+#[derive(Debug, Default, PartialEq)]
+pub struct Imported {
+    /// The number of presets written.
+    pub presets: usize,
+    /// The number of installed mods whose activation state was set.
+    pub mods: usize,
+    /// Mods in the state that aren't installed, with their repository version if known.
+    pub missing: Vec<(String, Option<RepoVersion>)>,
+}
+
+impl State {
+    /// Capture the current state.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg with the installed mods.
+    /// `metadata`: BeamMM's mod metadata.
+    /// `presets_dir`: Where preset config files are stored.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO or serde_json errors if there is an issue loading the presets.
+    pub fn capture(
+        mod_config: &ModCfg,
+        metadata: &MetadataDb,
+        presets_dir: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let presets_dir = presets_dir.as_ref();
+        let mut presets = Preset::list(presets_dir)?
+            .map(|p| Preset::load_from_path(&p, presets_dir))
+            .collect::<Result<Vec<_>>>()?;
+        presets.sort_by(|a, b| a.name().cmp(b.name()));
+
+        let mods = mod_config
+            .get_mods()
+            .filter_map(|m| {
+                let active = mod_config.is_mod_active(m)?;
+                let repo_version = mod_config
+                    .repo_version(m)
+                    .or_else(|| metadata.repo_version(m).cloned());
+                Some((
+                    m.clone(),
+                    ModState {
+                        active,
+                        repo_version,
+                    },
+                ))
+            })
+            .collect();
+
+        Ok(Self {
+            presets,
+            metadata: metadata.clone(),
+            mods,
+        })
+    }
+
+    /// Load a state from a reader.
+    ///
+    /// # Arguments
+    ///
+    /// `reader`: The reader to load the state from.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue reading or deserializing the state.
+    pub fn load<R: BufRead>(reader: R) -> Result<Self> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Serialize and save the state to a writer.
+    ///
+    /// # Arguments
+    ///
+    /// `writer`: The writer to save the state to.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue serializing the state or writing.
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Apply the state. Presets are saved, replacing presets with the same name, metadata is merged
+    /// into the existing metadata, and installed mods are enabled or disabled to match.
+    ///
+    /// Mods that aren't installed are reported instead, so they can be downloaded. Mods that can't
+    /// be enabled, e.g. because they are blacklisted, are left as they are.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg to apply the activation state to.
+    /// `metadata`: The metadata to merge the state's metadata into.
+    /// `presets_dir`: Where preset config files are stored.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO or serde_json errors if there is an issue saving the presets.
+    pub fn apply(
+        self,
+        mod_config: &mut ModCfg,
+        metadata: &mut MetadataDb,
+        presets_dir: impl AsRef<Path>,
+    ) -> Result<Imported> {
+        let presets_dir = presets_dir.as_ref();
+        let mut imported = Imported::default();
+        for preset in &self.presets {
+            preset.save_to_path(presets_dir)?;
+            imported.presets += 1;
+        }
+        metadata.merge(self.metadata);
+        for (mod_name, state) in self.mods {
+            if mod_config.is_mod_active(&mod_name).is_none() {
+                imported.missing.push((mod_name, state.repo_version));
+            } else if mod_config.set_mod_active(&mod_name, state.active).is_ok() {
+                imported.mods += 1;
+            }
+        }
+        Ok(imported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+
+    #[test]
+    fn exporting_and_importing() {
+        let mock = MockData::new();
+        let mut metadata = MetadataDb::default();
+        metadata.set_note("mod2", Some("note".into()));
+        metadata.set_repo_version(
+            "mod3",
+            Some(RepoVersion {
+                resource_id: 1,
+                version_id: 2,
+                version: None,
+            }),
+        );
+
+        let mut exported = vec![];
+        State::capture(&mock.modcfg, &metadata, &mock.presets_dir)
+            .unwrap()
+            .save(&mut exported)
+            .unwrap();
+
+        let mut other_cfg =
+            ModCfg::load(r#"{"mods":{"mod1":{"active":false}}}"#.as_bytes()).unwrap();
+        let other_presets_dir = tempfile::tempdir().unwrap();
+        let mut other_metadata = MetadataDb::default();
+        let imported = State::load(exported.as_slice())
+            .unwrap()
+            .apply(
+                &mut other_cfg,
+                &mut other_metadata,
+                other_presets_dir.path(),
+            )
+            .unwrap();
+
+        assert_eq!(imported.presets, 2);
+        assert_eq!(imported.mods, 1);
+        assert_eq!(
+            imported.missing,
+            vec![
+                ("mod2".into(), None),
+                ("mod3".into(), metadata.repo_version("mod3").cloned()),
+            ]
+        );
+        assert_eq!(other_cfg.is_mod_active("mod1"), Some(true));
+        assert_eq!(other_metadata.note("mod2"), Some("note"));
+        assert_eq!(
+            Preset::load_from_path("preset2", other_presets_dir.path()).unwrap(),
+            mock.preset2
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use crate::{
+    game::{ModCfg, ModEntry},
+    index::PresetIndex,
+    Preset, Result,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+/// Totals about the installed mods and the presets, for `--stats` and frontends.
+///
+/// Sizes are the sizes of the archives on disk, or what the game recorded when an archive can't
+/// be found.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{game::ModCfg, stats::Stats};
+/// # use tempfile::tempdir;
+///
+/// # let temp_presets_dir = tempdir().unwrap();
+/// # let presets_dir = temp_presets_dir.path();
+/// # let temp_mods_dir = tempdir().unwrap();
+/// # let mods_dir = temp_mods_dir.path();
+/// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{\"mod1\":{\"active\":true}}}").unwrap();
+/// let mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+/// let stats = Stats::collect(&mod_cfg, &mods_dir, &presets_dir).unwrap();
+/// assert_eq!(stats.mods, 1);
+/// assert_eq!(stats.enabled, 1);
+/// ```
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// The number of installed mods.
+    pub mods: usize,
+    /// The number of enabled mods.
+    pub enabled: usize,
+    /// The number of disabled mods.
+    pub disabled: usize,
+    /// The size of every mod, in bytes.
+    pub total_size: u64,
+    /// The size of the enabled mods, in bytes.
+    pub enabled_size: u64,
+    /// The number of mods of each type the game recorded, e.g. `vehicle`. Mods the game hasn't
+    /// recorded a type for are counted as `unknown`.
+    pub by_type: BTreeMap<String, usize>,
+    /// The number of mods from the repository, `repo`, installed by hand, `local`, and unpacked,
+    /// `unpacked`.
+    pub by_source: BTreeMap<String, usize>,
+    /// The number of presets.
+    pub presets: usize,
+    /// The number of enabled presets.
+    pub enabled_presets: usize,
+    /// The largest mods along with their size in bytes, largest first, up to `Stats::LARGEST`.
+    pub largest: Vec<(String, u64)>,
+}
+
+impl Stats {
+    /// How many of the largest mods are kept.
+    pub const LARGEST: usize = 5;
+
+    /// Collect the totals of the installed mods and the presets.
+    ///
+    /// # Arguments
+    ///
+    /// `mod_config`: The ModCfg of the installed mods.
+    /// `mods_dir`: The mods directory of the current game version.
+    /// `presets_dir`: The directory where the presets are stored.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading the presets directory.
+    pub fn collect(
+        mod_config: &ModCfg,
+        mods_dir: impl AsRef<Path>,
+        presets_dir: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let mods_dir = mods_dir.as_ref();
+        let mut stats = Self::default();
+        let mut sizes = vec![];
+        for entry in mod_config.iter_mods() {
+            let size = entry_size(mod_config, &entry, mods_dir);
+
+            stats.mods += 1;
+            stats.total_size += size;
+            if entry.is_active() {
+                stats.enabled += 1;
+                stats.enabled_size += size;
+            } else {
+                stats.disabled += 1;
+            }
+            let mod_type = entry.mod_type().unwrap_or("unknown");
+            *stats.by_type.entry(mod_type.into()).or_default() += 1;
+            let source = entry.entry().root().to_string();
+            *stats.by_source.entry(source).or_default() += 1;
+            sizes.push((entry.name().to_string(), size));
+        }
+
+        sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        sizes.truncate(Self::LARGEST);
+        stats.largest = sizes;
+
+        let index = PresetIndex::load_from_path(presets_dir)?;
+        stats.presets = index.iter().count();
+        stats.enabled_presets = index.enabled().len();
+        Ok(stats)
+    }
+}
+
+/// How much disk space the mods of a preset take up, for `--preset-size`.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct PresetSize {
+    /// The name of the preset.
+    pub name: String,
+    /// The size of the preset's installed mods, including those from the presets it extends, in
+    /// bytes.
+    pub size: u64,
+    /// The preset's installed mods along with their size in bytes, largest first.
+    pub mods: Vec<(String, u64)>,
+    /// The preset's mods that aren't installed, so their size is unknown, sorted.
+    pub missing: Vec<String>,
+}
+
+/// How much disk space the mods of some presets take up, found by `Footprint::collect`.
+///
+/// Sizes are the sizes of the archives on disk, or what the game recorded when an archive can't
+/// be found, the same as in `Stats`.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{game::ModCfg, stats::Footprint, Preset};
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let mods_dir = temp_dir.path().join("mods");
+/// # let presets_dir = temp_dir.path().join("presets");
+/// # std::fs::create_dir_all(&mods_dir).unwrap();
+/// # std::fs::create_dir_all(&presets_dir).unwrap();
+/// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{}}").unwrap();
+/// std::fs::write(mods_dir.join("car.zip"), "0123456789").unwrap();
+/// let mut mod_cfg = ModCfg::load_from_path(&mods_dir).unwrap();
+/// mod_cfg.register_archive(mods_dir.join("car.zip"));
+/// Preset::new("racing".into(), vec!["car".into()]).unwrap().save_to_path(&presets_dir).unwrap();
+/// Preset::new("drift".into(), vec!["car".into()]).unwrap().save_to_path(&presets_dir).unwrap();
+///
+/// let footprint = Footprint::collect(["racing", "drift"], &mod_cfg, &mods_dir, &presets_dir).unwrap();
+/// assert_eq!(footprint.presets[0].size, 10);
+/// // The shared mod is only counted once in the total.
+/// assert_eq!(footprint.total_size, 10);
+/// ```
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct Footprint {
+    /// The size of each preset, largest first.
+    pub presets: Vec<PresetSize>,
+    /// The size of every mod in any of the presets, counting mods shared by several presets once,
+    /// in bytes.
+    pub total_size: u64,
+}
+
+impl Footprint {
+    /// Find how much disk space the mods of some presets take up.
+    ///
+    /// # Arguments
+    ///
+    /// `names`: The names of the presets.
+    /// `mod_config`: The ModCfg of the installed mods.
+    /// `mods_dir`: The mods directory of the current game version.
+    /// `presets_dir`: The directory where the presets are stored.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingPreset`: If a preset, or a preset it extends, doesn't exist.
+    /// * `PresetCycle`: If a preset extends itself, directly or not.
+    /// * `std::io::Error`: If there is an issue reading a preset.
+    pub fn collect(
+        names: impl IntoIterator<Item = impl AsRef<str>>,
+        mod_config: &ModCfg,
+        mods_dir: impl AsRef<Path>,
+        presets_dir: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let presets_dir = presets_dir.as_ref();
+        Self::collect_with(names, mod_config, mods_dir, |name| {
+            Preset::load_from_path(name, presets_dir)
+        })
+    }
+
+    /// Find how much disk space the mods of some presets take up, loading the presets and the
+    /// presets they extend with a function, e.g. `Namespaces::load` to include shared presets.
+    ///
+    /// # Arguments
+    ///
+    /// `names`: The names of the presets.
+    /// `mod_config`: The ModCfg of the installed mods.
+    /// `mods_dir`: The mods directory of the current game version.
+    /// `load`: Loads a preset by name.
+    ///
+    /// # Errors
+    ///
+    /// Errors from `load`, and PresetCycle if a preset extends itself, directly or not.
+    pub fn collect_with(
+        names: impl IntoIterator<Item = impl AsRef<str>>,
+        mod_config: &ModCfg,
+        mods_dir: impl AsRef<Path>,
+        mut load: impl FnMut(&str) -> Result<Preset>,
+    ) -> Result<Self> {
+        let mods_dir = mods_dir.as_ref();
+        let sizes: HashMap<&str, u64> = mod_config
+            .iter_mods()
+            .map(|entry| (entry.name(), entry_size(mod_config, &entry, mods_dir)))
+            .collect();
+
+        let mut footprint = Self::default();
+        let mut counted = BTreeMap::new();
+        for name in names {
+            let preset = load(name.as_ref())?;
+            let mut preset_size = PresetSize {
+                name: preset.name().into(),
+                ..Default::default()
+            };
+            for mod_name in preset.resolve_mods_with(&mut load)? {
+                let installed = mod_config.resolve_alias(&mod_name);
+                match sizes.get(installed) {
+                    Some(&size) => {
+                        preset_size.mods.push((installed.to_string(), size));
+                        counted.insert(installed.to_string(), size);
+                    }
+                    None => preset_size.missing.push(mod_name),
+                }
+            }
+            preset_size
+                .mods
+                .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            // A mod and an alias of it are the same archive.
+            preset_size.mods.dedup();
+            preset_size.size = preset_size.mods.iter().map(|(_, size)| size).sum();
+            preset_size.missing.sort();
+            preset_size.missing.dedup();
+            footprint.presets.push(preset_size);
+        }
+        footprint
+            .presets
+            .sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+        footprint.total_size = counted.values().sum();
+        Ok(footprint)
+    }
+}
+
+/// Get the size of a mod's archive on disk, or what the game recorded when it can't be found.
+fn entry_size(mod_config: &ModCfg, entry: &ModEntry, mods_dir: &Path) -> u64 {
+    mod_config
+        .archive_path(entry.name(), mods_dir)
+        .and_then(|archive| fs::metadata(archive).ok())
+        .map(|metadata| metadata.len())
+        .or_else(|| entry.entry().file_size())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+
+    #[test]
+    fn collecting_stats() {
+        let mock = MockData::new();
+        fs::write(mock.mods_dir.join("mod1.zip"), "0123456789").unwrap();
+        fs::write(mock.mods_dir.join("mod2.zip"), "01234").unwrap();
+        let mut mod_cfg = mock.modcfg;
+        for mod_name in ["mod1", "mod2"] {
+            mod_cfg.remove_mod(mod_name).unwrap();
+            mod_cfg.register_archive(mock.mods_dir.join(format!("{}.zip", mod_name)));
+        }
+        mod_cfg.set_mod_active("mod2", false).unwrap();
+
+        let stats = Stats::collect(&mod_cfg, &mock.mods_dir, &mock.presets_dir).unwrap();
+
+        assert_eq!(stats.mods, 3);
+        assert_eq!(stats.enabled, 2);
+        assert_eq!(stats.disabled, 1);
+        assert_eq!(stats.total_size, 15);
+        assert_eq!(stats.enabled_size, 10);
+        assert_eq!(stats.by_type, BTreeMap::from([("unknown".into(), 3)]));
+        assert_eq!(stats.by_source, BTreeMap::from([("local".into(), 3)]));
+        assert_eq!(stats.presets, 2);
+        assert_eq!(stats.enabled_presets, 1);
+        assert_eq!(
+            stats.largest,
+            vec![("mod1".into(), 10), ("mod2".into(), 5), ("mod3".into(), 0)]
+        );
+    }
+
+    #[test]
+    fn collecting_preset_footprints() {
+        let mock = MockData::new();
+        fs::write(mock.mods_dir.join("mod1.zip"), "0123456789").unwrap();
+        fs::write(mock.mods_dir.join("mod2.zip"), "01234").unwrap();
+        let mut mod_cfg = mock.modcfg;
+        for mod_name in ["mod1", "mod2"] {
+            mod_cfg.remove_mod(mod_name).unwrap();
+            mod_cfg.register_archive(mock.mods_dir.join(format!("{}.zip", mod_name)));
+        }
+        let mut preset3 = Preset::new("preset3".into(), vec!["mod4".into()]).unwrap();
+        preset3.set_extends(Some("preset1".into()));
+        preset3.save_to_path(&mock.presets_dir).unwrap();
+
+        let footprint = Footprint::collect(
+            ["preset1", "preset2", "preset3"],
+            &mod_cfg,
+            &mock.mods_dir,
+            &mock.presets_dir,
+        )
+        .unwrap();
+
+        let sizes: Vec<_> = footprint
+            .presets
+            .iter()
+            .map(|p| (p.name.as_str(), p.size))
+            .collect();
+        assert_eq!(
+            sizes,
+            vec![("preset2", 15), ("preset1", 10), ("preset3", 10)]
+        );
+        assert_eq!(
+            footprint.presets[0].mods,
+            vec![("mod1".into(), 10), ("mod2".into(), 5)]
+        );
+        // Inherited mods count, and mods that aren't installed are listed instead.
+        assert_eq!(footprint.presets[2].mods, vec![("mod1".into(), 10)]);
+        assert_eq!(footprint.presets[2].missing, vec!["mod4"]);
+        // mod1 is in every preset but only counted once.
+        assert_eq!(footprint.total_size, 15);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{config::Config, game::ModCfg, health, index::PresetIndex, GameVersion, Result};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+/// Diagnostics to attach to a bug report, collected into a single zip by `write`.
+///
+/// A bundle holds a summary of the setup, the config, the health report of the mods directory,
+/// the preset index, and recent operation log lines. Nothing else from the mods directory or the
+/// presets is included. Before anything is written, every path in `redactions` is replaced with
+/// its placeholder, so the user's directory layout and username aren't shared unless they choose
+/// to keep the full paths.
+///
+/// # Examples
+/// ```rust
+/// use beammm::{config::Config, game::ModCfg, support::SupportBundle, GameVersion};
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let mods_dir = temp_dir.path().join("mods");
+/// # let presets_dir = temp_dir.path().join("presets");
+/// # std::fs::create_dir_all(&mods_dir).unwrap();
+/// # std::fs::create_dir_all(&presets_dir).unwrap();
+/// # std::fs::write(mods_dir.join("db.json"), "{\"mods\":{}}").unwrap();
+/// let mod_config = ModCfg::load_from_path(&mods_dir).unwrap();
+/// let bundle = SupportBundle {
+///     config: &Config::default(),
+///     mod_config: &mod_config,
+///     game_version: &GameVersion { major: 0, minor: 32, patch: None },
+///     mods_dir: &mods_dir,
+///     presets_dir: &presets_dir,
+///     log_lines: vec![],
+///     redactions: vec![(mods_dir.clone(), "<mods>".into())],
+/// };
+/// let files = bundle.write(temp_dir.path().join("support.zip")).unwrap();
+/// assert!(files.contains(&"summary.txt".to_string()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SupportBundle<'a> {
+    /// BeamMM's settings.
+    pub config: &'a Config,
+    /// The ModCfg of the installed mods.
+    pub mod_config: &'a ModCfg,
+    /// The current game version.
+    pub game_version: &'a GameVersion,
+    /// The mods directory of the current game version.
+    pub mods_dir: &'a Path,
+    /// Where preset config files are stored.
+    pub presets_dir: &'a Path,
+    /// Recent lines of the operation log, oldest first.
+    pub log_lines: Vec<String>,
+    /// Paths paired with the placeholders that replace them. Leave it empty to keep full paths.
+    pub redactions: Vec<(PathBuf, String)>,
+}
+
+impl SupportBundle<'_> {
+    /// Collect the diagnostics and write them to a zip.
+    ///
+    /// # Arguments
+    ///
+    /// `out`: The path of the bundle to create. An existing file is overwritten.
+    ///
+    /// # Returns
+    ///
+    /// The names of the files in the bundle.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue checking the mods directory, reading the presets,
+    /// or writing the bundle. Possible serde_json errors if there is an issue serializing the
+    /// diagnostics.
+    pub fn write(&self, out: impl AsRef<Path>) -> Result<Vec<String>> {
+        let health = health::check(self.mod_config, self.mods_dir)?;
+        let index = PresetIndex::load_from_path(self.presets_dir)?;
+        let mod_count = self.mod_config.get_mods().count();
+        let enabled_count = self.mod_config.active_count();
+
+        let mut summary = format!("BeamMM {}\n", env!("CARGO_PKG_VERSION"));
+        summary += &format!(
+            "OS: {} ({})\n",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        );
+        summary += &format!("Game version: {}\n", self.game_version);
+        summary += &format!(
+            "Mods in db.json: {} ({} enabled)\n",
+            mod_count, enabled_count
+        );
+        summary += &format!(
+            "Presets: {} ({} enabled)\n",
+            index.iter().count(),
+            index.enabled().len()
+        );
+        summary += &format!("Mods directory: {}\n", self.mods_dir.display());
+
+        let files = [
+            ("summary.txt", summary),
+            ("config.json", serde_json::to_string_pretty(self.config)?),
+            ("health.json", serde_json::to_string_pretty(&health)?),
+            ("presets.json", serde_json::to_string_pretty(&index)?),
+            ("recent.log", self.log_lines.join("\n")),
+        ];
+
+        let mut zip = ZipWriter::new(BufWriter::new(File::create(out)?));
+        let options = SimpleFileOptions::default();
+        for (name, contents) in files.iter() {
+            zip.start_file(*name, options).map_err(io::Error::from)?;
+            zip.write_all(self.redact(contents).as_bytes())?;
+        }
+        zip.finish().map_err(io::Error::from)?.flush()?;
+        Ok(files.iter().map(|(name, _)| name.to_string()).collect())
+    }
+
+    /// Replace the redacted paths in some text with their placeholders.
+    ///
+    /// Longer paths are replaced first, so a directory inside another redacted directory gets its
+    /// own placeholder. Paths are also replaced as they appear escaped in JSON, where Windows
+    /// paths have their backslashes doubled.
+    fn redact(&self, text: &str) -> String {
+        let mut redactions: Vec<_> = self
+            .redactions
+            .iter()
+            .map(|(path, placeholder)| (path.to_string_lossy(), placeholder))
+            .filter(|(path, _)| !path.is_empty())
+            .collect();
+        redactions.sort_by_key(|(path, _)| std::cmp::Reverse(path.len()));
+
+        let mut text = text.to_string();
+        for (path, placeholder) in redactions {
+            text = text.replace(&path.replace('\\', "\\\\"), placeholder);
+            text = text.replace(path.as_ref(), placeholder);
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockData;
+    use std::{fs, io::Read};
+    use zip::ZipArchive;
+
+    #[test]
+    fn writing_support_bundles() {
+        let mock = MockData::new();
+        let out = mock.mods_dir.join("support.zip");
+        fs::write(mock.mods_dir.join("orphan.zip"), "").unwrap();
+        let mut config = Config::default();
+        config.set_game_dir(Some(mock.mods_dir.join("game")));
+        let parent = mock.mods_dir.parent().unwrap().to_path_buf();
+        let bundle = SupportBundle {
+            config: &config,
+            mod_config: &mock.modcfg,
+            game_version: &GameVersion {
+                major: 0,
+                minor: 32,
+                patch: None,
+            },
+            mods_dir: &mock.mods_dir,
+            presets_dir: &mock.presets_dir,
+            log_lines: vec![format!("Reading {}", mock.mods_dir.display())],
+            redactions: vec![
+                (parent, "<home>".into()),
+                (mock.mods_dir.clone(), "<mods>".into()),
+            ],
+        };
+
+        let files = bundle.write(&out).unwrap();
+        assert_eq!(files.len(), 5);
+
+        let mut zip = ZipArchive::new(File::open(&out).unwrap()).unwrap();
+        let mut read = |name: &str| {
+            let mut contents = String::new();
+            zip.by_name(name)
+                .unwrap()
+                .read_to_string(&mut contents)
+                .unwrap();
+            contents
+        };
+        let summary = read("summary.txt");
+        assert!(summary.contains("Mods in db.json: 3 (2 enabled)"));
+        assert!(summary.contains("Presets: 2 (1 enabled)"));
+        assert!(summary.contains("Mods directory: <mods>\n"));
+        assert!(read("config.json").contains("<mods>/game"));
+        assert!(read("health.json").contains("<mods>/orphan.zip"));
+        assert!(read("presets.json").contains("preset2"));
+        assert_eq!(read("recent.log"), "Reading <mods>");
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{pager, Result};
+use std::io::{self, IsTerminal};
+
+/// The space between columns.
+const GAP: &str = "  ";
+
+/// The narrowest a column is shrunk to when the table is too wide for the terminal.
+const MIN_WIDTH: usize = 8;
+
+/// A table of text, rendered with its columns aligned, for listings.
+///
+/// Cells may contain color codes, which don't count towards a column's width. When the table is
+/// wider than the space it has, the widest column is shrunk and cut off cells end with `…`. The
+/// last column is never padded, so lines don't end in spaces.
+///
+/// # Examples
+/// ```rust
+/// use beammm::table::Table;
+///
+/// let mut table = Table::new(["STATUS", "NAME"]);
+/// table.add_row(["enabled", "mod1"]);
+/// table.add_row(["disabled", "mod2"]);
+///
+/// assert_eq!(
+///     table.render(None),
+///     vec!["STATUS    NAME", "enabled   mod1", "disabled  mod2"]
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    /// The column headers, or none to leave the header line out.
+    headers: Vec<String>,
+    /// The rows of cells.
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Create an empty table with column headers.
+    ///
+    /// # Arguments
+    ///
+    /// `headers`: The column headers.
+    pub fn new(headers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            headers: headers.into_iter().map(Into::into).collect(),
+            rows: vec![],
+        }
+    }
+
+    /// Add a row of cells. Rows with fewer cells than the table has columns are left empty on the
+    /// right.
+    ///
+    /// # Arguments
+    ///
+    /// `row`: The cells of the row.
+    pub fn add_row(&mut self, row: impl IntoIterator<Item = impl Into<String>>) {
+        self.rows.push(row.into_iter().map(Into::into).collect());
+    }
+
+    /// Whether the table has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Render the table as lines of text.
+    ///
+    /// # Arguments
+    ///
+    /// `max_width`: How wide the lines may be, or `None` for no limit.
+    pub fn render(&self, max_width: Option<usize>) -> Vec<String> {
+        let lines: Vec<&Vec<String>> = (!self.headers.is_empty())
+            .then_some(&self.headers)
+            .into_iter()
+            .chain(&self.rows)
+            .collect();
+        let columns = lines.iter().map(|l| l.len()).max().unwrap_or(0);
+
+        let mut widths = vec![0; columns];
+        for line in &lines {
+            for (i, cell) in line.iter().enumerate() {
+                widths[i] = widths[i].max(visible_width(cell));
+            }
+        }
+
+        if let Some(max_width) = max_width {
+            let gaps = GAP.len() * columns.saturating_sub(1);
+            while widths.iter().sum::<usize>() + gaps > max_width {
+                let Some((widest, &width)) = widths.iter().enumerate().max_by_key(|(_, w)| **w)
+                else {
+                    break;
+                };
+                if width <= MIN_WIDTH {
+                    break;
+                }
+                widths[widest] -= 1;
+            }
+        }
+
+        lines
+            .iter()
+            .map(|line| {
+                let mut out = String::new();
+                for (i, width) in widths.iter().enumerate() {
+                    let cell = line.get(i).map(String::as_str).unwrap_or("");
+                    let cell = fit(cell, *width);
+                    let padding = width - visible_width(&cell);
+                    if i > 0 {
+                        out.push_str(GAP);
+                    }
+                    out.push_str(&cell);
+                    if i + 1 < columns {
+                        out.push_str(&" ".repeat(padding));
+                    }
+                }
+                out.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    /// Print the table to stdout, fitting it to the terminal's width and paging it with
+    /// `pager::print` if it is too long for the terminal.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue writing to stdout or the pager.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    pub fn print(&self) -> Result<()> {
+        pager::print(&self.render(terminal_width()))
+    }
+}
+
+/// Get the width of the terminal stdout is connected to, or `None` if stdout isn't a terminal.
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn terminal_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    terminal_size::terminal_size().map(|(width, _)| width.0 as usize)
+}
+
+/// The number of characters of text that will be shown, leaving out color codes.
+fn visible_width(text: &str) -> usize {
+    strip_codes(text).chars().count()
+}
+
+/// Remove color codes such as `\x1b[32m` from text.
+fn strip_codes(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip up to and including the letter that ends the code.
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// Cut text off at a width, ending it with `…` if anything was cut. Color codes are lost from text
+/// that is cut off.
+fn fit(text: &str, width: usize) -> String {
+    if visible_width(text) <= width {
+        return text.to_string();
+    }
+    let mut cut: String = strip_codes(text)
+        .chars()
+        .take(width.saturating_sub(1))
+        .collect();
+    cut.push('…');
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendering_tables() {
+        let mut table = Table::new(["STATUS", "NAME", "SIZE"]);
+        table.add_row(["\x1b[32menabled\x1b[0m", "a_mod_with_a_long_name", "1.0 MB"]);
+        table.add_row(["disabled", "short"]);
+
+        let lines = table.render(None);
+        assert_eq!(
+            lines,
+            vec![
+                "STATUS    NAME                    SIZE",
+                "\x1b[32menabled\x1b[0m   a_mod_with_a_long_name  1.0 MB",
+                "disabled  short",
+            ]
+        );
+
+        // The widest column is shrunk to fit.
+        let lines = table.render(Some(30));
+        assert_eq!(lines[1], "\x1b[32menabled\x1b[0m   a_mod_with_…  1.0 MB");
+        assert!(lines.iter().all(|l| visible_width(l) <= 30));
+    }
+}
@@ -0,0 +1,441 @@
+use crate::Result;
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Snapshots of files taken before a multi-step operation, so every file can be put back if a
+/// later step fails, instead of leaving some files saved and others not.
+///
+/// A transaction that is dropped without being committed is rolled back, so returning early with
+/// `?` undoes the changes. Errors while rolling back on drop are ignored, so call `rollback` to
+/// handle them.
+///
+/// # Examples
+/// ```rust
+/// use beammm::transaction::Transaction;
+/// # use tempfile::tempdir;
+///
+/// # let temp_dir = tempdir().unwrap();
+/// # let dir = temp_dir.path();
+/// let db_json = dir.join("db.json");
+/// std::fs::write(&db_json, "before").unwrap();
+///
+/// let mut transaction = Transaction::new();
+/// transaction.snapshot(&db_json).unwrap();
+/// std::fs::write(&db_json, "after").unwrap();
+/// transaction.rollback().unwrap();
+///
+/// assert_eq!(std::fs::read_to_string(&db_json).unwrap(), "before");
+/// ```
+#[derive(Debug, Default)]
+pub struct Transaction {
+    /// The files snapshotted, with their contents, or `None` if they didn't exist.
+    files: Vec<(PathBuf, Option<Vec<u8>>)>,
+    /// The directories snapshotted, with the files and subdirectories that were in them, or
+    /// `None` if they didn't exist.
+    dirs: Vec<(PathBuf, Option<BTreeSet<PathBuf>>)>,
+    /// Files moved during the transaction, from where to where, in the order they were moved.
+    moves: Vec<(PathBuf, PathBuf)>,
+    /// Files created during the transaction, which are removed on rollback.
+    created: Vec<PathBuf>,
+    /// Files to delete once the transaction is committed, and whether permanently.
+    removals: Vec<(PathBuf, bool)>,
+    /// Whether the transaction is finished, so there is nothing to roll back when dropped.
+    finished: bool,
+}
+
+impl Transaction {
+    /// Start a transaction with no snapshots.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snapshot a file before it is changed. A file that doesn't exist yet is removed again on
+    /// rollback. Snapshotting a file again keeps the first snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// `path`: The path to the file.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading the file.
+    pub fn snapshot(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if self.files.iter().any(|(p, _)| p == path) {
+            return Ok(());
+        }
+        let contents = if path.try_exists()? {
+            Some(fs::read(path)?)
+        } else {
+            None
+        };
+        self.files.push((path.to_path_buf(), contents));
+        Ok(())
+    }
+
+    /// Snapshot every file in a directory and its subdirectories, such as the presets directory
+    /// along with the history of its presets. Files and subdirectories added afterwards are
+    /// removed on rollback, and so is the whole directory if it doesn't exist yet.
+    ///
+    /// # Arguments
+    ///
+    /// `dir`: The path to the directory.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading the directory or its files.
+    pub fn snapshot_dir(&mut self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        if !dir.try_exists()? {
+            self.dirs.push((dir.to_path_buf(), None));
+            return Ok(());
+        }
+        let (files, subdirs) = walk(dir)?;
+        for path in files.iter() {
+            self.snapshot(path)?;
+        }
+        let existing = files.into_iter().chain(subdirs).collect();
+        self.dirs.push((dir.to_path_buf(), Some(existing)));
+        Ok(())
+    }
+
+    /// Record a file that was moved, such as a renamed mod archive, so it is moved back on
+    /// rollback. Moves are undone in the reverse order they were made.
+    ///
+    /// # Arguments
+    ///
+    /// `from`: Where the file was.
+    /// `to`: Where the file is now.
+    pub fn record_move(&mut self, from: impl AsRef<Path>, to: impl AsRef<Path>) {
+        self.moves
+            .push((from.as_ref().to_path_buf(), to.as_ref().to_path_buf()));
+    }
+
+    /// Record a file created during the transaction, such as an imported mod archive, so it is
+    /// removed on rollback. Unlike `Transaction::snapshot`, the file isn't read, so it suits large
+    /// files.
+    ///
+    /// # Arguments
+    ///
+    /// `path`: The path to the file.
+    pub fn record_created(&mut self, path: impl AsRef<Path>) {
+        self.created.push(path.as_ref().to_path_buf());
+    }
+
+    /// Delete a file, such as a mod archive, only once the transaction is committed, so it is
+    /// still there if a later step fails. It is sent to the system trash unless it should be
+    /// removed permanently.
+    ///
+    /// # Arguments
+    ///
+    /// `path`: The path to the file.
+    /// `permanent`: Whether to remove the file permanently instead of sending it to the trash.
+    pub fn remove_on_commit(&mut self, path: impl AsRef<Path>, permanent: bool) {
+        self.removals.push((path.as_ref().to_path_buf(), permanent));
+    }
+
+    /// Get the files that changed since they were snapshotted, including files added to
+    /// snapshotted directories, in the order they were snapshotted.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading a file or directory.
+    pub fn changes(&self) -> Result<Vec<FileChange>> {
+        let mut changes = vec![];
+        for (path, before) in &self.files {
+            let after = if path.try_exists()? {
+                Some(fs::read(path)?)
+            } else {
+                None
+            };
+            if &after != before {
+                changes.push(FileChange {
+                    path: path.clone(),
+                    before: before.clone(),
+                    after,
+                });
+            }
+        }
+        for (dir, _) in &self.dirs {
+            if !dir.try_exists()? {
+                continue;
+            }
+            let (files, _) = walk(dir)?;
+            for path in files {
+                if self.files.iter().any(|(p, _)| *p == path) {
+                    continue;
+                }
+                changes.push(FileChange {
+                    after: Some(fs::read(&path)?),
+                    path,
+                    before: None,
+                });
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Keep the changes, drop the snapshots and delete the files waiting for the commit.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue deleting a file. The changes are kept anyway.
+    pub fn commit(mut self) -> Result<()> {
+        self.finished = true;
+        for (path, permanent) in self.removals.drain(..) {
+            crate::path::remove_file(path, permanent)?;
+        }
+        Ok(())
+    }
+
+    /// Put every snapshotted file back the way it was.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue writing or removing a file. The other files are
+    /// still put back, and the first error is returned.
+    pub fn rollback(mut self) -> Result<()> {
+        self.finished = true;
+        self.restore()
+    }
+
+    /// Move moved files back, write the snapshots back and remove files that didn't exist.
+    ///
+    /// Every step is tried even if an earlier one fails, so one file that can't be put back
+    /// doesn't leave the rest changed. The first error is returned once all steps have run.
+    fn restore(&mut self) -> Result<()> {
+        let mut first_error = None;
+        let mut attempt = |result: Result<()>| {
+            if let Err(e) = result {
+                first_error.get_or_insert(e);
+            }
+        };
+        for path in self.created.iter() {
+            attempt(remove_if_exists(path));
+        }
+        for (from, to) in self.moves.iter().rev() {
+            attempt(move_back(from, to));
+        }
+        for (dir, existing) in &self.dirs {
+            let Some(existing) = existing else {
+                attempt(remove_dir_if_exists(dir));
+                continue;
+            };
+            let (files, subdirs) = match walk(dir) {
+                Ok(walked) => walked,
+                Err(e) => {
+                    attempt(Err(e));
+                    continue;
+                }
+            };
+            for path in files.into_iter().filter(|p| !existing.contains(p)) {
+                attempt(fs::remove_file(path).map_err(Into::into));
+            }
+            // Subdirectories come before their own subdirectories, so those are removed first.
+            for path in subdirs.into_iter().rev().filter(|p| !existing.contains(p)) {
+                attempt(fs::remove_dir_all(path).map_err(Into::into));
+            }
+        }
+        for (path, contents) in &self.files {
+            attempt(match contents {
+                Some(contents) => write_back(path, contents),
+                None => remove_if_exists(path),
+            });
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+/// Remove a file if it exists.
+fn remove_if_exists(path: &Path) -> Result<()> {
+    if path.try_exists()? {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Remove a directory and everything in it if it exists.
+fn remove_dir_if_exists(dir: &Path) -> Result<()> {
+    if dir.try_exists()? {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+/// Move a moved file back, if it is still where it was moved to.
+fn move_back(from: &Path, to: &Path) -> Result<()> {
+    if to.try_exists()? {
+        // Archives may have been moved across drives, e.g. out of the inbox.
+        crate::archive::move_archive(to, from)?;
+    }
+    Ok(())
+}
+
+/// Write a snapshotted file back.
+fn write_back(path: &Path, contents: &[u8]) -> Result<()> {
+    // The file's directory may have been removed along with it.
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// List the files and the subdirectories in a directory and all of its subdirectories, with
+/// every subdirectory listed before the subdirectories in it.
+fn walk(dir: &Path) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let (mut files, mut subdirs) = (vec![], vec![]);
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                subdirs.push(path.clone());
+                pending.push(path);
+            } else if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok((files, subdirs))
+}
+
+/// A file that changed during a transaction, as found by `Transaction::changes`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChange {
+    /// The path to the file.
+    pub path: PathBuf,
+    /// The contents when the file was snapshotted, or `None` if it didn't exist.
+    pub before: Option<Vec<u8>>,
+    /// The contents now, or `None` if the file was removed.
+    pub after: Option<Vec<u8>>,
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.restore();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils::MockData, Preset};
+
+    #[test]
+    fn rolling_back() {
+        let mock = MockData::new();
+        let db_json = mock.mods_dir.join("db.json");
+        let new_file = mock.mods_dir.join("new.json");
+        let before = fs::read(&db_json).unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction.snapshot(&db_json).unwrap();
+        transaction.snapshot(&new_file).unwrap();
+        transaction.snapshot_dir(&mock.presets_dir).unwrap();
+        let backups_dir = mock.mods_dir.join("backups");
+        transaction.snapshot_dir(&backups_dir).unwrap();
+
+        fs::write(&db_json, "{}").unwrap();
+        fs::create_dir_all(&backups_dir).unwrap();
+        fs::write(backups_dir.join("preset1.json"), "{}").unwrap();
+        fs::write(&new_file, "{}").unwrap();
+        let archive = mock.mods_dir.join("mod1.zip");
+        let renamed = mock.mods_dir.join("renamed.zip");
+        fs::write(&archive, "").unwrap();
+        fs::rename(&archive, &renamed).unwrap();
+        transaction.record_move(&archive, &renamed);
+        let imported = mock.mods_dir.join("imported.zip");
+        fs::write(&imported, "").unwrap();
+        transaction.record_created(&imported);
+        Preset::delete("preset1", &mock.presets_dir, true).unwrap();
+        Preset::new("preset3".into(), vec![])
+            .unwrap()
+            .save_to_path(&mock.presets_dir)
+            .unwrap();
+        let mut preset2 = Preset::load_from_path("preset2", &mock.presets_dir).unwrap();
+        preset2.add_mod("mod3");
+        preset2.save_to_path(&mock.presets_dir).unwrap();
+
+        let changed: Vec<PathBuf> = transaction
+            .changes()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.path)
+            .collect();
+        assert_eq!(
+            changed,
+            vec![
+                db_json.clone(),
+                new_file.clone(),
+                mock.presets_dir.join("preset1.json"),
+                mock.presets_dir.join("preset2.json"),
+                mock.presets_dir.join(".history/preset2/1.json"),
+                mock.presets_dir.join("preset3.json"),
+                backups_dir.join("preset1.json"),
+            ]
+        );
+        transaction.rollback().unwrap();
+
+        assert_eq!(fs::read(&db_json).unwrap(), before);
+        assert!(!new_file.exists());
+        assert!(!renamed.exists());
+        assert!(archive.exists());
+        assert!(!imported.exists());
+        let mut presets: Vec<String> = Preset::list(&mock.presets_dir).unwrap().collect();
+        presets.sort();
+        assert_eq!(presets, vec!["preset1", "preset2"]);
+        assert!(!backups_dir.exists());
+        // Subdirectories are snapshotted too.
+        assert!(!mock.presets_dir.join(".history").exists());
+    }
+
+    #[test]
+    fn rolling_back_past_errors() {
+        let mock = MockData::new();
+        let db_json = mock.mods_dir.join("db.json");
+        let before = fs::read(&db_json).unwrap();
+
+        let mut transaction = Transaction::new();
+        transaction.snapshot(&db_json).unwrap();
+        fs::write(&db_json, "{}").unwrap();
+        // A directory can't be removed as a file, so putting this back fails.
+        let stuck = mock.mods_dir.join("stuck.zip");
+        fs::create_dir(&stuck).unwrap();
+        transaction.record_created(&stuck);
+
+        assert!(transaction.rollback().is_err());
+        // The files after the failed step are put back anyway.
+        assert_eq!(fs::read(&db_json).unwrap(), before);
+    }
+
+    #[test]
+    fn committing_and_dropping() {
+        let mock = MockData::new();
+        let db_json = mock.mods_dir.join("db.json");
+
+        let mut transaction = Transaction::new();
+        transaction.snapshot(&db_json).unwrap();
+        fs::write(&db_json, "{}").unwrap();
+        let archive = mock.mods_dir.join("mod1.zip");
+        fs::write(&archive, "").unwrap();
+        transaction.remove_on_commit(&archive, true);
+        assert!(archive.exists());
+        transaction.commit().unwrap();
+        assert_eq!(fs::read(&db_json).unwrap(), b"{}");
+        assert!(!archive.exists());
+
+        let mut transaction = Transaction::new();
+        transaction.snapshot(&db_json).unwrap();
+        fs::write(&db_json, "[]").unwrap();
+        transaction.remove_on_commit(mock.mods_dir.join("db.json"), true);
+        drop(transaction);
+        assert_eq!(fs::read(&db_json).unwrap(), b"{}");
+    }
+}
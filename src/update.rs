@@ -0,0 +1,271 @@
+use crate::{config::RetryPolicy, net, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The feed with the latest BeamMM release.
+pub const RELEASES_URL: &str = "https://api.github.com/repos/trevin-j/BeamMM/releases/latest";
+
+/// How long a checked release is trusted before the releases feed is asked again.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The cached result of checking for a newer BeamMM release.
+///
+/// The check is cached in the BeamMM directory so the releases feed is asked at most once per
+/// `CHECK_INTERVAL`.
+///
+/// # Examples
+/// ```rust
+/// use beammm::update::UpdateCheck;
+/// use std::time::SystemTime;
+///
+/// let mut check = UpdateCheck::default();
+/// let now = SystemTime::now();
+/// assert!(check.is_stale(now));
+///
+/// check.record(Some("v9.0.0".into()), now);
+/// assert!(!check.is_stale(now));
+/// assert_eq!(check.newer_than("0.1.0"), Some("9.0.0"));
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct UpdateCheck {
+    /// When the releases feed was last asked, in seconds since the Unix epoch.
+    #[serde(default)]
+    checked: u64,
+    /// The latest released version, without a leading `v`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    latest: Option<String>,
+}
+
+/// The parts of a release in the releases feed that matter to us.
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+}
+
+impl UpdateCheck {
+    /// The filename of the update check file.
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    fn filename() -> PathBuf {
+        PathBuf::from("update_check.json")
+    }
+
+    /// Load the update check from a reader.
+    ///
+    /// # Arguments
+    ///
+    /// `reader`: The reader to load the update check from.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue reading or deserializing the update check.
+    pub fn load<R: BufRead>(reader: R) -> Result<Self> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Load the update check from the BeamMM directory. If there is no update check file, nothing
+    /// has been checked yet.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The BeamMM directory where the update check is stored.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue reading the file or serde_json errors if there is
+    /// an issue deserializing the update check.
+    pub fn load_from_path(beammm_dir: impl AsRef<Path>) -> Result<Self> {
+        let beammm_dir = beammm_dir.as_ref();
+        let path = beammm_dir.join(Self::filename());
+        if path.try_exists()? {
+            let file = File::open(path)?;
+            let reader = BufReader::new(file);
+            Self::load(reader)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Serialize and save the update check to a writer.
+    ///
+    /// # Arguments
+    ///
+    /// `writer`: The writer to save the update check to.
+    ///
+    /// # Errors
+    ///
+    /// Possible serde_json errors if there is an issue serializing the update check or writing.
+    pub fn save<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Serialize and save the update check to the BeamMM directory.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The BeamMM directory where the update check will be saved.
+    ///
+    /// # Errors
+    ///
+    /// Possible IO errors if there is an issue creating the file or writing to it.
+    /// Possible serde_json errors if there is an issue serializing the update check.
+    pub fn save_to_path(&self, beammm_dir: impl AsRef<Path>) -> Result<()> {
+        let beammm_dir = beammm_dir.as_ref();
+        let file = File::create(beammm_dir.join(Self::filename()))?;
+        let writer = BufWriter::new(file);
+        self.save(writer)
+    }
+
+    /// Whether the cached release is too old to trust, so the releases feed should be asked again.
+    ///
+    /// # Arguments
+    ///
+    /// `now`: The current time.
+    pub fn is_stale(&self, now: SystemTime) -> bool {
+        let checked = UNIX_EPOCH + Duration::from_secs(self.checked);
+        now.duration_since(checked)
+            .map_or(true, |elapsed| elapsed >= CHECK_INTERVAL)
+    }
+
+    /// Record a check of the releases feed.
+    ///
+    /// # Arguments
+    ///
+    /// `latest`: The latest release's version or tag, e.g. `v0.2.0`, or `None` if the check
+    ///   failed, which keeps the previously found release.
+    /// `now`: The current time.
+    pub fn record(&mut self, latest: Option<String>, now: SystemTime) {
+        if let Some(latest) = latest {
+            self.latest = Some(latest.trim_start_matches('v').to_string());
+        }
+        self.checked = now
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_secs();
+    }
+
+    /// Get the latest release if it is newer than a version.
+    ///
+    /// # Arguments
+    ///
+    /// `current`: The running version, e.g. `0.1.0`.
+    pub fn newer_than(&self, current: &str) -> Option<&str> {
+        let latest = self.latest.as_deref()?;
+        (version_parts(latest) > version_parts(current)).then_some(latest)
+    }
+
+    /// Check for a newer release than the running BeamMM, asking the releases feed only if the
+    /// cached release is stale. The update check file is updated when the feed is asked.
+    ///
+    /// # Arguments
+    ///
+    /// `beammm_dir`: The BeamMM directory where the update check is stored.
+    /// `now`: The current time.
+    /// `policy`: How failed requests are retried.
+    ///
+    /// # Returns
+    ///
+    /// `Some(String)`: The newer release's version.
+    /// `None`: If BeamMM is up to date.
+    ///
+    /// # Errors
+    ///
+    /// Download: If the releases feed can't be reached.
+    /// Possible IO or serde_json errors if there is an issue with the update check file or the
+    /// feed's response.
+    pub fn run(
+        beammm_dir: impl AsRef<Path>,
+        now: SystemTime,
+        policy: &RetryPolicy,
+    ) -> Result<Option<String>> {
+        let beammm_dir = beammm_dir.as_ref();
+        let mut check = Self::load_from_path(beammm_dir)?;
+        if check.is_stale(now) {
+            // Failed checks are recorded too, so an unreachable feed isn't asked on every run.
+            let latest = latest_release(policy);
+            check.record(latest.as_ref().ok().cloned(), now);
+            check.save_to_path(beammm_dir)?;
+            latest?;
+        }
+        Ok(check
+            .newer_than(env!("CARGO_PKG_VERSION"))
+            .map(String::from))
+    }
+}
+
+/// Split a version into its numeric parts for comparison. Anything after a `-` or `+`, like a
+/// pre-release tag, is ignored.
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Get the tag of the latest release from the releases feed.
+///
+/// # Arguments
+///
+/// `policy`: How failed requests are retried.
+///
+/// # Errors
+///
+/// Download: If the releases feed can't be reached.
+/// Possible serde_json errors if the feed's response can't be deserialized.
+pub fn latest_release(policy: &RetryPolicy) -> Result<String> {
+    let headers = [
+        ("User-Agent", concat!("beammm/", env!("CARGO_PKG_VERSION"))),
+        ("Accept", "application/vnd.github+json"),
+    ];
+    let bytes = net::get(RELEASES_URL, &headers, policy)?;
+    let release: Release = serde_json::from_slice(&bytes)?;
+    Ok(release.tag_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comparing_versions() {
+        let mut check = UpdateCheck::default();
+        assert_eq!(check.newer_than("0.1.0"), None);
+
+        check.record(Some("v0.2.0".into()), SystemTime::now());
+        assert_eq!(check.newer_than("0.1.0"), Some("0.2.0"));
+        assert_eq!(check.newer_than("0.2.0"), None);
+        assert_eq!(check.newer_than("0.10.0"), None);
+        assert_eq!(check.newer_than("0.2.0-beta"), None);
+
+        check.record(None, SystemTime::now());
+        assert_eq!(check.newer_than("0.1.0"), Some("0.2.0"));
+    }
+
+    #[test]
+    fn caching_checks() {
+        let tmp = tempfile::tempdir().unwrap();
+        let beammm_dir = tmp.path();
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        let mut check = UpdateCheck::load_from_path(beammm_dir).unwrap();
+        assert!(check.is_stale(now));
+        check.record(Some("0.2.0".into()), now);
+        check.save_to_path(beammm_dir).unwrap();
+
+        let check = UpdateCheck::load_from_path(beammm_dir).unwrap();
+        assert!(!check.is_stale(now + Duration::from_secs(60)));
+        assert!(check.is_stale(now + CHECK_INTERVAL));
+        // A clock set back doesn't stop the checks forever.
+        assert!(check.is_stale(now - Duration::from_secs(60)));
+    }
+}